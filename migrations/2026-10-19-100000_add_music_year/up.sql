@@ -0,0 +1,2 @@
+-- Release year read from the id3 tags, used by the year: search filter
+ALTER TABLE music ADD COLUMN year INTEGER;
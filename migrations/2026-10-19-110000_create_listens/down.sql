@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_listens_music_time;
+DROP INDEX IF EXISTS idx_listens_user_time;
+DROP TABLE listens;
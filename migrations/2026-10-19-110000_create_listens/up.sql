@@ -0,0 +1,28 @@
+-- Append-only history of every listen
+-- play_log stays as the per user/track summary of this table
+CREATE TABLE listens (
+	listen_id TEXT PRIMARY KEY NOT NULL,
+	user_id TEXT NOT NULL REFERENCES users(user_id),
+	music_id TEXT NOT NULL REFERENCES music(music_id),
+	listened_at TEXT NOT NULL,
+	listened_duration BIGINT NOT NULL, -- seconds actually listened
+	source_type TEXT NOT NULL, -- solo, lobby or playlist
+	source_id TEXT, -- lobby or playlist id
+	skipped BOOLEAN NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_listens_user_time ON listens(user_id, listened_at);
+CREATE INDEX IF NOT EXISTS idx_listens_music_time ON listens(music_id, listened_at);
+
+-- Only the last listen of every user/track pair is known, seeding the history with it
+INSERT INTO listens (listen_id, user_id, music_id, listened_at, listened_duration, source_type, source_id, skipped)
+SELECT
+	lower(hex(randomblob(16))),
+	play_log.user_id,
+	play_log.music_id,
+	play_log.music_played_date_time,
+	COALESCE((SELECT music.duration FROM music WHERE music.music_id = play_log.music_id), 0),
+	'solo',
+	NULL,
+	0
+FROM play_log;
@@ -0,0 +1,5 @@
+ALTER TABLE music DROP COLUMN analyzed_at;
+ALTER TABLE music DROP COLUMN danceability;
+ALTER TABLE music DROP COLUMN energy;
+ALTER TABLE music DROP COLUMN musical_key;
+ALTER TABLE music DROP COLUMN bpm;
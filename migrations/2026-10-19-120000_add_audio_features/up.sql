@@ -0,0 +1,7 @@
+-- Audio features estimated from the decoded track, NULL until the analyzer has processed the file
+ALTER TABLE music ADD COLUMN bpm DOUBLE;
+ALTER TABLE music ADD COLUMN musical_key TEXT;
+ALTER TABLE music ADD COLUMN energy DOUBLE;
+ALTER TABLE music ADD COLUMN danceability DOUBLE;
+-- Set once the file went through the analyzer (even if it failed) so that the backfill doesn't retry it forever
+ALTER TABLE music ADD COLUMN analyzed_at TEXT;
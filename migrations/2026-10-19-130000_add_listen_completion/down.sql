@@ -0,0 +1 @@
+ALTER TABLE listens DROP COLUMN completed;
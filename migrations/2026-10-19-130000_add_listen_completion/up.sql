@@ -0,0 +1,4 @@
+-- Whether the listen went past the completion threshold and counted as a play
+ALTER TABLE listens ADD COLUMN completed BOOLEAN NOT NULL DEFAULT 0;
+-- Every listen logged so far that wasn't a skip was counted as a play
+UPDATE listens SET completed = NOT skipped;
@@ -0,0 +1,19 @@
+CREATE TABLE playlist_songs_old (
+	playlist_id TEXT NOT NULL REFERENCES playlists(playlist_id),
+	music_id TEXT NOT NULL REFERENCES music(music_id),
+	song_adder_id TEXT NOT NULL REFERENCES users(user_id), --for combined playlist added by
+	song_added_date_time TEXT NOT NULL,
+	PRIMARY KEY (playlist_id, music_id)
+);
+
+-- Only the first occurrence of a duplicated song survives
+INSERT INTO playlist_songs_old (playlist_id, music_id, song_adder_id, song_added_date_time)
+SELECT playlist_id, music_id, song_adder_id, song_added_date_time
+FROM playlist_songs AS outer_songs
+WHERE position = (
+	SELECT MIN(position) FROM playlist_songs AS inner_songs
+	WHERE inner_songs.playlist_id = outer_songs.playlist_id AND inner_songs.music_id = outer_songs.music_id
+);
+
+DROP TABLE playlist_songs;
+ALTER TABLE playlist_songs_old RENAME TO playlist_songs;
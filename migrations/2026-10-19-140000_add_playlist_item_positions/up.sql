@@ -0,0 +1,26 @@
+-- Playlist entries get their own id so that the same song can be in a playlist more than once,
+-- and an explicit position (gaps of 1024, moves take the midpoint of their new neighbours)
+CREATE TABLE playlist_songs_new (
+	item_id TEXT PRIMARY KEY NOT NULL,
+	playlist_id TEXT NOT NULL REFERENCES playlists(playlist_id),
+	music_id TEXT NOT NULL REFERENCES music(music_id),
+	song_adder_id TEXT NOT NULL REFERENCES users(user_id), --for combined playlist added by
+	song_added_date_time TEXT NOT NULL,
+	position DOUBLE NOT NULL
+);
+
+-- Keeping the order the songs were added in
+INSERT INTO playlist_songs_new (item_id, playlist_id, music_id, song_adder_id, song_added_date_time, position)
+SELECT
+	lower(hex(randomblob(16))),
+	playlist_id,
+	music_id,
+	song_adder_id,
+	song_added_date_time,
+	ROW_NUMBER() OVER (PARTITION BY playlist_id ORDER BY song_added_date_time, music_id) * 1024.0
+FROM playlist_songs;
+
+DROP TABLE playlist_songs;
+ALTER TABLE playlist_songs_new RENAME TO playlist_songs;
+
+CREATE INDEX IF NOT EXISTS idx_playlist_songs_position ON playlist_songs(playlist_id, position);
@@ -0,0 +1,2 @@
+-- Rules of smart playlists as json, the songs of a smart playlist are computed from them instead of playlist_songs
+ALTER TABLE playlists ADD COLUMN smart_rules TEXT;
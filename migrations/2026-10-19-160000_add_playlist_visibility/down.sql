@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS playlist_follows;
+DROP TABLE IF EXISTS playlist_share_tokens;
+ALTER TABLE playlists DROP COLUMN visibility;
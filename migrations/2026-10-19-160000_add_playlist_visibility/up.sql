@@ -0,0 +1,23 @@
+-- Who can see a playlist: private, friends, unlisted or public.
+-- Existing playlists start out unlisted so they can still be read by id like before, new playlists are created
+-- private by the server.
+ALTER TABLE playlists ADD COLUMN visibility TEXT NOT NULL DEFAULT 'unlisted';
+
+-- Revocable links that give read access to a playlist whatever its visibility
+CREATE TABLE IF NOT EXISTS playlist_share_tokens (
+	token TEXT PRIMARY KEY NOT NULL,
+	playlist_id TEXT NOT NULL REFERENCES playlists(playlist_id),
+	created_by TEXT NOT NULL REFERENCES users(user_id),
+	created_at TEXT NOT NULL
+);
+
+-- Public playlists of other users in someone's library
+CREATE TABLE IF NOT EXISTS playlist_follows (
+	user_id TEXT NOT NULL REFERENCES users(user_id),
+	playlist_id TEXT NOT NULL REFERENCES playlists(playlist_id),
+	followed_at TEXT NOT NULL,
+	PRIMARY KEY (user_id, playlist_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_playlist_share_tokens_playlist ON playlist_share_tokens(playlist_id);
+CREATE INDEX IF NOT EXISTS idx_playlist_follows_playlist ON playlist_follows(playlist_id);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS playlist_invitations;
+ALTER TABLE playlist_shares DROP COLUMN role;
@@ -0,0 +1,15 @@
+-- Role of a contributor: editor or viewer, the owner of a playlist is playlists.user_id
+ALTER TABLE playlist_shares ADD COLUMN role TEXT NOT NULL DEFAULT 'editor';
+
+-- Contributors have to accept before being added, one pending invitation per user and playlist
+CREATE TABLE IF NOT EXISTS playlist_invitations (
+	invitation_id TEXT PRIMARY KEY NOT NULL,
+	playlist_id TEXT NOT NULL REFERENCES playlists(playlist_id),
+	inviter_id TEXT NOT NULL REFERENCES users(user_id),
+	invitee_id TEXT NOT NULL REFERENCES users(user_id),
+	role TEXT NOT NULL,
+	created_at TEXT NOT NULL,
+	UNIQUE (playlist_id, invitee_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_playlist_invitations_invitee ON playlist_invitations(invitee_id);
@@ -0,0 +1,2 @@
+ALTER TABLE playlists DROP COLUMN deleted_at;
+DROP TABLE IF EXISTS playlist_history;
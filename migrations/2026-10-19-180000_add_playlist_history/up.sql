@@ -0,0 +1,15 @@
+-- Every change to the songs, the name or the cover of a playlist, undone ones are kept with undone_at set
+CREATE TABLE IF NOT EXISTS playlist_history (
+	event_id TEXT PRIMARY KEY NOT NULL,
+	playlist_id TEXT NOT NULL REFERENCES playlists(playlist_id),
+	actor_id TEXT NOT NULL REFERENCES users(user_id),
+	kind TEXT NOT NULL, -- add, remove, reorder, rename or cover
+	change TEXT NOT NULL, -- json, what is needed to undo it, see core::playlist_history
+	created_at TEXT NOT NULL,
+	undone_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_playlist_history_playlist ON playlist_history(playlist_id, created_at);
+
+-- Deleted playlists can be restored for 30 days before they are purged
+ALTER TABLE playlists ADD COLUMN deleted_at TEXT;
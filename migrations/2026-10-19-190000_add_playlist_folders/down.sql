@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS playlist_library_entries;
+DROP TABLE IF EXISTS playlist_folders;
+DROP TABLE IF EXISTS playlist_tags;
+ALTER TABLE playlists DROP COLUMN description;
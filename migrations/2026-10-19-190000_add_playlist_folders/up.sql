@@ -0,0 +1,32 @@
+-- Shown on the playlist page, empty when not set
+ALTER TABLE playlists ADD COLUMN description TEXT NOT NULL DEFAULT '';
+
+-- Lowercase labels of a playlist, see core::playlist_library
+CREATE TABLE IF NOT EXISTS playlist_tags (
+	playlist_id TEXT NOT NULL REFERENCES playlists(playlist_id),
+	tag TEXT NOT NULL,
+	PRIMARY KEY (playlist_id, tag)
+);
+
+-- Folders of a user's library, nested through parent_id, NULL being the top level
+CREATE TABLE IF NOT EXISTS playlist_folders (
+	folder_id TEXT PRIMARY KEY NOT NULL,
+	user_id TEXT NOT NULL REFERENCES users(user_id),
+	parent_id TEXT REFERENCES playlist_folders(folder_id),
+	folder_name TEXT NOT NULL,
+	created_at TEXT NOT NULL
+);
+
+-- Where a playlist sits in a user's library and whether it's pinned there, everyone organises their own library.
+-- Playlists without a row are unpinned at the top level
+CREATE TABLE IF NOT EXISTS playlist_library_entries (
+	user_id TEXT NOT NULL REFERENCES users(user_id),
+	playlist_id TEXT NOT NULL REFERENCES playlists(playlist_id),
+	folder_id TEXT REFERENCES playlist_folders(folder_id),
+	pinned_at TEXT,
+	PRIMARY KEY (user_id, playlist_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_playlist_tags_tag ON playlist_tags(tag);
+CREATE INDEX IF NOT EXISTS idx_playlist_folders_user ON playlist_folders(user_id);
+CREATE INDEX IF NOT EXISTS idx_playlist_library_entries_playlist ON playlist_library_entries(playlist_id);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS playlist_blends;
+DROP TABLE IF EXISTS playlist_forks;
@@ -0,0 +1,16 @@
+-- Forks keep a link to the playlist they were made from, with the songs it had when the fork was last updated
+-- from it, to tell what changed in the source since
+CREATE TABLE IF NOT EXISTS playlist_forks (
+	playlist_id TEXT PRIMARY KEY NOT NULL REFERENCES playlists(playlist_id),
+	source_id TEXT NOT NULL REFERENCES playlists(playlist_id),
+	source_snapshot TEXT NOT NULL, -- json array of music ids
+	synced_at TEXT NOT NULL
+);
+
+-- Combined playlists made of the top tracks and likes of their members, see core::blend
+CREATE TABLE IF NOT EXISTS playlist_blends (
+	playlist_id TEXT PRIMARY KEY NOT NULL REFERENCES playlists(playlist_id),
+	refreshed_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_playlist_forks_source ON playlist_forks(source_id);
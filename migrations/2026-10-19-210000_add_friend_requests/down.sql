@@ -0,0 +1,6 @@
+-- Pending requests go back to being one-way friendships
+INSERT OR IGNORE INTO user_friendship (user_id, friend_id)
+SELECT sender_id, receiver_id FROM friend_requests WHERE status = 'pending';
+
+DROP TABLE IF EXISTS user_blocks;
+DROP TABLE IF EXISTS friend_requests;
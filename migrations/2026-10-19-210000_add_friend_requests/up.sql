@@ -0,0 +1,42 @@
+-- Friend requests go from pending to accepted, declined or cancelled (by the sender). Accepting adds the
+-- friendship both ways, user_friendship only holds mutual friendships from now on
+CREATE TABLE IF NOT EXISTS friend_requests (
+	request_id TEXT PRIMARY KEY NOT NULL,
+	sender_id TEXT NOT NULL REFERENCES users(user_id),
+	receiver_id TEXT NOT NULL REFERENCES users(user_id),
+	status TEXT NOT NULL DEFAULT 'pending',
+	created_at TEXT NOT NULL,
+	responded_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_friend_requests_receiver ON friend_requests(receiver_id, status);
+CREATE INDEX IF NOT EXISTS idx_friend_requests_sender ON friend_requests(sender_id, status);
+
+-- Blocking is one way, but hides the two users from each other
+CREATE TABLE IF NOT EXISTS user_blocks (
+	user_id TEXT NOT NULL REFERENCES users(user_id),
+	blocked_id TEXT NOT NULL REFERENCES users(user_id),
+	created_at TEXT NOT NULL,
+	PRIMARY KEY (user_id, blocked_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_blocks_blocked ON user_blocks(blocked_id);
+
+-- The existing one-way friendships become pending requests
+INSERT INTO friend_requests (request_id, sender_id, receiver_id, status, created_at)
+SELECT
+	lower(hex(randomblob(16))),
+	f.user_id,
+	f.friend_id,
+	'pending',
+	strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')
+FROM user_friendship f
+WHERE NOT EXISTS (
+	SELECT 1 FROM user_friendship r WHERE r.user_id = f.friend_id AND r.friend_id = f.user_id
+);
+
+DELETE FROM user_friendship
+WHERE NOT EXISTS (
+	SELECT 1 FROM user_friendship r
+	WHERE r.user_id = user_friendship.friend_id AND r.friend_id = user_friendship.user_id
+);
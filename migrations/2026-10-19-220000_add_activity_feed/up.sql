@@ -0,0 +1,19 @@
+-- What users do, shown to their friends: now_playing, liked_track, new_playlist or hosting_lobby. subject_id is
+-- the music, playlist or lobby id, details holds what the feed shows about it as json
+CREATE TABLE IF NOT EXISTS activity_events (
+	event_id TEXT PRIMARY KEY NOT NULL,
+	user_id TEXT NOT NULL REFERENCES users(user_id),
+	kind TEXT NOT NULL,
+	subject_id TEXT NOT NULL,
+	details TEXT NOT NULL DEFAULT '{}',
+	created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_activity_events_user ON activity_events(user_id, created_at);
+
+-- Users without a row share both their activity and their presence
+CREATE TABLE IF NOT EXISTS user_privacy (
+	user_id TEXT PRIMARY KEY NOT NULL REFERENCES users(user_id),
+	share_activity BOOLEAN NOT NULL DEFAULT 1,
+	share_presence BOOLEAN NOT NULL DEFAULT 1
+);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS direct_messages;
+DROP TABLE IF EXISTS conversation_members;
+DROP TABLE IF EXISTS conversations;
@@ -0,0 +1,34 @@
+-- Direct messages between friends, one to one or in small groups. A one to one conversation is reused for the
+-- same two users, groups can have a name
+CREATE TABLE IF NOT EXISTS conversations (
+	conversation_id TEXT PRIMARY KEY NOT NULL,
+	is_group BOOLEAN NOT NULL DEFAULT 0,
+	name TEXT,
+	created_by TEXT NOT NULL REFERENCES users(user_id),
+	created_at TEXT NOT NULL,
+	last_message_at TEXT NOT NULL
+);
+
+-- last_read_at is the read receipt of the member, messages sent after it are unread
+CREATE TABLE IF NOT EXISTS conversation_members (
+	conversation_id TEXT NOT NULL REFERENCES conversations(conversation_id),
+	user_id TEXT NOT NULL REFERENCES users(user_id),
+	joined_at TEXT NOT NULL,
+	last_read_at TEXT,
+	PRIMARY KEY (conversation_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversation_members_user ON conversation_members(user_id);
+
+-- A message can embed a track or a playlist as a share card, attachment_kind is 'track' or 'playlist'
+CREATE TABLE IF NOT EXISTS direct_messages (
+	message_id TEXT PRIMARY KEY NOT NULL,
+	conversation_id TEXT NOT NULL REFERENCES conversations(conversation_id),
+	sender_id TEXT NOT NULL REFERENCES users(user_id),
+	body TEXT NOT NULL,
+	attachment_kind TEXT,
+	attachment_id TEXT,
+	sent_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_direct_messages_conversation ON direct_messages(conversation_id, sent_at);
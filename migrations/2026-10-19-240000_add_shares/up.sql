@@ -0,0 +1,23 @@
+-- A track, album or playlist sent to friends. Albums are identified by their name like when browsing them
+CREATE TABLE IF NOT EXISTS shares (
+	share_id TEXT PRIMARY KEY NOT NULL,
+	sender_id TEXT NOT NULL REFERENCES users(user_id),
+	kind TEXT NOT NULL, -- 'track', 'album' or 'playlist'
+	subject_id TEXT NOT NULL,
+	message TEXT,
+	created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_shares_sender ON shares(sender_id, created_at);
+
+-- What each recipient did with it, which the sender gets to see
+CREATE TABLE IF NOT EXISTS share_recipients (
+	share_id TEXT NOT NULL REFERENCES shares(share_id),
+	recipient_id TEXT NOT NULL REFERENCES users(user_id),
+	opened_at TEXT,
+	reaction TEXT,
+	reacted_at TEXT,
+	PRIMARY KEY (share_id, recipient_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_share_recipients_recipient ON share_recipients(recipient_id);
@@ -0,0 +1,17 @@
+-- Public profile of a user. avatar is the file name of the profile picture, if any. The stats toggles decide whether
+-- others see the top artists and the listening stats of the user
+CREATE TABLE IF NOT EXISTS user_profiles (
+	user_id TEXT PRIMARY KEY NOT NULL REFERENCES users(user_id),
+	display_name TEXT,
+	bio TEXT NOT NULL DEFAULT '',
+	avatar TEXT,
+	pronouns TEXT,
+	country TEXT, -- ISO 3166-1 alpha-2
+	joined_at TEXT NOT NULL,
+	show_top_artists BOOLEAN NOT NULL DEFAULT 1,
+	show_stats BOOLEAN NOT NULL DEFAULT 1
+);
+
+-- When existing users joined isn't known, they join now
+INSERT OR IGNORE INTO user_profiles (user_id, joined_at)
+SELECT user_id, strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now') FROM users;
@@ -0,0 +1,3 @@
+-- Normalized title, album and artist of every track (see core::search_index), so that searches can narrow down
+-- the candidates in SQL before ranking them. Existing tracks are filled in by the server on startup.
+ALTER TABLE music ADD COLUMN search_text TEXT NOT NULL DEFAULT '';
@@ -12,6 +12,11 @@ pub const COVER_IMG_STORAGE: &str = "./storage/cover_images";
 pub const MUSIC_STORAGE: &str = "./storage/music_db";
 pub const USER_PFP_STORAGE: &str = "./storage/users_pfps";
 pub const PLAYLIST_COVER_IMG_STORAGE: &str = "./storage/playlists_cover_img";
+pub const PLAYLIST_COVER_HISTORY_STORAGE: &str = "./storage/playlists_cover_img/history";
+pub const PLAYLIST_MOSAIC_STORAGE: &str = "./storage/playlists_cover_img/mosaic";
+pub const REPORT_CARD_STORAGE: &str = "./storage/report_cards";
+// Overridden by LISTENBRAINZ_API_URL in .env, any ListenBrainz compatible server works
+pub const LISTENBRAINZ_API_URL: &str = "https://api.listenbrainz.org";
 pub const DEV: bool = true;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -49,6 +54,48 @@ pub enum OpCode {
 	NOTIFICATION,
 	#[allow(non_camel_case_types)]
 	REQUEST_MUSIC_PLAY,
+	#[allow(non_camel_case_types)]
+	SET_AUTO_RADIO,
+	#[allow(non_camel_case_types)]
+	PLAYLIST_SONGS_ADDED,
+	#[allow(non_camel_case_types)]
+	PLAYLIST_INVITE,
+	#[allow(non_camel_case_types)]
+	PLAYLIST_INVITE_RESPONSE,
+	#[allow(non_camel_case_types)]
+	PLAYLIST_ROLE_CHANGED,
+	#[allow(non_camel_case_types)]
+	PLAYLIST_CONTRIBUTOR_REMOVED,
+	#[allow(non_camel_case_types)]
+	PLAYLIST_OWNER_CHANGED,
+	#[allow(non_camel_case_types)]
+	SUBSCRIBE_PLAYLIST,
+	#[allow(non_camel_case_types)]
+	UNSUBSCRIBE_PLAYLIST,
+	#[allow(non_camel_case_types)]
+	SET_PLAYLIST_PRESENCE,
+	#[allow(non_camel_case_types)]
+	PLAYLIST_PRESENCE,
+	#[allow(non_camel_case_types)]
+	PLAYLIST_EVENT,
+	#[allow(non_camel_case_types)]
+	FRIEND_REQUEST,
+	#[allow(non_camel_case_types)]
+	FRIEND_REQUEST_RESPONSE,
+	#[allow(non_camel_case_types)]
+	FRIEND_ACTIVITY,
+	#[allow(non_camel_case_types)]
+	FRIEND_PRESENCE,
+	#[allow(non_camel_case_types)]
+	DIRECT_MESSAGE,
+	#[allow(non_camel_case_types)]
+	DIRECT_MESSAGE_READ,
+	#[allow(non_camel_case_types)]
+	SHARE_RECEIVED,
+	#[allow(non_camel_case_types)]
+	SHARE_OPENED,
+	#[allow(non_camel_case_types)]
+	SHARE_REACTION,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
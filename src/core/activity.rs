@@ -0,0 +1,362 @@
+// Friend activity and presence.
+//
+// The handlers record what users do as activity events: the track they started playing, alone or as the host of a
+// lobby, the tracks they like, the playlists they create and the lobbies they host. Friends see them in their feed
+// and get them live over the socket. Playlists only show up while their visibility lets friends see them.
+// Presence (online, idle or offline) comes from the socket connections in the user pool. Users can stop sharing
+// either of them, sharing nothing also hides what was recorded before.
+
+use crate::config::{OpCode, SocketResponse};
+use crate::core::friendship;
+use crate::core::playlist_access::Visibility;
+use crate::core::user_pool::{UserPool, UserStatus};
+use crate::lobic_db::models::{ActivityEvent, Music, Playlist, UserPrivacy};
+use crate::schema::{activity_events, music, playlists, user_privacy};
+
+use axum::extract::ws::Message;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+// The same thing done again within this window isn't recorded twice, e.g. a track paused and resumed
+const REPEAT_WINDOW_MINUTES: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActivityKind {
+	NowPlaying,
+	LikedTrack,
+	NewPlaylist,
+	HostingLobby,
+}
+
+impl ActivityKind {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ActivityKind::NowPlaying => "now_playing",
+			ActivityKind::LikedTrack => "liked_track",
+			ActivityKind::NewPlaylist => "new_playlist",
+			ActivityKind::HostingLobby => "hosting_lobby",
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityEntry {
+	pub event_id: String,
+	pub user_id: String,
+	pub kind: String,
+	pub subject_id: String,
+	pub details: Value,
+	pub created_at: String,
+}
+
+impl From<ActivityEvent> for ActivityEntry {
+	fn from(event: ActivityEvent) -> Self {
+		ActivityEntry {
+			event_id: event.event_id,
+			user_id: event.user_id,
+			kind: event.kind,
+			subject_id: event.subject_id,
+			details: serde_json::from_str(&event.details).unwrap_or(Value::Null),
+			created_at: event.created_at,
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub struct FriendPresence {
+	pub user_id: String,
+	pub status: UserStatus,
+}
+
+pub fn privacy_of(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<UserPrivacy> {
+	Ok(user_privacy::table
+		.filter(user_privacy::user_id.eq(user_id))
+		.first::<UserPrivacy>(db_conn)
+		.optional()?
+		.unwrap_or(UserPrivacy {
+			user_id: user_id.to_string(),
+			share_activity: true,
+			share_presence: true,
+		}))
+}
+
+// Leaves the settings that aren't given as they are
+pub fn set_privacy(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	share_activity: Option<bool>,
+	share_presence: Option<bool>,
+) -> QueryResult<UserPrivacy> {
+	let current = privacy_of(db_conn, user_id)?;
+	let privacy = UserPrivacy {
+		user_id: user_id.to_string(),
+		share_activity: share_activity.unwrap_or(current.share_activity),
+		share_presence: share_presence.unwrap_or(current.share_presence),
+	};
+	diesel::replace_into(user_privacy::table)
+		.values(&privacy)
+		.execute(db_conn)?;
+	Ok(privacy)
+}
+
+// Those of the users who turned the setting off
+fn not_sharing(
+	db_conn: &mut SqliteConnection,
+	user_ids: &[String],
+	presence: bool,
+) -> QueryResult<HashSet<String>> {
+	let query = user_privacy::table
+		.filter(user_privacy::user_id.eq_any(user_ids))
+		.select(user_privacy::user_id);
+	let hidden: Vec<String> = if presence {
+		query.filter(user_privacy::share_presence.eq(false)).load(db_conn)?
+	} else {
+		query.filter(user_privacy::share_activity.eq(false)).load(db_conn)?
+	};
+	Ok(hidden.into_iter().collect())
+}
+
+// What the feed shows about a track
+fn music_details(db_conn: &mut SqliteConnection, music_id: &str) -> QueryResult<Value> {
+	let entry = music::table
+		.filter(music::music_id.eq(music_id))
+		.first::<Music>(db_conn)?;
+	Ok(json!({ "music": Music::create_music_response(entry) }))
+}
+
+// None when the user doesn't share their activity or just did the same thing
+fn record(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	kind: ActivityKind,
+	subject_id: &str,
+	details: Value,
+) -> QueryResult<Option<ActivityEvent>> {
+	if !privacy_of(db_conn, user_id)?.share_activity {
+		return Ok(None);
+	}
+	let since = (Utc::now() - Duration::minutes(REPEAT_WINDOW_MINUTES)).to_rfc3339();
+	let latest = activity_events::table
+		.filter(activity_events::user_id.eq(user_id))
+		.filter(activity_events::kind.eq(kind.as_str()))
+		.filter(activity_events::created_at.ge(since))
+		.order(activity_events::created_at.desc())
+		.select(activity_events::subject_id)
+		.first::<String>(db_conn)
+		.optional()?;
+	if latest.as_deref() == Some(subject_id) {
+		return Ok(None);
+	}
+
+	let event = ActivityEvent {
+		event_id: Uuid::new_v4().to_string(),
+		user_id: user_id.to_string(),
+		kind: kind.as_str().to_string(),
+		subject_id: subject_id.to_string(),
+		details: details.to_string(),
+		created_at: Utc::now().to_rfc3339(),
+	};
+	diesel::insert_into(activity_events::table)
+		.values(&event)
+		.execute(db_conn)?;
+	Ok(Some(event))
+}
+
+// Whether friends of the owner can see the playlist
+fn playlist_is_visible(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<bool> {
+	let count = playlists::table
+		.filter(playlists::playlist_id.eq(playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.filter(playlists::visibility.eq_any([Visibility::Friends.as_str(), Visibility::Public.as_str()]))
+		.count()
+		.get_result::<i64>(db_conn)?;
+	Ok(count > 0)
+}
+
+fn send_to_friends(db_conn: &mut SqliteConnection, user_pool: &UserPool, user_id: &str, response: SocketResponse) {
+	let friends = match friendship::friends_of(db_conn, user_id) {
+		Ok(friends) => friends,
+		Err(err) => {
+			println!("Error {}:{}: Failed to query friends: {err}", file!(), line!());
+			return;
+		}
+	};
+	let response = response.to_string();
+	for friend_id in friends {
+		if let Some(conn) = user_pool.get(&friend_id) {
+			let _ = conn.send(Message::Text(response.clone()));
+		}
+	}
+}
+
+// Records the activity and sends it to the friends who are online. Failing to do so doesn't fail what the user
+// did, so errors are only logged
+pub fn share(
+	db_conn: &mut SqliteConnection,
+	user_pool: &UserPool,
+	user_id: &str,
+	kind: ActivityKind,
+	subject_id: &str,
+	details: Value,
+) {
+	let event = match record(db_conn, user_id, kind, subject_id, details) {
+		Ok(Some(event)) => event,
+		Ok(None) => return,
+		Err(err) => {
+			println!("Error {}:{}: Failed to record activity: {err}", file!(), line!());
+			return;
+		}
+	};
+	if kind == ActivityKind::NewPlaylist && !playlist_is_visible(db_conn, subject_id).unwrap_or(false) {
+		return;
+	}
+	publish(db_conn, user_pool, event);
+}
+
+fn publish(db_conn: &mut SqliteConnection, user_pool: &UserPool, event: ActivityEvent) {
+	let user_id = event.user_id.clone();
+	let response = SocketResponse {
+		op_code: OpCode::OK,
+		r#for: OpCode::FRIEND_ACTIVITY,
+		value: serde_json::to_value(ActivityEntry::from(event)).unwrap(),
+	};
+	send_to_friends(db_conn, user_pool, &user_id, response);
+}
+
+// New playlists are private until their owner says otherwise, see announce_playlist
+pub fn share_playlist(db_conn: &mut SqliteConnection, user_pool: &UserPool, playlist: &Playlist) {
+	let details = json!({
+		"playlist_name": playlist.playlist_name,
+		"is_smart": playlist.smart_rules.is_some(),
+		"is_playlist_combined": playlist.is_playlist_combined,
+	});
+	share(
+		db_conn,
+		user_pool,
+		&playlist.user_id,
+		ActivityKind::NewPlaylist,
+		&playlist.playlist_id,
+		details,
+	);
+}
+
+// Sends the creation of the playlist to the friends of its owner once they can see it
+pub fn announce_playlist(db_conn: &mut SqliteConnection, user_pool: &UserPool, playlist_id: &str) {
+	let event = activity_events::table
+		.filter(activity_events::kind.eq(ActivityKind::NewPlaylist.as_str()))
+		.filter(activity_events::subject_id.eq(playlist_id))
+		.order(activity_events::created_at.desc())
+		.first::<ActivityEvent>(db_conn)
+		.optional();
+	let event = match event {
+		Ok(Some(event)) => event,
+		Ok(None) => return,
+		Err(err) => {
+			println!("Error {}:{}: Failed to query activity: {err}", file!(), line!());
+			return;
+		}
+	};
+	match privacy_of(db_conn, &event.user_id) {
+		Ok(privacy) if privacy.share_activity => publish(db_conn, user_pool, event),
+		Ok(_) => (),
+		Err(err) => println!("Error {}:{}: Failed to query privacy settings: {err}", file!(), line!()),
+	}
+}
+
+// Shares activity about a track with the track in the details, along with the extra ones
+pub fn share_track(
+	db_conn: &mut SqliteConnection,
+	user_pool: &UserPool,
+	user_id: &str,
+	kind: ActivityKind,
+	music_id: &str,
+	extra: Value,
+) {
+	let mut details = match music_details(db_conn, music_id) {
+		Ok(details) => details,
+		Err(err) => {
+			println!("Error {}:{}: Failed to load music for activity: {err}", file!(), line!());
+			return;
+		}
+	};
+	if let (Value::Object(details), Value::Object(extra)) = (&mut details, extra) {
+		details.extend(extra);
+	}
+	share(db_conn, user_pool, user_id, kind, music_id, details);
+}
+
+// Activity of the friends of the user, latest first
+pub fn feed(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	start_index: i64,
+	page_length: Option<i64>,
+) -> QueryResult<Vec<ActivityEntry>> {
+	let friends = friendship::friends_of(db_conn, user_id)?;
+	let hidden = not_sharing(db_conn, &friends, false)?;
+	let sharing: Vec<&String> = friends.iter().filter(|friend_id| !hidden.contains(*friend_id)).collect();
+
+	let visible_playlists = playlists::table
+		.filter(playlists::deleted_at.is_null())
+		.filter(playlists::visibility.eq_any([Visibility::Friends.as_str(), Visibility::Public.as_str()]))
+		.select(playlists::playlist_id);
+	let events = activity_events::table
+		.filter(activity_events::user_id.eq_any(sharing))
+		.filter(
+			activity_events::kind
+				.ne(ActivityKind::NewPlaylist.as_str())
+				.or(activity_events::subject_id.eq_any(visible_playlists)),
+		)
+		.order(activity_events::created_at.desc())
+		.offset(start_index)
+		.limit(page_length.filter(|length| *length > 0).unwrap_or(-1)) // sqlite treats a negative limit as none
+		.load::<ActivityEvent>(db_conn)?;
+	Ok(events.into_iter().map(ActivityEntry::from).collect())
+}
+
+// Offline for friends who don't share their presence
+pub fn friends_presence(
+	db_conn: &mut SqliteConnection,
+	user_pool: &UserPool,
+	user_id: &str,
+) -> QueryResult<Vec<FriendPresence>> {
+	let friends = friendship::friends_of(db_conn, user_id)?;
+	let hidden = not_sharing(db_conn, &friends, true)?;
+	Ok(friends
+		.into_iter()
+		.map(|friend_id| FriendPresence {
+			status: if hidden.contains(&friend_id) {
+				UserStatus::Offline
+			} else {
+				user_pool.status(&friend_id)
+			},
+			user_id: friend_id,
+		})
+		.collect())
+}
+
+// Lets the friends who are online know the current presence of the user
+pub fn broadcast_presence(db_conn: &mut SqliteConnection, user_pool: &UserPool, user_id: &str) {
+	let status = match privacy_of(db_conn, user_id) {
+		Ok(privacy) if privacy.share_presence => user_pool.status(user_id),
+		Ok(_) => UserStatus::Offline,
+		Err(err) => {
+			println!("Error {}:{}: Failed to query privacy settings: {err}", file!(), line!());
+			return;
+		}
+	};
+	let response = SocketResponse {
+		op_code: OpCode::OK,
+		r#for: OpCode::FRIEND_PRESENCE,
+		value: serde_json::to_value(FriendPresence {
+			user_id: user_id.to_string(),
+			status,
+		})
+		.unwrap(),
+	};
+	send_to_friends(db_conn, user_pool, user_id, response);
+}
@@ -1,4 +1,8 @@
+use crate::core::charts::ChartCache;
 use crate::core::lobby::LobbyPool;
+use crate::core::playback::PlaybackTracker;
+use crate::core::playlist_room::PlaylistRoomPool;
+use crate::core::recommender::Recommender;
 use crate::core::user_pool::UserPool;
 use crate::lobic_db::db::*;
 
@@ -7,6 +11,10 @@ pub struct AppState {
 	pub db_pool: DatabasePool,
 	pub lobby_pool: LobbyPool,
 	pub user_pool: UserPool,
+	pub chart_cache: ChartCache,
+	pub recommender: Recommender,
+	pub playback_tracker: PlaybackTracker,
+	pub playlist_rooms: PlaylistRoomPool,
 }
 
 impl AppState {
@@ -15,6 +23,10 @@ impl AppState {
 			db_pool: generate_db_pool(),
 			lobby_pool: LobbyPool::new(),
 			user_pool: UserPool::new(),
+			chart_cache: ChartCache::new(),
+			recommender: Recommender::new(),
+			playback_tracker: PlaybackTracker::new(),
+			playlist_rooms: PlaylistRoomPool::new(),
 		}
 	}
 }
@@ -0,0 +1,381 @@
+// Estimates the tempo, key, energy and danceability of the stored tracks.
+//
+// The first couple of minutes of a track are decoded, mixed down to mono and downsampled. Tempo comes from the
+// autocorrelation of the spectral flux (onset strength), key from a chromagram matched against the
+// Krumhansl-Schmuckler key profiles. Energy is the loudness of the track and danceability combines how clear
+// the beat is with how close the tempo is to the usual dance range. These are estimates, not ground truth.
+
+use crate::config::MUSIC_STORAGE;
+use crate::lobic_db::db::DatabasePool;
+use crate::schema::music;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use rustfft::{num_complex::Complex, FftPlanner};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use symphonia::core::{
+	audio::SampleBuffer, codecs::DecoderOptions, errors::Error as SymphoniaError, formats::FormatOptions,
+	io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+// Only the beginning of the track is analyzed, that is plenty for the tempo and the key
+const MAX_ANALYSIS_SECS: usize = 150;
+const TARGET_SAMPLE_RATE: u32 = 11025;
+
+const ONSET_FRAME: usize = 1024;
+const ONSET_HOP: usize = 256;
+const CHROMA_FRAME: usize = 4096;
+const CHROMA_HOP: usize = 2048;
+
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+
+const ANALYZER_BATCH: i64 = 20;
+
+const PITCH_CLASSES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+const MAJOR_PROFILE: [f64; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f64; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+// Only one backfill runs at a time
+static ANALYZER_RUNNING: AtomicBool = AtomicBool::new(false);
+// Set whenever tracks were stored, so a running backfill knows it has to look for pending tracks again
+static ANALYZER_DIRTY: AtomicBool = AtomicBool::new(false);
+
+// Lets the next backfill start once this one is over, even if it panicked
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+	fn drop(&mut self) {
+		ANALYZER_RUNNING.store(false, Ordering::SeqCst);
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFeatures {
+	pub bpm: f64,
+	pub musical_key: String, // eg: "F# minor"
+	pub energy: f64,
+	pub danceability: f64,
+}
+
+// Decodes the audio file into mono samples, returns them with their sample rate
+fn decode_mono(path: &Path) -> Result<(Vec<f32>, u32), String> {
+	let file = File::open(path).map_err(|err| format!("Failed to open file: {err}"))?;
+	let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+	let mut hint = Hint::new();
+	if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+		hint.with_extension(extension);
+	}
+
+	let probed = symphonia::default::get_probe()
+		.format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+		.map_err(|err| format!("Unsupported format: {err}"))?;
+	let mut format = probed.format;
+
+	let track = format.default_track().ok_or("No audio track found")?;
+	let track_id = track.id;
+	let sample_rate = track.codec_params.sample_rate.ok_or("Unknown sample rate")?;
+	let mut decoder = symphonia::default::get_codecs()
+		.make(&track.codec_params, &DecoderOptions::default())
+		.map_err(|err| format!("Unsupported codec: {err}"))?;
+
+	let max_samples = sample_rate as usize * MAX_ANALYSIS_SECS;
+	let mut samples: Vec<f32> = Vec::new();
+	while samples.len() < max_samples {
+		let packet = match format.next_packet() {
+			Ok(packet) => packet,
+			Err(SymphoniaError::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+			Err(err) => return Err(format!("Failed to read packet: {err}")),
+		};
+		if packet.track_id() != track_id {
+			continue;
+		}
+
+		let decoded = match decoder.decode(&packet) {
+			Ok(decoded) => decoded,
+			// Corrupted frames are skipped
+			Err(SymphoniaError::DecodeError(_)) => continue,
+			Err(err) => return Err(format!("Failed to decode: {err}")),
+		};
+
+		let spec = *decoded.spec();
+		let channels = spec.channels.count().max(1);
+		let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+		buffer.copy_interleaved_ref(decoded);
+		for frame in buffer.samples().chunks(channels) {
+			samples.push(frame.iter().sum::<f32>() / channels as f32);
+		}
+	}
+
+	// Averaging blocks of samples is enough of a low pass for the features we are after
+	let factor = (sample_rate / TARGET_SAMPLE_RATE).max(1) as usize;
+	let downsampled = samples
+		.chunks(factor)
+		.map(|chunk| chunk.iter().sum::<f32>() / chunk.len() as f32)
+		.collect();
+
+	Ok((downsampled, sample_rate / factor as u32))
+}
+
+fn hann_window(size: usize) -> Vec<f32> {
+	(0..size)
+		.map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / size as f32).cos())
+		.collect()
+}
+
+// Magnitude spectrum of every frame of the signal
+fn spectrogram(samples: &[f32], frame: usize, hop: usize) -> Vec<Vec<f32>> {
+	let fft = FftPlanner::<f32>::new().plan_fft_forward(frame);
+	let window = hann_window(frame);
+
+	let mut frames = Vec::new();
+	let mut start = 0;
+	while start + frame <= samples.len() {
+		let mut buffer: Vec<Complex<f32>> = samples[start..start + frame]
+			.iter()
+			.zip(&window)
+			.map(|(sample, weight)| Complex::new(sample * weight, 0.0))
+			.collect();
+		fft.process(&mut buffer);
+		frames.push(buffer[..frame / 2].iter().map(|bin| bin.norm()).collect());
+		start += hop;
+	}
+	frames
+}
+
+// Half wave rectified spectral flux of the log compressed spectrum, with its local mean removed
+fn onset_envelope(samples: &[f32]) -> Vec<f64> {
+	let frames = spectrogram(samples, ONSET_FRAME, ONSET_HOP);
+	let mut flux: Vec<f64> = Vec::with_capacity(frames.len());
+	for pair in frames.windows(2) {
+		let value: f32 = pair[1]
+			.iter()
+			.zip(&pair[0])
+			.map(|(current, previous)| ((1.0 + 100.0 * current).ln() - (1.0 + 100.0 * previous).ln()).max(0.0))
+			.sum();
+		flux.push(value as f64);
+	}
+
+	let radius = 16;
+	(0..flux.len())
+		.map(|i| {
+			let from = i.saturating_sub(radius);
+			let to = (i + radius + 1).min(flux.len());
+			let mean = flux[from..to].iter().sum::<f64>() / (to - from) as f64;
+			(flux[i] - mean).max(0.0)
+		})
+		.collect()
+}
+
+// Returns the tempo in bpm and the pulse clarity (0.0..=1.0) of the onset envelope
+fn estimate_tempo(envelope: &[f64], frame_rate: f64) -> Option<(f64, f64)> {
+	let autocorrelation = |lag: usize| -> f64 { envelope.iter().zip(&envelope[lag..]).map(|(a, b)| a * b).sum() };
+
+	let energy = autocorrelation(0);
+	if energy <= 0.0 {
+		return None;
+	}
+
+	let min_lag = (60.0 * frame_rate / MAX_BPM).floor() as usize;
+	let max_lag = ((60.0 * frame_rate / MIN_BPM).ceil() as usize).min(envelope.len().saturating_sub(1));
+	if min_lag < 1 || min_lag + 2 > max_lag {
+		return None;
+	}
+
+	let correlations: Vec<f64> = (min_lag - 1..=max_lag + 1).map(autocorrelation).collect();
+	// Listeners tend to tap along around 120 bpm, which resolves the usual half/double tempo confusion
+	let weight = |lag: f64| {
+		let bpm = 60.0 * frame_rate / lag;
+		(-0.5 * (bpm / 120.0).log2().powi(2)).exp()
+	};
+
+	let mut best = 1;
+	for i in 1..correlations.len() - 1 {
+		let lag = (min_lag - 1 + i) as f64;
+		let best_lag = (min_lag - 1 + best) as f64;
+		if correlations[i] * weight(lag) > correlations[best] * weight(best_lag) {
+			best = i;
+		}
+	}
+
+	// Parabolic interpolation around the peak for a fractional lag
+	let (left, center, right) = (correlations[best - 1], correlations[best], correlations[best + 1]);
+	let denominator = left - 2.0 * center + right;
+	let offset = if denominator.abs() > f64::EPSILON {
+		(0.5 * (left - right) / denominator).clamp(-0.5, 0.5)
+	} else {
+		0.0
+	};
+	let lag = (min_lag - 1 + best) as f64 + offset;
+
+	let bpm = 60.0 * frame_rate / lag;
+	let clarity = (center / energy).clamp(0.0, 1.0);
+	Some((bpm, clarity))
+}
+
+fn correlation(a: &[f64; 12], b: &[f64; 12]) -> f64 {
+	let mean_a = a.iter().sum::<f64>() / 12.0;
+	let mean_b = b.iter().sum::<f64>() / 12.0;
+	let mut covariance = 0.0;
+	let mut variance_a = 0.0;
+	let mut variance_b = 0.0;
+	for i in 0..12 {
+		covariance += (a[i] - mean_a) * (b[i] - mean_b);
+		variance_a += (a[i] - mean_a).powi(2);
+		variance_b += (b[i] - mean_b).powi(2);
+	}
+	if variance_a <= 0.0 || variance_b <= 0.0 {
+		return 0.0;
+	}
+	covariance / (variance_a * variance_b).sqrt()
+}
+
+fn estimate_key(samples: &[f32], sample_rate: u32) -> Option<String> {
+	let bin_width = sample_rate as f64 / CHROMA_FRAME as f64;
+
+	let mut chroma = [0.0_f64; 12];
+	for frame in spectrogram(samples, CHROMA_FRAME, CHROMA_HOP) {
+		for (bin, magnitude) in frame.iter().enumerate().skip(1) {
+			let frequency = bin as f64 * bin_width;
+			// A1 to roughly B6, outside of it the bins are either too coarse or mostly harmonics
+			if !(55.0..=2000.0).contains(&frequency) {
+				continue;
+			}
+			let midi = 69.0 + 12.0 * (frequency / 440.0).log2();
+			let pitch_class = (midi.round() as i64).rem_euclid(12) as usize;
+			chroma[pitch_class] += *magnitude as f64;
+		}
+	}
+	if chroma.iter().all(|value| *value <= 0.0) {
+		return None;
+	}
+
+	let mut best: Option<(f64, String)> = None;
+	for tonic in 0..12 {
+		for (profile, mode) in [(&MAJOR_PROFILE, "major"), (&MINOR_PROFILE, "minor")] {
+			// Rotating the profile so that its first entry lands on the tonic
+			let mut rotated = [0.0; 12];
+			for i in 0..12 {
+				rotated[(i + tonic) % 12] = profile[i];
+			}
+			let score = correlation(&chroma, &rotated);
+			if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+				best = Some((score, format!("{} {}", PITCH_CLASSES[tonic], mode)));
+			}
+		}
+	}
+	best.map(|(_, key)| key)
+}
+
+pub fn analyze_file(path: &Path) -> Result<AudioFeatures, String> {
+	let (samples, sample_rate) = decode_mono(path)?;
+	if samples.len() < CHROMA_FRAME * 4 {
+		return Err("Track is too short to analyze".to_string());
+	}
+
+	// Mapping the rms level from -40dBFS..0dBFS to 0.0..1.0
+	let rms = (samples.iter().map(|sample| (*sample as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt();
+	if rms < 1e-4 {
+		return Err("Track is silent".to_string());
+	}
+	let energy = ((20.0 * rms.log10() + 40.0) / 40.0).clamp(0.0, 1.0);
+
+	let frame_rate = sample_rate as f64 / ONSET_HOP as f64;
+	let (bpm, clarity) = estimate_tempo(&onset_envelope(&samples), frame_rate).ok_or("No beat found")?;
+	let musical_key = estimate_key(&samples, sample_rate).ok_or("No pitch content found")?;
+
+	// Steady strong beats around 100-130 bpm are the easiest to dance to
+	let tempo_fit = (-0.5 * ((bpm / 115.0).log2() / 0.35).powi(2)).exp();
+	let danceability = (0.6 * (clarity * 2.0).min(1.0) + 0.25 * tempo_fit + 0.15 * energy).clamp(0.0, 1.0);
+
+	let round = |value: f64, digits: i32| (value * 10_f64.powi(digits)).round() / 10_f64.powi(digits);
+	Ok(AudioFeatures {
+		bpm: round(bpm, 1),
+		musical_key,
+		energy: round(energy, 3),
+		danceability: round(danceability, 3),
+	})
+}
+
+pub fn music_file_path(music_id: &str) -> PathBuf {
+	PathBuf::from(MUSIC_STORAGE).join(format!("{}.mp3", music_id))
+}
+
+// Analyzes every track that hasn't gone through the analyzer yet, returns the number of analyzed tracks
+pub fn analyze_pending(db_pool: &DatabasePool) -> Result<usize, String> {
+	let mut db_conn = db_pool.get().map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+
+	let mut analyzed = 0;
+	loop {
+		let pending = music::table
+			.filter(music::analyzed_at.is_null())
+			.select(music::music_id)
+			.limit(ANALYZER_BATCH)
+			.load::<String>(&mut db_conn)
+			.map_err(|err| format!("Failed to load pending tracks: {err}"))?;
+		if pending.is_empty() {
+			break;
+		}
+
+		for music_id in pending {
+			let target = music::table.filter(music::music_id.eq(&music_id));
+			// A malformed file can make the decoder panic, it is marked as analyzed like any other failure
+			let path = music_file_path(&music_id);
+			let features = std::panic::catch_unwind(|| analyze_file(&path))
+				.unwrap_or_else(|_| Err("Analysis panicked".to_string()));
+			let result = match features {
+				Ok(features) => {
+					analyzed += 1;
+					diesel::update(target)
+						.set((
+							music::bpm.eq(features.bpm),
+							music::musical_key.eq(features.musical_key),
+							music::energy.eq(features.energy),
+							music::danceability.eq(features.danceability),
+							music::analyzed_at.eq(Utc::now().to_rfc3339()),
+						))
+						.execute(&mut db_conn)
+				}
+				Err(err) => {
+					println!("[audio_analyzer]: {music_id}: {err}");
+					diesel::update(target)
+						.set(music::analyzed_at.eq(Utc::now().to_rfc3339()))
+						.execute(&mut db_conn)
+				}
+			};
+			result.map_err(|err| format!("Failed to store audio features: {err}"))?;
+		}
+	}
+
+	Ok(analyzed)
+}
+
+// Runs the analyzer over the pending tracks in the background.
+// If it is already running, it goes over the pending tracks once more before stopping.
+pub fn spawn_audio_analyzer(db_pool: DatabasePool) {
+	ANALYZER_DIRTY.store(true, Ordering::SeqCst);
+	if ANALYZER_RUNNING.swap(true, Ordering::SeqCst) {
+		return;
+	}
+
+	tokio::task::spawn_blocking(move || loop {
+		{
+			let _running = RunningGuard;
+			while ANALYZER_DIRTY.swap(false, Ordering::SeqCst) {
+				match analyze_pending(&db_pool) {
+					Ok(0) => (),
+					Ok(count) => println!("[audio_analyzer]: Analyzed {count} tracks"),
+					Err(err) => println!("[audio_analyzer]: {err}"),
+				}
+			}
+		}
+
+		// Tracks stored right before the flag was cleared would otherwise wait for the next upload
+		if !ANALYZER_DIRTY.load(Ordering::SeqCst) || ANALYZER_RUNNING.swap(true, Ordering::SeqCst) {
+			break;
+		}
+	});
+}
@@ -0,0 +1,265 @@
+// Blends: combined playlists made of the top tracks and likes of their members.
+//
+// The owner invites friends to a blend like to any other combined playlist, as viewers since the songs are picked
+// by the server. Every member brings up to `BLEND_TRACKS_PER_MEMBER` songs, their top tracks first and then their
+// latest likes, and the members take turns so that everyone is represented. Each song is added in the name of the
+// member it comes from. Blends are refreshed every day and whenever someone joins, the songs are replaced as a
+// whole so the refreshes are not part of the history.
+
+use crate::core::collaboration::{self, CollabError};
+use crate::core::playlist::{self, CreatePlaylistError, POSITION_GAP};
+use crate::core::playlist_room::PlaylistRoomPool;
+use crate::core::user_pool::UserPool;
+use crate::lobic_db::db::DatabasePool;
+use crate::lobic_db::models::{Playlist, PlaylistBlend, PlaylistInvitation, PlaylistSong};
+use crate::schema::{liked_songs, play_log, playlist_blends, playlist_songs, playlists, user_friendship};
+
+use chrono::{Duration, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::Double;
+use serde_json::json;
+use std::collections::HashSet;
+use std::fmt;
+use uuid::Uuid;
+
+pub const BLEND_TRACKS_PER_MEMBER: i64 = 20;
+pub const BLEND_REFRESH_HOURS: i64 = 24;
+const BLEND_CHECK_INTERVAL_SECS: u64 = 3600;
+
+#[derive(Debug)]
+pub enum BlendError {
+	NoFriends,
+	NotFriend(String),
+	ManualEdit,
+	Storage(String),
+	Collab(CollabError),
+	Database(diesel::result::Error),
+}
+
+impl fmt::Display for BlendError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			BlendError::NoFriends => write!(f, "A blend needs at least one friend"),
+			BlendError::NotFriend(user_id) => write!(f, "{user_id} is not a friend"),
+			BlendError::ManualEdit => {
+				write!(f, "The songs of a blend are picked automatically and can't be changed by hand")
+			}
+			BlendError::Storage(err) => write!(f, "{err}"),
+			BlendError::Collab(err) => write!(f, "{err}"),
+			BlendError::Database(err) => write!(f, "Database error: {err}"),
+		}
+	}
+}
+
+impl From<diesel::result::Error> for BlendError {
+	fn from(err: diesel::result::Error) -> BlendError {
+		BlendError::Database(err)
+	}
+}
+
+impl From<CollabError> for BlendError {
+	fn from(err: CollabError) -> BlendError {
+		match err {
+			CollabError::Database(err) => BlendError::Database(err),
+			err => BlendError::Collab(err),
+		}
+	}
+}
+
+impl From<CreatePlaylistError> for BlendError {
+	fn from(err: CreatePlaylistError) -> BlendError {
+		match err {
+			CreatePlaylistError::Storage(err) => BlendError::Storage(err),
+			CreatePlaylistError::Database(err) => BlendError::Database(err),
+		}
+	}
+}
+
+// Top tracks of the user, scored like /music/get_top_tracks, then their latest likes
+fn picks(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<String>> {
+	let score = sql::<Double>(
+		"play_log.user_times_played - 0.5 * (SELECT COUNT(*) FROM listens \
+		WHERE listens.user_id = play_log.user_id AND listens.music_id = play_log.music_id AND listens.skipped)",
+	);
+	let top_tracks: Vec<String> = play_log::table
+		.filter(play_log::user_id.eq(user_id))
+		.filter(play_log::user_times_played.ge(1))
+		.order((score.desc(), play_log::user_times_played.desc()))
+		.select(play_log::music_id)
+		.limit(BLEND_TRACKS_PER_MEMBER)
+		.load(db_conn)?;
+	let likes: Vec<String> = liked_songs::table
+		.filter(liked_songs::user_id.eq(user_id))
+		.order(liked_songs::song_added_date_time.desc())
+		.select(liked_songs::music_id)
+		.limit(BLEND_TRACKS_PER_MEMBER)
+		.load(db_conn)?;
+
+	let mut seen = HashSet::new();
+	Ok(top_tracks
+		.into_iter()
+		.chain(likes)
+		.filter(|music_id| seen.insert(music_id.clone()))
+		.take(BLEND_TRACKS_PER_MEMBER as usize)
+		.collect())
+}
+
+pub fn is_blend(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<bool> {
+	let count = playlist_blends::table
+		.filter(playlist_blends::playlist_id.eq(playlist_id))
+		.count()
+		.get_result::<i64>(db_conn)?;
+	Ok(count > 0)
+}
+
+// The songs of a blend are picked again on every refresh, manual changes would be lost
+pub fn ensure_not_blend(db_conn: &mut SqliteConnection, playlist_id: &str) -> Result<(), BlendError> {
+	if is_blend(db_conn, playlist_id)? {
+		return Err(BlendError::ManualEdit);
+	}
+	Ok(())
+}
+
+// Creates the blend with the songs of the owner and invites the friends, they are blended in once they accept
+pub fn create(
+	db_conn: &mut SqliteConnection,
+	owner_id: &str,
+	friend_ids: &[String],
+	playlist_name: String,
+) -> Result<(Playlist, Vec<PlaylistInvitation>), BlendError> {
+	if friend_ids.is_empty() {
+		return Err(BlendError::NoFriends);
+	}
+	let friends: HashSet<String> = user_friendship::table
+		.filter(user_friendship::user_id.eq(owner_id))
+		.select(user_friendship::friend_id)
+		.load::<String>(db_conn)?
+		.into_iter()
+		.collect();
+	if let Some(stranger) = friend_ids.iter().find(|friend_id| !friends.contains(*friend_id)) {
+		return Err(BlendError::NotFriend(stranger.clone()));
+	}
+
+	db_conn.transaction::<_, BlendError, _>(|conn| {
+		let blend = playlist::create_playlist(conn, playlist_name, owner_id.to_string(), true, &[])?;
+		diesel::insert_into(playlist_blends::table)
+			.values(&PlaylistBlend {
+				playlist_id: blend.playlist_id.clone(),
+				refreshed_at: Utc::now().to_rfc3339(),
+			})
+			.execute(conn)?;
+
+		let mut invitations = Vec::new();
+		for friend_id in friend_ids.iter().collect::<HashSet<&String>>() {
+			invitations.push(collaboration::invite(conn, &blend, owner_id, friend_id, "viewer")?);
+		}
+		refresh(conn, &blend.playlist_id)?;
+		Ok((blend, invitations))
+	})
+}
+
+// Replaces the songs of the blend
+pub fn refresh(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<()> {
+	db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+		let mut member_picks = Vec::new();
+		for member in collaboration::members(conn, playlist_id)? {
+			let member_songs = picks(conn, &member)?;
+			member_picks.push((member, member_songs));
+		}
+
+		// Taking turns, a song liked by several members goes to the first one to pick it
+		let now = Utc::now().to_rfc3339();
+		let mut seen = HashSet::new();
+		let mut entries = Vec::new();
+		for turn in 0..BLEND_TRACKS_PER_MEMBER as usize {
+			for (member, picks) in &member_picks {
+				let Some(music_id) = picks.get(turn) else {
+					continue;
+				};
+				if !seen.insert(music_id.clone()) {
+					continue;
+				}
+				entries.push(PlaylistSong {
+					item_id: Uuid::new_v4().to_string(),
+					playlist_id: playlist_id.to_string(),
+					music_id: music_id.clone(),
+					song_adder_id: member.clone(),
+					song_added_date_time: now.clone(),
+					position: POSITION_GAP * (entries.len() + 1) as f64,
+				});
+			}
+		}
+
+		diesel::delete(playlist_songs::table.filter(playlist_songs::playlist_id.eq(playlist_id))).execute(conn)?;
+		if !entries.is_empty() {
+			diesel::insert_into(playlist_songs::table)
+				.values(&entries)
+				.execute(conn)?;
+		}
+		diesel::update(playlist_blends::table.filter(playlist_blends::playlist_id.eq(playlist_id)))
+			.set(playlist_blends::refreshed_at.eq(&now))
+			.execute(conn)?;
+		playlist::touch_playlist(conn, playlist_id)?;
+		Ok(())
+	})
+}
+
+// Lets the people who have the blend open know that its songs were replaced
+pub fn publish_refresh(
+	playlist_rooms: &PlaylistRoomPool,
+	user_pool: &UserPool,
+	db_conn: &mut SqliteConnection,
+	playlist_id: &str,
+	owner_id: &str,
+) -> QueryResult<Option<u64>> {
+	let change = json!({ "kind": "blend_refresh" });
+	playlist_rooms.publish(user_pool, db_conn, playlist_id, owner_id, change)
+}
+
+fn refresh_due_blends(
+	db_pool: &DatabasePool,
+	playlist_rooms: &PlaylistRoomPool,
+	user_pool: &UserPool,
+) -> Result<usize, String> {
+	let mut db_conn = db_pool
+		.get()
+		.map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+
+	let deadline = (Utc::now() - Duration::hours(BLEND_REFRESH_HOURS)).to_rfc3339();
+	let due: Vec<(String, String)> = playlist_blends::table
+		.inner_join(playlists::table)
+		.filter(playlist_blends::refreshed_at.le(deadline))
+		.filter(playlists::deleted_at.is_null())
+		.select((playlists::playlist_id, playlists::user_id))
+		.load(&mut db_conn)
+		.map_err(|err| format!("Failed to query blends: {err}"))?;
+
+	for (playlist_id, owner_id) in &due {
+		refresh(&mut db_conn, playlist_id).map_err(|err| format!("Failed to refresh blend: {err}"))?;
+		publish_refresh(playlist_rooms, user_pool, &mut db_conn, playlist_id, owner_id)
+			.map_err(|err| format!("Failed to publish blend refresh: {err}"))?;
+	}
+	Ok(due.len())
+}
+
+pub fn spawn_blend_refresher(db_pool: DatabasePool, playlist_rooms: PlaylistRoomPool, user_pool: UserPool) {
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(std::time::Duration::from_secs(BLEND_CHECK_INTERVAL_SECS));
+		loop {
+			interval.tick().await;
+
+			let db_pool = db_pool.clone();
+			let playlist_rooms = playlist_rooms.clone();
+			let user_pool = user_pool.clone();
+			let result =
+				tokio::task::spawn_blocking(move || refresh_due_blends(&db_pool, &playlist_rooms, &user_pool)).await;
+			match result {
+				Ok(Ok(0)) => (),
+				Ok(Ok(refreshed)) => println!("[blend]: Refreshed {refreshed} blends"),
+				Ok(Err(err)) => println!("[blend]: {err}"),
+				Err(err) => println!("[blend]: Task failed: {err}"),
+			}
+		}
+	});
+}
@@ -0,0 +1,330 @@
+// Trending charts computed from the listen history over rolling windows.
+//
+// Every listen adds a weight that decays with its age (half of the weight is lost every `half_life`),
+// so a track that was hot last month slowly drops off even if its all-time play count is huge. Only completed
+// listens add weight, skips take some of it away again.
+// Global and per genre charts are rebuilt periodically by a background task, charts among the friends of
+// a user are computed on demand and cached for the same amount of time, up to `MAX_ON_DEMAND_CHARTS` of them.
+
+use crate::lobic_db::db::DatabasePool;
+use crate::lobic_db::models::{ListenSource, Music};
+use crate::schema::{listens, music, user_friendship};
+
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub const CHART_REFRESH_INTERVAL_SECS: u64 = 10 * 60;
+
+// Weight a skip takes away relative to a completed listen
+const SKIP_PENALTY: f64 = 0.5;
+
+// Upper bound on the friends charts kept in memory, past it they are computed without being cached
+const MAX_ON_DEMAND_CHARTS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChartWindow {
+	Day,
+	Week,
+	Month,
+}
+
+impl ChartWindow {
+	pub const ALL: [ChartWindow; 3] = [ChartWindow::Day, ChartWindow::Week, ChartWindow::Month];
+
+	pub fn parse(value: &str) -> Option<ChartWindow> {
+		match value {
+			"24h" | "1d" => Some(ChartWindow::Day),
+			"7d" => Some(ChartWindow::Week),
+			"30d" => Some(ChartWindow::Month),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ChartWindow::Day => "24h",
+			ChartWindow::Week => "7d",
+			ChartWindow::Month => "30d",
+		}
+	}
+
+	pub fn duration(&self) -> Duration {
+		match self {
+			ChartWindow::Day => Duration::hours(24),
+			ChartWindow::Week => Duration::days(7),
+			ChartWindow::Month => Duration::days(30),
+		}
+	}
+
+	fn half_life_secs(&self) -> f64 {
+		self.duration().num_seconds() as f64 / 3.0
+	}
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChartEntry {
+	pub music_id: String,
+	pub score: f64,
+	pub rank: usize,
+	pub previous_rank: Option<usize>, // rank over the window right before this one, None if it wasn't charting
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Chart {
+	pub window: &'static str,
+	pub generated_at: DateTime<Utc>,
+	pub entries: Vec<ChartEntry>,
+}
+
+#[derive(Debug, Default)]
+struct ChartCacheInner {
+	refreshed: HashMap<String, Chart>, // global and per genre charts, replaced as a whole by `refresh_charts`
+	on_demand: HashMap<String, Chart>, // friends charts
+}
+
+#[derive(Debug, Clone)]
+pub struct ChartCache {
+	inner: Arc<Mutex<ChartCacheInner>>,
+}
+
+fn is_fresh(chart: &Chart) -> bool {
+	(Utc::now() - chart.generated_at).num_seconds() < CHART_REFRESH_INTERVAL_SECS as i64
+}
+
+impl ChartCache {
+	pub fn new() -> ChartCache {
+		ChartCache {
+			inner: Arc::new(Mutex::new(ChartCacheInner::default())),
+		}
+	}
+
+	pub fn get(&self, key: &str) -> Option<Chart> {
+		let inner = self.inner.lock().unwrap();
+		inner.refreshed.get(key).or_else(|| inner.on_demand.get(key)).cloned()
+	}
+
+	fn replace_refreshed(&self, charts: HashMap<String, Chart>) {
+		let mut inner = self.inner.lock().unwrap();
+		inner.refreshed = charts;
+	}
+
+	fn insert_on_demand(&self, key: &str, chart: Chart) {
+		let mut inner = self.inner.lock().unwrap();
+		inner.on_demand.retain(|_, chart| is_fresh(chart));
+		if inner.on_demand.len() < MAX_ON_DEMAND_CHARTS || inner.on_demand.contains_key(key) {
+			inner.on_demand.insert(key.to_string(), chart);
+		}
+	}
+
+	// Returns the cached chart or computes it when it is missing or stale.
+	// Only friends charts are cached here, global and genre charts are cached by `refresh_charts` so that
+	// a genre that isn't in the library can't add entries.
+	pub fn get_or_compute(
+		&self,
+		window: ChartWindow,
+		genre: Option<&str>,
+		friends_of: Option<&str>,
+		db_pool: &DatabasePool,
+	) -> Result<Chart, String> {
+		let key = cache_key(window, genre, friends_of);
+		if let Some(chart) = self.get(&key).filter(is_fresh) {
+			return Ok(chart);
+		}
+
+		let mut db_conn = db_pool.get().map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+		let listeners = match friends_of {
+			Some(user_id) => Some(
+				user_friendship::table
+					.filter(user_friendship::user_id.eq(user_id))
+					.select(user_friendship::friend_id)
+					.load::<String>(&mut db_conn)
+					.map_err(|err| format!("Failed to load friends: {err}"))?,
+			),
+			None => None,
+		};
+
+		let now = Utc::now();
+		let points = load_listen_points(&mut db_conn, now - window.duration() * 2, listeners.as_deref())
+			.map_err(|err| format!("Failed to load listens: {err}"))?;
+		let chart = build_chart(&points, window, genre, now);
+		if friends_of.is_some() {
+			self.insert_on_demand(&key, chart.clone());
+		}
+		Ok(chart)
+	}
+}
+
+pub fn cache_key(window: ChartWindow, genre: Option<&str>, friends_of: Option<&str>) -> String {
+	format!(
+		"{}:{}:{}",
+		window.as_str(),
+		genre.map(|g| g.to_lowercase()).unwrap_or_default(),
+		friends_of.unwrap_or_default()
+	)
+}
+
+struct ListenPoint {
+	music_id: String,
+	genre: String,
+	listened_at: DateTime<Utc>,
+	skipped: bool,
+}
+
+fn load_listen_points(
+	db_conn: &mut SqliteConnection,
+	since: DateTime<Utc>,
+	listeners: Option<&[String]>,
+) -> QueryResult<Vec<ListenPoint>> {
+	let mut query = listens::table
+		.inner_join(music::table)
+		.filter(listens::listened_at.ge(since.to_rfc3339()))
+		.filter(listens::completed.eq(true).or(listens::skipped.eq(true)))
+		.filter(listens::source_type.ne(ListenSource::Import.as_str()))
+		.select((listens::music_id, music::genre, listens::listened_at, listens::skipped))
+		.into_boxed();
+
+	if let Some(listeners) = listeners {
+		query = query.filter(listens::user_id.eq_any(listeners));
+	}
+
+	let rows = query.load::<(String, String, String, bool)>(db_conn)?;
+	let points = rows
+		.into_iter()
+		.filter_map(|(music_id, genre, listened_at, skipped)| {
+			let listened_at = DateTime::parse_from_rfc3339(&listened_at).ok()?.with_timezone(&Utc);
+			Some(ListenPoint {
+				music_id,
+				genre,
+				listened_at,
+				skipped,
+			})
+		})
+		.collect();
+	Ok(points)
+}
+
+// Scores the listens that happened in the window ending at `end`, decaying them by their age
+fn score_window(points: &[&ListenPoint], window: ChartWindow, end: DateTime<Utc>) -> Vec<(String, f64)> {
+	let start = end - window.duration();
+	let half_life = window.half_life_secs();
+
+	let mut scores: HashMap<&str, f64> = HashMap::new();
+	for point in points {
+		if point.listened_at < start || point.listened_at > end {
+			continue;
+		}
+		let age = (end - point.listened_at).num_seconds().max(0) as f64;
+		let weight = if point.skipped { -SKIP_PENALTY } else { 1.0 };
+		*scores.entry(&point.music_id).or_insert(0.0) += weight * 0.5_f64.powf(age / half_life);
+	}
+
+	// Tracks that were skipped more than they were listened to don't chart at all
+	let mut ranked: Vec<(String, f64)> = scores
+		.into_iter()
+		.filter(|(_, score)| *score > 0.0)
+		.map(|(id, score)| (id.to_string(), score))
+		.collect();
+	// Ties are broken by id so that ranks are stable between refreshes
+	ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+	ranked
+}
+
+fn build_chart(points: &[ListenPoint], window: ChartWindow, genre: Option<&str>, now: DateTime<Utc>) -> Chart {
+	let points: Vec<&ListenPoint> = points
+		.iter()
+		.filter(|point| genre.is_none_or(|genre| point.genre.eq_ignore_ascii_case(genre)))
+		.collect();
+
+	let current = score_window(&points, window, now);
+	let previous_ranks: HashMap<String, usize> = score_window(&points, window, now - window.duration())
+		.into_iter()
+		.enumerate()
+		.map(|(idx, (music_id, _))| (music_id, idx + 1))
+		.collect();
+
+	let entries = current
+		.into_iter()
+		.enumerate()
+		.map(|(idx, (music_id, score))| ChartEntry {
+			previous_rank: previous_ranks.get(&music_id).copied(),
+			music_id,
+			score,
+			rank: idx + 1,
+		})
+		.collect();
+
+	Chart {
+		window: window.as_str(),
+		generated_at: now,
+		entries,
+	}
+}
+
+// Loads the music of the given chart entries, keeping the chart order
+pub fn load_chart_music(db_conn: &mut SqliteConnection, entries: Vec<ChartEntry>) -> QueryResult<Vec<(ChartEntry, Music)>> {
+	let ids: Vec<&String> = entries.iter().map(|entry| &entry.music_id).collect();
+	let mut musics: HashMap<String, Music> = music::table
+		.filter(music::music_id.eq_any(ids))
+		.load::<Music>(db_conn)?
+		.into_iter()
+		.map(|entry| (entry.music_id.clone(), entry))
+		.collect();
+
+	Ok(entries
+		.into_iter()
+		.filter_map(|entry| {
+			let music = musics.remove(&entry.music_id)?;
+			Some((entry, music))
+		})
+		.collect())
+}
+
+// Rebuilds the global chart and the chart of every genre for all of the windows
+pub fn refresh_charts(db_pool: &DatabasePool, cache: &ChartCache) -> Result<(), String> {
+	let mut db_conn = db_pool.get().map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+
+	let genres = music::table
+		.select(music::genre)
+		.distinct()
+		.load::<String>(&mut db_conn)
+		.map_err(|err| format!("Failed to load genres: {err}"))?;
+
+	// Loading once for the largest window (and the one before it), smaller windows are a subset of it
+	let now = Utc::now();
+	let longest = ChartWindow::Month.duration() * 2;
+	let points = load_listen_points(&mut db_conn, now - longest, None)
+		.map_err(|err| format!("Failed to load listens: {err}"))?;
+
+	let mut charts = HashMap::new();
+	for window in ChartWindow::ALL {
+		charts.insert(cache_key(window, None, None), build_chart(&points, window, None, now));
+		for genre in &genres {
+			let chart = build_chart(&points, window, Some(genre), now);
+			charts.insert(cache_key(window, Some(genre), None), chart);
+		}
+	}
+	cache.replace_refreshed(charts);
+
+	Ok(())
+}
+
+pub fn spawn_chart_refresher(db_pool: DatabasePool, cache: ChartCache) {
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(std::time::Duration::from_secs(CHART_REFRESH_INTERVAL_SECS));
+		loop {
+			interval.tick().await;
+
+			let db_pool = db_pool.clone();
+			let cache = cache.clone();
+			let result = tokio::task::spawn_blocking(move || refresh_charts(&db_pool, &cache)).await;
+			match result {
+				Ok(Ok(())) => (),
+				Ok(Err(err)) => println!("[chart_refresher]: {err}"),
+				Err(err) => println!("[chart_refresher]: Task failed: {err}"),
+			}
+		}
+	});
+}
@@ -0,0 +1,253 @@
+// Contributors of combined playlists.
+//
+// The owner invites users as editors or viewers, they only become contributors once they accept. The owner can
+// change their roles, remove them and hand the playlist over to one of them, contributors can leave on their own.
+// Every change is sent to the people it concerns through notify.
+
+use crate::config::OpCode;
+use crate::core::app_state::AppState;
+use crate::core::playlist;
+use crate::core::playlist_access::{self, Role};
+use crate::lobic_db::models::{Notification, Playlist, PlaylistInvitation, PlaylistShare};
+use crate::routes::notify::notify_from;
+use crate::schema::{playlist_follows, playlist_invitations, playlist_shares, playlists};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use serde_json::Value;
+use std::fmt;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum CollabError {
+	SoloPlaylist,
+	NotOwner,
+	InvalidRole(String),
+	AlreadyMember(String),
+	NotMember(String),
+	InvalidInvitation(String),
+	Database(diesel::result::Error),
+}
+
+impl fmt::Display for CollabError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			CollabError::SoloPlaylist => write!(f, "Cannot add contributors to a solo playlist"),
+			CollabError::NotOwner => write!(f, "Only the owner can do this"),
+			CollabError::InvalidRole(role) => write!(f, "Invalid role: {role}, expected editor or viewer"),
+			CollabError::AlreadyMember(user_id) => write!(f, "{user_id} is already part of the playlist"),
+			CollabError::NotMember(user_id) => write!(f, "{user_id} is not a contributor of the playlist"),
+			CollabError::InvalidInvitation(invitation_id) => write!(f, "Invalid invitation_id: {invitation_id}"),
+			CollabError::Database(err) => write!(f, "Database error: {err}"),
+		}
+	}
+}
+
+impl From<diesel::result::Error> for CollabError {
+	fn from(err: diesel::result::Error) -> CollabError {
+		CollabError::Database(err)
+	}
+}
+
+fn ensure_owner(playlist: &Playlist, user_id: &str) -> Result<(), CollabError> {
+	if playlist.user_id != user_id {
+		return Err(CollabError::NotOwner);
+	}
+	Ok(())
+}
+
+// Contributors can only be editors or viewers, there is one owner
+fn contributor_role(role: &str) -> Result<Role, CollabError> {
+	match Role::parse(role) {
+		Some(role) if role != Role::Owner => Ok(role),
+		_ => Err(CollabError::InvalidRole(role.to_string())),
+	}
+}
+
+pub fn invite(
+	db_conn: &mut SqliteConnection,
+	playlist: &Playlist,
+	inviter_id: &str,
+	invitee_id: &str,
+	role: &str,
+) -> Result<PlaylistInvitation, CollabError> {
+	ensure_owner(playlist, inviter_id)?;
+	if !playlist.is_playlist_combined {
+		return Err(CollabError::SoloPlaylist);
+	}
+	let role = contributor_role(role)?;
+	if playlist_access::role_of(db_conn, &playlist.playlist_id, invitee_id)?.is_some() {
+		return Err(CollabError::AlreadyMember(invitee_id.to_string()));
+	}
+
+	// Inviting again replaces the pending invitation
+	let invitation = PlaylistInvitation {
+		invitation_id: Uuid::new_v4().to_string(),
+		playlist_id: playlist.playlist_id.clone(),
+		inviter_id: inviter_id.to_string(),
+		invitee_id: invitee_id.to_string(),
+		role: role.as_str().to_string(),
+		created_at: Utc::now().to_rfc3339(),
+	};
+	diesel::replace_into(playlist_invitations::table)
+		.values(&invitation)
+		.execute(db_conn)?;
+	Ok(invitation)
+}
+
+pub fn pending_invitations(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<PlaylistInvitation>> {
+	playlist_invitations::table
+		.filter(playlist_invitations::invitee_id.eq(user_id))
+		.order(playlist_invitations::created_at.desc())
+		.load(db_conn)
+}
+
+// Accepting adds the invitee with the role of the invitation, the invitation is gone either way
+pub fn respond(
+	db_conn: &mut SqliteConnection,
+	invitation_id: &str,
+	user_id: &str,
+	accept: bool,
+) -> Result<PlaylistInvitation, CollabError> {
+	db_conn.transaction::<_, CollabError, _>(|conn| {
+		let invitation = playlist_invitations::table
+			.filter(playlist_invitations::invitation_id.eq(invitation_id))
+			.filter(playlist_invitations::invitee_id.eq(user_id))
+			.first::<PlaylistInvitation>(conn)
+			.optional()?
+			.ok_or_else(|| CollabError::InvalidInvitation(invitation_id.to_string()))?;
+		diesel::delete(playlist_invitations::table.filter(playlist_invitations::invitation_id.eq(invitation_id)))
+			.execute(conn)?;
+
+		if accept {
+			diesel::insert_or_ignore_into(playlist_shares::table)
+				.values(&PlaylistShare {
+					playlist_id: invitation.playlist_id.clone(),
+					contributor_user_id: invitation.invitee_id.clone(),
+					role: invitation.role.clone(),
+				})
+				.execute(conn)?;
+			// The playlist is in their library as a contributor now
+			diesel::delete(
+				playlist_follows::table
+					.filter(playlist_follows::playlist_id.eq(&invitation.playlist_id))
+					.filter(playlist_follows::user_id.eq(user_id)),
+			)
+			.execute(conn)?;
+			playlist::touch_playlist(conn, &invitation.playlist_id)?;
+		}
+		Ok(invitation)
+	})
+}
+
+pub fn set_role(
+	db_conn: &mut SqliteConnection,
+	playlist: &Playlist,
+	user_id: &str,
+	contributor_user_id: &str,
+	role: &str,
+) -> Result<Role, CollabError> {
+	ensure_owner(playlist, user_id)?;
+	let role = contributor_role(role)?;
+	let updated = diesel::update(
+		playlist_shares::table
+			.filter(playlist_shares::playlist_id.eq(&playlist.playlist_id))
+			.filter(playlist_shares::contributor_user_id.eq(contributor_user_id)),
+	)
+	.set(playlist_shares::role.eq(role.as_str()))
+	.execute(db_conn)?;
+	if updated == 0 {
+		return Err(CollabError::NotMember(contributor_user_id.to_string()));
+	}
+	playlist::touch_playlist(db_conn, &playlist.playlist_id)?;
+	Ok(role)
+}
+
+// The owner can remove anyone, contributors can only remove themselves
+pub fn remove_contributor(
+	db_conn: &mut SqliteConnection,
+	playlist: &Playlist,
+	user_id: &str,
+	contributor_user_id: &str,
+) -> Result<(), CollabError> {
+	if user_id != contributor_user_id {
+		ensure_owner(playlist, user_id)?;
+	}
+	let removed = diesel::delete(
+		playlist_shares::table
+			.filter(playlist_shares::playlist_id.eq(&playlist.playlist_id))
+			.filter(playlist_shares::contributor_user_id.eq(contributor_user_id)),
+	)
+	.execute(db_conn)?;
+	if removed == 0 {
+		return Err(CollabError::NotMember(contributor_user_id.to_string()));
+	}
+	playlist::touch_playlist(db_conn, &playlist.playlist_id)?;
+	Ok(())
+}
+
+// The new owner has to be a contributor already, the previous owner stays on as an editor
+pub fn transfer_ownership(
+	db_conn: &mut SqliteConnection,
+	playlist: &Playlist,
+	user_id: &str,
+	new_owner_id: &str,
+) -> Result<(), CollabError> {
+	ensure_owner(playlist, user_id)?;
+	db_conn.transaction::<_, CollabError, _>(|conn| {
+		let removed = diesel::delete(
+			playlist_shares::table
+				.filter(playlist_shares::playlist_id.eq(&playlist.playlist_id))
+				.filter(playlist_shares::contributor_user_id.eq(new_owner_id)),
+		)
+		.execute(conn)?;
+		if removed == 0 {
+			return Err(CollabError::NotMember(new_owner_id.to_string()));
+		}
+		diesel::insert_into(playlist_shares::table)
+			.values(&PlaylistShare {
+				playlist_id: playlist.playlist_id.clone(),
+				contributor_user_id: user_id.to_string(),
+				role: Role::Editor.as_str().to_string(),
+			})
+			.execute(conn)?;
+		diesel::update(playlists::table.filter(playlists::playlist_id.eq(&playlist.playlist_id)))
+			.set(playlists::user_id.eq(new_owner_id))
+			.execute(conn)?;
+		playlist::touch_playlist(conn, &playlist.playlist_id)?;
+		Ok(())
+	})
+}
+
+// Owner and contributors
+pub fn members(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<Vec<String>> {
+	let owner_id = playlists::table
+		.filter(playlists::playlist_id.eq(playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.select(playlists::user_id)
+		.first::<String>(db_conn)?;
+	let mut members: Vec<String> = playlist_shares::table
+		.filter(playlist_shares::playlist_id.eq(playlist_id))
+		.select(playlist_shares::contributor_user_id)
+		.load(db_conn)?;
+	members.insert(0, owner_id);
+	Ok(members)
+}
+
+// Sends the notification to everyone in the playlist except whoever made the change
+pub fn notify_members(
+	app_state: &AppState,
+	db_conn: &mut SqliteConnection,
+	playlist_id: &str,
+	actor_id: &str,
+	op_code: OpCode,
+	value: Value,
+) -> QueryResult<()> {
+	for member in members(db_conn, playlist_id)? {
+		if member != actor_id {
+			let notif = Notification::new(op_code.clone(), value.clone());
+			notify_from(actor_id, &member, notif, &app_state.db_pool, &app_state.user_pool);
+		}
+	}
+	Ok(())
+}
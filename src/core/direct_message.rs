@@ -0,0 +1,548 @@
+// Direct messages between friends.
+//
+// A conversation is either one to one, reused whenever the same two users talk again, or a small named group
+// started by one user with some of their friends. Messages are kept in the database and paged back latest first.
+// Each member has a read receipt, the time they last read the conversation, which gives the unread counts. New
+// messages go to the members who are online over the socket and are left as notifications for the others. A message
+// can embed a track or a playlist, shown as a share card made for whoever reads it: a playlist they can't see only
+// shows up as unavailable.
+//
+// Users who blocked one another don't see each other's messages in the groups they share, and can't message each
+// other one to one since blocking ends the friendship.
+
+use crate::config::{OpCode, SocketResponse};
+use crate::core::app_state::AppState;
+use crate::core::friendship;
+use crate::core::playlist_access;
+use crate::lobic_db::models::{Conversation, ConversationMember, DirectMessage, Music, Notification, Playlist};
+use crate::routes::notify::notify;
+use crate::schema::{conversation_members, conversations, direct_messages, music, playlists};
+
+use axum::extract::ws::Message;
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fmt;
+use uuid::Uuid;
+
+// Counting the user who starts it
+pub const MAX_GROUP_MEMBERS: usize = 10;
+pub const MAX_MESSAGE_LENGTH: usize = 2000;
+
+#[derive(Debug)]
+pub enum DmError {
+	NoMembers,
+	TooManyMembers,
+	NotFriend(String),
+	NotMember(String),
+	EmptyMessage,
+	MessageTooLong,
+	InvalidAttachment(String),
+	Database(diesel::result::Error),
+}
+
+impl fmt::Display for DmError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			DmError::NoMembers => write!(f, "A conversation needs someone else in it"),
+			DmError::TooManyMembers => write!(f, "A conversation can have at most {MAX_GROUP_MEMBERS} members"),
+			DmError::NotFriend(user_id) => write!(f, "{user_id} is not a friend"),
+			DmError::NotMember(conversation_id) => write!(f, "Not a member of conversation {conversation_id}"),
+			DmError::EmptyMessage => write!(f, "The message can't be empty"),
+			DmError::MessageTooLong => write!(f, "A message can be at most {MAX_MESSAGE_LENGTH} characters"),
+			DmError::InvalidAttachment(msg) => write!(f, "Invalid attachment: {msg}"),
+			DmError::Database(err) => write!(f, "Database error: {err}"),
+		}
+	}
+}
+
+impl From<diesel::result::Error> for DmError {
+	fn from(err: diesel::result::Error) -> DmError {
+		DmError::Database(err)
+	}
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentKind {
+	Track,
+	Playlist,
+}
+
+impl AttachmentKind {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			AttachmentKind::Track => "track",
+			AttachmentKind::Playlist => "playlist",
+		}
+	}
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Attachment {
+	pub kind: AttachmentKind,
+	pub id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageEntry {
+	pub message_id: String,
+	pub conversation_id: String,
+	pub sender_id: String,
+	pub body: String,
+	pub card: Option<Value>,
+	pub sent_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberEntry {
+	pub user_id: String,
+	pub last_read_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationEntry {
+	pub conversation_id: String,
+	pub is_group: bool,
+	pub name: Option<String>,
+	pub created_by: String,
+	pub created_at: String,
+	pub last_message_at: String,
+	pub members: Vec<MemberEntry>,
+	pub last_message: Option<MessageEntry>,
+	pub unread_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadReceipt {
+	pub conversation_id: String,
+	pub user_id: String,
+	pub last_read_at: String,
+}
+
+pub fn members(db_conn: &mut SqliteConnection, conversation_id: &str) -> QueryResult<Vec<ConversationMember>> {
+	conversation_members::table
+		.filter(conversation_members::conversation_id.eq(conversation_id))
+		.order(conversation_members::joined_at.asc())
+		.load(db_conn)
+}
+
+fn membership(
+	db_conn: &mut SqliteConnection,
+	conversation_id: &str,
+	user_id: &str,
+) -> Result<ConversationMember, DmError> {
+	conversation_members::table
+		.filter(conversation_members::conversation_id.eq(conversation_id))
+		.filter(conversation_members::user_id.eq(user_id))
+		.first::<ConversationMember>(db_conn)
+		.optional()?
+		.ok_or_else(|| DmError::NotMember(conversation_id.to_string()))
+}
+
+// The one to one conversation between the two, if they already have one
+fn direct_between(db_conn: &mut SqliteConnection, user_id: &str, other_id: &str) -> QueryResult<Option<Conversation>> {
+	let (theirs, mine) = diesel::alias!(conversation_members as theirs, conversation_members as mine);
+	let with_other = theirs
+		.filter(theirs.field(conversation_members::user_id).eq(other_id))
+		.select(theirs.field(conversation_members::conversation_id));
+	let with_user = mine
+		.filter(mine.field(conversation_members::user_id).eq(user_id))
+		.select(mine.field(conversation_members::conversation_id));
+	conversations::table
+		.filter(conversations::is_group.eq(false))
+		.filter(conversations::conversation_id.eq_any(with_user))
+		.filter(conversations::conversation_id.eq_any(with_other))
+		.first::<Conversation>(db_conn)
+		.optional()
+}
+
+// Starting a one to one conversation that already exists gives it back, the bool says whether it's new
+pub fn start(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	member_ids: &[String],
+	name: Option<String>,
+) -> Result<(Conversation, bool), DmError> {
+	let mut others: Vec<&String> = Vec::new();
+	for member_id in member_ids {
+		if member_id != user_id && !others.contains(&member_id) {
+			others.push(member_id);
+		}
+	}
+	if others.is_empty() {
+		return Err(DmError::NoMembers);
+	}
+	if others.len() + 1 > MAX_GROUP_MEMBERS {
+		return Err(DmError::TooManyMembers);
+	}
+
+	db_conn.transaction::<_, DmError, _>(|conn| {
+		for member_id in &others {
+			if !friendship::are_friends(conn, user_id, member_id)? {
+				return Err(DmError::NotFriend(member_id.to_string()));
+			}
+		}
+
+		let is_group = others.len() > 1;
+		if !is_group {
+			if let Some(conversation) = direct_between(conn, user_id, others[0])? {
+				return Ok((conversation, false));
+			}
+		}
+
+		let now = Utc::now().to_rfc3339();
+		let conversation = Conversation {
+			conversation_id: Uuid::new_v4().to_string(),
+			is_group,
+			name: name.filter(|_| is_group),
+			created_by: user_id.to_string(),
+			created_at: now.clone(),
+			last_message_at: now.clone(),
+		};
+		diesel::insert_into(conversations::table)
+			.values(&conversation)
+			.execute(conn)?;
+		let rows: Vec<ConversationMember> = std::iter::once(user_id)
+			.chain(others.iter().map(|member_id| member_id.as_str()))
+			.map(|member_id| ConversationMember {
+				conversation_id: conversation.conversation_id.clone(),
+				user_id: member_id.to_string(),
+				joined_at: now.clone(),
+				last_read_at: None,
+			})
+			.collect();
+		diesel::insert_into(conversation_members::table)
+			.values(&rows)
+			.execute(conn)?;
+		Ok((conversation, true))
+	})
+}
+
+fn check_attachment(db_conn: &mut SqliteConnection, sender_id: &str, attachment: &Attachment) -> Result<(), DmError> {
+	match attachment.kind {
+		AttachmentKind::Track => {
+			let count = music::table
+				.filter(music::music_id.eq(&attachment.id))
+				.count()
+				.get_result::<i64>(db_conn)?;
+			if count == 0 {
+				return Err(DmError::InvalidAttachment(format!("no track {}", attachment.id)));
+			}
+		}
+		AttachmentKind::Playlist => {
+			let playlist = playlists::table
+				.filter(playlists::playlist_id.eq(&attachment.id))
+				.filter(playlists::deleted_at.is_null())
+				.first::<Playlist>(db_conn)
+				.optional()?;
+			let readable = match playlist {
+				Some(playlist) => playlist_access::can_read(db_conn, &playlist, Some(sender_id), None)?,
+				None => false,
+			};
+			if !readable {
+				return Err(DmError::InvalidAttachment(format!("no playlist {}", attachment.id)));
+			}
+		}
+	}
+	Ok(())
+}
+
+// Sending a message also reads the conversation up to it
+pub fn send(
+	db_conn: &mut SqliteConnection,
+	sender_id: &str,
+	conversation_id: &str,
+	body: &str,
+	attachment: Option<Attachment>,
+) -> Result<DirectMessage, DmError> {
+	let body = body.trim();
+	if body.is_empty() && attachment.is_none() {
+		return Err(DmError::EmptyMessage);
+	}
+	if body.chars().count() > MAX_MESSAGE_LENGTH {
+		return Err(DmError::MessageTooLong);
+	}
+
+	db_conn.transaction::<_, DmError, _>(|conn| {
+		membership(conn, conversation_id, sender_id)?;
+		let conversation = conversations::table
+			.filter(conversations::conversation_id.eq(conversation_id))
+			.first::<Conversation>(conn)?;
+		if !conversation.is_group {
+			for member in members(conn, conversation_id)? {
+				if member.user_id != sender_id && !friendship::are_friends(conn, sender_id, &member.user_id)? {
+					return Err(DmError::NotFriend(member.user_id));
+				}
+			}
+		}
+		if let Some(attachment) = &attachment {
+			check_attachment(conn, sender_id, attachment)?;
+		}
+
+		let message = DirectMessage {
+			message_id: Uuid::new_v4().to_string(),
+			conversation_id: conversation_id.to_string(),
+			sender_id: sender_id.to_string(),
+			body: body.to_string(),
+			attachment_kind: attachment.as_ref().map(|attachment| attachment.kind.as_str().to_string()),
+			attachment_id: attachment.map(|attachment| attachment.id),
+			sent_at: Utc::now().to_rfc3339(),
+		};
+		diesel::insert_into(direct_messages::table)
+			.values(&message)
+			.execute(conn)?;
+		diesel::update(conversations::table.filter(conversations::conversation_id.eq(conversation_id)))
+			.set(conversations::last_message_at.eq(&message.sent_at))
+			.execute(conn)?;
+		diesel::update(
+			conversation_members::table
+				.filter(conversation_members::conversation_id.eq(conversation_id))
+				.filter(conversation_members::user_id.eq(sender_id)),
+		)
+		.set(conversation_members::last_read_at.eq(&message.sent_at))
+		.execute(conn)?;
+		Ok(message)
+	})
+}
+
+// The share card of the attachment as the viewer gets to see it
+fn card(db_conn: &mut SqliteConnection, viewer_id: &str, kind: &str, id: &str) -> QueryResult<Value> {
+	if kind == AttachmentKind::Track.as_str() {
+		let entry = music::table
+			.filter(music::music_id.eq(id))
+			.first::<Music>(db_conn)
+			.optional()?;
+		return Ok(match entry {
+			Some(entry) => json!({
+				"kind": kind,
+				"id": id,
+				"available": true,
+				"music": Music::create_music_response(entry),
+			}),
+			None => json!({ "kind": kind, "id": id, "available": false }),
+		});
+	}
+
+	let playlist = playlists::table
+		.filter(playlists::playlist_id.eq(id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(db_conn)
+		.optional()?;
+	let playlist = match playlist {
+		Some(playlist) if playlist_access::can_read(db_conn, &playlist, Some(viewer_id), None)? => playlist,
+		_ => return Ok(json!({ "kind": kind, "id": id, "available": false })),
+	};
+	Ok(json!({
+		"kind": kind,
+		"id": id,
+		"available": true,
+		"playlist_name": playlist.playlist_name,
+		"user_id": playlist.user_id,
+		"description": playlist.description,
+		"is_playlist_combined": playlist.is_playlist_combined,
+	}))
+}
+
+fn entry_for(db_conn: &mut SqliteConnection, viewer_id: &str, message: DirectMessage) -> QueryResult<MessageEntry> {
+	let card = match (&message.attachment_kind, &message.attachment_id) {
+		(Some(kind), Some(id)) => Some(card(db_conn, viewer_id, kind, id)?),
+		_ => None,
+	};
+	Ok(MessageEntry {
+		message_id: message.message_id,
+		conversation_id: message.conversation_id,
+		sender_id: message.sender_id,
+		body: message.body,
+		card,
+		sent_at: message.sent_at,
+	})
+}
+
+// Messages of the conversation latest first, without those from users the viewer blocked or was blocked by
+pub fn history(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	conversation_id: &str,
+	start_index: i64,
+	page_length: Option<i64>,
+) -> Result<Vec<MessageEntry>, DmError> {
+	membership(db_conn, conversation_id, user_id)?;
+	let hidden: Vec<String> = friendship::hidden_from(db_conn, user_id)?.into_iter().collect();
+	let messages = direct_messages::table
+		.filter(direct_messages::conversation_id.eq(conversation_id))
+		.filter(direct_messages::sender_id.ne_all(hidden))
+		.order(direct_messages::sent_at.desc())
+		.offset(start_index)
+		.limit(page_length.filter(|length| *length > 0).unwrap_or(-1)) // sqlite treats a negative limit as none
+		.load::<DirectMessage>(db_conn)?;
+	let mut entries = Vec::with_capacity(messages.len());
+	for message in messages {
+		entries.push(entry_for(db_conn, user_id, message)?);
+	}
+	Ok(entries)
+}
+
+fn unread_in(
+	db_conn: &mut SqliteConnection,
+	member: &ConversationMember,
+	hidden: &HashSet<String>,
+) -> QueryResult<i64> {
+	let mut query = direct_messages::table
+		.filter(direct_messages::conversation_id.eq(&member.conversation_id))
+		.filter(direct_messages::sender_id.ne(&member.user_id))
+		.filter(direct_messages::sender_id.ne_all(hidden.iter().cloned().collect::<Vec<_>>()))
+		.into_boxed();
+	if let Some(last_read_at) = &member.last_read_at {
+		query = query.filter(direct_messages::sent_at.gt(last_read_at.clone()));
+	}
+	query.count().get_result(db_conn)
+}
+
+// Marks everything sent so far as read
+pub fn mark_read(db_conn: &mut SqliteConnection, user_id: &str, conversation_id: &str) -> Result<ReadReceipt, DmError> {
+	membership(db_conn, conversation_id, user_id)?;
+	let now = Utc::now().to_rfc3339();
+	diesel::update(
+		conversation_members::table
+			.filter(conversation_members::conversation_id.eq(conversation_id))
+			.filter(conversation_members::user_id.eq(user_id)),
+	)
+	.set(conversation_members::last_read_at.eq(&now))
+	.execute(db_conn)?;
+	Ok(ReadReceipt {
+		conversation_id: conversation_id.to_string(),
+		user_id: user_id.to_string(),
+		last_read_at: now,
+	})
+}
+
+// The conversations of the user, the one with the latest message first
+pub fn conversations_of(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<ConversationEntry>> {
+	let hidden = friendship::hidden_from(db_conn, user_id)?;
+	let joined = conversation_members::table
+		.filter(conversation_members::user_id.eq(user_id))
+		.load::<ConversationMember>(db_conn)?;
+	let mut entries = Vec::with_capacity(joined.len());
+	for member in joined {
+		let conversation = conversations::table
+			.filter(conversations::conversation_id.eq(&member.conversation_id))
+			.first::<Conversation>(db_conn)?;
+		let last_message = direct_messages::table
+			.filter(direct_messages::conversation_id.eq(&member.conversation_id))
+			.filter(direct_messages::sender_id.ne_all(hidden.iter().cloned().collect::<Vec<_>>()))
+			.order(direct_messages::sent_at.desc())
+			.first::<DirectMessage>(db_conn)
+			.optional()?;
+		let last_message = match last_message {
+			Some(message) => Some(entry_for(db_conn, user_id, message)?),
+			None => None,
+		};
+		let unread_count = unread_in(db_conn, &member, &hidden)?;
+		let members = members(db_conn, &member.conversation_id)?
+			.into_iter()
+			.map(|member| MemberEntry {
+				user_id: member.user_id,
+				last_read_at: member.last_read_at,
+			})
+			.collect();
+		entries.push(ConversationEntry {
+			conversation_id: conversation.conversation_id,
+			is_group: conversation.is_group,
+			name: conversation.name,
+			created_by: conversation.created_by,
+			created_at: conversation.created_at,
+			last_message_at: conversation.last_message_at,
+			members,
+			last_message,
+			unread_count,
+		});
+	}
+	entries.sort_by(|a, b| b.last_message_at.cmp(&a.last_message_at));
+	Ok(entries)
+}
+
+// Unread messages of the user in all their conversations
+pub fn unread_total(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<i64> {
+	let hidden = friendship::hidden_from(db_conn, user_id)?;
+	let joined = conversation_members::table
+		.filter(conversation_members::user_id.eq(user_id))
+		.load::<ConversationMember>(db_conn)?;
+	let mut total = 0;
+	for member in joined {
+		total += unread_in(db_conn, &member, &hidden)?;
+	}
+	Ok(total)
+}
+
+// The other members who haven't blocked the user nor been blocked by them
+fn recipients(db_conn: &mut SqliteConnection, conversation_id: &str, user_id: &str) -> QueryResult<Vec<String>> {
+	let hidden = friendship::hidden_from(db_conn, user_id)?;
+	Ok(members(db_conn, conversation_id)?
+		.into_iter()
+		.map(|member| member.user_id)
+		.filter(|member_id| member_id != user_id && !hidden.contains(member_id))
+		.collect())
+}
+
+// Sends the message to the members who are online and leaves it as a notification for the others. The message is
+// already stored, so errors are only logged
+pub fn deliver(app_state: &AppState, db_conn: &mut SqliteConnection, message: &DirectMessage) {
+	let recipients = match recipients(db_conn, &message.conversation_id, &message.sender_id) {
+		Ok(recipients) => recipients,
+		Err(err) => {
+			println!("Error {}:{}: Failed to query conversation members: {err}", file!(), line!());
+			return;
+		}
+	};
+	for recipient_id in recipients {
+		let entry = match entry_for(db_conn, &recipient_id, message.clone()) {
+			Ok(entry) => entry,
+			Err(err) => {
+				println!("Error {}:{}: Failed to load message attachment: {err}", file!(), line!());
+				continue;
+			}
+		};
+		let value = serde_json::to_value(entry).unwrap();
+		match app_state.user_pool.get(&recipient_id) {
+			Some(conn) => {
+				let response = SocketResponse {
+					op_code: OpCode::OK,
+					r#for: OpCode::DIRECT_MESSAGE,
+					value,
+				};
+				let _ = conn.send(Message::Text(response.to_string()));
+			}
+			None => notify(
+				&recipient_id,
+				Notification::new(OpCode::DIRECT_MESSAGE, value),
+				&app_state.db_pool,
+				&app_state.user_pool,
+			),
+		}
+	}
+}
+
+// Lets the other members who are online know how far the user has read
+pub fn broadcast_read(app_state: &AppState, db_conn: &mut SqliteConnection, receipt: &ReadReceipt) {
+	let recipients = match recipients(db_conn, &receipt.conversation_id, &receipt.user_id) {
+		Ok(recipients) => recipients,
+		Err(err) => {
+			println!("Error {}:{}: Failed to query conversation members: {err}", file!(), line!());
+			return;
+		}
+	};
+	let response = SocketResponse {
+		op_code: OpCode::OK,
+		r#for: OpCode::DIRECT_MESSAGE_READ,
+		value: serde_json::to_value(receipt.clone()).unwrap(),
+	}
+	.to_string();
+	for recipient_id in recipients {
+		if let Some(conn) = app_state.user_pool.get(&recipient_id) {
+			let _ = conn.send(Message::Text(response.clone()));
+		}
+	}
+}
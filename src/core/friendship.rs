@@ -0,0 +1,297 @@
+// Friends and blocked users.
+//
+// Friendships are mutual: a user sends a friend request, which stays pending until the other user accepts or
+// declines it or the sender cancels it. Accepting adds the friendship both ways. Blocking a user ends the friendship
+// and any pending request between the two, and hides them from each other in search, lobbies and notifications,
+// whichever of the two blocked the other.
+
+use crate::lobic_db::models::{FriendRequest, UserBlock, UserFriendship};
+use crate::schema::{friend_requests, user_blocks, user_friendship};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use std::collections::HashSet;
+use std::fmt;
+use uuid::Uuid;
+
+pub const PENDING: &str = "pending";
+pub const ACCEPTED: &str = "accepted";
+pub const DECLINED: &str = "declined";
+pub const CANCELLED: &str = "cancelled";
+
+#[derive(Debug)]
+pub enum FriendError {
+	SelfRequest,
+	AlreadyFriends(String),
+	AlreadyRequested(String),
+	Blocked(String),
+	NotFriend(String),
+	InvalidRequest(String),
+	Database(diesel::result::Error),
+}
+
+impl fmt::Display for FriendError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			FriendError::SelfRequest => write!(f, "Can't befriend or block yourself"),
+			FriendError::AlreadyFriends(user_id) => write!(f, "{user_id} is already a friend"),
+			FriendError::AlreadyRequested(user_id) => write!(f, "A friend request to {user_id} is already pending"),
+			// Doesn't tell who blocked who
+			FriendError::Blocked(user_id) => write!(f, "Can't send a friend request to {user_id}"),
+			FriendError::NotFriend(user_id) => write!(f, "{user_id} is not a friend"),
+			FriendError::InvalidRequest(request_id) => write!(f, "Invalid request_id: {request_id}"),
+			FriendError::Database(err) => write!(f, "Database error: {err}"),
+		}
+	}
+}
+
+impl From<diesel::result::Error> for FriendError {
+	fn from(err: diesel::result::Error) -> FriendError {
+		FriendError::Database(err)
+	}
+}
+
+pub fn friends_of(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<String>> {
+	user_friendship::table
+		.filter(user_friendship::user_id.eq(user_id))
+		.select(user_friendship::friend_id)
+		.load(db_conn)
+}
+
+pub fn are_friends(db_conn: &mut SqliteConnection, user_id: &str, other_id: &str) -> QueryResult<bool> {
+	let count = user_friendship::table
+		.filter(user_friendship::user_id.eq(user_id))
+		.filter(user_friendship::friend_id.eq(other_id))
+		.count()
+		.get_result::<i64>(db_conn)?;
+	Ok(count > 0)
+}
+
+// The users the user blocked and the users who blocked them
+pub fn hidden_from(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<HashSet<String>> {
+	let blocked: Vec<String> = user_blocks::table
+		.filter(user_blocks::user_id.eq(user_id))
+		.select(user_blocks::blocked_id)
+		.load(db_conn)?;
+	let blocked_by: Vec<String> = user_blocks::table
+		.filter(user_blocks::blocked_id.eq(user_id))
+		.select(user_blocks::user_id)
+		.load(db_conn)?;
+	Ok(blocked.into_iter().chain(blocked_by).collect())
+}
+
+// Whether either of the two blocked the other
+pub fn is_blocked(db_conn: &mut SqliteConnection, user_id: &str, other_id: &str) -> QueryResult<bool> {
+	let count = user_blocks::table
+		.filter(
+			user_blocks::user_id
+				.eq(user_id)
+				.and(user_blocks::blocked_id.eq(other_id))
+				.or(user_blocks::user_id.eq(other_id).and(user_blocks::blocked_id.eq(user_id))),
+		)
+		.count()
+		.get_result::<i64>(db_conn)?;
+	Ok(count > 0)
+}
+
+fn pending_between(
+	db_conn: &mut SqliteConnection,
+	sender_id: &str,
+	receiver_id: &str,
+) -> QueryResult<Option<FriendRequest>> {
+	friend_requests::table
+		.filter(friend_requests::sender_id.eq(sender_id))
+		.filter(friend_requests::receiver_id.eq(receiver_id))
+		.filter(friend_requests::status.eq(PENDING))
+		.first::<FriendRequest>(db_conn)
+		.optional()
+}
+
+fn close(db_conn: &mut SqliteConnection, request: &mut FriendRequest, status: &str) -> QueryResult<()> {
+	let now = Utc::now().to_rfc3339();
+	diesel::update(friend_requests::table.filter(friend_requests::request_id.eq(&request.request_id)))
+		.set((
+			friend_requests::status.eq(status),
+			friend_requests::responded_at.eq(&now),
+		))
+		.execute(db_conn)?;
+	request.status = status.to_string();
+	request.responded_at = Some(now);
+	Ok(())
+}
+
+fn befriend(db_conn: &mut SqliteConnection, user_id: &str, other_id: &str) -> QueryResult<()> {
+	diesel::insert_or_ignore_into(user_friendship::table)
+		.values(&vec![
+			UserFriendship {
+				user_id: user_id.to_string(),
+				friend_id: other_id.to_string(),
+			},
+			UserFriendship {
+				user_id: other_id.to_string(),
+				friend_id: user_id.to_string(),
+			},
+		])
+		.execute(db_conn)?;
+	Ok(())
+}
+
+// Sending a request to someone who already sent one accepts theirs, the returned request says which happened
+pub fn send_request(
+	db_conn: &mut SqliteConnection,
+	sender_id: &str,
+	receiver_id: &str,
+) -> Result<FriendRequest, FriendError> {
+	if sender_id == receiver_id {
+		return Err(FriendError::SelfRequest);
+	}
+	db_conn.transaction::<_, FriendError, _>(|conn| {
+		if is_blocked(conn, sender_id, receiver_id)? {
+			return Err(FriendError::Blocked(receiver_id.to_string()));
+		}
+		if are_friends(conn, sender_id, receiver_id)? {
+			return Err(FriendError::AlreadyFriends(receiver_id.to_string()));
+		}
+		if pending_between(conn, sender_id, receiver_id)?.is_some() {
+			return Err(FriendError::AlreadyRequested(receiver_id.to_string()));
+		}
+		if let Some(mut request) = pending_between(conn, receiver_id, sender_id)? {
+			close(conn, &mut request, ACCEPTED)?;
+			befriend(conn, sender_id, receiver_id)?;
+			return Ok(request);
+		}
+
+		let request = FriendRequest {
+			request_id: Uuid::new_v4().to_string(),
+			sender_id: sender_id.to_string(),
+			receiver_id: receiver_id.to_string(),
+			status: PENDING.to_string(),
+			created_at: Utc::now().to_rfc3339(),
+			responded_at: None,
+		};
+		diesel::insert_into(friend_requests::table)
+			.values(&request)
+			.execute(conn)?;
+		Ok(request)
+	})
+}
+
+// Only the receiver of a pending request can respond to it
+pub fn respond(
+	db_conn: &mut SqliteConnection,
+	request_id: &str,
+	user_id: &str,
+	accept: bool,
+) -> Result<FriendRequest, FriendError> {
+	db_conn.transaction::<_, FriendError, _>(|conn| {
+		let mut request = friend_requests::table
+			.filter(friend_requests::request_id.eq(request_id))
+			.filter(friend_requests::receiver_id.eq(user_id))
+			.filter(friend_requests::status.eq(PENDING))
+			.first::<FriendRequest>(conn)
+			.optional()?
+			.ok_or_else(|| FriendError::InvalidRequest(request_id.to_string()))?;
+
+		if accept {
+			close(conn, &mut request, ACCEPTED)?;
+			befriend(conn, &request.sender_id, &request.receiver_id)?;
+		} else {
+			close(conn, &mut request, DECLINED)?;
+		}
+		Ok(request)
+	})
+}
+
+// Only the sender of a pending request can cancel it
+pub fn cancel(db_conn: &mut SqliteConnection, request_id: &str, user_id: &str) -> Result<FriendRequest, FriendError> {
+	db_conn.transaction::<_, FriendError, _>(|conn| {
+		let mut request = friend_requests::table
+			.filter(friend_requests::request_id.eq(request_id))
+			.filter(friend_requests::sender_id.eq(user_id))
+			.filter(friend_requests::status.eq(PENDING))
+			.first::<FriendRequest>(conn)
+			.optional()?
+			.ok_or_else(|| FriendError::InvalidRequest(request_id.to_string()))?;
+		close(conn, &mut request, CANCELLED)?;
+		Ok(request)
+	})
+}
+
+// Pending requests sent to and by the user, latest first
+pub fn pending_requests(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+) -> QueryResult<(Vec<FriendRequest>, Vec<FriendRequest>)> {
+	let incoming = friend_requests::table
+		.filter(friend_requests::receiver_id.eq(user_id))
+		.filter(friend_requests::status.eq(PENDING))
+		.order(friend_requests::created_at.desc())
+		.load(db_conn)?;
+	let outgoing = friend_requests::table
+		.filter(friend_requests::sender_id.eq(user_id))
+		.filter(friend_requests::status.eq(PENDING))
+		.order(friend_requests::created_at.desc())
+		.load(db_conn)?;
+	Ok((incoming, outgoing))
+}
+
+// Removes the friendship both ways
+pub fn unfriend(db_conn: &mut SqliteConnection, user_id: &str, friend_id: &str) -> Result<(), FriendError> {
+	let removed = diesel::delete(
+		user_friendship::table.filter(
+			user_friendship::user_id
+				.eq(user_id)
+				.and(user_friendship::friend_id.eq(friend_id))
+				.or(user_friendship::user_id.eq(friend_id).and(user_friendship::friend_id.eq(user_id))),
+		),
+	)
+	.execute(db_conn)?;
+	if removed == 0 {
+		return Err(FriendError::NotFriend(friend_id.to_string()));
+	}
+	Ok(())
+}
+
+// Blocking again is a no-op
+pub fn block(db_conn: &mut SqliteConnection, user_id: &str, blocked_id: &str) -> Result<(), FriendError> {
+	if user_id == blocked_id {
+		return Err(FriendError::SelfRequest);
+	}
+	db_conn.transaction::<_, FriendError, _>(|conn| {
+		diesel::insert_or_ignore_into(user_blocks::table)
+			.values(&UserBlock {
+				user_id: user_id.to_string(),
+				blocked_id: blocked_id.to_string(),
+				created_at: Utc::now().to_rfc3339(),
+			})
+			.execute(conn)?;
+		match unfriend(conn, user_id, blocked_id) {
+			Ok(()) | Err(FriendError::NotFriend(_)) => (),
+			Err(err) => return Err(err),
+		}
+		for (sender_id, receiver_id) in [(user_id, blocked_id), (blocked_id, user_id)] {
+			if let Some(mut request) = pending_between(conn, sender_id, receiver_id)? {
+				close(conn, &mut request, CANCELLED)?;
+			}
+		}
+		Ok(())
+	})
+}
+
+// Whether the user was blocked
+pub fn unblock(db_conn: &mut SqliteConnection, user_id: &str, blocked_id: &str) -> QueryResult<bool> {
+	let removed = diesel::delete(
+		user_blocks::table
+			.filter(user_blocks::user_id.eq(user_id))
+			.filter(user_blocks::blocked_id.eq(blocked_id)),
+	)
+	.execute(db_conn)?;
+	Ok(removed > 0)
+}
+
+pub fn blocked_users(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<UserBlock>> {
+	user_blocks::table
+		.filter(user_blocks::user_id.eq(user_id))
+		.order(user_blocks::created_at.desc())
+		.load(db_conn)
+}
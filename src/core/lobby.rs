@@ -1,7 +1,10 @@
 use crate::config::{MusicState, OpCode, SocketResponse};
+use crate::core::friendship;
+use crate::core::radio::{RadioStation, RADIO_WINDOW_LENGTH};
+use crate::core::recommender::Recommender;
 use crate::core::user_pool::UserPool;
 use crate::lobic_db::db::*;
-use crate::lobic_db::models::Notification;
+use crate::lobic_db::models::{self, Notification};
 use crate::routes::notify::notify;
 use crate::utils::timestamp;
 use crate::lobic_db::models::UserFriendship;
@@ -58,6 +61,17 @@ impl From<Music> for Value {
 	}
 }
 
+// Topping up the queue with radio tracks once it gets shorter than this
+pub const AUTO_RADIO_MIN_QUEUE: usize = 2;
+pub const AUTO_RADIO_BATCH: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoRadio {
+	pub station: RadioStation,
+	pub position: usize, // index of the next station track to be queued
+	pub window: Vec<String>, // last tracks queued from the station, the next ones are generated after them
+}
+
 #[derive(Debug, Clone)]
 pub struct Lobby {
 	pub id: String,
@@ -67,6 +81,7 @@ pub struct Lobby {
 	pub music: Music,
 	pub queue: Vec<Music>,
 	pub requested_musics: HashMap<String, Music>,
+	pub auto_radio: Option<AutoRadio>,
 }
 
 #[derive(Debug, Clone)]
@@ -92,11 +107,14 @@ impl LobbyPool {
 	}
 
 	// Retrives the ids of lobby in which host is there friend
-	pub fn get_ids_with_rel(&self, user_id: String, db_pool: &DatabasePool) -> Vec<String> {
-		let mut db_conn = db_pool.get().unwrap();
+	pub fn get_ids_with_rel(&self, user_id: String, db_pool: &DatabasePool) -> Result<Vec<String>, String> {
+		let mut db_conn = db_pool.get().map_err(|err| format!("Failed to get DB from pool: {err}"))?;
 
 		let mut lobby_ids: Vec<String> = Vec::new();
 
+		// Lobbies with someone the user blocked or was blocked by are hidden
+		let hidden = friendship::hidden_from(&mut db_conn, &user_id).map_err(|err| err.to_string())?;
+
 		let inner = self.inner.lock().unwrap();
 		for (lobby_id, lobby) in inner.clone().into_iter() {
 			let host_id = lobby.host_id;
@@ -105,7 +123,7 @@ impl LobbyPool {
 			let friendships = user_friendship::table
 				.filter(user_friendship::user_id.eq(&host_id))
 				.load::<UserFriendship>(&mut db_conn)
-				.unwrap();
+				.map_err(|err| err.to_string())?;
 
 			// Collecting all the friends ids
 			let friends: Vec<String> = friendships
@@ -113,12 +131,12 @@ impl LobbyPool {
 				.map(|f| f.friend_id.clone())
 				.collect();
 
-			if friends.contains(&user_id) {
+			if friends.contains(&user_id) && !lobby.clients.iter().any(|client| hidden.contains(client)) {
 				lobby_ids.push(lobby_id);
 			}
 		}
 
-		return lobby_ids;
+		Ok(lobby_ids)
 	}
 
 	pub fn get(&self, key: &str) -> Option<Lobby> {
@@ -159,6 +177,7 @@ impl LobbyPool {
 			music: Music::new(),
 			queue: Vec::new(),
 			requested_musics: HashMap::new(),
+			auto_radio: None,
 		};
 		self.insert(&lobby_id, lobby);
 
@@ -194,6 +213,13 @@ impl LobbyPool {
 			return Err(format!("Client: {} is already in lobby: {}", client_id, lobby_id));
 		}
 
+		// Users blocked either way can't be in the same lobby
+		let mut db_conn = db_pool.get().map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+		let hidden = friendship::hidden_from(&mut db_conn, client_id).map_err(|err| err.to_string())?;
+		if lobby.clients.iter().any(|client| hidden.contains(client)) {
+			return Err(format!("Can't join lobby: {}", lobby_id));
+		}
+
 		// Adding the client
 		lobby.clients.push(client_id.to_string());
 
@@ -348,6 +374,110 @@ impl LobbyPool {
 		Ok(())
 	}
 
+	pub fn set_auto_radio(&self, lobby_id: &str, user_id: &str, station: Option<RadioStation>) -> Result<(), String> {
+		let mut inner = self.inner.lock().unwrap();
+		let lobby = match inner.get_mut(lobby_id) {
+			Some(lobby) => lobby,
+			None => {
+				return Err(format!("Invalid lobby id: {}", lobby_id));
+			}
+		};
+
+		if lobby.host_id != user_id {
+			return Err(format!("User {} is not the host of lobby {}", user_id, lobby_id));
+		}
+		lobby.auto_radio = station.map(|station| AutoRadio {
+			station,
+			position: 0,
+			window: Vec::new(),
+		});
+		Ok(())
+	}
+
+	// Appends the next radio tracks to the queue when auto radio is on and the queue is about to run out.
+	// Returns true if the queue was changed (and synced to the clients).
+	pub fn top_up_radio(
+		&self,
+		lobby_id: &str,
+		db_pool: &DatabasePool,
+		recommender: &Recommender,
+		user_pool: &UserPool,
+	) -> Result<bool, String> {
+		let lobby = match self.get(lobby_id) {
+			Some(lobby) => lobby,
+			None => {
+				return Err(format!("Invalid lobby id: {}", lobby_id));
+			}
+		};
+
+		let auto_radio = match lobby.auto_radio {
+			Some(auto_radio) if lobby.queue.len() < AUTO_RADIO_MIN_QUEUE => auto_radio,
+			_ => return Ok(false),
+		};
+
+		// Station is personalized for the host
+		let mut db_conn = db_pool.get().map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+		let tracks = auto_radio
+			.station
+			.generate(
+				&mut db_conn,
+				recommender,
+				Some(&lobby.host_id),
+				&auto_radio.window,
+				auto_radio.position,
+				AUTO_RADIO_BATCH,
+			)
+			.map_err(|err| format!("Failed to generate radio: {err}"))?;
+
+		// Lobby might have changed while the tracks were being generated
+		let mut inner = self.inner.lock().unwrap();
+		let lobby = match inner.get_mut(lobby_id) {
+			Some(lobby) => lobby,
+			None => {
+				return Err(format!("Invalid lobby id: {}", lobby_id));
+			}
+		};
+		match &mut lobby.auto_radio {
+			Some(current) if current.station.session == auto_radio.station.session => {
+				current.position = auto_radio.position + tracks.len();
+				current.window.extend(tracks.iter().map(|track| track.music_id.clone()));
+				let overflow = current.window.len().saturating_sub(RADIO_WINDOW_LENGTH);
+				current.window.drain(..overflow);
+			}
+			_ => return Ok(false),
+		}
+
+		for track in tracks {
+			if track.music_id == lobby.music.id || lobby.queue.iter().any(|queued| queued.id == track.music_id) {
+				continue;
+			}
+			let track = models::Music::create_music_response(track);
+			lobby.queue.push(Music {
+				id: track.id,
+				title: track.title,
+				artist: track.artist,
+				image_url: track.image_url,
+				timestamp: 0.0,
+				state: MusicState::PAUSE,
+			});
+		}
+
+		// Everyone including the host has to pick up the queue changed by the server
+		for client in &lobby.clients {
+			if let Some(conn) = user_pool.get(client) {
+				let response = SocketResponse {
+					op_code: OpCode::OK,
+					r#for: OpCode::SYNC_QUEUE,
+					value: lobby.queue.clone().into(),
+				}
+				.to_string();
+				let _ = conn.send(Message::Text(response));
+			}
+		}
+
+		Ok(true)
+	}
+
 	pub fn add_requested_music(
 		&self,
 		lobby_id: &str,
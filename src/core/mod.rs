@@ -1,6 +1,32 @@
+pub mod activity;
 pub mod app_state;
+pub mod audio_analysis;
+pub mod blend;
+pub mod charts;
+pub mod collaboration;
+pub mod direct_message;
+pub mod friendship;
 pub mod lobby;
 pub mod migrations;
+pub mod playback;
+pub mod playlist;
+pub mod playlist_access;
+pub mod playlist_cover;
+pub mod playlist_file;
+pub mod playlist_fork;
+pub mod playlist_history;
+pub mod playlist_library;
+pub mod playlist_room;
+pub mod profile;
+pub mod radio;
+pub mod recommender;
+pub mod report;
 pub mod routes;
+pub mod scrobble;
+pub mod search_index;
+pub mod search_query;
 pub mod server;
+pub mod share;
+pub mod smart_playlist;
+pub mod stats;
 pub mod user_pool;
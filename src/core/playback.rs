@@ -0,0 +1,273 @@
+// Playback tracking, the server decides when a listen counts as a play.
+//
+// Clients report the progress of a playback (start, position heartbeats, end or skip) and the time actually
+// listened is accumulated from the position changes, capped by the wall clock so that seeking forward doesn't
+// count. A listen is completed once it reaches the completion threshold of the track, skips before that are
+// recorded as skips. Playbacks that stop reporting are closed by a background sweep.
+
+use crate::lobic_db::db::DatabasePool;
+use crate::lobic_db::models::{Listen, ListenSource, PlayLog};
+use crate::schema::{listens, music, play_log};
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+// Half of the track counts as a play, long tracks count after 4 minutes
+const COMPLETION_RATIO: f64 = 0.5;
+const MAX_COMPLETION_SECS: i64 = 4 * 60;
+
+// Playbacks without any event for this long are closed with what was listened so far
+pub const PLAYBACK_TIMEOUT_SECS: i64 = 2 * 60;
+pub const PLAYBACK_SWEEP_INTERVAL_SECS: u64 = 60;
+
+// Position changes may run slightly ahead of the wall clock due to buffering and network delays
+const CLOCK_TOLERANCE_SECS: i64 = 5;
+
+pub fn completion_threshold(duration: i64) -> i64 {
+	((duration as f64 * COMPLETION_RATIO).round() as i64).clamp(1, MAX_COMPLETION_SECS)
+}
+
+#[derive(Debug)]
+pub struct NewListen {
+	pub user_id: String,
+	pub music_id: String,
+	pub listened_at: String,
+	pub listened_duration: i64,
+	pub source: ListenSource,
+	pub source_id: Option<String>,
+	pub skip_requested: bool,
+}
+
+// Appends the listen to the history, completed listens also count towards the play counts.
+// A skip after the threshold still counts as a play and isn't recorded as a skip.
+pub fn record_listen(db_conn: &mut SqliteConnection, new_listen: NewListen) -> QueryResult<Listen> {
+	let music_duration = music::table
+		.filter(music::music_id.eq(&new_listen.music_id))
+		.select(music::duration)
+		.first::<i64>(db_conn)?;
+
+	let listened_duration = new_listen.listened_duration.max(0);
+	let completed = listened_duration >= completion_threshold(music_duration);
+	let listen = Listen {
+		listen_id: Uuid::new_v4().to_string(),
+		user_id: new_listen.user_id,
+		music_id: new_listen.music_id,
+		listened_at: new_listen.listened_at,
+		listened_duration,
+		source_type: new_listen.source.as_str().to_string(),
+		source_id: new_listen.source_id,
+		skipped: new_listen.skip_requested && !completed,
+		completed,
+	};
+	diesel::insert_into(listens::table).values(&listen).execute(db_conn)?;
+
+	if !completed {
+		return Ok(listen);
+	}
+
+	let played_at = Utc::now().to_rfc3339();
+	let new_play_log = PlayLog {
+		user_id: listen.user_id.clone(),
+		music_id: listen.music_id.clone(),
+		music_played_date_time: played_at.clone(),
+		user_times_played: 1,
+	};
+
+	// Update the play log summary
+	diesel::insert_into(play_log::table)
+		.values(&new_play_log)
+		.on_conflict((play_log::user_id, play_log::music_id))
+		.do_update()
+		.set((
+			play_log::music_played_date_time.eq(played_at),
+			play_log::user_times_played.eq(play_log::user_times_played + 1),
+		))
+		.execute(db_conn)?;
+
+	// Update global play count
+	diesel::update(music::table)
+		.filter(music::music_id.eq(&listen.music_id))
+		.set(music::times_played.eq(music::times_played + 1))
+		.execute(db_conn)?;
+
+	Ok(listen)
+}
+
+#[derive(Debug, Clone)]
+pub struct Playback {
+	pub user_id: String,
+	pub music_id: String,
+	pub source: ListenSource,
+	pub source_id: Option<String>,
+	pub duration: i64,
+	pub started_at: DateTime<Utc>,
+	pub position: i64,
+	pub listened: i64,
+	pub last_event_at: DateTime<Utc>,
+}
+
+impl Playback {
+	// Accumulates the progress since the last event, seeking backwards only moves the position
+	fn advance(&mut self, position: i64, now: DateTime<Utc>) {
+		let elapsed = (now - self.last_event_at).num_seconds().max(0);
+		let progress = position - self.position;
+		if progress > 0 {
+			self.listened += progress.min(elapsed + CLOCK_TOLERANCE_SECS);
+		}
+		// A track on repeat within the same playback counts once
+		self.listened = self.listened.min(self.duration);
+		self.position = position.max(0);
+		self.last_event_at = now;
+	}
+
+	pub fn threshold(&self) -> i64 {
+		completion_threshold(self.duration)
+	}
+
+	fn into_listen(self, skip_requested: bool) -> NewListen {
+		NewListen {
+			user_id: self.user_id,
+			music_id: self.music_id,
+			listened_at: self.started_at.to_rfc3339(),
+			listened_duration: self.listened,
+			source: self.source,
+			source_id: self.source_id,
+			skip_requested,
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlaybackProgress {
+	pub playback_id: String,
+	pub position: i64,
+	pub listened: i64,
+	pub threshold: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaybackTracker {
+	inner: Arc<Mutex<HashMap<String, Playback>>>,
+}
+
+impl PlaybackTracker {
+	pub fn new() -> PlaybackTracker {
+		PlaybackTracker {
+			inner: Arc::new(Mutex::new(HashMap::new())),
+		}
+	}
+
+	// Registers a new playback, the previous playbacks of the user are returned so they can be recorded
+	pub fn start(&self, playback: Playback) -> (PlaybackProgress, Vec<Playback>) {
+		let mut inner = self.inner.lock().unwrap();
+		let previous_ids: Vec<String> = inner
+			.iter()
+			.filter(|(_, previous)| previous.user_id == playback.user_id)
+			.map(|(playback_id, _)| playback_id.clone())
+			.collect();
+		let previous = previous_ids
+			.iter()
+			.filter_map(|playback_id| inner.remove(playback_id))
+			.collect();
+
+		let playback_id = Uuid::new_v4().to_string();
+		let progress = PlaybackProgress {
+			playback_id: playback_id.clone(),
+			position: playback.position,
+			listened: playback.listened,
+			threshold: playback.threshold(),
+		};
+		inner.insert(playback_id, playback);
+		(progress, previous)
+	}
+
+	// Updates the position of a playback of the user, None if there is no such playback
+	pub fn heartbeat(&self, playback_id: &str, user_id: &str, position: i64) -> Option<PlaybackProgress> {
+		let mut inner = self.inner.lock().unwrap();
+		let playback = inner
+			.get_mut(playback_id)
+			.filter(|playback| playback.user_id == user_id)?;
+		playback.advance(position, Utc::now());
+		Some(PlaybackProgress {
+			playback_id: playback_id.to_string(),
+			position: playback.position,
+			listened: playback.listened,
+			threshold: playback.threshold(),
+		})
+	}
+
+	// Closes a playback of the user, the returned listen still has to be recorded
+	pub fn finish(&self, playback_id: &str, user_id: &str, position: i64, skip_requested: bool) -> Option<NewListen> {
+		let mut inner = self.inner.lock().unwrap();
+		if inner
+			.get(playback_id)
+			.is_none_or(|playback| playback.user_id != user_id)
+		{
+			return None;
+		}
+		let mut playback = inner.remove(playback_id)?;
+		playback.advance(position, Utc::now());
+		Some(playback.into_listen(skip_requested))
+	}
+
+	// Removes the playbacks that stopped reporting
+	fn take_expired(&self, now: DateTime<Utc>) -> Vec<Playback> {
+		let mut inner = self.inner.lock().unwrap();
+		let expired_ids: Vec<String> = inner
+			.iter()
+			.filter(|(_, playback)| (now - playback.last_event_at).num_seconds() > PLAYBACK_TIMEOUT_SECS)
+			.map(|(playback_id, _)| playback_id.clone())
+			.collect();
+		expired_ids
+			.iter()
+			.filter_map(|playback_id| inner.remove(playback_id))
+			.collect()
+	}
+}
+
+// Records playbacks that were abandoned (a new one was started or they timed out), they were never skipped.
+// Every listen is written in its own transaction along with its play counts.
+pub fn record_abandoned(db_conn: &mut SqliteConnection, playbacks: Vec<Playback>) -> QueryResult<()> {
+	for playback in playbacks {
+		let recorded = db_conn.transaction(|conn| record_listen(conn, playback.into_listen(false)));
+		match recorded {
+			// The track may have been deleted in the meantime
+			Ok(_) | Err(diesel::result::Error::NotFound) => (),
+			Err(err) => return Err(err),
+		}
+	}
+	Ok(())
+}
+
+fn sweep_playbacks(db_pool: &DatabasePool, tracker: &PlaybackTracker) -> Result<(), String> {
+	let expired = tracker.take_expired(Utc::now());
+	if expired.is_empty() {
+		return Ok(());
+	}
+	let mut db_conn = db_pool
+		.get()
+		.map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+	record_abandoned(&mut db_conn, expired).map_err(|err| format!("Failed to record listens: {err}"))
+}
+
+pub fn spawn_playback_sweeper(db_pool: DatabasePool, tracker: PlaybackTracker) {
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(std::time::Duration::from_secs(PLAYBACK_SWEEP_INTERVAL_SECS));
+		loop {
+			interval.tick().await;
+
+			let db_pool = db_pool.clone();
+			let tracker = tracker.clone();
+			let result = tokio::task::spawn_blocking(move || sweep_playbacks(&db_pool, &tracker)).await;
+			match result {
+				Ok(Ok(())) => (),
+				Ok(Err(err)) => println!("[playback]: {err}"),
+				Err(err) => println!("[playback]: Task failed: {err}"),
+			}
+		}
+	});
+}
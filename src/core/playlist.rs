@@ -0,0 +1,262 @@
+// Ordering of the songs in a playlist.
+//
+// Every entry has a fractional position, new songs are appended `POSITION_GAP` after the last one and moved
+// entries take positions between their new neighbours, so a move only rewrites the moved rows. Once the
+// neighbours get too close to fit the moved entries the whole playlist is renumbered.
+
+use crate::config::PLAYLIST_COVER_IMG_STORAGE;
+use crate::core::playlist_access::Visibility;
+use crate::core::smart_playlist::{self, EvaluateError, SmartRules};
+use crate::lobic_db::models::{Music, Playlist, PlaylistSong};
+use crate::schema::{music, playlist_songs, playlists};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+pub const POSITION_GAP: f64 = 1024.0;
+// Below this the midpoints start losing precision
+const MIN_POSITION_GAP: f64 = 1e-6;
+
+#[derive(Debug)]
+pub enum CreatePlaylistError {
+	Storage(String),
+	Database(diesel::result::Error),
+}
+
+// Creates an empty playlist, the cover image is left out when empty
+pub fn create_playlist(
+	db_conn: &mut SqliteConnection,
+	playlist_name: String,
+	user_id: String,
+	is_playlist_combined: bool,
+	cover_img: &[u8],
+) -> Result<Playlist, CreatePlaylistError> {
+	let curr_playlist_id = Uuid::new_v4(); //now a user can create a playlist with the same name
+	let curr_creation_date_time = Utc::now().to_rfc3339();
+	let new_playlist = Playlist {
+		playlist_id: curr_playlist_id.to_string(),
+		playlist_name,
+		user_id,
+		creation_date_time: curr_creation_date_time.clone(),
+		last_updated_date_time: curr_creation_date_time,
+		is_playlist_combined,
+		smart_rules: None,
+		visibility: Visibility::Private.as_str().to_string(),
+		deleted_at: None,
+		description: String::new(),
+	};
+
+	//save the image inside the storage
+	let storage_path = Path::new(PLAYLIST_COVER_IMG_STORAGE);
+	fs::create_dir_all(storage_path)
+		.map_err(|err| CreatePlaylistError::Storage(format!("Failed to create directory: {}", err)))?;
+	if !cover_img.is_empty() {
+		let image_path = storage_path.join(format!("{}.png", curr_playlist_id));
+		fs::write(&image_path, cover_img)
+			.map_err(|err| CreatePlaylistError::Storage(format!("Failed to save image: {}", err)))?;
+	}
+
+	diesel::insert_into(playlists::table)
+		.values(&new_playlist)
+		.execute(db_conn)
+		.map_err(CreatePlaylistError::Database)?;
+	Ok(new_playlist)
+}
+
+// Every change to a playlist goes through here so that it shows up as recently updated
+pub fn touch_playlist(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<usize> {
+	diesel::update(playlists::table.filter(playlists::playlist_id.eq(playlist_id)))
+		.set(playlists::last_updated_date_time.eq(Utc::now().to_rfc3339()))
+		.execute(db_conn)
+}
+
+pub fn is_smart(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<bool> {
+	playlists::table
+		.filter(playlists::playlist_id.eq(playlist_id))
+		.filter(playlists::smart_rules.is_not_null())
+		.count()
+		.get_result::<i64>(db_conn)
+		.map(|count| count > 0)
+}
+
+// Position right after the last song of the playlist
+pub fn next_position(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<f64> {
+	let last = playlist_songs::table
+		.filter(playlist_songs::playlist_id.eq(playlist_id))
+		.select(diesel::dsl::max(playlist_songs::position))
+		.first::<Option<f64>>(db_conn)?;
+	Ok(last.unwrap_or(0.0) + POSITION_GAP)
+}
+
+// Appends the songs at the end of the playlist in the given order
+pub fn append_songs(
+	db_conn: &mut SqliteConnection,
+	playlist_id: &str,
+	music_ids: &[String],
+	song_adder_id: &str,
+) -> QueryResult<usize> {
+	Ok(append_entries(db_conn, playlist_id, music_ids, song_adder_id)?.len())
+}
+
+// Same as append_songs, returning the new entries
+pub fn append_entries(
+	db_conn: &mut SqliteConnection,
+	playlist_id: &str,
+	music_ids: &[String],
+	song_adder_id: &str,
+) -> QueryResult<Vec<PlaylistSong>> {
+	let first_position = next_position(db_conn, playlist_id)?;
+	let song_added_date_time = Utc::now().to_rfc3339();
+	let entries: Vec<PlaylistSong> = music_ids
+		.iter()
+		.enumerate()
+		.map(|(idx, music_id)| PlaylistSong {
+			item_id: Uuid::new_v4().to_string(),
+			playlist_id: playlist_id.to_string(),
+			music_id: music_id.clone(),
+			song_adder_id: song_adder_id.to_string(),
+			song_added_date_time: song_added_date_time.clone(),
+			position: first_position + POSITION_GAP * idx as f64,
+		})
+		.collect();
+	diesel::insert_into(playlist_songs::table)
+		.values(&entries)
+		.execute(db_conn)?;
+	touch_playlist(db_conn, playlist_id)?;
+	Ok(entries)
+}
+
+// Songs of the playlist in order, smart playlists are evaluated
+pub fn load_songs(db_conn: &mut SqliteConnection, playlist: &Playlist) -> Result<Vec<Music>, EvaluateError> {
+	if let Some(rules) = &playlist.smart_rules {
+		let rules = SmartRules::from_json(rules).map_err(EvaluateError::InvalidRules)?;
+		return smart_playlist::evaluate(db_conn, &playlist.user_id, &rules);
+	}
+	Ok(playlist_songs::table
+		.filter(playlist_songs::playlist_id.eq(&playlist.playlist_id))
+		.inner_join(music::table)
+		.order((playlist_songs::position.asc(), playlist_songs::item_id.asc()))
+		.select(Music::as_select())
+		.load(db_conn)?)
+}
+
+// Item ids of the playlist in order
+pub fn load_order(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<Vec<(String, f64)>> {
+	playlist_songs::table
+		.filter(playlist_songs::playlist_id.eq(playlist_id))
+		.select((playlist_songs::item_id, playlist_songs::position))
+		.order((playlist_songs::position.asc(), playlist_songs::item_id.asc()))
+		.load(db_conn)
+}
+
+#[derive(Debug)]
+pub enum MoveTarget {
+	Start,
+	End,
+	Before(String),
+	After(String),
+}
+
+#[derive(Debug)]
+pub enum MoveError {
+	InvalidItem(String),
+	InvalidAnchor(String),
+	Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for MoveError {
+	fn from(err: diesel::result::Error) -> MoveError {
+		MoveError::Database(err)
+	}
+}
+
+// Moves the items as one block to the target, keeping the order they were given in
+pub fn move_items(
+	db_conn: &mut SqliteConnection,
+	playlist_id: &str,
+	item_ids: &[String],
+	target: &MoveTarget,
+) -> Result<(), MoveError> {
+	db_conn.transaction::<_, MoveError, _>(|conn| {
+		let order = load_order(conn, playlist_id)?;
+		let known: HashSet<&str> = order.iter().map(|(item_id, _)| item_id.as_str()).collect();
+
+		let mut moved: HashSet<&str> = HashSet::new();
+		for item_id in item_ids {
+			if !known.contains(item_id.as_str()) || !moved.insert(item_id) {
+				return Err(MoveError::InvalidItem(item_id.clone()));
+			}
+		}
+
+		// The rest of the playlist, and the slot in it where the block goes
+		let rest: Vec<&(String, f64)> = order
+			.iter()
+			.filter(|(item_id, _)| !moved.contains(item_id.as_str()))
+			.collect();
+		let slot = match target {
+			MoveTarget::Start => 0,
+			MoveTarget::End => rest.len(),
+			MoveTarget::Before(anchor) | MoveTarget::After(anchor) => {
+				let idx = rest
+					.iter()
+					.position(|(item_id, _)| item_id == anchor)
+					.ok_or_else(|| MoveError::InvalidAnchor(anchor.clone()))?;
+				if matches!(target, MoveTarget::After(_)) {
+					idx + 1
+				} else {
+					idx
+				}
+			}
+		};
+
+		let lower = slot.checked_sub(1).map(|idx| rest[idx].1);
+		let upper = rest.get(slot).map(|(_, position)| *position);
+		let count = item_ids.len() as f64;
+		let positions: Option<Vec<f64>> = match (lower, upper) {
+			(Some(lower), Some(upper)) => {
+				let step = (upper - lower) / (count + 1.0);
+				(step >= MIN_POSITION_GAP).then(|| (1..=item_ids.len()).map(|idx| lower + step * idx as f64).collect())
+			}
+			(Some(lower), None) => Some(
+				(1..=item_ids.len())
+					.map(|idx| lower + POSITION_GAP * idx as f64)
+					.collect(),
+			),
+			(None, Some(upper)) => Some(
+				(0..item_ids.len())
+					.map(|idx| upper - POSITION_GAP * (count - idx as f64))
+					.collect(),
+			),
+			(None, None) => Some((1..=item_ids.len()).map(|idx| POSITION_GAP * idx as f64).collect()),
+		};
+
+		match positions {
+			Some(positions) => {
+				for (item_id, position) in item_ids.iter().zip(positions) {
+					set_position(conn, item_id, position)?;
+				}
+			}
+			// No room left between the neighbours, spreading the whole playlist out again
+			None => {
+				let mut new_order: Vec<&str> = rest.iter().map(|(item_id, _)| item_id.as_str()).collect();
+				new_order.splice(slot..slot, item_ids.iter().map(String::as_str));
+				for (idx, item_id) in new_order.iter().enumerate() {
+					set_position(conn, item_id, POSITION_GAP * (idx + 1) as f64)?;
+				}
+			}
+		}
+
+		touch_playlist(conn, playlist_id)?;
+		Ok(())
+	})
+}
+
+fn set_position(db_conn: &mut SqliteConnection, item_id: &str, position: f64) -> QueryResult<usize> {
+	diesel::update(playlist_songs::table.filter(playlist_songs::item_id.eq(item_id)))
+		.set(playlist_songs::position.eq(position))
+		.execute(db_conn)
+}
@@ -0,0 +1,306 @@
+// Who can see and change a playlist.
+//
+// The owner can do everything, editors can change the songs and the cover and viewers can only read. Contributors
+// (editors and viewers) join combined playlists through invitations, see core::collaboration.
+//
+// The owner and the contributors of a playlist can always see it, otherwise it depends on its visibility:
+//     private:  nobody else
+//     friends:  the users the owner has added as friends
+//     unlisted: anyone with its id, it doesn't show up in search or on the owner's profile
+//     public:   anyone, and other users can follow it into their library
+// A share token gives read access to anyone holding it whatever the visibility, until the owner revokes it.
+
+use crate::config::OpCode;
+use crate::core::app_state::AppState;
+use crate::lobic_db::models::{Notification, Playlist, PlaylistFollow, PlaylistShareToken};
+use crate::routes::notify::notify_from;
+use crate::schema::{playlist_follows, playlist_share_tokens, playlist_shares, playlists, user_friendship};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use serde_json::json;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visibility {
+	Private,
+	Friends,
+	Unlisted,
+	Public,
+}
+
+impl Visibility {
+	pub fn parse(value: &str) -> Option<Visibility> {
+		match value.to_lowercase().as_str() {
+			"private" => Some(Visibility::Private),
+			"friends" => Some(Visibility::Friends),
+			"unlisted" => Some(Visibility::Unlisted),
+			"public" => Some(Visibility::Public),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Visibility::Private => "private",
+			Visibility::Friends => "friends",
+			Visibility::Unlisted => "unlisted",
+			Visibility::Public => "public",
+		}
+	}
+
+	// Unknown values are treated as the most restrictive
+	pub fn of(playlist: &Playlist) -> Visibility {
+		Visibility::parse(&playlist.visibility).unwrap_or(Visibility::Private)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+	Viewer,
+	Editor,
+	Owner,
+}
+
+impl Role {
+	pub fn parse(value: &str) -> Option<Role> {
+		match value.to_lowercase().as_str() {
+			"viewer" => Some(Role::Viewer),
+			"editor" => Some(Role::Editor),
+			"owner" => Some(Role::Owner),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Role::Viewer => "viewer",
+			Role::Editor => "editor",
+			Role::Owner => "owner",
+		}
+	}
+}
+
+// None when the user isn't part of the playlist, NotFound when the playlist doesn't exist
+pub fn role_of(db_conn: &mut SqliteConnection, playlist_id: &str, user_id: &str) -> QueryResult<Option<Role>> {
+	let owner_id = playlists::table
+		.filter(playlists::playlist_id.eq(playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.select(playlists::user_id)
+		.first::<String>(db_conn)?;
+	if owner_id == user_id {
+		return Ok(Some(Role::Owner));
+	}
+	let role = playlist_shares::table
+		.filter(playlist_shares::playlist_id.eq(playlist_id))
+		.filter(playlist_shares::contributor_user_id.eq(user_id))
+		.select(playlist_shares::role)
+		.first::<String>(db_conn)
+		.optional()?;
+	// Unknown roles are treated as the most restrictive
+	Ok(role.map(|role| Role::parse(&role).unwrap_or(Role::Viewer)))
+}
+
+// Whether the user has at least that role
+pub fn has_role(db_conn: &mut SqliteConnection, playlist_id: &str, user_id: &str, role: Role) -> QueryResult<bool> {
+	Ok(role_of(db_conn, playlist_id, user_id)?.is_some_and(|user_role| user_role >= role))
+}
+
+// Owner or contributor
+pub fn is_member(db_conn: &mut SqliteConnection, playlist: &Playlist, user_id: &str) -> QueryResult<bool> {
+	if playlist.user_id == user_id {
+		return Ok(true);
+	}
+	playlist_shares::table
+		.filter(playlist_shares::playlist_id.eq(&playlist.playlist_id))
+		.filter(playlist_shares::contributor_user_id.eq(user_id))
+		.count()
+		.get_result::<i64>(db_conn)
+		.map(|count| count > 0)
+}
+
+// Owners that have added the user as a friend
+fn friend_of(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<String>> {
+	user_friendship::table
+		.filter(user_friendship::friend_id.eq(user_id))
+		.select(user_friendship::user_id)
+		.load(db_conn)
+}
+
+pub fn can_read(
+	db_conn: &mut SqliteConnection,
+	playlist: &Playlist,
+	viewer_id: Option<&str>,
+	share_token: Option<&str>,
+) -> QueryResult<bool> {
+	if let Some(token) = share_token {
+		let valid = playlist_share_tokens::table
+			.filter(playlist_share_tokens::token.eq(token))
+			.filter(playlist_share_tokens::playlist_id.eq(&playlist.playlist_id))
+			.count()
+			.get_result::<i64>(db_conn)?;
+		if valid > 0 {
+			return Ok(true);
+		}
+	}
+
+	let visibility = Visibility::of(playlist);
+	if matches!(visibility, Visibility::Public | Visibility::Unlisted) {
+		return Ok(true);
+	}
+	let Some(viewer_id) = viewer_id else {
+		return Ok(false);
+	};
+	if is_member(db_conn, playlist, viewer_id)? {
+		return Ok(true);
+	}
+	Ok(visibility == Visibility::Friends && friend_of(db_conn, viewer_id)?.contains(&playlist.user_id))
+}
+
+// Restricts the query to the playlists listed for the viewer in search and on profiles, without a viewer only
+// the public ones
+pub fn filter_listed<'a>(
+	db_conn: &mut SqliteConnection,
+	query: playlists::BoxedQuery<'a, Sqlite>,
+	viewer_id: Option<&str>,
+) -> QueryResult<playlists::BoxedQuery<'a, Sqlite>> {
+	let query = query.filter(playlists::deleted_at.is_null());
+	let public = playlists::visibility.eq(Visibility::Public.as_str());
+	let Some(viewer_id) = viewer_id else {
+		return Ok(query.filter(public));
+	};
+	let friend_of = friend_of(db_conn, viewer_id)?;
+	Ok(query.filter(
+		public
+			.or(playlists::user_id.eq(viewer_id.to_string()))
+			.or(playlists::visibility
+				.eq(Visibility::Friends.as_str())
+				.and(playlists::user_id.eq_any(friend_of))),
+	))
+}
+
+pub fn set_visibility(db_conn: &mut SqliteConnection, playlist_id: &str, visibility: Visibility) -> QueryResult<usize> {
+	diesel::update(playlists::table.filter(playlists::playlist_id.eq(playlist_id)))
+		.set(playlists::visibility.eq(visibility.as_str()))
+		.execute(db_conn)
+}
+
+pub fn create_share_token(
+	db_conn: &mut SqliteConnection,
+	playlist_id: &str,
+	created_by: &str,
+) -> QueryResult<PlaylistShareToken> {
+	let share_token = PlaylistShareToken {
+		token: Uuid::new_v4().simple().to_string(),
+		playlist_id: playlist_id.to_string(),
+		created_by: created_by.to_string(),
+		created_at: Utc::now().to_rfc3339(),
+	};
+	diesel::insert_into(playlist_share_tokens::table)
+		.values(&share_token)
+		.execute(db_conn)?;
+	Ok(share_token)
+}
+
+pub fn share_tokens(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<Vec<PlaylistShareToken>> {
+	playlist_share_tokens::table
+		.filter(playlist_share_tokens::playlist_id.eq(playlist_id))
+		.order(playlist_share_tokens::created_at.desc())
+		.load(db_conn)
+}
+
+// Returns whether the token existed
+pub fn revoke_share_token(db_conn: &mut SqliteConnection, playlist_id: &str, token: &str) -> QueryResult<bool> {
+	diesel::delete(
+		playlist_share_tokens::table
+			.filter(playlist_share_tokens::playlist_id.eq(playlist_id))
+			.filter(playlist_share_tokens::token.eq(token)),
+	)
+	.execute(db_conn)
+	.map(|deleted| deleted > 0)
+}
+
+#[derive(Debug)]
+pub enum FollowError {
+	NotPublic,
+	OwnPlaylist,
+	Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for FollowError {
+	fn from(err: diesel::result::Error) -> FollowError {
+		FollowError::Database(err)
+	}
+}
+
+// Following twice is a no-op
+pub fn follow(db_conn: &mut SqliteConnection, playlist: &Playlist, user_id: &str) -> Result<(), FollowError> {
+	if Visibility::of(playlist) != Visibility::Public {
+		return Err(FollowError::NotPublic);
+	}
+	if is_member(db_conn, playlist, user_id)? {
+		return Err(FollowError::OwnPlaylist);
+	}
+	diesel::insert_or_ignore_into(playlist_follows::table)
+		.values(&PlaylistFollow {
+			user_id: user_id.to_string(),
+			playlist_id: playlist.playlist_id.clone(),
+			followed_at: Utc::now().to_rfc3339(),
+		})
+		.execute(db_conn)?;
+	Ok(())
+}
+
+// Returns whether the user was following the playlist
+pub fn unfollow(db_conn: &mut SqliteConnection, playlist_id: &str, user_id: &str) -> QueryResult<bool> {
+	diesel::delete(
+		playlist_follows::table
+			.filter(playlist_follows::playlist_id.eq(playlist_id))
+			.filter(playlist_follows::user_id.eq(user_id)),
+	)
+	.execute(db_conn)
+	.map(|deleted| deleted > 0)
+}
+
+// Followed playlists stay in the library only while they are public, they come back if made public again
+pub fn followed_playlists(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<Playlist>> {
+	playlist_follows::table
+		.filter(playlist_follows::user_id.eq(user_id))
+		.inner_join(playlists::table)
+		.filter(playlists::visibility.eq(Visibility::Public.as_str()))
+		.filter(playlists::deleted_at.is_null())
+		.order(playlist_follows::followed_at.desc())
+		.select(Playlist::as_select())
+		.load(db_conn)
+}
+
+// Lets the followers know about songs added to a public playlist, except whoever added them
+pub fn notify_followers(
+	app_state: &AppState,
+	db_conn: &mut SqliteConnection,
+	playlist: &Playlist,
+	music_ids: &[String],
+	song_adder_id: &str,
+) -> QueryResult<()> {
+	if music_ids.is_empty() || Visibility::of(playlist) != Visibility::Public {
+		return Ok(());
+	}
+	let followers: Vec<String> = playlist_follows::table
+		.filter(playlist_follows::playlist_id.eq(&playlist.playlist_id))
+		.filter(playlist_follows::user_id.ne(song_adder_id))
+		.select(playlist_follows::user_id)
+		.load(db_conn)?;
+
+	for follower in followers {
+		let value = json!({
+			"playlist_id": playlist.playlist_id,
+			"playlist_name": playlist.playlist_name,
+			"music_ids": music_ids,
+			"song_adder_id": song_adder_id,
+		});
+		let notif = Notification::new(OpCode::PLAYLIST_SONGS_ADDED, value);
+		notify_from(song_adder_id, &follower, notif, &app_state.db_pool, &app_state.user_pool);
+	}
+	Ok(())
+}
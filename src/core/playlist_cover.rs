@@ -0,0 +1,130 @@
+// Covers of playlists without an uploaded one.
+//
+// The cover is a 2x2 mosaic of the first four distinct album covers of the playlist, or the first album cover on
+// its own when there are fewer than four. Mosaics are cached on disk under the albums they were made from, so
+// they are only composed again once the first albums of the playlist change.
+
+use crate::config::{COVER_IMG_STORAGE, PLAYLIST_MOSAIC_STORAGE};
+use crate::core::playlist;
+use crate::core::smart_playlist::EvaluateError;
+use crate::lobic_db::models::{Music, Playlist};
+
+use diesel::prelude::*;
+use image::{imageops::FilterType, DynamicImage, ImageReader, RgbImage};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+pub const MOSAIC_SIZE: u32 = 600;
+const MOSAIC_TILES: usize = 4;
+
+// Album covers are stored with a png extension whatever their actual format
+fn album_cover_path(image_uuid: &str) -> PathBuf {
+	Path::new(COVER_IMG_STORAGE).join(format!("{image_uuid}.png"))
+}
+
+fn load_album_cover(image_uuid: &str) -> Option<DynamicImage> {
+	ImageReader::open(album_cover_path(image_uuid)).ok()?.with_guessed_format().ok()?.decode().ok()
+}
+
+// The first album cover alone when there aren't enough for a mosaic
+fn tiles_of<T>(mut tiles: Vec<T>) -> Vec<T> {
+	if tiles.len() < MOSAIC_TILES {
+		tiles.truncate(1);
+	}
+	tiles
+}
+
+fn mosaic_path(playlist_id: &str, image_uuids: &[String]) -> PathBuf {
+	let mut hasher = DefaultHasher::new();
+	image_uuids.hash(&mut hasher);
+	Path::new(PLAYLIST_MOSAIC_STORAGE).join(format!("{playlist_id}-{:016x}.png", hasher.finish()))
+}
+
+// Mosaics of the playlist made from other albums than the current one
+fn remove_stale_mosaics(playlist_id: &str, current: &Path) {
+	let Ok(entries) = fs::read_dir(PLAYLIST_MOSAIC_STORAGE) else {
+		return;
+	};
+	let prefix = format!("{playlist_id}-");
+	for entry in entries.flatten() {
+		let path = entry.path();
+		let is_stale = entry.file_name().to_string_lossy().starts_with(&prefix) && path != current;
+		if is_stale {
+			let _ = fs::remove_file(path);
+		}
+	}
+}
+
+pub fn remove_mosaics(playlist_id: &str) {
+	remove_stale_mosaics(playlist_id, Path::new(""));
+}
+
+// The path of the cached mosaic, composing it first if needed. None when no song of the playlist has a cover
+pub fn mosaic_cover(db_conn: &mut SqliteConnection, playlist: &Playlist) -> Result<Option<PathBuf>, String> {
+	let songs = playlist::load_songs(db_conn, playlist).map_err(|err| match err {
+		EvaluateError::InvalidRules(err) => err,
+		EvaluateError::Database(err) => format!("Failed to load the songs of the playlist: {err}"),
+	})?;
+
+	// Songs whose album has no cover are skipped, covers are only decoded when the mosaic isn't cached yet
+	let mut seen = HashSet::new();
+	let mut image_uuids = Vec::new();
+	for song in songs {
+		if image_uuids.len() == MOSAIC_TILES {
+			break;
+		}
+		let image_uuid = Music::image_uuid(&song.artist, &song.album).to_string();
+		if seen.insert(image_uuid.clone()) && album_cover_path(&image_uuid).is_file() {
+			image_uuids.push(image_uuid);
+		}
+	}
+	let image_uuids = tiles_of(image_uuids);
+	if image_uuids.is_empty() {
+		return Ok(None);
+	}
+	let path = mosaic_path(&playlist.playlist_id, &image_uuids);
+	if path.exists() {
+		return Ok(Some(path));
+	}
+
+	// Covers that turn out to be unreadable are left out of the mosaic
+	let (image_uuids, covers): (Vec<String>, Vec<DynamicImage>) = tiles_of(
+		image_uuids
+			.into_iter()
+			.filter_map(|image_uuid| load_album_cover(&image_uuid).map(|cover| (image_uuid, cover)))
+			.collect(),
+	)
+	.into_iter()
+	.unzip();
+	if covers.is_empty() {
+		return Ok(None);
+	}
+	let path = mosaic_path(&playlist.playlist_id, &image_uuids);
+	if path.exists() {
+		return Ok(Some(path));
+	}
+
+	let mosaic = if covers.len() == 1 {
+		covers[0].resize_to_fill(MOSAIC_SIZE, MOSAIC_SIZE, FilterType::Triangle).to_rgb8()
+	} else {
+		let tile_size = MOSAIC_SIZE / 2;
+		let mut mosaic = RgbImage::new(MOSAIC_SIZE, MOSAIC_SIZE);
+		for (idx, cover) in covers.iter().enumerate() {
+			let tile = cover.resize_to_fill(tile_size, tile_size, FilterType::Triangle).to_rgb8();
+			let x = (idx as u32 % 2) * tile_size;
+			let y = (idx as u32 / 2) * tile_size;
+			image::imageops::replace(&mut mosaic, &tile, x as i64, y as i64);
+		}
+		mosaic
+	};
+
+	fs::create_dir_all(PLAYLIST_MOSAIC_STORAGE).map_err(|err| format!("Failed to create directory: {err}"))?;
+	mosaic
+		.save(&path)
+		.map_err(|err| format!("Failed to save the playlist mosaic: {err}"))?;
+	remove_stale_mosaics(&playlist.playlist_id, &path);
+	Ok(Some(path))
+}
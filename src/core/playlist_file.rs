@@ -0,0 +1,417 @@
+// Playlist files: export as M3U8, XSPF or Lobic json and import them back.
+//
+// Exported entries point at the stream url of the track (/music/:music_id) so that desktop players can play them
+// straight from the server. Imported entries are resolved against the library by music id, by the file name of
+// their location (stream urls and stored files are named after the music id) and finally by artist/title/album,
+// fuzzy if needed. Locations are only ever read as text, the files they point at are never opened.
+
+use crate::config::{server_ip, PORT};
+use crate::core::scrobble::{MatchKind, MusicMatcher};
+use crate::lobic_db::models::Music;
+use crate::schema::music;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+pub const LOBIC_PLAYLIST_VERSION: u32 = 1;
+const XSPF_NAMESPACE: &str = "http://xspf.org/ns/0/";
+const IDENTIFIER_PREFIX: &str = "lobic:music:";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaylistFormat {
+	M3u8,
+	Xspf,
+	Json,
+}
+
+impl PlaylistFormat {
+	pub fn parse(value: &str) -> Option<PlaylistFormat> {
+		match value.to_lowercase().as_str() {
+			"m3u8" | "m3u" => Some(PlaylistFormat::M3u8),
+			"xspf" => Some(PlaylistFormat::Xspf),
+			"json" => Some(PlaylistFormat::Json),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			PlaylistFormat::M3u8 => "m3u8",
+			PlaylistFormat::Xspf => "xspf",
+			PlaylistFormat::Json => "json",
+		}
+	}
+
+	pub fn content_type(&self) -> &'static str {
+		match self {
+			PlaylistFormat::M3u8 => "audio/x-mpegurl",
+			PlaylistFormat::Xspf => "application/xspf+xml",
+			PlaylistFormat::Json => "application/json",
+		}
+	}
+}
+
+// An entry read out of a playlist file, everything it has to be matched with is optional
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlaylistEntry {
+	pub music_id: Option<String>,
+	pub location: Option<String>,
+	pub artist: Option<String>,
+	pub title: Option<String>,
+	pub album: Option<String>,
+	pub duration: Option<i64>, // seconds
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedPlaylist {
+	pub name: Option<String>,
+	pub entries: Vec<PlaylistEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LobicPlaylistFile {
+	pub lobic_playlist: u32, // version of the format
+	pub name: String,
+	#[serde(default)]
+	pub exported_at: Option<String>,
+	pub songs: Vec<LobicPlaylistSong>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LobicPlaylistSong {
+	#[serde(default)]
+	pub music_id: Option<String>,
+	#[serde(default)]
+	pub artist: Option<String>,
+	#[serde(default)]
+	pub title: Option<String>,
+	#[serde(default)]
+	pub album: Option<String>,
+	#[serde(default)]
+	pub duration: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename = "playlist")]
+struct Xspf {
+	#[serde(rename = "@version")]
+	version: String,
+	#[serde(rename = "@xmlns", default)]
+	xmlns: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	title: Option<String>,
+	#[serde(rename = "trackList")]
+	track_list: XspfTrackList,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct XspfTrackList {
+	#[serde(rename = "track", default)]
+	tracks: Vec<XspfTrack>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct XspfTrack {
+	#[serde(default)]
+	location: Vec<String>,
+	#[serde(default)]
+	identifier: Vec<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	title: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	creator: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	album: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	duration: Option<i64>, // milliseconds
+}
+
+fn stream_url(music_id: &str) -> String {
+	format!("http://{}:{}/music/{}", server_ip(), PORT, music_id)
+}
+
+pub fn export(format: PlaylistFormat, name: &str, songs: &[Music]) -> Result<String, String> {
+	match format {
+		PlaylistFormat::M3u8 => Ok(to_m3u8(name, songs)),
+		PlaylistFormat::Xspf => to_xspf(name, songs),
+		PlaylistFormat::Json => to_json(name, songs),
+	}
+}
+
+fn to_m3u8(name: &str, songs: &[Music]) -> String {
+	let mut m3u8 = format!("#EXTM3U\n#PLAYLIST:{}\n", single_line(name));
+	for song in songs {
+		m3u8.push_str(&format!(
+			"#EXTINF:{},{} - {}\n#EXTALB:{}\n{}\n",
+			song.duration,
+			single_line(&song.artist),
+			single_line(&song.title),
+			single_line(&song.album),
+			stream_url(&song.music_id)
+		));
+	}
+	m3u8
+}
+
+fn single_line(text: &str) -> String {
+	text.replace(['\r', '\n'], " ")
+}
+
+fn to_xspf(name: &str, songs: &[Music]) -> Result<String, String> {
+	let xspf = Xspf {
+		version: "1".to_string(),
+		xmlns: XSPF_NAMESPACE.to_string(),
+		title: Some(name.to_string()),
+		track_list: XspfTrackList {
+			tracks: songs
+				.iter()
+				.map(|song| XspfTrack {
+					location: vec![stream_url(&song.music_id)],
+					identifier: vec![format!("{IDENTIFIER_PREFIX}{}", song.music_id)],
+					title: Some(song.title.clone()),
+					creator: Some(song.artist.clone()),
+					album: Some(song.album.clone()),
+					duration: Some(song.duration * 1000),
+				})
+				.collect(),
+		},
+	};
+	let xml = quick_xml::se::to_string(&xspf).map_err(|err| format!("Failed to serialize playlist: {err}"))?;
+	Ok(format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{xml}\n"))
+}
+
+fn to_json(name: &str, songs: &[Music]) -> Result<String, String> {
+	let file = LobicPlaylistFile {
+		lobic_playlist: LOBIC_PLAYLIST_VERSION,
+		name: name.to_string(),
+		exported_at: Some(Utc::now().to_rfc3339()),
+		songs: songs
+			.iter()
+			.map(|song| LobicPlaylistSong {
+				music_id: Some(song.music_id.clone()),
+				artist: Some(song.artist.clone()),
+				title: Some(song.title.clone()),
+				album: Some(song.album.clone()),
+				duration: Some(song.duration),
+			})
+			.collect(),
+	};
+	serde_json::to_string_pretty(&file).map_err(|err| format!("Failed to serialize playlist: {err}"))
+}
+
+pub fn parse(format: PlaylistFormat, data: &str) -> Result<ParsedPlaylist, String> {
+	let data = data.trim_start_matches('\u{feff}');
+	let parsed = match format {
+		PlaylistFormat::M3u8 => parse_m3u8(data),
+		PlaylistFormat::Xspf => parse_xspf(data)?,
+		PlaylistFormat::Json => parse_json(data)?,
+	};
+	if parsed.entries.is_empty() {
+		return Err("The playlist file has no entries".to_string());
+	}
+	Ok(parsed)
+}
+
+// Plain m3u works too, the #EXT lines are only hints for the matching
+fn parse_m3u8(data: &str) -> ParsedPlaylist {
+	let mut parsed = ParsedPlaylist::default();
+	let mut pending = PlaylistEntry::default();
+
+	for line in data.lines().map(str::trim).filter(|line| !line.is_empty()) {
+		if let Some(name) = line.strip_prefix("#PLAYLIST:") {
+			parsed.name = Some(name.trim().to_string());
+		} else if let Some(info) = line.strip_prefix("#EXTINF:") {
+			// #EXTINF:<seconds> [attributes],<artist> - <title>
+			let (duration, display) = info.split_once(',').unwrap_or((info, ""));
+			let duration = duration.split_whitespace().next().unwrap_or_default();
+			pending.duration = duration.parse::<i64>().ok().filter(|duration| *duration > 0);
+			match display.split_once(" - ") {
+				Some((artist, title)) => {
+					pending.artist = Some(artist.trim().to_string());
+					pending.title = Some(title.trim().to_string());
+				}
+				None if !display.trim().is_empty() => pending.title = Some(display.trim().to_string()),
+				None => (),
+			}
+		} else if let Some(album) = line.strip_prefix("#EXTALB:") {
+			pending.album = Some(album.trim().to_string());
+		} else if let Some(artist) = line.strip_prefix("#EXTART:") {
+			pending.artist = Some(artist.trim().to_string());
+		} else if !line.starts_with('#') {
+			pending.location = Some(line.to_string());
+			parsed.entries.push(std::mem::take(&mut pending));
+		}
+	}
+	parsed
+}
+
+fn parse_xspf(data: &str) -> Result<ParsedPlaylist, String> {
+	let xspf: Xspf = quick_xml::de::from_str(data).map_err(|err| format!("Invalid XSPF playlist: {err}"))?;
+	let entries = xspf
+		.track_list
+		.tracks
+		.into_iter()
+		.map(|track| PlaylistEntry {
+			music_id: track
+				.identifier
+				.iter()
+				.find_map(|identifier| identifier.strip_prefix(IDENTIFIER_PREFIX))
+				.map(str::to_string),
+			location: track.location.into_iter().next(),
+			artist: track.creator,
+			title: track.title,
+			album: track.album,
+			duration: track.duration.map(|duration| duration / 1000),
+		})
+		.collect();
+	Ok(ParsedPlaylist {
+		name: xspf.title,
+		entries,
+	})
+}
+
+fn parse_json(data: &str) -> Result<ParsedPlaylist, String> {
+	let file: LobicPlaylistFile = serde_json::from_str(data).map_err(|err| format!("Invalid Lobic playlist: {err}"))?;
+	if file.lobic_playlist > LOBIC_PLAYLIST_VERSION {
+		return Err(format!("Unsupported Lobic playlist version: {}", file.lobic_playlist));
+	}
+	let entries = file
+		.songs
+		.into_iter()
+		.map(|song| PlaylistEntry {
+			music_id: song.music_id,
+			location: None,
+			artist: song.artist,
+			title: song.title,
+			album: song.album,
+			duration: song.duration,
+		})
+		.collect();
+	Ok(ParsedPlaylist {
+		name: Some(file.name),
+		entries,
+	})
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchedBy {
+	MusicId,
+	Path,
+	Metadata,
+	Fuzzy,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EntryMatch {
+	pub index: usize, // position of the entry in the file
+	pub matched_by: Option<MatchedBy>,
+	pub music_id: Option<String>,
+	pub entry: PlaylistEntry, // as read from the file
+}
+
+// Resolves the entries against the library, in order
+pub fn resolve(db_conn: &mut SqliteConnection, entries: Vec<PlaylistEntry>) -> QueryResult<Vec<EntryMatch>> {
+	let known_ids: HashSet<String> = music::table
+		.select(music::music_id)
+		.load(db_conn)?
+		.into_iter()
+		.collect();
+	let matcher = MusicMatcher::load(db_conn)?;
+
+	let matches = entries
+		.into_iter()
+		.enumerate()
+		.map(|(index, mut entry)| {
+			// Files without #EXTINF are often named "artist - title.mp3"
+			if entry.title.is_none() {
+				if let Some((artist, title)) = entry.location.as_deref().and_then(file_stem).and_then(split_display) {
+					entry.artist.get_or_insert(artist);
+					entry.title = Some(title);
+				}
+			}
+
+			let (matched_by, music_id) = match resolve_entry(&entry, &known_ids, &matcher) {
+				Some((matched_by, music_id)) => (Some(matched_by), Some(music_id)),
+				None => (None, None),
+			};
+			EntryMatch {
+				index,
+				matched_by,
+				music_id,
+				entry,
+			}
+		})
+		.collect();
+	Ok(matches)
+}
+
+fn resolve_entry(
+	entry: &PlaylistEntry,
+	known_ids: &HashSet<String>,
+	matcher: &MusicMatcher,
+) -> Option<(MatchedBy, String)> {
+	if let Some(music_id) = entry.music_id.as_ref().filter(|music_id| known_ids.contains(*music_id)) {
+		return Some((MatchedBy::MusicId, music_id.clone()));
+	}
+
+	if let Some(location) = &entry.location {
+		// Stream urls and stored files are named after the music id
+		if let Some(music_id) = file_stem(location).filter(|stem| known_ids.contains(stem)) {
+			return Some((MatchedBy::Path, music_id));
+		}
+	}
+
+	let title = entry.title.as_deref()?;
+	let (music_id, _, kind) = matcher.find(
+		entry.artist.as_deref().unwrap_or_default(),
+		title,
+		entry.album.as_deref(),
+	)?;
+	let matched_by = match kind {
+		MatchKind::Exact => MatchedBy::Metadata,
+		MatchKind::Fuzzy => MatchedBy::Fuzzy,
+	};
+	Some((matched_by, music_id.to_string()))
+}
+
+// Last segment of a path or url without its extension
+fn file_stem(location: &str) -> Option<String> {
+	let location = location.split(['?', '#']).next().unwrap_or_default();
+	let file_name = location.rsplit(['/', '\\']).next()?;
+	let stem = file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem);
+	let stem = percent_decode(stem);
+	(!stem.is_empty()).then_some(stem)
+}
+
+fn split_display(display: String) -> Option<(String, String)> {
+	let (artist, title) = display.split_once(" - ")?;
+	Some((artist.trim().to_string(), title.trim().to_string()))
+}
+
+// Decodes the %XX escapes of file urls, invalid escapes are kept as they are
+fn percent_decode(text: &str) -> String {
+	let bytes = text.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut idx = 0;
+	while idx < bytes.len() {
+		let escaped = (bytes[idx] == b'%')
+			.then(|| bytes.get(idx + 1..idx + 3))
+			.flatten()
+			.and_then(|hex| std::str::from_utf8(hex).ok())
+			.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+		match escaped {
+			Some(byte) => {
+				decoded.push(byte);
+				idx += 3;
+			}
+			None => {
+				decoded.push(bytes[idx]);
+				idx += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&decoded).into_owned()
+}
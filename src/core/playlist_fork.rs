@@ -0,0 +1,252 @@
+// Forking and merging playlists.
+//
+// A fork is a copy of a playlist the user can read, owned by the user, that keeps a link to its source. The songs
+// the source had when the fork was made (or last updated from it) are kept, so the songs added to and removed from
+// the source since can be told apart from the changes made to the fork, and applied to it on request.
+
+use crate::config::PLAYLIST_COVER_IMG_STORAGE;
+use crate::core::playlist::{self, CreatePlaylistError};
+use crate::core::playlist_history::{self, Change};
+use crate::core::playlist_library;
+use crate::core::smart_playlist::EvaluateError;
+use crate::lobic_db::models::{Playlist, PlaylistFork, PlaylistSong};
+use crate::schema::{playlist_forks, playlist_songs, playlists};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ForkError {
+	NotAFork(String),
+	SourceDeleted,
+	Storage(String),
+	InvalidRules(String),
+	Database(diesel::result::Error),
+}
+
+impl fmt::Display for ForkError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ForkError::NotAFork(playlist_id) => write!(f, "Playlist {playlist_id} is not a fork"),
+			ForkError::SourceDeleted => write!(f, "The source playlist was deleted"),
+			ForkError::Storage(err) => write!(f, "{err}"),
+			ForkError::InvalidRules(err) => write!(f, "Invalid smart rules: {err}"),
+			ForkError::Database(err) => write!(f, "Database error: {err}"),
+		}
+	}
+}
+
+impl From<diesel::result::Error> for ForkError {
+	fn from(err: diesel::result::Error) -> ForkError {
+		ForkError::Database(err)
+	}
+}
+
+impl From<EvaluateError> for ForkError {
+	fn from(err: EvaluateError) -> ForkError {
+		match err {
+			EvaluateError::InvalidRules(err) => ForkError::InvalidRules(err),
+			EvaluateError::Database(err) => ForkError::Database(err),
+		}
+	}
+}
+
+impl From<CreatePlaylistError> for ForkError {
+	fn from(err: CreatePlaylistError) -> ForkError {
+		match err {
+			CreatePlaylistError::Storage(err) => ForkError::Storage(err),
+			CreatePlaylistError::Database(err) => ForkError::Database(err),
+		}
+	}
+}
+
+// Songs added to and removed from the source since the fork was last updated from it, in the order of the source
+#[derive(Debug)]
+pub struct SourceDiff {
+	pub source: Playlist,
+	pub added: Vec<String>,
+	pub removed: Vec<String>,
+	current: Vec<String>,
+}
+
+// Music ids of the playlist in order, without repeats
+fn distinct_songs(db_conn: &mut SqliteConnection, playlist: &Playlist) -> Result<Vec<String>, ForkError> {
+	let mut seen = HashSet::new();
+	Ok(playlist::load_songs(db_conn, playlist)?
+		.into_iter()
+		.map(|song| song.music_id)
+		.filter(|music_id| seen.insert(music_id.clone()))
+		.collect())
+}
+
+// Smart playlists are forked as the songs they evaluate to
+pub fn fork(
+	db_conn: &mut SqliteConnection,
+	source: &Playlist,
+	user_id: &str,
+	playlist_name: String,
+) -> Result<Playlist, ForkError> {
+	let cover_img =
+		fs::read(Path::new(PLAYLIST_COVER_IMG_STORAGE).join(format!("{}.png", source.playlist_id))).unwrap_or_default();
+
+	db_conn.transaction::<_, ForkError, _>(|conn| {
+		let songs: Vec<String> = playlist::load_songs(conn, source)?
+			.into_iter()
+			.map(|song| song.music_id)
+			.collect();
+		let mut new_playlist = playlist::create_playlist(conn, playlist_name, user_id.to_string(), false, &cover_img)?;
+		playlist::append_songs(conn, &new_playlist.playlist_id, &songs, user_id)?;
+
+		playlist_library::set_description(conn, &new_playlist.playlist_id, &source.description)?;
+		new_playlist.description = source.description.clone();
+		let tags = playlist_library::tags_of(conn, std::slice::from_ref(&source.playlist_id))?
+			.remove(&source.playlist_id)
+			.unwrap_or_default();
+		playlist_library::set_tags(conn, &new_playlist.playlist_id, &tags)?;
+
+		let mut seen = HashSet::new();
+		let snapshot: Vec<&String> = songs.iter().filter(|music_id| seen.insert(*music_id)).collect();
+		diesel::insert_into(playlist_forks::table)
+			.values(&PlaylistFork {
+				playlist_id: new_playlist.playlist_id.clone(),
+				source_id: source.playlist_id.clone(),
+				source_snapshot: serde_json::to_string(&snapshot).unwrap(),
+				synced_at: Utc::now().to_rfc3339(),
+			})
+			.execute(conn)?;
+		Ok(new_playlist)
+	})
+}
+
+pub fn fork_of(db_conn: &mut SqliteConnection, playlist_id: &str) -> Result<PlaylistFork, ForkError> {
+	playlist_forks::table
+		.filter(playlist_forks::playlist_id.eq(playlist_id))
+		.first::<PlaylistFork>(db_conn)
+		.optional()?
+		.ok_or_else(|| ForkError::NotAFork(playlist_id.to_string()))
+}
+
+pub fn source_of(db_conn: &mut SqliteConnection, fork: &PlaylistFork) -> Result<Playlist, ForkError> {
+	playlists::table
+		.filter(playlists::playlist_id.eq(&fork.source_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(db_conn)
+		.optional()?
+		.ok_or(ForkError::SourceDeleted)
+}
+
+pub fn source_diff(db_conn: &mut SqliteConnection, fork: &PlaylistFork) -> Result<SourceDiff, ForkError> {
+	let source = source_of(db_conn, fork)?;
+	let snapshot: Vec<String> = serde_json::from_str(&fork.source_snapshot).unwrap_or_default();
+	let current = distinct_songs(db_conn, &source)?;
+	let snapshot_set: HashSet<&String> = snapshot.iter().collect();
+	let current_set: HashSet<&String> = current.iter().collect();
+	let added = current
+		.iter()
+		.filter(|music_id| !snapshot_set.contains(music_id))
+		.cloned()
+		.collect();
+	let removed = snapshot
+		.iter()
+		.filter(|music_id| !current_set.contains(music_id))
+		.cloned()
+		.collect();
+	Ok(SourceDiff {
+		source,
+		added,
+		removed,
+		current,
+	})
+}
+
+// Appends the songs added to the source that the fork doesn't have yet and, with include_removals, removes the
+// ones removed from the source. Either way the fork is up to date with the source afterwards. The changes are
+// recorded in the history of the fork, in that order
+pub fn update_from_source(
+	db_conn: &mut SqliteConnection,
+	playlist_id: &str,
+	user_id: &str,
+	include_removals: bool,
+) -> Result<Vec<Change>, ForkError> {
+	db_conn.transaction::<_, ForkError, _>(|conn| {
+		let fork = fork_of(conn, playlist_id)?;
+		let diff = source_diff(conn, &fork)?;
+		let mut changes = Vec::new();
+
+		let existing: HashSet<String> = playlist_songs::table
+			.filter(playlist_songs::playlist_id.eq(playlist_id))
+			.select(playlist_songs::music_id)
+			.load::<String>(conn)?
+			.into_iter()
+			.collect();
+		let additions: Vec<String> = diff
+			.added
+			.iter()
+			.filter(|music_id| !existing.contains(*music_id))
+			.cloned()
+			.collect();
+		if !additions.is_empty() {
+			let entries = playlist::append_entries(conn, playlist_id, &additions, user_id)?;
+			changes.push(Change::Add { entries });
+		}
+
+		if include_removals && !diff.removed.is_empty() {
+			let entries = playlist_songs::table
+				.filter(playlist_songs::playlist_id.eq(playlist_id))
+				.filter(playlist_songs::music_id.eq_any(&diff.removed))
+				.select(PlaylistSong::as_select())
+				.load(conn)?;
+			if !entries.is_empty() {
+				let item_ids: Vec<&str> = entries.iter().map(|entry| entry.item_id.as_str()).collect();
+				diesel::delete(playlist_songs::table.filter(playlist_songs::item_id.eq_any(item_ids))).execute(conn)?;
+				playlist::touch_playlist(conn, playlist_id)?;
+				changes.push(Change::Remove { entries });
+			}
+		}
+
+		for change in &changes {
+			playlist_history::record(conn, playlist_id, user_id, change)?;
+		}
+		diesel::update(playlist_forks::table.filter(playlist_forks::playlist_id.eq(playlist_id)))
+			.set((
+				playlist_forks::source_snapshot.eq(serde_json::to_string(&diff.current).unwrap()),
+				playlist_forks::synced_at.eq(Utc::now().to_rfc3339()),
+			))
+			.execute(conn)?;
+		Ok(changes)
+	})
+}
+
+// Appends the songs of the source the target doesn't have yet, in the order of the source and each one once.
+// None when there was nothing to add
+pub fn merge(
+	db_conn: &mut SqliteConnection,
+	target_id: &str,
+	source: &Playlist,
+	user_id: &str,
+) -> Result<Option<Change>, ForkError> {
+	db_conn.transaction::<_, ForkError, _>(|conn| {
+		let existing: HashSet<String> = playlist_songs::table
+			.filter(playlist_songs::playlist_id.eq(target_id))
+			.select(playlist_songs::music_id)
+			.load::<String>(conn)?
+			.into_iter()
+			.collect();
+		let additions: Vec<String> = distinct_songs(conn, source)?
+			.into_iter()
+			.filter(|music_id| !existing.contains(music_id))
+			.collect();
+		if additions.is_empty() {
+			return Ok(None);
+		}
+
+		let entries = playlist::append_entries(conn, target_id, &additions, user_id)?;
+		let change = Change::Add { entries };
+		playlist_history::record(conn, target_id, user_id, &change)?;
+		Ok(Some(change))
+	})
+}
@@ -0,0 +1,341 @@
+// History of the changes to a playlist, and undoing them.
+//
+// Every change records what is needed to revert it: the entries that were added or removed, the positions that were
+// moved, the previous name, and a copy of the previous cover. Undoing reverts the latest changes that aren't undone
+// yet, newest first, restoring to a point in time undoes everything after it. Undone changes stay in the history.
+//
+// Deleted playlists are only marked as such and can be restored for RESTORE_WINDOW_DAYS, after that they are purged.
+
+use crate::config::{PLAYLIST_COVER_HISTORY_STORAGE, PLAYLIST_COVER_IMG_STORAGE};
+use crate::core::{playlist, playlist_cover};
+use crate::lobic_db::db::DatabasePool;
+use crate::lobic_db::models::{Playlist, PlaylistHistoryEntry, PlaylistSong};
+use crate::schema::{
+	playlist_blends, playlist_follows, playlist_forks, playlist_history, playlist_invitations, playlist_library_entries,
+	playlist_share_tokens, playlist_shares, playlist_songs, playlist_tags, playlists,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+pub const RESTORE_WINDOW_DAYS: i64 = 30;
+pub const PURGE_INTERVAL_SECS: u64 = 60 * 60;
+pub const MAX_HISTORY_LENGTH: i64 = 500;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionChange {
+	pub item_id: String,
+	pub from: f64,
+	pub to: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Change {
+	Add { entries: Vec<PlaylistSong> },
+	Remove { entries: Vec<PlaylistSong> },
+	Reorder { moves: Vec<PositionChange> },
+	Rename { from: String, to: String },
+	Cover { backup: Option<String> }, // the previous cover in PLAYLIST_COVER_HISTORY_STORAGE, None if there was none
+}
+
+impl Change {
+	pub fn kind(&self) -> &'static str {
+		match self {
+			Change::Add { .. } => "add",
+			Change::Remove { .. } => "remove",
+			Change::Reorder { .. } => "reorder",
+			Change::Rename { .. } => "rename",
+			Change::Cover { .. } => "cover",
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum HistoryError {
+	InvalidChange(String),
+	Storage(String),
+	Database(diesel::result::Error),
+}
+
+impl fmt::Display for HistoryError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			HistoryError::InvalidChange(err) => write!(f, "Invalid change in the history: {err}"),
+			HistoryError::Storage(err) => write!(f, "{err}"),
+			HistoryError::Database(err) => write!(f, "Database error: {err}"),
+		}
+	}
+}
+
+impl From<diesel::result::Error> for HistoryError {
+	fn from(err: diesel::result::Error) -> HistoryError {
+		HistoryError::Database(err)
+	}
+}
+
+pub fn record(db_conn: &mut SqliteConnection, playlist_id: &str, actor_id: &str, change: &Change) -> QueryResult<()> {
+	let entry = PlaylistHistoryEntry {
+		event_id: Uuid::new_v4().to_string(),
+		playlist_id: playlist_id.to_string(),
+		actor_id: actor_id.to_string(),
+		kind: change.kind().to_string(),
+		change: serde_json::to_string(change).unwrap(),
+		created_at: Utc::now().to_rfc3339(),
+		undone_at: None,
+	};
+	diesel::insert_into(playlist_history::table)
+		.values(&entry)
+		.execute(db_conn)?;
+	Ok(())
+}
+
+// Newest first
+pub fn history(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<Vec<PlaylistHistoryEntry>> {
+	playlist_history::table
+		.filter(playlist_history::playlist_id.eq(playlist_id))
+		.order(playlist_history::created_at.desc())
+		.limit(MAX_HISTORY_LENGTH)
+		.load(db_conn)
+}
+
+// Positions that changed between two orders from playlist::load_order
+pub fn position_changes(before: &[(String, f64)], after: &[(String, f64)]) -> Vec<PositionChange> {
+	let before: HashMap<&str, f64> = before.iter().map(|(item_id, position)| (item_id.as_str(), *position)).collect();
+	after
+		.iter()
+		.filter_map(|(item_id, to)| {
+			let from = *before.get(item_id.as_str())?;
+			(from != *to).then(|| PositionChange {
+				item_id: item_id.clone(),
+				from,
+				to: *to,
+			})
+		})
+		.collect()
+}
+
+// Keeps a copy of the current cover before it gets replaced, returns its name for Change::Cover
+pub fn backup_cover(playlist_id: &str) -> Result<Option<String>, HistoryError> {
+	let cover_path = Path::new(PLAYLIST_COVER_IMG_STORAGE).join(format!("{playlist_id}.png"));
+	if !cover_path.exists() {
+		return Ok(None);
+	}
+	let backup = format!("{playlist_id}_{}.png", Uuid::new_v4());
+	let history_path = Path::new(PLAYLIST_COVER_HISTORY_STORAGE);
+	fs::create_dir_all(history_path)
+		.map_err(|err| HistoryError::Storage(format!("Failed to create directory: {err}")))?;
+	fs::copy(&cover_path, history_path.join(&backup))
+		.map_err(|err| HistoryError::Storage(format!("Failed to back up cover image: {err}")))?;
+	Ok(Some(backup))
+}
+
+fn revert(db_conn: &mut SqliteConnection, playlist_id: &str, change: &Change) -> Result<(), HistoryError> {
+	match change {
+		Change::Add { entries } => {
+			let item_ids: Vec<&str> = entries.iter().map(|entry| entry.item_id.as_str()).collect();
+			diesel::delete(playlist_songs::table.filter(playlist_songs::item_id.eq_any(item_ids))).execute(db_conn)?;
+		}
+		Change::Remove { entries } => {
+			diesel::insert_or_ignore_into(playlist_songs::table)
+				.values(entries)
+				.execute(db_conn)?;
+		}
+		Change::Reorder { moves } => {
+			for position_change in moves {
+				diesel::update(playlist_songs::table.filter(playlist_songs::item_id.eq(&position_change.item_id)))
+					.set(playlist_songs::position.eq(position_change.from))
+					.execute(db_conn)?;
+			}
+		}
+		Change::Rename { from, .. } => {
+			diesel::update(playlists::table.filter(playlists::playlist_id.eq(playlist_id)))
+				.set(playlists::playlist_name.eq(from))
+				.execute(db_conn)?;
+		}
+		Change::Cover { backup } => {
+			let cover_path = Path::new(PLAYLIST_COVER_IMG_STORAGE).join(format!("{playlist_id}.png"));
+			let result = match backup {
+				Some(backup) => fs::copy(Path::new(PLAYLIST_COVER_HISTORY_STORAGE).join(backup), &cover_path).map(|_| ()),
+				None if cover_path.exists() => fs::remove_file(&cover_path),
+				None => Ok(()),
+			};
+			result.map_err(|err| HistoryError::Storage(format!("Failed to restore cover image: {err}")))?;
+		}
+	}
+	Ok(())
+}
+
+// Reverts the changes in the given order and marks them as undone
+fn undo_entries(
+	db_conn: &mut SqliteConnection,
+	playlist_id: &str,
+	entries: Vec<PlaylistHistoryEntry>,
+) -> Result<usize, HistoryError> {
+	if entries.is_empty() {
+		return Ok(0);
+	}
+	db_conn.transaction::<_, HistoryError, _>(|conn| {
+		let undone_at = Utc::now().to_rfc3339();
+		for entry in &entries {
+			let change: Change =
+				serde_json::from_str(&entry.change).map_err(|err| HistoryError::InvalidChange(err.to_string()))?;
+			revert(conn, playlist_id, &change)?;
+			diesel::update(playlist_history::table.filter(playlist_history::event_id.eq(&entry.event_id)))
+				.set(playlist_history::undone_at.eq(&undone_at))
+				.execute(conn)?;
+		}
+		playlist::touch_playlist(conn, playlist_id)?;
+		Ok(entries.len())
+	})
+}
+
+// Undoes the latest `count` changes that aren't undone yet, returns how many were undone
+pub fn undo(db_conn: &mut SqliteConnection, playlist_id: &str, count: i64) -> Result<usize, HistoryError> {
+	let entries = playlist_history::table
+		.filter(playlist_history::playlist_id.eq(playlist_id))
+		.filter(playlist_history::undone_at.is_null())
+		.order(playlist_history::created_at.desc())
+		.limit(count)
+		.load::<PlaylistHistoryEntry>(db_conn)?;
+	undo_entries(db_conn, playlist_id, entries)
+}
+
+// Undoes every change made after `at`, returns how many were undone
+pub fn restore_to(db_conn: &mut SqliteConnection, playlist_id: &str, at: DateTime<Utc>) -> Result<usize, HistoryError> {
+	let entries = playlist_history::table
+		.filter(playlist_history::playlist_id.eq(playlist_id))
+		.filter(playlist_history::undone_at.is_null())
+		.filter(playlist_history::created_at.gt(at.to_rfc3339()))
+		.order(playlist_history::created_at.desc())
+		.load::<PlaylistHistoryEntry>(db_conn)?;
+	undo_entries(db_conn, playlist_id, entries)
+}
+
+pub fn soft_delete(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<usize> {
+	diesel::update(
+		playlists::table
+			.filter(playlists::playlist_id.eq(playlist_id))
+			.filter(playlists::deleted_at.is_null()),
+	)
+	.set(playlists::deleted_at.eq(Utc::now().to_rfc3339()))
+	.execute(db_conn)
+}
+
+fn restore_deadline() -> String {
+	(Utc::now() - Duration::days(RESTORE_WINDOW_DAYS)).to_rfc3339()
+}
+
+// Deleted playlists of the user that can still be restored, latest deleted first
+pub fn deleted_playlists(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<Playlist>> {
+	playlists::table
+		.filter(playlists::user_id.eq(user_id))
+		.filter(playlists::deleted_at.gt(restore_deadline()))
+		.order(playlists::deleted_at.desc())
+		.load(db_conn)
+}
+
+// Returns whether the playlist was restored, it has to be deleted and within the restore window
+pub fn restore_deleted(db_conn: &mut SqliteConnection, playlist_id: &str) -> QueryResult<bool> {
+	diesel::update(
+		playlists::table
+			.filter(playlists::playlist_id.eq(playlist_id))
+			.filter(playlists::deleted_at.gt(restore_deadline())),
+	)
+	.set(playlists::deleted_at.eq(None::<String>))
+	.execute(db_conn)
+	.map(|restored| restored > 0)
+}
+
+// Removes the playlists deleted before the restore window along with everything that refers to them
+fn purge_deleted_playlists(db_pool: &DatabasePool) -> Result<usize, String> {
+	let mut db_conn = db_pool
+		.get()
+		.map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+
+	let expired: Vec<String> = playlists::table
+		.filter(playlists::deleted_at.le(restore_deadline()))
+		.select(playlists::playlist_id)
+		.load(&mut db_conn)
+		.map_err(|err| format!("Failed to query deleted playlists: {err}"))?;
+	if expired.is_empty() {
+		return Ok(0);
+	}
+
+	let backups: Vec<String> = playlist_history::table
+		.filter(playlist_history::playlist_id.eq_any(&expired))
+		.filter(playlist_history::kind.eq("cover"))
+		.select(playlist_history::change)
+		.load::<String>(&mut db_conn)
+		.map_err(|err| format!("Failed to query playlist history: {err}"))?
+		.into_iter()
+		.filter_map(|change| match serde_json::from_str(&change) {
+			Ok(Change::Cover { backup }) => backup,
+			_ => None,
+		})
+		.collect();
+
+	db_conn
+		.transaction::<_, diesel::result::Error, _>(|conn| {
+			diesel::delete(playlist_songs::table.filter(playlist_songs::playlist_id.eq_any(&expired))).execute(conn)?;
+			diesel::delete(playlist_shares::table.filter(playlist_shares::playlist_id.eq_any(&expired))).execute(conn)?;
+			diesel::delete(playlist_share_tokens::table.filter(playlist_share_tokens::playlist_id.eq_any(&expired)))
+				.execute(conn)?;
+			diesel::delete(playlist_follows::table.filter(playlist_follows::playlist_id.eq_any(&expired)))
+				.execute(conn)?;
+			diesel::delete(playlist_invitations::table.filter(playlist_invitations::playlist_id.eq_any(&expired)))
+				.execute(conn)?;
+			diesel::delete(playlist_history::table.filter(playlist_history::playlist_id.eq_any(&expired)))
+				.execute(conn)?;
+			diesel::delete(
+				playlist_forks::table.filter(
+					playlist_forks::playlist_id
+						.eq_any(&expired)
+						.or(playlist_forks::source_id.eq_any(&expired)),
+				),
+			)
+			.execute(conn)?;
+			diesel::delete(playlist_blends::table.filter(playlist_blends::playlist_id.eq_any(&expired))).execute(conn)?;
+			diesel::delete(playlist_tags::table.filter(playlist_tags::playlist_id.eq_any(&expired))).execute(conn)?;
+			diesel::delete(
+				playlist_library_entries::table.filter(playlist_library_entries::playlist_id.eq_any(&expired)),
+			)
+			.execute(conn)?;
+			diesel::delete(playlists::table.filter(playlists::playlist_id.eq_any(&expired))).execute(conn)
+		})
+		.map_err(|err| format!("Failed to purge deleted playlists: {err}"))?;
+
+	// The files go last, a failure here only leaves unused images behind
+	for playlist_id in &expired {
+		let _ = fs::remove_file(Path::new(PLAYLIST_COVER_IMG_STORAGE).join(format!("{playlist_id}.png")));
+		playlist_cover::remove_mosaics(playlist_id);
+	}
+	for backup in backups {
+		let _ = fs::remove_file(Path::new(PLAYLIST_COVER_HISTORY_STORAGE).join(backup));
+	}
+	Ok(expired.len())
+}
+
+pub fn spawn_deleted_playlist_purger(db_pool: DatabasePool) {
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(std::time::Duration::from_secs(PURGE_INTERVAL_SECS));
+		loop {
+			interval.tick().await;
+
+			let db_pool = db_pool.clone();
+			let result = tokio::task::spawn_blocking(move || purge_deleted_playlists(&db_pool)).await;
+			match result {
+				Ok(Ok(0)) => (),
+				Ok(Ok(purged)) => println!("[playlist]: Purged {purged} deleted playlists"),
+				Ok(Err(err)) => println!("[playlist]: {err}"),
+				Err(err) => println!("[playlist]: Task failed: {err}"),
+			}
+		}
+	});
+}
@@ -0,0 +1,377 @@
+// How a user organises their library: nested folders and pinned playlists, plus the description and tags of
+// playlists.
+//
+// Folders and pins belong to the user, the same playlist can sit in different folders in the libraries of its
+// owner, contributors and followers. The description and tags belong to the playlist and are set by its editors.
+
+use crate::core::playlist_access;
+use crate::lobic_db::models::{PlaylistFolder, PlaylistInfo, PlaylistLibraryEntry};
+use crate::schema::{
+	playlist_blends, playlist_folders, playlist_follows, playlist_forks, playlist_library_entries, playlist_tags, playlists,
+};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use uuid::Uuid;
+
+pub const MAX_FOLDER_DEPTH: usize = 8;
+pub const MAX_TAGS: usize = 20;
+pub const MAX_TAG_LENGTH: usize = 32;
+pub const MAX_DESCRIPTION_LENGTH: usize = 1000;
+
+#[derive(Debug)]
+pub enum LibraryError {
+	InvalidFolder(String),
+	FolderCycle,
+	TooDeep,
+	NotInLibrary(String),
+	InvalidTag(String),
+	TooManyTags,
+	Database(diesel::result::Error),
+}
+
+impl fmt::Display for LibraryError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			LibraryError::InvalidFolder(folder_id) => write!(f, "Invalid folder_id: {folder_id}"),
+			LibraryError::FolderCycle => write!(f, "A folder can't be moved inside itself"),
+			LibraryError::TooDeep => write!(f, "Folders can't be nested more than {MAX_FOLDER_DEPTH} deep"),
+			LibraryError::NotInLibrary(playlist_id) => write!(f, "Playlist {playlist_id} is not in the library"),
+			LibraryError::InvalidTag(tag) => write!(
+				f,
+				"Invalid tag: {tag}, tags are up to {MAX_TAG_LENGTH} letters, digits, spaces and dashes"
+			),
+			LibraryError::TooManyTags => write!(f, "A playlist can't have more than {MAX_TAGS} tags"),
+			LibraryError::Database(err) => write!(f, "Database error: {err}"),
+		}
+	}
+}
+
+impl From<diesel::result::Error> for LibraryError {
+	fn from(err: diesel::result::Error) -> LibraryError {
+		LibraryError::Database(err)
+	}
+}
+
+pub fn folders(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<PlaylistFolder>> {
+	playlist_folders::table
+		.filter(playlist_folders::user_id.eq(user_id))
+		.order((playlist_folders::folder_name.asc(), playlist_folders::created_at.asc()))
+		.load(db_conn)
+}
+
+// Parent of every folder of the user, to walk the tree without a query per level
+fn parents(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<HashMap<String, Option<String>>> {
+	Ok(folders(db_conn, user_id)?
+		.into_iter()
+		.map(|folder| (folder.folder_id, folder.parent_id))
+		.collect())
+}
+
+// 1 for a top level folder, 0 for the top level itself
+fn depth(parents: &HashMap<String, Option<String>>, folder_id: Option<&str>) -> usize {
+	let mut depth = 0;
+	let mut curr = folder_id;
+	while let Some(folder_id) = curr {
+		depth += 1;
+		curr = parents.get(folder_id).and_then(|parent_id| parent_id.as_deref());
+	}
+	depth
+}
+
+// Levels of folders below the folder, the folder included
+fn height(parents: &HashMap<String, Option<String>>, folder_id: &str) -> usize {
+	1 + parents
+		.iter()
+		.filter(|(_, parent_id)| parent_id.as_deref() == Some(folder_id))
+		.map(|(child_id, _)| height(parents, child_id))
+		.max()
+		.unwrap_or(0)
+}
+
+fn ensure_folder(parents: &HashMap<String, Option<String>>, folder_id: Option<&str>) -> Result<(), LibraryError> {
+	match folder_id {
+		Some(folder_id) if !parents.contains_key(folder_id) => Err(LibraryError::InvalidFolder(folder_id.to_string())),
+		_ => Ok(()),
+	}
+}
+
+pub fn create_folder(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	folder_name: &str,
+	parent_id: Option<&str>,
+) -> Result<PlaylistFolder, LibraryError> {
+	let parents = parents(db_conn, user_id)?;
+	ensure_folder(&parents, parent_id)?;
+	if depth(&parents, parent_id) >= MAX_FOLDER_DEPTH {
+		return Err(LibraryError::TooDeep);
+	}
+
+	let folder = PlaylistFolder {
+		folder_id: Uuid::new_v4().to_string(),
+		user_id: user_id.to_string(),
+		parent_id: parent_id.map(str::to_string),
+		folder_name: folder_name.to_string(),
+		created_at: Utc::now().to_rfc3339(),
+	};
+	diesel::insert_into(playlist_folders::table)
+		.values(&folder)
+		.execute(db_conn)?;
+	Ok(folder)
+}
+
+pub fn rename_folder(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	folder_id: &str,
+	folder_name: &str,
+) -> Result<(), LibraryError> {
+	let updated = diesel::update(
+		playlist_folders::table
+			.filter(playlist_folders::folder_id.eq(folder_id))
+			.filter(playlist_folders::user_id.eq(user_id)),
+	)
+	.set(playlist_folders::folder_name.eq(folder_name))
+	.execute(db_conn)?;
+	if updated == 0 {
+		return Err(LibraryError::InvalidFolder(folder_id.to_string()));
+	}
+	Ok(())
+}
+
+// Moves the folder with everything in it, None moves it to the top level
+pub fn move_folder(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	folder_id: &str,
+	parent_id: Option<&str>,
+) -> Result<(), LibraryError> {
+	let parents = parents(db_conn, user_id)?;
+	ensure_folder(&parents, Some(folder_id))?;
+	ensure_folder(&parents, parent_id)?;
+
+	// The new parent can't be the folder or one of its subfolders
+	let mut curr = parent_id;
+	while let Some(ancestor_id) = curr {
+		if ancestor_id == folder_id {
+			return Err(LibraryError::FolderCycle);
+		}
+		curr = parents.get(ancestor_id).and_then(|parent_id| parent_id.as_deref());
+	}
+	if depth(&parents, parent_id) + height(&parents, folder_id) > MAX_FOLDER_DEPTH {
+		return Err(LibraryError::TooDeep);
+	}
+
+	diesel::update(playlist_folders::table.filter(playlist_folders::folder_id.eq(folder_id)))
+		.set(playlist_folders::parent_id.eq(parent_id))
+		.execute(db_conn)?;
+	Ok(())
+}
+
+// The subfolders and playlists of the folder move up to its parent, nothing else is deleted
+pub fn delete_folder(db_conn: &mut SqliteConnection, user_id: &str, folder_id: &str) -> Result<(), LibraryError> {
+	db_conn.transaction::<_, LibraryError, _>(|conn| {
+		let folder = playlist_folders::table
+			.filter(playlist_folders::folder_id.eq(folder_id))
+			.filter(playlist_folders::user_id.eq(user_id))
+			.first::<PlaylistFolder>(conn)
+			.optional()?
+			.ok_or_else(|| LibraryError::InvalidFolder(folder_id.to_string()))?;
+
+		diesel::update(playlist_folders::table.filter(playlist_folders::parent_id.eq(folder_id)))
+			.set(playlist_folders::parent_id.eq(&folder.parent_id))
+			.execute(conn)?;
+		diesel::update(playlist_library_entries::table.filter(playlist_library_entries::folder_id.eq(folder_id)))
+			.set(playlist_library_entries::folder_id.eq(&folder.parent_id))
+			.execute(conn)?;
+		diesel::delete(playlist_folders::table.filter(playlist_folders::folder_id.eq(folder_id))).execute(conn)?;
+		Ok(())
+	})
+}
+
+// Owned, contributed to or followed
+fn in_library(db_conn: &mut SqliteConnection, user_id: &str, playlist_id: &str) -> Result<(), LibraryError> {
+	let is_member = match playlist_access::role_of(db_conn, playlist_id, user_id) {
+		Ok(role) => role.is_some(),
+		Err(diesel::result::Error::NotFound) => false,
+		Err(err) => return Err(err.into()),
+	};
+	if is_member {
+		return Ok(());
+	}
+	let followed = playlist_follows::table
+		.filter(playlist_follows::user_id.eq(user_id))
+		.filter(playlist_follows::playlist_id.eq(playlist_id))
+		.count()
+		.get_result::<i64>(db_conn)?;
+	if followed > 0 {
+		Ok(())
+	} else {
+		Err(LibraryError::NotInLibrary(playlist_id.to_string()))
+	}
+}
+
+fn library_entry(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	playlist_id: &str,
+) -> QueryResult<PlaylistLibraryEntry> {
+	Ok(playlist_library_entries::table
+		.filter(playlist_library_entries::user_id.eq(user_id))
+		.filter(playlist_library_entries::playlist_id.eq(playlist_id))
+		.first::<PlaylistLibraryEntry>(db_conn)
+		.optional()?
+		.unwrap_or(PlaylistLibraryEntry {
+			user_id: user_id.to_string(),
+			playlist_id: playlist_id.to_string(),
+			folder_id: None,
+			pinned_at: None,
+		}))
+}
+
+// None puts the playlist back at the top level
+pub fn move_playlist(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	playlist_id: &str,
+	folder_id: Option<&str>,
+) -> Result<(), LibraryError> {
+	in_library(db_conn, user_id, playlist_id)?;
+	ensure_folder(&parents(db_conn, user_id)?, folder_id)?;
+
+	let entry = PlaylistLibraryEntry {
+		folder_id: folder_id.map(str::to_string),
+		..library_entry(db_conn, user_id, playlist_id)?
+	};
+	diesel::replace_into(playlist_library_entries::table)
+		.values(&entry)
+		.execute(db_conn)?;
+	Ok(())
+}
+
+// Pinning again keeps the playlist where it was among the pinned ones
+pub fn pin(db_conn: &mut SqliteConnection, user_id: &str, playlist_id: &str, pinned: bool) -> Result<(), LibraryError> {
+	in_library(db_conn, user_id, playlist_id)?;
+
+	let entry = library_entry(db_conn, user_id, playlist_id)?;
+	let pinned_at = match (pinned, entry.pinned_at) {
+		(true, Some(pinned_at)) => Some(pinned_at),
+		(true, None) => Some(Utc::now().to_rfc3339()),
+		(false, _) => None,
+	};
+	diesel::replace_into(playlist_library_entries::table)
+		.values(&PlaylistLibraryEntry { pinned_at, ..entry })
+		.execute(db_conn)?;
+	Ok(())
+}
+
+// Trimmed, lowercased and without duplicates, in the order they were given
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, LibraryError> {
+	let mut seen = HashSet::new();
+	let mut normalized = Vec::new();
+	for tag in tags {
+		let tag = tag.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase();
+		if tag.is_empty() {
+			continue;
+		}
+		if tag.chars().count() > MAX_TAG_LENGTH || !tag.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-') {
+			return Err(LibraryError::InvalidTag(tag));
+		}
+		if seen.insert(tag.clone()) {
+			normalized.push(tag);
+		}
+	}
+	if normalized.len() > MAX_TAGS {
+		return Err(LibraryError::TooManyTags);
+	}
+	Ok(normalized)
+}
+
+pub fn set_tags(db_conn: &mut SqliteConnection, playlist_id: &str, tags: &[String]) -> QueryResult<()> {
+	db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+		diesel::delete(playlist_tags::table.filter(playlist_tags::playlist_id.eq(playlist_id))).execute(conn)?;
+		for tag in tags {
+			diesel::insert_or_ignore_into(playlist_tags::table)
+				.values((playlist_tags::playlist_id.eq(playlist_id), playlist_tags::tag.eq(tag)))
+				.execute(conn)?;
+		}
+		Ok(())
+	})
+}
+
+pub fn set_description(db_conn: &mut SqliteConnection, playlist_id: &str, description: &str) -> QueryResult<usize> {
+	diesel::update(playlists::table.filter(playlists::playlist_id.eq(playlist_id)))
+		.set(playlists::description.eq(description))
+		.execute(db_conn)
+}
+
+pub fn tags_of(db_conn: &mut SqliteConnection, playlist_ids: &[String]) -> QueryResult<HashMap<String, Vec<String>>> {
+	let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+	let rows: Vec<(String, String)> = playlist_tags::table
+		.filter(playlist_tags::playlist_id.eq_any(playlist_ids))
+		.select((playlist_tags::playlist_id, playlist_tags::tag))
+		.order(playlist_tags::tag.asc())
+		.load(db_conn)?;
+	for (playlist_id, tag) in rows {
+		tags.entry(playlist_id).or_default().push(tag);
+	}
+	Ok(tags)
+}
+
+// Fills in the tags, where forks come from, which ones are blends, and the folder and pin of the playlists in the
+// library of the user. The pinned ones go first, the latest pinned on top, the others keep their order
+pub fn annotate(
+	db_conn: &mut SqliteConnection,
+	user_id: Option<&str>,
+	mut playlists: Vec<PlaylistInfo>,
+) -> QueryResult<Vec<PlaylistInfo>> {
+	let playlist_ids: Vec<String> = playlists.iter().map(|playlist| playlist.playlist_id.clone()).collect();
+	let mut tags = tags_of(db_conn, &playlist_ids)?;
+	let sources: HashMap<String, String> = playlist_forks::table
+		.filter(playlist_forks::playlist_id.eq_any(&playlist_ids))
+		.select((playlist_forks::playlist_id, playlist_forks::source_id))
+		.load::<(String, String)>(db_conn)?
+		.into_iter()
+		.collect();
+	let blends: HashSet<String> = playlist_blends::table
+		.filter(playlist_blends::playlist_id.eq_any(&playlist_ids))
+		.select(playlist_blends::playlist_id)
+		.load::<String>(db_conn)?
+		.into_iter()
+		.collect();
+	let entries: HashMap<String, PlaylistLibraryEntry> = match user_id {
+		Some(user_id) => playlist_library_entries::table
+			.filter(playlist_library_entries::user_id.eq(user_id))
+			.filter(playlist_library_entries::playlist_id.eq_any(&playlist_ids))
+			.load::<PlaylistLibraryEntry>(db_conn)?
+			.into_iter()
+			.map(|entry| (entry.playlist_id.clone(), entry))
+			.collect(),
+		None => HashMap::new(),
+	};
+
+	for playlist in playlists.iter_mut() {
+		playlist.tags = tags.remove(&playlist.playlist_id).unwrap_or_default();
+		playlist.forked_from = sources.get(&playlist.playlist_id).cloned();
+		playlist.is_blend = blends.contains(&playlist.playlist_id);
+		if let Some(entry) = entries.get(&playlist.playlist_id) {
+			playlist.folder_id = entry.folder_id.clone();
+			playlist.is_pinned = entry.pinned_at.is_some();
+		}
+	}
+	let pinned_at = |playlist: &PlaylistInfo| {
+		entries
+			.get(&playlist.playlist_id)
+			.and_then(|entry| entry.pinned_at.clone())
+	};
+	playlists.sort_by(|a, b| match (pinned_at(a), pinned_at(b)) {
+		(Some(a), Some(b)) => b.cmp(&a),
+		(Some(_), None) => std::cmp::Ordering::Less,
+		(None, Some(_)) => std::cmp::Ordering::Greater,
+		(None, None) => std::cmp::Ordering::Equal,
+	});
+	Ok(playlists)
+}
@@ -0,0 +1,187 @@
+// Live collaboration on playlists over the socket.
+//
+// Clients subscribe to a playlist to get every change made to it as it happens, along with who else has it open
+// and whether they are viewing or editing. Every change bumps the revision of the room so that a client that missed
+// one knows it has to refetch. Concurrent edits can be published in a different order than they were committed, so
+// every event carries the order of the playlist read back from the database under the room lock: whatever order the
+// changes arrive in, the last revision always has the latest order and every client ends up with the same one.
+// Moves refer to item ids rather than indices, so a move made on a stale copy still lands next to the same neighbours.
+
+use crate::config::{OpCode, SocketResponse};
+use crate::core::playlist;
+use crate::core::playlist_history::Change;
+use crate::core::user_pool::UserPool;
+
+use axum::extract::ws::Message;
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceStatus {
+	Viewing,
+	Editing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Presence {
+	pub user_id: String,
+	pub status: PresenceStatus,
+	pub since: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistRoom {
+	pub revision: u64,
+	pub subscribers: HashMap<String, Presence>,
+}
+
+impl PlaylistRoom {
+	// Sorted so that everyone sees the members in the same order
+	pub fn presence(&self) -> Vec<Presence> {
+		let mut presence: Vec<Presence> = self.subscribers.values().cloned().collect();
+		presence.sort_by(|a, b| a.since.cmp(&b.since).then_with(|| a.user_id.cmp(&b.user_id)));
+		presence
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaylistRoomPool {
+	inner: Arc<Mutex<HashMap<String, PlaylistRoom>>>,
+}
+
+impl PlaylistRoomPool {
+	pub fn new() -> PlaylistRoomPool {
+		PlaylistRoomPool {
+			inner: Arc::new(Mutex::new(HashMap::new())),
+		}
+	}
+
+	// Subscribing again keeps the current status
+	pub fn subscribe(&self, playlist_id: &str, user_id: &str) -> PlaylistRoom {
+		let mut inner = self.inner.lock().unwrap();
+		let room = inner.entry(playlist_id.to_string()).or_default();
+		room.subscribers.entry(user_id.to_string()).or_insert_with(|| Presence {
+			user_id: user_id.to_string(),
+			status: PresenceStatus::Viewing,
+			since: Utc::now().to_rfc3339(),
+		});
+		room.clone()
+	}
+
+	// The room goes away with its last subscriber, None if the user wasn't subscribed
+	pub fn unsubscribe(&self, playlist_id: &str, user_id: &str) -> Option<PlaylistRoom> {
+		let mut inner = self.inner.lock().unwrap();
+		let room = inner.get_mut(playlist_id)?;
+		room.subscribers.remove(user_id)?;
+		let room = room.clone();
+		if room.subscribers.is_empty() {
+			inner.remove(playlist_id);
+		}
+		Some(room)
+	}
+
+	pub fn set_status(&self, playlist_id: &str, user_id: &str, status: PresenceStatus) -> Result<PlaylistRoom, String> {
+		let mut inner = self.inner.lock().unwrap();
+		let presence = inner
+			.get_mut(playlist_id)
+			.and_then(|room| room.subscribers.get_mut(user_id))
+			.ok_or_else(|| format!("{user_id} is not subscribed to playlist {playlist_id}"))?;
+		if presence.status != status {
+			presence.status = status;
+			presence.since = Utc::now().to_rfc3339();
+		}
+		Ok(inner.get(playlist_id).unwrap().clone())
+	}
+
+	// Sends the presence of the room to all its subscribers
+	pub fn broadcast_presence(&self, playlist_id: &str, room: &PlaylistRoom, user_pool: &UserPool) {
+		let response = SocketResponse {
+			op_code: OpCode::OK,
+			r#for: OpCode::PLAYLIST_PRESENCE,
+			value: json!({
+				"playlist_id": playlist_id,
+				"presence": room.presence(),
+			}),
+		}
+		.to_string();
+		for user_id in room.subscribers.keys() {
+			if let Some(conn) = user_pool.get(user_id) {
+				let _ = conn.send(Message::Text(response.clone()));
+			}
+		}
+	}
+
+	// Sends the change along with the current order of the playlist to every subscriber, the actor included so that
+	// their other views stay in sync. Has to be called after the change is committed.
+	// Returns the new revision, None when nobody is subscribed.
+	pub fn publish(
+		&self,
+		user_pool: &UserPool,
+		db_conn: &mut SqliteConnection,
+		playlist_id: &str,
+		actor_id: &str,
+		change: Value,
+	) -> QueryResult<Option<u64>> {
+		// Held until the event is sent, so that revisions go out in the order the playlist was read in
+		let mut inner = self.inner.lock().unwrap();
+		let Some(room) = inner.get_mut(playlist_id) else {
+			return Ok(None);
+		};
+		let order: Vec<String> = playlist::load_order(db_conn, playlist_id)?
+			.into_iter()
+			.map(|(item_id, _)| item_id)
+			.collect();
+		room.revision += 1;
+
+		let response = SocketResponse {
+			op_code: OpCode::OK,
+			r#for: OpCode::PLAYLIST_EVENT,
+			value: json!({
+				"playlist_id": playlist_id,
+				"revision": room.revision,
+				"actor_id": actor_id,
+				"change": change,
+				"order": order,
+			}),
+		}
+		.to_string();
+		for user_id in room.subscribers.keys() {
+			if let Some(conn) = user_pool.get(user_id) {
+				let _ = conn.send(Message::Text(response.clone()));
+			}
+		}
+		Ok(Some(room.revision))
+	}
+
+	pub fn publish_change(
+		&self,
+		user_pool: &UserPool,
+		db_conn: &mut SqliteConnection,
+		playlist_id: &str,
+		actor_id: &str,
+		change: &Change,
+	) -> QueryResult<Option<u64>> {
+		self.publish(user_pool, db_conn, playlist_id, actor_id, serde_json::to_value(change).unwrap())
+	}
+
+	// Undoing can touch anything in the playlist, subscribers get the resulting order and refetch the rest
+	pub fn publish_undo(
+		&self,
+		user_pool: &UserPool,
+		db_conn: &mut SqliteConnection,
+		playlist_id: &str,
+		actor_id: &str,
+		undone: usize,
+	) -> QueryResult<Option<u64>> {
+		let change = json!({
+			"kind": "undo",
+			"undone": undone,
+		});
+		self.publish(user_pool, db_conn, playlist_id, actor_id, change)
+	}
+}
@@ -0,0 +1,294 @@
+// User profiles.
+//
+// Every user has a public profile: a display name, a bio, a profile picture, pronouns, a country and the date they
+// joined, along with their public playlists and, unless they turned it off, their top artists and listening stats.
+// Viewers also see the friends they have in common with the user. Email addresses never show up on profiles or in
+// search results, only the user themselves gets theirs back.
+
+use crate::core::friendship;
+use crate::core::playlist_access::Visibility;
+use crate::core::stats::{self, GroupStat, ListeningSummary, StatsDimension, StatsRange};
+use crate::lobic_db::models::{Playlist, PlaylistInfo, User, UserDataResponse, UserProfile};
+use crate::schema::{playlists, user_profiles, users};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 50;
+pub const MAX_BIO_LENGTH: usize = 300;
+pub const MAX_PRONOUNS_LENGTH: usize = 30;
+
+// What the profile shows of the listening of the user
+const TOP_ARTISTS_COUNT: i64 = 5;
+const STATS_RANGE: &str = "30d";
+
+#[derive(Debug)]
+pub enum ProfileError {
+	TooLong(&'static str, usize),
+	InvalidCountry(String),
+	Database(diesel::result::Error),
+}
+
+impl fmt::Display for ProfileError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ProfileError::TooLong(field, max) => write!(f, "The {field} can be at most {max} characters"),
+			ProfileError::InvalidCountry(country) => {
+				write!(f, "Invalid country: {country}, expected a two letter country code")
+			}
+			ProfileError::Database(err) => write!(f, "Database error: {err}"),
+		}
+	}
+}
+
+impl From<diesel::result::Error> for ProfileError {
+	fn from(err: diesel::result::Error) -> ProfileError {
+		ProfileError::Database(err)
+	}
+}
+
+// The fields that are left out stay as they are, empty ones are cleared
+#[derive(Debug, Deserialize)]
+pub struct ProfileUpdate {
+	pub display_name: Option<String>,
+	pub bio: Option<String>,
+	pub pronouns: Option<String>,
+	pub country: Option<String>,
+	pub show_top_artists: Option<bool>,
+	pub show_stats: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileResponse {
+	pub user_id: String,
+	pub username: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub email: Option<String>, // only on the profile of the user themselves
+	pub display_name: Option<String>,
+	pub bio: String,
+	pub avatar: Option<String>,
+	pub pronouns: Option<String>,
+	pub country: Option<String>,
+	pub joined_at: String,
+	pub show_top_artists: bool,
+	pub show_stats: bool,
+	pub friend_count: usize,
+	pub public_playlists: Vec<PlaylistInfo>,
+	pub top_artists: Option<Vec<GroupStat>>,
+	pub stats: Option<ListeningSummary>,
+	pub mutual_friends: Vec<UserDataResponse>,
+}
+
+// Created along with the user
+pub fn create(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<UserProfile> {
+	let profile = UserProfile {
+		user_id: user_id.to_string(),
+		display_name: None,
+		bio: String::new(),
+		avatar: None,
+		pronouns: None,
+		country: None,
+		joined_at: Utc::now().to_rfc3339(),
+		show_top_artists: true,
+		show_stats: true,
+	};
+	diesel::insert_or_ignore_into(user_profiles::table)
+		.values(&profile)
+		.execute(db_conn)?;
+	Ok(profile)
+}
+
+pub fn profile_of(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<UserProfile> {
+	match user_profiles::table
+		.filter(user_profiles::user_id.eq(user_id))
+		.first::<UserProfile>(db_conn)
+		.optional()?
+	{
+		Some(profile) => Ok(profile),
+		None => create(db_conn, user_id),
+	}
+}
+
+// The parts of the listening of the user they can hide from others
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListeningSection {
+	Stats,
+	TopArtists,
+}
+
+// Whether the viewer gets to see that part of the listening of the user, the user always sees their own
+pub fn shows(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	viewer_id: Option<&str>,
+	section: ListeningSection,
+) -> QueryResult<bool> {
+	if viewer_id == Some(user_id) {
+		return Ok(true);
+	}
+	// Looked up without creating the profile, the user might not exist. New profiles show everything
+	let profile = user_profiles::table
+		.filter(user_profiles::user_id.eq(user_id))
+		.first::<UserProfile>(db_conn)
+		.optional()?;
+	Ok(match (profile, section) {
+		(Some(profile), ListeningSection::Stats) => profile.show_stats,
+		(Some(profile), ListeningSection::TopArtists) => profile.show_top_artists,
+		(None, _) => true,
+	})
+}
+
+// Trimmed, None when empty
+fn text_field(value: String, field: &'static str, max: usize) -> Result<Option<String>, ProfileError> {
+	let value = value.trim();
+	if value.chars().count() > max {
+		return Err(ProfileError::TooLong(field, max));
+	}
+	Ok(Some(value.to_string()).filter(|value| !value.is_empty()))
+}
+
+pub fn update(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	changes: ProfileUpdate,
+) -> Result<UserProfile, ProfileError> {
+	let mut profile = profile_of(db_conn, user_id)?;
+	if let Some(display_name) = changes.display_name {
+		profile.display_name = text_field(display_name, "display name", MAX_DISPLAY_NAME_LENGTH)?;
+	}
+	if let Some(bio) = changes.bio {
+		profile.bio = text_field(bio, "bio", MAX_BIO_LENGTH)?.unwrap_or_default();
+	}
+	if let Some(pronouns) = changes.pronouns {
+		profile.pronouns = text_field(pronouns, "pronouns", MAX_PRONOUNS_LENGTH)?;
+	}
+	if let Some(country) = changes.country {
+		let country = country.trim().to_uppercase();
+		if !country.is_empty() && (country.len() != 2 || !country.chars().all(|c| c.is_ascii_uppercase())) {
+			return Err(ProfileError::InvalidCountry(country));
+		}
+		profile.country = Some(country).filter(|country| !country.is_empty());
+	}
+	profile.show_top_artists = changes.show_top_artists.unwrap_or(profile.show_top_artists);
+	profile.show_stats = changes.show_stats.unwrap_or(profile.show_stats);
+
+	diesel::replace_into(user_profiles::table)
+		.values(&profile)
+		.execute(db_conn)?;
+	Ok(profile)
+}
+
+pub fn set_avatar(db_conn: &mut SqliteConnection, user_id: &str, avatar: &str) -> QueryResult<()> {
+	profile_of(db_conn, user_id)?;
+	diesel::update(user_profiles::table.filter(user_profiles::user_id.eq(user_id)))
+		.set(user_profiles::avatar.eq(avatar))
+		.execute(db_conn)?;
+	Ok(())
+}
+
+// Display names of the users who set one
+pub fn display_names(db_conn: &mut SqliteConnection, user_ids: &[String]) -> QueryResult<HashMap<String, String>> {
+	let names: Vec<(String, Option<String>)> = user_profiles::table
+		.filter(user_profiles::user_id.eq_any(user_ids))
+		.select((user_profiles::user_id, user_profiles::display_name))
+		.load(db_conn)?;
+	Ok(names
+		.into_iter()
+		.filter_map(|(user_id, display_name)| display_name.map(|name| (user_id, name)))
+		.collect())
+}
+
+// What other users see of the given ones
+pub fn people(db_conn: &mut SqliteConnection, entries: Vec<User>) -> QueryResult<Vec<UserDataResponse>> {
+	let user_ids: Vec<String> = entries.iter().map(|entry| entry.user_id.clone()).collect();
+	let mut names = display_names(db_conn, &user_ids)?;
+	Ok(entries
+		.into_iter()
+		.map(|entry| UserDataResponse {
+			display_name: names.remove(&entry.user_id),
+			user_id: entry.user_id,
+			username: entry.username,
+		})
+		.collect())
+}
+
+fn mutual_friends(db_conn: &mut SqliteConnection, user_id: &str, viewer_id: &str) -> QueryResult<Vec<User>> {
+	let theirs: HashSet<String> = friendship::friends_of(db_conn, user_id)?.into_iter().collect();
+	let mutual: Vec<String> = friendship::friends_of(db_conn, viewer_id)?
+		.into_iter()
+		.filter(|friend_id| theirs.contains(friend_id))
+		.collect();
+	users::table
+		.filter(users::user_id.eq_any(mutual))
+		.order(users::username.asc())
+		.load(db_conn)
+}
+
+// The profile as the viewer sees it. is_self is for the user themselves, who also gets their email and whatever
+// they chose to hide from others
+pub fn view(
+	db_conn: &mut SqliteConnection,
+	user: User,
+	viewer_id: Option<&str>,
+	is_self: bool,
+) -> QueryResult<ProfileResponse> {
+	let profile = profile_of(db_conn, &user.user_id)?;
+	let friend_count = friendship::friends_of(db_conn, &user.user_id)?.len();
+	let public_playlists = playlists::table
+		.filter(playlists::user_id.eq(&user.user_id))
+		.filter(playlists::deleted_at.is_null())
+		.filter(playlists::visibility.eq(Visibility::Public.as_str()))
+		.order(playlists::last_updated_date_time.desc())
+		.load::<Playlist>(db_conn)?
+		.into_iter()
+		.map(PlaylistInfo::from)
+		.collect();
+
+	let range = StatsRange::parse(STATS_RANGE).unwrap();
+	let top_artists = if profile.show_top_artists || is_self {
+		Some(stats::top_grouped(
+			db_conn,
+			&user.user_id,
+			&range,
+			StatsDimension::Artist,
+			0,
+			Some(TOP_ARTISTS_COUNT),
+		)?)
+	} else {
+		None
+	};
+	let stats = if profile.show_stats || is_self {
+		Some(stats::summary(db_conn, &user.user_id, &range)?)
+	} else {
+		None
+	};
+	let mutual_friends = match viewer_id.filter(|viewer_id| *viewer_id != user.user_id) {
+		Some(viewer_id) => {
+			let mutual = mutual_friends(db_conn, &user.user_id, viewer_id)?;
+			people(db_conn, mutual)?
+		}
+		None => Vec::new(),
+	};
+
+	Ok(ProfileResponse {
+		email: is_self.then_some(user.email),
+		user_id: user.user_id,
+		username: user.username,
+		display_name: profile.display_name,
+		bio: profile.bio,
+		avatar: profile.avatar,
+		pronouns: profile.pronouns,
+		country: profile.country,
+		joined_at: profile.joined_at,
+		show_top_artists: profile.show_top_artists,
+		show_stats: profile.show_stats,
+		friend_count,
+		public_playlists,
+		top_artists,
+		stats,
+		mutual_friends,
+	})
+}
@@ -0,0 +1,310 @@
+// Endless radio stations generated out of a seed track, artist, genre or playlist.
+//
+// Every track of the library gets a score: how close it is to the seed (collaborative similarity plus shared
+// artist/genre), how well it fits the listener's history and a bit of popularity. Every page is laid out after the
+// last tracks the station played (the rolling window): they are pulled in as extra seeds so the station drifts,
+// they aren't repeated and their artists are kept apart from the first tracks of the page. Pages are shuffled
+// slightly by an rng seeded with (session, start_index), so the same request always gets the same page and the
+// stream has no end.
+
+use crate::core::playlist_access;
+use crate::core::recommender::{self, Recommender};
+use crate::lobic_db::models::{Music, Playlist};
+use crate::schema::{music, playlist_songs, playlists};
+
+use diesel::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// Minimum number of tracks between two tracks of the same artist
+pub const DEFAULT_ARTIST_WINDOW: usize = 4;
+// Number of the last played tracks of the station a page is laid out after
+pub const RADIO_WINDOW_LENGTH: usize = 20;
+// Upper bound of page_length
+pub const MAX_RADIO_PAGE_LENGTH: usize = 100;
+
+const SIMILARITY_WEIGHT: f64 = 1.0;
+// Similarity to the tracks of the window relative to the similarity to the seed
+const WINDOW_SIMILARITY_WEIGHT: f64 = 0.5;
+const ARTIST_WEIGHT: f64 = 0.6;
+const GENRE_WEIGHT: f64 = 0.4;
+const HISTORY_WEIGHT: f64 = 0.3;
+const POPULARITY_WEIGHT: f64 = 0.05;
+// Scores are multiplied by a random factor in 1 +/- JITTER
+const JITTER: f64 = 0.2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "seed_type", content = "seed", rename_all = "snake_case")]
+pub enum RadioSeed {
+	Music(String),
+	Artist(String),
+	Genre(String),
+	Playlist(String),
+}
+
+impl RadioSeed {
+	pub fn parse(seed_type: &str, seed: &str) -> Option<RadioSeed> {
+		match seed_type {
+			"music" | "music_id" => Some(RadioSeed::Music(seed.to_string())),
+			"artist" => Some(RadioSeed::Artist(seed.to_string())),
+			"genre" => Some(RadioSeed::Genre(seed.to_string())),
+			"playlist" | "playlist_id" => Some(RadioSeed::Playlist(seed.to_string())),
+			_ => None,
+		}
+	}
+
+	// Whether the listener may start a station from the seed, only playlists are restricted
+	pub fn is_visible_to(&self, db_conn: &mut SqliteConnection, user_id: Option<&str>) -> QueryResult<bool> {
+		match self {
+			RadioSeed::Playlist(playlist_id) => Ok(seed_playlist_tracks(db_conn, playlist_id, user_id)?.is_some()),
+			_ => Ok(true),
+		}
+	}
+}
+
+// Tracks of the seed playlist, None if it is deleted or the listener can't see it
+fn seed_playlist_tracks(
+	db_conn: &mut SqliteConnection,
+	playlist_id: &str,
+	user_id: Option<&str>,
+) -> QueryResult<Option<Vec<String>>> {
+	let playlist = playlists::table
+		.filter(playlists::playlist_id.eq(playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(db_conn)
+		.optional()?;
+	match playlist {
+		Some(playlist) if playlist_access::can_read(db_conn, &playlist, user_id, None)? => playlist_songs::table
+			.filter(playlist_songs::playlist_id.eq(playlist_id))
+			.select(playlist_songs::music_id)
+			.load::<String>(db_conn)
+			.map(Some),
+		_ => Ok(None),
+	}
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+	music_id: String,
+	artist: String,
+	score: f64,
+}
+
+// Scores every track of the library against the seed, the tracks played last and the listener
+fn score_candidates(
+	db_conn: &mut SqliteConnection,
+	recommender: &Recommender,
+	seed: &RadioSeed,
+	window: &[String],
+	user_id: Option<&str>,
+) -> QueryResult<Vec<Candidate>> {
+	let library = music::table.load::<Music>(db_conn)?;
+
+	// Tracks the seed is made of
+	let seed_tracks: Vec<String> = match seed {
+		RadioSeed::Music(music_id) => vec![music_id.clone()],
+		RadioSeed::Artist(artist) => library
+			.iter()
+			.filter(|entry| entry.artist.eq_ignore_ascii_case(artist))
+			.map(|entry| entry.music_id.clone())
+			.collect(),
+		RadioSeed::Genre(_) => Vec::new(),
+		// The playlist might have been deleted or hidden since the station started
+		RadioSeed::Playlist(playlist_id) => seed_playlist_tracks(db_conn, playlist_id, user_id)?.unwrap_or_default(),
+	};
+
+	let mut seed_artists = HashSet::new();
+	let mut seed_genres = HashSet::new();
+	for entry in library.iter().filter(|entry| seed_tracks.contains(&entry.music_id)) {
+		seed_artists.insert(entry.artist.to_lowercase());
+		seed_genres.insert(entry.genre.to_lowercase());
+	}
+	match seed {
+		RadioSeed::Artist(artist) => {
+			seed_artists.insert(artist.to_lowercase());
+		}
+		RadioSeed::Genre(genre) => {
+			seed_genres.insert(genre.to_lowercase());
+		}
+		_ => (),
+	}
+
+	let mut similarity: HashMap<String, f64> = HashMap::new();
+	for music_id in &seed_tracks {
+		for (neighbour, score) in recommender.similar(music_id) {
+			*similarity.entry(neighbour).or_insert(0.0) += score;
+		}
+	}
+	if !window.is_empty() {
+		// Normalized by the size of the window so it doesn't drown out a single seed track
+		let weight = WINDOW_SIMILARITY_WEIGHT * seed_tracks.len().max(1) as f64 / window.len() as f64;
+		for music_id in window {
+			for (neighbour, score) in recommender.similar(music_id) {
+				*similarity.entry(neighbour).or_insert(0.0) += weight * score;
+			}
+		}
+	}
+	let max_similarity = similarity.values().cloned().fold(0.0, f64::max);
+
+	let history = match user_id {
+		Some(user_id) => {
+			let profile = recommender::load_user_profile(db_conn, user_id)?;
+			let mut history: HashMap<String, f64> = recommender.recommend(&profile).into_iter().collect();
+			for (music_id, weight) in profile {
+				*history.entry(music_id).or_insert(0.0) += weight;
+			}
+			history
+		}
+		None => HashMap::new(),
+	};
+	let max_history = history.values().cloned().fold(0.0, f64::max);
+
+	let max_plays = library.iter().map(|entry| entry.times_played).max().unwrap_or(0).max(1) as f64;
+
+	let candidates = library
+		.into_iter()
+		// The seed track is the one playing already
+		.filter(|entry| !matches!(seed, RadioSeed::Music(music_id) if *music_id == entry.music_id))
+		.map(|entry| {
+			let mut score = 0.0;
+			if max_similarity > 0.0 {
+				score += SIMILARITY_WEIGHT * similarity.get(&entry.music_id).unwrap_or(&0.0) / max_similarity;
+			}
+			if seed_artists.contains(&entry.artist.to_lowercase()) {
+				score += ARTIST_WEIGHT;
+			}
+			if seed_genres.contains(&entry.genre.to_lowercase()) {
+				score += GENRE_WEIGHT;
+			}
+			if max_history > 0.0 {
+				score += HISTORY_WEIGHT * history.get(&entry.music_id).unwrap_or(&0.0) / max_history;
+			}
+			score += POPULARITY_WEIGHT * (1.0 + entry.times_played.max(0) as f64).ln() / (1.0 + max_plays).ln();
+
+			Candidate {
+				music_id: entry.music_id,
+				artist: entry.artist.to_lowercase(),
+				score,
+			}
+		})
+		.collect();
+
+	Ok(candidates)
+}
+
+// Lays out `length` tracks following the `window` tracks.
+// No artist (nor track) is repeated within `artist_window` tracks unless the library leaves no other choice, the
+// tracks of the window only come back once the rest of the library has been played.
+fn layout(
+	candidates: &[Candidate],
+	rng_seed: u64,
+	artist_window: usize,
+	window: &[String],
+	length: usize,
+) -> Vec<String> {
+	let mut stream: Vec<String> = Vec::with_capacity(length);
+	if candidates.is_empty() {
+		return stream;
+	}
+
+	let mut recent_artists: VecDeque<&str> = VecDeque::with_capacity(artist_window + 1);
+	let mut recent_tracks: VecDeque<&str> = VecDeque::with_capacity(artist_window + 1);
+	let by_id: HashMap<&str, &Candidate> = candidates
+		.iter()
+		.map(|candidate| (candidate.music_id.as_str(), candidate))
+		.collect();
+	for music_id in window.iter().rev().take(artist_window).rev() {
+		if let Some(candidate) = by_id.get(music_id.as_str()) {
+			recent_artists.push_back(&candidate.artist);
+			recent_tracks.push_back(&candidate.music_id);
+		}
+	}
+	while recent_tracks.len() > artist_window.min(candidates.len() - 1) {
+		recent_tracks.pop_front();
+	}
+
+	let mut rng = StdRng::seed_from_u64(rng_seed);
+	let mut first_round = true;
+
+	while stream.len() < length {
+		let mut pool: Vec<(&Candidate, f64)> = candidates
+			.iter()
+			.filter(|candidate| !first_round || !window.contains(&candidate.music_id))
+			.map(|candidate| (candidate, candidate.score * rng.random_range(1.0 - JITTER..=1.0 + JITTER)))
+			.collect();
+		pool.sort_by(|a, b| {
+			b.1.partial_cmp(&a.1)
+				.unwrap_or(std::cmp::Ordering::Equal)
+				.then(a.0.music_id.cmp(&b.0.music_id))
+		});
+
+		while !pool.is_empty() && stream.len() < length {
+			let pick = pool
+				.iter()
+				.position(|(candidate, _)| {
+					!recent_artists.contains(&candidate.artist.as_str())
+						&& !recent_tracks.contains(&candidate.music_id.as_str())
+				})
+				.or_else(|| {
+					pool.iter()
+						.position(|(candidate, _)| !recent_tracks.contains(&candidate.music_id.as_str()))
+				})
+				.unwrap_or(0);
+			let (candidate, _) = pool.remove(pick);
+
+			stream.push(candidate.music_id.clone());
+			recent_artists.push_back(&candidate.artist);
+			recent_tracks.push_back(&candidate.music_id);
+			if recent_artists.len() > artist_window {
+				recent_artists.pop_front();
+			}
+			if recent_tracks.len() > artist_window.min(candidates.len() - 1) {
+				recent_tracks.pop_front();
+			}
+		}
+
+		first_round = false;
+	}
+
+	stream
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RadioStation {
+	#[serde(flatten)]
+	pub seed: RadioSeed,
+	pub session: u64,
+	pub artist_window: usize,
+}
+
+impl RadioStation {
+	pub fn new(seed: RadioSeed, session: Option<u64>, artist_window: Option<usize>) -> RadioStation {
+		RadioStation {
+			seed,
+			// Kept within u32 so that javascript clients can pass it back without losing precision
+			session: session.unwrap_or_else(|| rand::rng().random::<u32>() as u64),
+			artist_window: artist_window.unwrap_or(DEFAULT_ARTIST_WINDOW),
+		}
+	}
+
+	// Returns `page_length` tracks of the station starting at `start_index`.
+	// `window` holds the last tracks of the station that were played (oldest first), only the last
+	// `RADIO_WINDOW_LENGTH` of them are used.
+	pub fn generate(
+		&self,
+		db_conn: &mut SqliteConnection,
+		recommender: &Recommender,
+		user_id: Option<&str>,
+		window: &[String],
+		start_index: usize,
+		page_length: usize,
+	) -> QueryResult<Vec<Music>> {
+		let window = &window[window.len().saturating_sub(RADIO_WINDOW_LENGTH)..];
+		let candidates = score_candidates(db_conn, recommender, &self.seed, window, user_id)?;
+		let rng_seed = self.session.wrapping_add((start_index as u64) << 32);
+		let length = page_length.min(MAX_RADIO_PAGE_LENGTH);
+		let ids = layout(&candidates, rng_seed, self.artist_window, window, length);
+		recommender::load_music_in_order(db_conn, &ids)
+	}
+}
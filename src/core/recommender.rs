@@ -0,0 +1,418 @@
+// Item to item collaborative filtering.
+//
+// Every user is a sparse vector over the tracks they interacted with (plays, likes and playlist membership),
+// two tracks are similar when the same users keep coming back to both of them (cosine similarity of the
+// track columns of the user x track matrix). The neighbours of every track are computed by a background job
+// and kept in memory, requests only combine the neighbours of the tracks a user already knows.
+// Skips count against a track: for the user who skipped it, and globally through its skip rate which damps its
+// similarity to everything else.
+
+use crate::lobic_db::db::DatabasePool;
+use crate::lobic_db::models::{ListenSource, Music};
+use crate::schema::{liked_songs, listens, music, play_log, playlist_blends, playlist_songs, playlists};
+
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+pub const RECOMMENDER_REFRESH_INTERVAL_SECS: u64 = 30 * 60;
+
+// Number of neighbours kept per track
+const MAX_NEIGHBOURS: usize = 50;
+// Only the strongest interactions of very active users are used, otherwise they dominate the co-occurrences
+const MAX_ITEMS_PER_USER: usize = 200;
+
+const LIKE_WEIGHT: f64 = 3.0;
+const PLAYLIST_WEIGHT: f64 = 2.0;
+// Every skip takes away half of a play
+const SKIP_WEIGHT: f64 = 0.5;
+// How much of the similarity is lost by a track that is always skipped
+const SKIP_RATE_PENALTY: f64 = 0.5;
+// Prior of completed listens so a single skip doesn't sink a track that was barely listened to
+const SKIP_RATE_PRIOR: f64 = 2.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interaction {
+	pub user_id: String,
+	pub music_id: String,
+	pub weight: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct SimilarityIndex {
+	neighbours: HashMap<String, Vec<(String, f64)>>,
+}
+
+impl SimilarityIndex {
+	// Builds the index out of the raw interactions, repeated (user, track) pairs are summed up.
+	// Pairs that end up with no positive weight (mostly skipped) are not considered an interaction.
+	pub fn build(interactions: &[Interaction], skip_rates: &HashMap<String, f64>) -> SimilarityIndex {
+		let mut user_items: HashMap<&str, HashMap<&str, f64>> = HashMap::new();
+		for interaction in interactions {
+			*user_items
+				.entry(&interaction.user_id)
+				.or_default()
+				.entry(&interaction.music_id)
+				.or_insert(0.0) += interaction.weight;
+		}
+
+		let mut norms: HashMap<&str, f64> = HashMap::new();
+		let mut dots: HashMap<(&str, &str), f64> = HashMap::new();
+		for items in user_items.values() {
+			let mut items: Vec<(&str, f64)> = items
+				.iter()
+				.filter(|(_, weight)| **weight > 0.0)
+				.map(|(id, weight)| (*id, *weight))
+				.collect();
+			items.sort_by(|a, b| {
+				b.1.partial_cmp(&a.1)
+					.unwrap_or(std::cmp::Ordering::Equal)
+					.then(a.0.cmp(b.0))
+			});
+			items.truncate(MAX_ITEMS_PER_USER);
+
+			for (idx, (a, weight_a)) in items.iter().enumerate() {
+				*norms.entry(a).or_insert(0.0) += weight_a * weight_a;
+				for (b, weight_b) in &items[idx + 1..] {
+					let key = if a < b { (*a, *b) } else { (*b, *a) };
+					*dots.entry(key).or_insert(0.0) += weight_a * weight_b;
+				}
+			}
+		}
+
+		let mut neighbours: HashMap<String, Vec<(String, f64)>> = HashMap::new();
+		for ((a, b), dot) in dots {
+			let similarity = dot / (norms[a].sqrt() * norms[b].sqrt());
+			let damping = |id: &str| 1.0 - SKIP_RATE_PENALTY * skip_rates.get(id).copied().unwrap_or(0.0);
+			neighbours
+				.entry(a.to_string())
+				.or_default()
+				.push((b.to_string(), similarity * damping(b)));
+			neighbours
+				.entry(b.to_string())
+				.or_default()
+				.push((a.to_string(), similarity * damping(a)));
+		}
+		for list in neighbours.values_mut() {
+			sort_by_score(list);
+			list.truncate(MAX_NEIGHBOURS);
+		}
+
+		SimilarityIndex { neighbours }
+	}
+
+	// Most similar tracks first
+	pub fn similar(&self, music_id: &str) -> &[(String, f64)] {
+		self.neighbours.get(music_id).map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	// Scores the neighbours of the given (track, weight) profile, tracks already in the profile are left out.
+	// Negative weights (skipped tracks) push their neighbours down.
+	pub fn recommend(&self, profile: &HashMap<String, f64>) -> Vec<(String, f64)> {
+		let mut scores: HashMap<&str, f64> = HashMap::new();
+		for (music_id, weight) in profile {
+			for (neighbour, similarity) in self.similar(music_id) {
+				if profile.contains_key(neighbour) {
+					continue;
+				}
+				*scores.entry(neighbour).or_insert(0.0) += weight * similarity;
+			}
+		}
+
+		let mut ranked: Vec<(String, f64)> = scores.into_iter().map(|(id, score)| (id.to_string(), score)).collect();
+		sort_by_score(&mut ranked);
+		ranked
+	}
+}
+
+fn sort_by_score(list: &mut [(String, f64)]) {
+	list.sort_by(|a, b| {
+		b.1.partial_cmp(&a.1)
+			.unwrap_or(std::cmp::Ordering::Equal)
+			.then(a.0.cmp(&b.0))
+	});
+}
+
+#[derive(Debug, Clone)]
+pub struct Recommender {
+	index: Arc<RwLock<SimilarityIndex>>,
+}
+
+impl Recommender {
+	pub fn new() -> Recommender {
+		Recommender {
+			index: Arc::new(RwLock::new(SimilarityIndex::default())),
+		}
+	}
+
+	pub fn replace(&self, index: SimilarityIndex) {
+		let mut inner = self.index.write().unwrap();
+		*inner = index;
+	}
+
+	pub fn similar(&self, music_id: &str) -> Vec<(String, f64)> {
+		let inner = self.index.read().unwrap();
+		inner.similar(music_id).to_vec()
+	}
+
+	pub fn recommend(&self, profile: &HashMap<String, f64>) -> Vec<(String, f64)> {
+		let inner = self.index.read().unwrap();
+		inner.recommend(profile)
+	}
+}
+
+// Plays are damped logarithmically so that a song on repeat doesn't outweigh an explicit like
+fn play_weight(times_played: i32) -> f64 {
+	1.0 + (times_played.max(1) as f64).ln()
+}
+
+fn load_interactions(db_conn: &mut SqliteConnection, user_id: Option<&str>) -> QueryResult<Vec<Interaction>> {
+	let mut plays = play_log::table
+		.select((play_log::user_id, play_log::music_id, play_log::user_times_played))
+		.into_boxed();
+	let mut skips = listens::table
+		.filter(listens::skipped.eq(true))
+		.group_by((listens::user_id, listens::music_id))
+		.select((listens::user_id, listens::music_id, sql::<BigInt>("COUNT(*)")))
+		.into_boxed();
+	let mut likes = liked_songs::table
+		.select((liked_songs::user_id, liked_songs::music_id))
+		.into_boxed();
+	// Songs count for the owner of the playlist, combined playlists also for whoever added them.
+	// The songs of blends are picked out of these interactions, counting them again would feed them back in.
+	let blends = playlist_blends::table.select(playlist_blends::playlist_id);
+	let mut owned = playlist_songs::table
+		.inner_join(playlists::table)
+		.filter(playlists::deleted_at.is_null())
+		.filter(playlists::playlist_id.ne_all(blends))
+		.select((playlists::user_id, playlist_songs::music_id))
+		.into_boxed();
+	let mut added = playlist_songs::table
+		.inner_join(playlists::table)
+		.filter(playlists::deleted_at.is_null())
+		.filter(playlists::playlist_id.ne_all(blends))
+		.select((playlist_songs::song_adder_id, playlist_songs::music_id))
+		.into_boxed();
+
+	if let Some(user_id) = user_id {
+		plays = plays.filter(play_log::user_id.eq(user_id));
+		skips = skips.filter(listens::user_id.eq(user_id));
+		likes = likes.filter(liked_songs::user_id.eq(user_id));
+		owned = owned.filter(playlists::user_id.eq(user_id));
+		added = added.filter(playlist_songs::song_adder_id.eq(user_id));
+	}
+
+	let mut interactions = Vec::new();
+	for (user_id, music_id, times_played) in plays.load::<(String, String, i32)>(db_conn)? {
+		interactions.push(Interaction {
+			user_id,
+			music_id,
+			weight: play_weight(times_played),
+		});
+	}
+	for (user_id, music_id, skips) in skips.load::<(String, String, i64)>(db_conn)? {
+		interactions.push(Interaction {
+			user_id,
+			music_id,
+			weight: -SKIP_WEIGHT * skips as f64,
+		});
+	}
+	for (user_id, music_id) in likes.load::<(String, String)>(db_conn)? {
+		interactions.push(Interaction {
+			user_id,
+			music_id,
+			weight: LIKE_WEIGHT,
+		});
+	}
+
+	let mut in_playlist = HashSet::new();
+	in_playlist.extend(owned.load::<(String, String)>(db_conn)?);
+	in_playlist.extend(added.load::<(String, String)>(db_conn)?);
+	for (user_id, music_id) in in_playlist {
+		interactions.push(Interaction {
+			user_id,
+			music_id,
+			weight: PLAYLIST_WEIGHT,
+		});
+	}
+
+	Ok(interactions)
+}
+
+// Weighted tracks the user already knows
+pub fn load_user_profile(db_conn: &mut SqliteConnection, user_id: &str) -> QueryResult<HashMap<String, f64>> {
+	let mut profile = HashMap::new();
+	for interaction in load_interactions(db_conn, Some(user_id))? {
+		*profile.entry(interaction.music_id).or_insert(0.0) += interaction.weight;
+	}
+	Ok(profile)
+}
+
+// Share of the listens of every track that were skipped
+fn load_skip_rates(db_conn: &mut SqliteConnection) -> QueryResult<HashMap<String, f64>> {
+	let rows = listens::table
+		.filter(listens::source_type.ne(ListenSource::Import.as_str()))
+		.group_by(listens::music_id)
+		.select((
+			listens::music_id,
+			sql::<BigInt>("SUM(CASE WHEN skipped THEN 1 ELSE 0 END)"),
+			sql::<BigInt>("SUM(CASE WHEN completed THEN 1 ELSE 0 END)"),
+		))
+		.load::<(String, i64, i64)>(db_conn)?;
+
+	Ok(rows
+		.into_iter()
+		.filter(|(_, skips, _)| *skips > 0)
+		.map(|(music_id, skips, completed)| {
+			let rate = skips as f64 / ((skips + completed) as f64 + SKIP_RATE_PRIOR);
+			(music_id, rate)
+		})
+		.collect())
+}
+
+// Loads the given tracks keeping the order of the ids, unknown ids are skipped
+pub fn load_music_in_order(db_conn: &mut SqliteConnection, ids: &[String]) -> QueryResult<Vec<Music>> {
+	let musics: HashMap<String, Music> = music::table
+		.filter(music::music_id.eq_any(ids))
+		.load::<Music>(db_conn)?
+		.into_iter()
+		.map(|entry| (entry.music_id.clone(), entry))
+		.collect();
+	Ok(ids.iter().filter_map(|id| musics.get(id).cloned()).collect())
+}
+
+pub fn rebuild_index(db_pool: &DatabasePool, recommender: &Recommender) -> Result<(), String> {
+	let mut db_conn = db_pool
+		.get()
+		.map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+	let interactions =
+		load_interactions(&mut db_conn, None).map_err(|err| format!("Failed to load interactions: {err}"))?;
+	let skip_rates = load_skip_rates(&mut db_conn).map_err(|err| format!("Failed to load skip rates: {err}"))?;
+	recommender.replace(SimilarityIndex::build(&interactions, &skip_rates));
+	Ok(())
+}
+
+pub fn spawn_recommender_refresher(db_pool: DatabasePool, recommender: Recommender) {
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(std::time::Duration::from_secs(RECOMMENDER_REFRESH_INTERVAL_SECS));
+		loop {
+			interval.tick().await;
+
+			let db_pool = db_pool.clone();
+			let recommender = recommender.clone();
+			let result = tokio::task::spawn_blocking(move || rebuild_index(&db_pool, &recommender)).await;
+			match result {
+				Ok(Ok(())) => (),
+				Ok(Err(err)) => println!("[recommender]: {err}"),
+				Err(err) => println!("[recommender]: Task failed: {err}"),
+			}
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn interaction(user_id: &str, music_id: &str, weight: f64) -> Interaction {
+		Interaction {
+			user_id: user_id.to_string(),
+			music_id: music_id.to_string(),
+			weight,
+		}
+	}
+
+	fn profile(entries: &[(&str, f64)]) -> HashMap<String, f64> {
+		entries.iter().map(|(id, weight)| (id.to_string(), *weight)).collect()
+	}
+
+	fn ids(ranked: &[(String, f64)]) -> Vec<&str> {
+		ranked.iter().map(|(id, _)| id.as_str()).collect()
+	}
+
+	// "a" and "b" are liked together by two users, "c" is only played once next to "a"
+	fn interactions() -> Vec<Interaction> {
+		vec![
+			interaction("u1", "a", LIKE_WEIGHT),
+			interaction("u1", "b", LIKE_WEIGHT),
+			interaction("u2", "a", LIKE_WEIGHT),
+			interaction("u2", "b", LIKE_WEIGHT),
+			interaction("u3", "a", play_weight(1)),
+			interaction("u3", "c", play_weight(1)),
+			interaction("u4", "d", LIKE_WEIGHT),
+		]
+	}
+
+	#[test]
+	fn co_liked_tracks_are_most_similar() {
+		let index = SimilarityIndex::build(&interactions(), &HashMap::new());
+
+		assert_eq!(ids(index.similar("a")), vec!["b", "c"]);
+		assert_eq!(ids(index.similar("b")), vec!["a"]);
+		// Nobody else interacted with "d"
+		assert!(index.similar("d").is_empty());
+	}
+
+	#[test]
+	fn repeated_interactions_are_summed_and_skipped_pairs_ignored() {
+		let mut interactions = interactions();
+		// u3 played "c" but skipped it more often, it doesn't count as an interaction anymore
+		interactions.push(interaction("u3", "c", -SKIP_WEIGHT * 4.0));
+		let index = SimilarityIndex::build(&interactions, &HashMap::new());
+
+		assert_eq!(ids(index.similar("a")), vec!["b"]);
+		assert!(index.similar("c").is_empty());
+	}
+
+	#[test]
+	fn skip_rate_pushes_tracks_down() {
+		// "b" and "c" are exactly as similar to "a"
+		let interactions = vec![
+			interaction("u1", "a", LIKE_WEIGHT),
+			interaction("u1", "b", LIKE_WEIGHT),
+			interaction("u2", "a", LIKE_WEIGHT),
+			interaction("u2", "c", LIKE_WEIGHT),
+		];
+		let without_skips = SimilarityIndex::build(&interactions, &HashMap::new());
+		assert_eq!(ids(&without_skips.recommend(&profile(&[("a", 1.0)]))), vec!["b", "c"]);
+
+		let skip_rates = HashMap::from([("b".to_string(), 0.8)]);
+		let index = SimilarityIndex::build(&interactions, &skip_rates);
+		let ranked = index.recommend(&profile(&[("a", 1.0)]));
+		assert_eq!(ids(&ranked), vec!["c", "b"]);
+		assert!(ranked[1].1 < without_skips.recommend(&profile(&[("a", 1.0)]))[0].1);
+	}
+
+	#[test]
+	fn skipped_tracks_push_their_neighbours_down() {
+		let index = SimilarityIndex::build(&interactions(), &HashMap::new());
+
+		// "a" is recommended because of "c", but "b" was skipped and "a" is its closest neighbour
+		let ranked = index.recommend(&profile(&[("b", -2.0), ("c", 1.0)]));
+		assert_eq!(ids(&ranked), vec!["a"]);
+		assert!(ranked[0].1 < index.recommend(&profile(&[("c", 1.0)]))[0].1);
+	}
+
+	#[test]
+	fn tracks_in_the_profile_are_not_recommended() {
+		let index = SimilarityIndex::build(&interactions(), &HashMap::new());
+
+		let ranked = index.recommend(&profile(&[("a", play_weight(3))]));
+		assert_eq!(ids(&ranked), vec!["b", "c"]);
+
+		let ranked = index.recommend(&profile(&[("a", play_weight(3)), ("b", LIKE_WEIGHT)]));
+		assert_eq!(ids(&ranked), vec!["c"]);
+	}
+
+	#[test]
+	fn recommender_serves_the_replaced_index() {
+		let recommender = Recommender::new();
+		assert!(recommender.recommend(&profile(&[("a", 1.0)])).is_empty());
+
+		recommender.replace(SimilarityIndex::build(&interactions(), &HashMap::new()));
+		assert_eq!(ids(&recommender.similar("a")), vec!["b", "c"]);
+		assert_eq!(ids(&recommender.recommend(&profile(&[("a", 1.0)]))), vec!["b", "c"]);
+	}
+}
@@ -0,0 +1,310 @@
+// Listening report ("wrapped") of a user over a month or a year, built from the listen history.
+//
+// Days and hours are computed in the timezone of the user (given as an offset from UTC) so that a late night
+// session doesn't count towards the next day's streak.
+
+use crate::lobic_db::models::{ListenSource, Music, MusicResponse};
+use crate::schema::{listens, music, user_friendship, users};
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Timelike};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[derive(Debug, Serialize)]
+pub struct TrackStat {
+	pub music: MusicResponse,
+	pub plays: i64,
+	pub minutes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NamedStat {
+	pub name: String,
+	pub plays: i64,
+	pub minutes: i64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Streak {
+	pub days: i64,
+	pub from: NaiveDate,
+	pub to: NaiveDate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LobbyStat {
+	pub lobby_id: String,
+	pub plays: i64,
+	pub minutes: i64,
+	pub listened_with: Vec<String>, // usernames
+}
+
+#[derive(Debug, Serialize)]
+pub struct FriendStat {
+	pub user_id: String,
+	pub username: String,
+	pub shared_lobbies: i64,
+	pub shared_tracks: i64, // tracks both of them listened to in the same lobby
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListeningReport {
+	pub user_id: String,
+	pub username: String,
+	pub period: String,
+	pub from: NaiveDate,
+	pub to: NaiveDate, // exclusive
+	pub total_minutes: i64,
+	pub total_plays: i64,
+	pub distinct_tracks: i64,
+	pub days_listened: i64,
+	pub longest_streak: Option<Streak>,
+	pub top_tracks: Vec<TrackStat>,
+	pub top_artists: Vec<NamedStat>,
+	pub top_albums: Vec<NamedStat>,
+	pub top_genres: Vec<NamedStat>,
+	pub heatmap: Vec<Vec<i64>>, // minutes listened, [weekday (monday first)][hour of day]
+	pub top_lobbies: Vec<LobbyStat>,
+	pub top_friends: Vec<FriendStat>,
+	pub card_url: Option<String>,
+}
+
+#[derive(Default)]
+struct Tally {
+	plays: i64,
+	seconds: i64,
+}
+
+impl Tally {
+	fn add(&mut self, seconds: i64, completed: bool) {
+		self.seconds += seconds;
+		if completed {
+			self.plays += 1;
+		}
+	}
+}
+
+// Most played first, listening time breaks the ties
+fn top<K: Clone + Ord>(tallies: &HashMap<K, Tally>, limit: usize) -> Vec<(K, i64, i64)> {
+	let mut entries: Vec<(K, i64, i64)> = tallies
+		.iter()
+		.map(|(key, tally)| (key.clone(), tally.plays, tally.seconds / 60))
+		.collect();
+	entries.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+	entries.truncate(limit);
+	entries
+}
+
+fn longest_streak(days: &BTreeSet<NaiveDate>) -> Option<Streak> {
+	let mut best: Option<Streak> = None;
+	let mut current: Option<Streak> = None;
+	for day in days {
+		current = match current {
+			Some(streak) if streak.to + Duration::days(1) == *day => Some(Streak {
+				days: streak.days + 1,
+				from: streak.from,
+				to: *day,
+			}),
+			_ => Some(Streak {
+				days: 1,
+				from: *day,
+				to: *day,
+			}),
+		};
+		let streak = current.unwrap();
+		if best.is_none_or(|best| streak.days > best.days) {
+			best = Some(streak);
+		}
+	}
+	best
+}
+
+pub fn build_report(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	period: &str,
+	(from, to): (NaiveDate, NaiveDate),
+	offset: FixedOffset,
+	limit: usize,
+) -> QueryResult<ListeningReport> {
+	let username = users::table
+		.filter(users::user_id.eq(user_id))
+		.select(users::username)
+		.first::<String>(db_conn)?;
+
+	// Local midnights of the period converted to the UTC timestamps stored in db
+	let bound = |date: NaiveDate| {
+		let local = date.and_hms_opt(0, 0, 0).unwrap();
+		(local - Duration::seconds(offset.local_minus_utc() as i64))
+			.and_utc()
+			.to_rfc3339()
+	};
+
+	let rows = listens::table
+		.inner_join(music::table)
+		.filter(listens::user_id.eq(user_id))
+		.filter(listens::listened_at.ge(bound(from)))
+		.filter(listens::listened_at.lt(bound(to)))
+		.select((
+			listens::listened_at,
+			listens::listened_duration,
+			listens::source_type,
+			listens::source_id,
+			listens::completed,
+			music::all_columns,
+		))
+		.load::<(String, i64, String, Option<String>, bool, Music)>(db_conn)?;
+
+	let mut total_seconds = 0;
+	let mut total_plays = 0;
+	let mut tracks: HashMap<String, Tally> = HashMap::new();
+	let mut artists: HashMap<String, Tally> = HashMap::new();
+	let mut albums: HashMap<String, Tally> = HashMap::new();
+	let mut genres: HashMap<String, Tally> = HashMap::new();
+	let mut lobbies: HashMap<String, Tally> = HashMap::new();
+	let mut lobby_tracks: HashSet<(String, String)> = HashSet::new();
+	let mut days: BTreeSet<NaiveDate> = BTreeSet::new();
+	let mut heatmap = vec![vec![0_i64; 24]; 7];
+	let mut musics: HashMap<String, Music> = HashMap::new();
+
+	for (listened_at, seconds, source_type, source_id, completed, entry) in rows {
+		let listened_at = match DateTime::parse_from_rfc3339(&listened_at) {
+			Ok(listened_at) => listened_at.with_timezone(&offset),
+			Err(_) => continue,
+		};
+
+		total_seconds += seconds;
+		if completed {
+			total_plays += 1;
+			days.insert(listened_at.date_naive());
+		}
+		heatmap[listened_at.weekday().num_days_from_monday() as usize][listened_at.hour() as usize] += seconds;
+
+		tracks
+			.entry(entry.music_id.clone())
+			.or_default()
+			.add(seconds, completed);
+		artists.entry(entry.artist.clone()).or_default().add(seconds, completed);
+		albums.entry(entry.album.clone()).or_default().add(seconds, completed);
+		genres.entry(entry.genre.clone()).or_default().add(seconds, completed);
+
+		if let (true, Some(lobby_id)) = (source_type == ListenSource::Lobby.as_str(), source_id) {
+			lobbies.entry(lobby_id.clone()).or_default().add(seconds, completed);
+			lobby_tracks.insert((lobby_id, entry.music_id.clone()));
+		}
+
+		musics.entry(entry.music_id.clone()).or_insert(entry);
+	}
+
+	for row in heatmap.iter_mut() {
+		for seconds in row.iter_mut() {
+			*seconds /= 60;
+		}
+	}
+
+	let named = |tallies: &HashMap<String, Tally>| -> Vec<NamedStat> {
+		top(tallies, limit)
+			.into_iter()
+			.map(|(name, plays, minutes)| NamedStat { name, plays, minutes })
+			.collect()
+	};
+
+	let top_tracks = top(&tracks, limit)
+		.into_iter()
+		.filter_map(|(music_id, plays, minutes)| {
+			Some(TrackStat {
+				music: Music::create_music_response(musics.get(&music_id)?.clone()),
+				plays,
+				minutes,
+			})
+		})
+		.collect();
+
+	// Everyone else who listened in the same lobbies
+	let lobby_ids: Vec<&String> = lobbies.keys().collect();
+	let others = listens::table
+		.inner_join(users::table)
+		.filter(listens::source_type.eq(ListenSource::Lobby.as_str()))
+		.filter(listens::source_id.eq_any(lobby_ids))
+		.filter(listens::user_id.ne(user_id))
+		.select((listens::source_id, listens::user_id, users::username, listens::music_id))
+		.load::<(Option<String>, String, String, String)>(db_conn)?;
+
+	let friends: HashSet<String> = user_friendship::table
+		.filter(user_friendship::user_id.eq(user_id))
+		.select(user_friendship::friend_id)
+		.load::<String>(db_conn)?
+		.into_iter()
+		.collect();
+
+	let mut lobby_members: HashMap<String, BTreeSet<String>> = HashMap::new();
+	let mut friend_lobbies: HashMap<(String, String), HashSet<String>> = HashMap::new();
+	let mut friend_tracks: HashMap<String, HashSet<(String, String)>> = HashMap::new();
+	for (lobby_id, other_id, other_name, music_id) in others {
+		let Some(lobby_id) = lobby_id else { continue };
+		lobby_members
+			.entry(lobby_id.clone())
+			.or_default()
+			.insert(other_name.clone());
+
+		if friends.contains(&other_id) {
+			let key = (lobby_id, music_id);
+			if lobby_tracks.contains(&key) {
+				friend_tracks.entry(other_id.clone()).or_default().insert(key.clone());
+			}
+			friend_lobbies.entry((other_id, other_name)).or_default().insert(key.0);
+		}
+	}
+
+	let top_lobbies = top(&lobbies, limit)
+		.into_iter()
+		.map(|(lobby_id, plays, minutes)| LobbyStat {
+			listened_with: lobby_members
+				.get(&lobby_id)
+				.map(|members| members.iter().cloned().collect())
+				.unwrap_or_default(),
+			lobby_id,
+			plays,
+			minutes,
+		})
+		.collect();
+
+	let mut top_friends: Vec<FriendStat> = friend_lobbies
+		.into_iter()
+		.map(|((friend_id, username), shared)| FriendStat {
+			shared_tracks: friend_tracks.get(&friend_id).map_or(0, |tracks| tracks.len() as i64),
+			shared_lobbies: shared.len() as i64,
+			user_id: friend_id,
+			username,
+		})
+		.collect();
+	top_friends.sort_by(|a, b| {
+		b.shared_tracks
+			.cmp(&a.shared_tracks)
+			.then(b.shared_lobbies.cmp(&a.shared_lobbies))
+			.then(a.user_id.cmp(&b.user_id))
+	});
+	top_friends.truncate(limit);
+
+	Ok(ListeningReport {
+		user_id: user_id.to_string(),
+		username,
+		period: period.to_string(),
+		from,
+		to,
+		total_minutes: total_seconds / 60,
+		total_plays,
+		distinct_tracks: tracks.len() as i64,
+		days_listened: days.len() as i64,
+		longest_streak: longest_streak(&days),
+		top_tracks,
+		top_artists: named(&artists),
+		top_albums: named(&albums),
+		top_genres: named(&genres),
+		heatmap,
+		top_lobbies,
+		top_friends,
+		card_url: None,
+	})
+}
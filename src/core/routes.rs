@@ -16,42 +16,107 @@ use crate::{
 			},
 			get_cover_image::get_cover_image,
 			get_music::get_music,
+			listen_history::get_listen_history::get_listen_history,
 			liked_songs::{
 				add_to_liked_song::add_to_liked_songs, get_liked_songs::get_liked_songs, is_song_liked::is_song_liked,
 				remove_from_liked_songs::remove_from_liked_songs, toggle_liked_song::toggle_liked_song,
 			},
 			log_song_play::log_song_play,
+			playback_event::playback_event,
 			recently_played::get_recently_played::get_recently_played,
 			save_music::save_music,
 			search_music::search_music,
 			send_music::send_music,
 			top_tracks::get_top_tracks::get_top_tracks,
-			trending::get_trending_songs::get_trending_songs,
+			trending::{get_trending_movers::get_trending_movers, get_trending_songs::get_trending_songs},
 		},
 		notify::{get_all_notif, remove_notif},
 		playlist::{
 			add_song_to_playlist::add_song_to_playlist,
 			combined_playlist::{
 				add_contributor::add_contributor, fetch_all_contributors::fetch_all_contributors,
-				remove_contributor::remove_contributor,
+				get_invitations::get_invitations, remove_contributor::remove_contributor,
+				respond_invitation::respond_invitation, transfer_ownership::transfer_ownership,
+				update_role::update_role,
 			},
 			create_new_playlist::create_playlist,
 			delete_playlist::delete_playlist,
+			export_playlist::export_playlist,
+			follow::{follow_playlist::follow_playlist, unfollow_playlist::unfollow_playlist},
 			get_playlist_cover_img::get_playlist_cover_img,
 			get_playlist_music::get_playlist_music,
 			get_users_playlists::get_users_playlists,
+			folder::{
+				create_folder::create_folder, delete_folder::delete_folder, get_folders::get_folders,
+				move_folder::move_folder, rename_folder::rename_folder,
+			},
+			library::{move_playlist_to_folder::move_playlist_to_folder, pin_playlist::pin_playlist},
+			history::{
+				get_deleted_playlists::get_deleted_playlists, get_playlist_history::get_playlist_history,
+				restore_deleted_playlist::restore_deleted_playlist, restore_playlist::restore_playlist,
+				undo_playlist_changes::undo_playlist_changes,
+			},
+			fork::{
+				fork_playlist::fork_playlist, get_source_diff::get_source_diff,
+				update_from_source::update_from_source,
+			},
+			blend::create_blend::create_blend,
+			merge_playlists::merge_playlists,
+			import_playlist::import_playlist,
 			remove_song_from_playlist::remove_song_from_playlist,
+			rename_playlist::rename_playlist,
+			update_playlist_details::update_playlist_details,
+			reorder_playlist::reorder_playlist,
+			share_token::{
+				create_share_token::create_share_token, get_share_tokens::get_share_tokens,
+				revoke_share_token::revoke_share_token,
+			},
+			smart_playlist::{
+				create_smart_playlist::create_smart_playlist, freeze_smart_playlist::freeze_smart_playlist,
+				update_smart_rules::update_smart_rules,
+			},
 			update_playlist_cover_img::update_playlist_cover_img,
+			update_visibility::update_visibility,
+		},
+		messages::{
+			get_conversations::get_conversations, get_messages::get_messages, get_unread_count::get_unread_count,
+			mark_read::mark_read, send_message::send_message, start_conversation::start_conversation,
+		},
+		radio::radio,
+		recommend::{because_you_liked::because_you_liked, for_you::for_you, similar::similar},
+		scrobbles::{
+			export_scrobbles::export_scrobbles,
+			import_scrobbles::{import_scrobbles, MAX_SCROBBLE_IMPORT_BYTES},
+			submit_scrobbles::submit_scrobbles,
 		},
 		search::search,
+		share::{
+			add_share_to_playlist::add_share_to_playlist,
+			get_shares::{get_received_shares, get_sent_shares},
+			open_share::open_share,
+			react_to_share::react_to_share,
+			share_item::share_item,
+		},
+		stats::{
+			compare_friends::compare_friends,
+			get_report::{get_report, get_report_card},
+			get_summary::get_summary,
+			get_top_groups::{get_top_albums, get_top_artists, get_top_genres},
+		},
 		socket::websocket_handler,
 		users::{
-			add_friend::add_friend, get_friend::get_friend, get_user::get_user, get_user_data::get_user_data,
-			get_user_pfp::get_user_pfp, remove_friend::remove_friend, search_user::search_user, update_pfp::update_pfp,
+			add_friend::add_friend, block_user::block_user, cancel_friend_request::cancel_friend_request,
+			get_blocked_users::get_blocked_users, get_feed::get_feed, get_friend::get_friend,
+			get_friend_requests::get_friend_requests, get_friends_presence::get_friends_presence,
+			get_privacy::get_privacy, get_profile::get_profile, get_user::get_user, get_user_data::get_user_data,
+			get_user_pfp::get_user_pfp, remove_friend::remove_friend, respond_friend_request::respond_friend_request,
+			search_user::search_user, unblock_user::unblock_user, update_pfp::update_pfp, update_privacy::update_privacy,
+			update_profile::update_profile,
 		},
 	},
 };
 use axum::{
+	extract::DefaultBodyLimit,
 	routing::{get, post},
 	Router,
 };
@@ -87,9 +152,13 @@ pub fn configure_routes(app_state: AppState) -> Router {
 		.route("/music/browse_genres", get(browse_genres)) //returns Vec<genre, song_count >
 		//recently played
 		.route("/music/log_song_play", post(log_song_play))
+		.route("/music/playback_event", post(playback_event))
 		.route("/music/get_recently_played", get(get_recently_played))
+		//full listen history of a user
+		.route("/music/get_listen_history", get(get_listen_history))
 		//trending songs
 		.route("/music/get_trending", get(get_trending_songs))
+		.route("/music/get_trending/movers", get(get_trending_movers))
 		//top tracks of a particular user
 		.route("/music/get_top_tracks", get(get_top_tracks))
 		//liked songs
@@ -106,7 +175,12 @@ pub fn configure_routes(app_state: AppState) -> Router {
 		.route("/playlist/update_cover_img", post(update_playlist_cover_img))
 		.route("/playlist/cover_img/:playlist_id", get(get_playlist_cover_img))
 		.route("/playlist/remove_song_from_playlist", post(remove_song_from_playlist))
+		.route("/playlist/reorder", post(reorder_playlist))
+		.route("/playlist/rename", post(rename_playlist))
+		.route("/playlist/update_details", post(update_playlist_details))
 		.route("/playlist/delete/:curr_playlist_id", post(delete_playlist))
+		.route("/playlist/export", get(export_playlist))
+		.route("/playlist/import", post(import_playlist))
 		//combined playlists
 		.route("/playlist/combined/add_contributor", post(add_contributor))
 		.route("/playlist/combined/remove_contributor", post(remove_contributor))
@@ -114,15 +188,98 @@ pub fn configure_routes(app_state: AppState) -> Router {
 			"/playlist/combined/fetch_all_contributors/:playlist_id",
 			get(fetch_all_contributors),
 		)
+		.route("/playlist/combined/invitations/:user_id", get(get_invitations))
+		.route("/playlist/combined/respond_invitation", post(respond_invitation))
+		.route("/playlist/combined/update_role", post(update_role))
+		.route("/playlist/combined/transfer_ownership", post(transfer_ownership))
+		//smart playlists
+		.route("/playlist/smart/new", post(create_smart_playlist))
+		.route("/playlist/smart/update_rules", post(update_smart_rules))
+		.route("/playlist/smart/freeze", post(freeze_smart_playlist))
+		//folders, pins
+		.route("/playlist/folders/:user_id", get(get_folders))
+		.route("/playlist/folder/new", post(create_folder))
+		.route("/playlist/folder/rename", post(rename_folder))
+		.route("/playlist/folder/move", post(move_folder))
+		.route("/playlist/folder/delete", post(delete_folder))
+		.route("/playlist/library/move", post(move_playlist_to_folder))
+		.route("/playlist/library/pin", post(pin_playlist))
+		//history, undo and restore
+		.route("/playlist/history", get(get_playlist_history))
+		.route("/playlist/history/undo", post(undo_playlist_changes))
+		.route("/playlist/history/restore", post(restore_playlist))
+		.route("/playlist/deleted/:user_id", get(get_deleted_playlists))
+		.route("/playlist/restore_deleted", post(restore_deleted_playlist))
+		//forks, merges and blends
+		.route("/playlist/fork", post(fork_playlist))
+		.route("/playlist/fork/diff", get(get_source_diff))
+		.route("/playlist/fork/update", post(update_from_source))
+		.route("/playlist/merge", post(merge_playlists))
+		.route("/playlist/blend/new", post(create_blend))
+		//visibility, share links and following
+		.route("/playlist/visibility", post(update_visibility))
+		.route("/playlist/share_token/new", post(create_share_token))
+		.route("/playlist/share_token/get", get(get_share_tokens))
+		.route("/playlist/share_token/revoke", post(revoke_share_token))
+		.route("/playlist/follow", post(follow_playlist))
+		.route("/playlist/unfollow", post(unfollow_playlist))
 		//user stuff
 		.route("/user/update_pfp", post(update_pfp)) // @TODO :support non png image
 		.route("/user/get_pfp/:filename", get(get_user_pfp)) // @TODO : support non png
 		.route("/user/get_user_data", get(get_user_data))
 		.route("/user/search", get(search_user))
+		.route("/user/:user_id/profile", get(get_profile))
+		.route("/user/profile", post(update_profile))
 		//friends stuff
 		.route("/friend/add", post(add_friend))
 		.route("/friend/remove", post(remove_friend))
 		.route("/friend/get/:user_id", get(get_friend))
+		.route("/friend/requests/:user_id", get(get_friend_requests))
+		.route("/friend/request/respond", post(respond_friend_request))
+		.route("/friend/request/cancel", post(cancel_friend_request))
+		.route("/friend/block", post(block_user))
+		.route("/friend/unblock", post(unblock_user))
+		.route("/friend/blocked/:user_id", get(get_blocked_users))
+		.route("/friend/presence/:user_id", get(get_friends_presence))
+		//friend activity
+		.route("/feed", get(get_feed))
+		.route("/user/privacy/:user_id", get(get_privacy))
+		.route("/user/privacy", post(update_privacy))
+		//direct messages
+		.route("/messages/conversation/new", post(start_conversation))
+		.route("/messages/conversations/:user_id", get(get_conversations))
+		.route("/messages/history", get(get_messages))
+		.route("/messages/send", post(send_message))
+		.route("/messages/read", post(mark_read))
+		.route("/messages/unread/:user_id", get(get_unread_count))
+		//sharing to friends
+		.route("/share", post(share_item))
+		.route("/share/open", post(open_share))
+		.route("/share/react", post(react_to_share))
+		.route("/share/add_to_playlist", post(add_share_to_playlist))
+		.route("/share/received", get(get_received_shares))
+		.route("/share/sent", get(get_sent_shares))
+		//radio
+		.route("/radio", get(radio))
+		//recommendations
+		.route("/recommend/for_you", get(for_you))
+		.route("/recommend/similar/:music_id", get(similar))
+		.route("/recommend/because_you_liked", get(because_you_liked))
+		//scrobbles
+		.route(
+			"/scrobbles/import",
+			post(import_scrobbles).layer(DefaultBodyLimit::max(MAX_SCROBBLE_IMPORT_BYTES)),
+		)
+		.route("/scrobbles/export", get(export_scrobbles))
+		.route("/scrobbles/submit", post(submit_scrobbles))
+		//stats
+		.route("/stats/report", get(get_report))
+		.route("/stats/report/card/:filename", get(get_report_card))
+		.route("/stats/top_artists", get(get_top_artists))
+		.route("/stats/top_albums", get(get_top_albums))
+		.route("/stats/top_genres", get(get_top_genres))
+		.route("/stats/summary", get(get_summary))
+		.route("/stats/compare", get(compare_friends))
 		//notification
 		.route("/notif/get/:client_id", get(get_all_notif))
 		.route("/notif/delete/:notif_id", post(remove_notif))
@@ -0,0 +1,577 @@
+// Scrobble import and export in the formats of ListenBrainz (json listens) and Last.fm (csv scrobble dumps).
+//
+// Imported scrobbles are matched against the music table on artist/title (album breaks the ties), first on the
+// normalized text and then with a fuzzy fallback. Whatever can't be matched ends up in the report of the import.
+// Scrobbles are completed listens by definition so they count towards the play counts of the importing user, the
+// global play counts of the tracks are left alone since they would be easy to inflate with a made up history.
+
+use crate::lobic_db::models::{Listen, ListenSource, Music, PlayLog};
+use crate::schema::{listens, music, play_log};
+use crate::utils::fuzzy;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+// Fuzzy matches need a close title and a loosely matching artist
+const FUZZY_TITLE_THRESHOLD: f64 = 0.75;
+const FUZZY_ARTIST_THRESHOLD: f64 = 0.6;
+
+// ListenBrainz accepts at most this many listens per submission
+pub const LISTENBRAINZ_BATCH_SIZE: usize = 1000;
+pub const SUBMISSION_CLIENT: &str = "Lobic";
+
+const LASTFM_DATE_FORMATS: [&str; 3] = ["%d %b %Y %H:%M", "%d %b %Y, %H:%M", "%Y-%m-%d %H:%M:%S"];
+
+// Leeway for the clock of the exporting service, anything further ahead is rejected
+const MAX_CLOCK_SKEW_MINUTES: i64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrobbleFormat {
+	ListenBrainz,
+	LastFm,
+}
+
+impl ScrobbleFormat {
+	pub fn parse(value: &str) -> Option<ScrobbleFormat> {
+		match value.to_lowercase().as_str() {
+			"listenbrainz" => Some(ScrobbleFormat::ListenBrainz),
+			"lastfm" => Some(ScrobbleFormat::LastFm),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ScrobbleFormat::ListenBrainz => "listenbrainz",
+			ScrobbleFormat::LastFm => "lastfm",
+		}
+	}
+
+	pub fn content_type(&self) -> &'static str {
+		match self {
+			ScrobbleFormat::ListenBrainz => "application/json",
+			ScrobbleFormat::LastFm => "text/csv",
+		}
+	}
+
+	pub fn extension(&self) -> &'static str {
+		match self {
+			ScrobbleFormat::ListenBrainz => "json",
+			ScrobbleFormat::LastFm => "csv",
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Scrobble {
+	pub artist: String,
+	pub title: String,
+	pub album: Option<String>,
+	pub listened_at: DateTime<Utc>,
+}
+
+// Scrobbles read out of an export, rows that couldn't be read are kept with the reason
+#[derive(Debug, Default)]
+pub struct ParsedScrobbles {
+	pub scrobbles: Vec<Scrobble>,
+	pub invalid: Vec<String>,
+}
+
+impl ParsedScrobbles {
+	fn push(&mut self, row: usize, scrobble: Result<Scrobble, String>) {
+		let latest = Utc::now() + Duration::minutes(MAX_CLOCK_SKEW_MINUTES);
+		match scrobble {
+			Ok(scrobble) if scrobble.listened_at > latest => {
+				self.invalid.push(format!("Row {row}: Date is in the future"))
+			}
+			Ok(scrobble) => self.scrobbles.push(scrobble),
+			Err(err) => self.invalid.push(format!("Row {row}: {err}")),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListenBrainzListen {
+	pub listened_at: i64, // unix timestamp
+	pub track_metadata: ListenBrainzTrackMetadata,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListenBrainzTrackMetadata {
+	pub artist_name: String,
+	pub track_name: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub release_name: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub additional_info: Option<ListenBrainzAdditionalInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListenBrainzAdditionalInfo {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub duration_ms: Option<i64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub submission_client: Option<String>,
+}
+
+impl ListenBrainzListen {
+	fn into_scrobble(self) -> Result<Scrobble, String> {
+		let listened_at = DateTime::from_timestamp(self.listened_at, 0).ok_or("Invalid listened_at")?;
+		Ok(Scrobble {
+			artist: self.track_metadata.artist_name,
+			title: self.track_metadata.track_name,
+			album: self.track_metadata.release_name.filter(|album| !album.is_empty()),
+			listened_at,
+		})
+	}
+}
+
+// ListenBrainz exports are either a json array of listens or one listen per line
+pub fn parse_listenbrainz(data: &str) -> Result<ParsedScrobbles, String> {
+	let mut parsed = ParsedScrobbles::default();
+
+	if data.trim_start().starts_with('[') {
+		let values: Vec<serde_json::Value> =
+			serde_json::from_str(data).map_err(|err| format!("Invalid ListenBrainz export: {err}"))?;
+		for (idx, value) in values.into_iter().enumerate() {
+			let scrobble = serde_json::from_value::<ListenBrainzListen>(value)
+				.map_err(|err| err.to_string())
+				.and_then(ListenBrainzListen::into_scrobble);
+			parsed.push(idx + 1, scrobble);
+		}
+		return Ok(parsed);
+	}
+
+	for (idx, line) in data.lines().enumerate() {
+		if line.trim().is_empty() {
+			continue;
+		}
+		let scrobble = serde_json::from_str::<ListenBrainzListen>(line)
+			.map_err(|err| err.to_string())
+			.and_then(ListenBrainzListen::into_scrobble);
+		parsed.push(idx + 1, scrobble);
+	}
+	Ok(parsed)
+}
+
+fn parse_lastfm_date(value: &str) -> Option<DateTime<Utc>> {
+	let value = value.trim();
+	if let Ok(uts) = value.parse::<i64>() {
+		return DateTime::from_timestamp(uts, 0);
+	}
+	LASTFM_DATE_FORMATS
+		.iter()
+		.find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+		.map(|date_time| date_time.and_utc())
+}
+
+// Last.fm dumps come in two flavours: headerless "artist,album,title,date" rows,
+// or with a header naming the columns ("uts,utc_time,artist,artist_mbid,album,album_mbid,track,track_mbid")
+pub fn parse_lastfm(data: &str) -> Result<ParsedScrobbles, String> {
+	let mut reader = csv::ReaderBuilder::new()
+		.has_headers(false)
+		.flexible(true)
+		.from_reader(data.as_bytes());
+	let mut records = reader.records();
+
+	let first = match records.next() {
+		Some(record) => record.map_err(|err| format!("Invalid Last.fm export: {err}"))?,
+		None => return Ok(ParsedScrobbles::default()),
+	};
+	let header: Vec<String> = first.iter().map(|field| field.trim().to_lowercase()).collect();
+	let column = |name: &str| header.iter().position(|field| field == name);
+
+	let has_header = column("artist").is_some() && (column("track").is_some() || column("title").is_some());
+	let (artist, album, title, date) = if has_header {
+		(
+			column("artist"),
+			column("album"),
+			column("track").or(column("title")),
+			column("uts").or(column("utc_time")).or(column("date")),
+		)
+	} else {
+		(Some(0), Some(1), Some(2), Some(3))
+	};
+	let (Some(artist), Some(title), Some(date)) = (artist, title, date) else {
+		return Err("Invalid Last.fm export: missing the artist, track or date column".to_string());
+	};
+
+	let mut parsed = ParsedScrobbles::default();
+	let rows = std::iter::once(Ok(first)).filter(|_| !has_header).chain(records);
+	for (idx, record) in rows.enumerate() {
+		let row = idx + 1 + has_header as usize;
+		let scrobble = record.map_err(|err| err.to_string()).and_then(|record| {
+			let field = |column: usize| record.get(column).map(str::trim).filter(|field| !field.is_empty());
+			Ok(Scrobble {
+				artist: field(artist).ok_or("Missing artist")?.to_string(),
+				title: field(title).ok_or("Missing track")?.to_string(),
+				album: album.and_then(field).map(str::to_string),
+				listened_at: field(date).and_then(parse_lastfm_date).ok_or("Invalid date")?,
+			})
+		});
+		parsed.push(row, scrobble);
+	}
+	Ok(parsed)
+}
+
+pub fn parse(format: ScrobbleFormat, data: &str) -> Result<ParsedScrobbles, String> {
+	match format {
+		ScrobbleFormat::ListenBrainz => parse_listenbrainz(data),
+		ScrobbleFormat::LastFm => parse_lastfm(data),
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchKind {
+	Exact,
+	Fuzzy,
+}
+
+struct Candidate {
+	music_id: String,
+	artist: String,
+	title: String,
+	album: String,
+	duration: i64,
+}
+
+// Looks up tracks of the music table by their (normalized) artist, title and album
+pub struct MusicMatcher {
+	candidates: Vec<Candidate>,
+	exact: HashMap<(String, String), Vec<usize>>,
+}
+
+// Symmetric version of the trigram similarity, so that short titles don't match everything that contains them
+fn similarity(a: &str, b: &str) -> f64 {
+	fuzzy::trigram_similarity(a, b).min(fuzzy::trigram_similarity(b, a))
+}
+
+impl MusicMatcher {
+	pub fn load(db_conn: &mut SqliteConnection) -> QueryResult<MusicMatcher> {
+		let rows = music::table
+			.select((
+				music::music_id,
+				music::artist,
+				music::title,
+				music::album,
+				music::duration,
+			))
+			.load::<(String, String, String, String, i64)>(db_conn)?;
+
+		let mut candidates = Vec::with_capacity(rows.len());
+		let mut exact: HashMap<(String, String), Vec<usize>> = HashMap::new();
+		for (music_id, artist, title, album, duration) in rows {
+			let candidate = Candidate {
+				music_id,
+				artist: fuzzy::normalize(&artist),
+				title: fuzzy::normalize(&title),
+				album: fuzzy::normalize(&album),
+				duration,
+			};
+			exact
+				.entry((candidate.artist.clone(), candidate.title.clone()))
+				.or_default()
+				.push(candidates.len());
+			candidates.push(candidate);
+		}
+		Ok(MusicMatcher { candidates, exact })
+	}
+
+	// Returns the music id and duration of the best match
+	pub fn find(&self, artist: &str, title: &str, album: Option<&str>) -> Option<(&str, i64, MatchKind)> {
+		let artist = fuzzy::normalize(artist);
+		let title = fuzzy::normalize(title);
+		let album = album.map(fuzzy::normalize);
+		let same_album = |candidate: &Candidate| album.as_ref().is_some_and(|album| *album == candidate.album);
+
+		if let Some(indices) = self.exact.get(&(artist.clone(), title.clone())) {
+			let idx = indices
+				.iter()
+				.copied()
+				.find(|idx| same_album(&self.candidates[*idx]))
+				.unwrap_or(indices[0]);
+			let candidate = &self.candidates[idx];
+			return Some((&candidate.music_id, candidate.duration, MatchKind::Exact));
+		}
+
+		let mut best: Option<(&Candidate, f64)> = None;
+		for candidate in &self.candidates {
+			let title_similarity = similarity(&title, &candidate.title);
+			if title_similarity < FUZZY_TITLE_THRESHOLD {
+				continue;
+			}
+			let artist_similarity = similarity(&artist, &candidate.artist);
+			if artist_similarity < FUZZY_ARTIST_THRESHOLD {
+				continue;
+			}
+
+			let album_bonus = if same_album(candidate) { 0.05 } else { 0.0 };
+			let score = title_similarity * 0.6 + artist_similarity * 0.4 + album_bonus;
+			if best.is_none_or(|(_, best_score)| score > best_score) {
+				best = Some((candidate, score));
+			}
+		}
+		best.map(|(candidate, _)| (candidate.music_id.as_str(), candidate.duration, MatchKind::Fuzzy))
+	}
+}
+
+// Artist, title and album as they appear in the export
+type TrackKey = (String, String, Option<String>);
+
+#[derive(Debug, Serialize)]
+pub struct UnmatchedItem {
+	pub artist: String,
+	pub title: String,
+	pub album: Option<String>,
+	pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+	pub format: &'static str,
+	pub total: usize,
+	pub imported: usize,
+	pub fuzzy_matched: usize,
+	pub duplicates: usize, // already in the history, importing the same export twice is a no-op
+	pub invalid: Vec<String>,
+	pub unmatched: Vec<UnmatchedItem>, // most frequent first
+}
+
+pub fn import_scrobbles(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	format: ScrobbleFormat,
+	parsed: ParsedScrobbles,
+) -> QueryResult<ImportReport> {
+	let matcher = MusicMatcher::load(db_conn)?;
+
+	// Listens already in the history, compared to the second as the exports don't keep anything finer
+	let existing: HashSet<(String, i64)> = listens::table
+		.filter(listens::user_id.eq(user_id))
+		.select((listens::music_id, listens::listened_at))
+		.load::<(String, String)>(db_conn)?
+		.into_iter()
+		.filter_map(|(music_id, listened_at)| {
+			let listened_at = DateTime::parse_from_rfc3339(&listened_at).ok()?;
+			Some((music_id, listened_at.timestamp()))
+		})
+		.collect();
+
+	let mut report = ImportReport {
+		format: format.as_str(),
+		total: parsed.scrobbles.len() + parsed.invalid.len(),
+		imported: 0,
+		fuzzy_matched: 0,
+		duplicates: 0,
+		invalid: parsed.invalid,
+		unmatched: Vec::new(),
+	};
+
+	let mut seen: HashSet<(String, i64)> = HashSet::new();
+	let mut unmatched: BTreeMap<TrackKey, i64> = BTreeMap::new();
+	let mut new_listens: Vec<Listen> = Vec::new();
+	// The same tracks come up over and over in a history, the fuzzy lookup is only done once per track
+	let mut matches: HashMap<TrackKey, Option<(&str, i64, MatchKind)>> = HashMap::new();
+	for scrobble in parsed.scrobbles {
+		let key = (scrobble.artist, scrobble.title, scrobble.album);
+		let found = *matches
+			.entry(key.clone())
+			.or_insert_with(|| matcher.find(&key.0, &key.1, key.2.as_deref()));
+		let Some((music_id, duration, kind)) = found else {
+			*unmatched.entry(key).or_insert(0) += 1;
+			continue;
+		};
+
+		let key = (music_id.to_string(), scrobble.listened_at.timestamp());
+		if existing.contains(&key) || !seen.insert(key) {
+			report.duplicates += 1;
+			continue;
+		}
+		if kind == MatchKind::Fuzzy {
+			report.fuzzy_matched += 1;
+		}
+
+		new_listens.push(Listen {
+			listen_id: Uuid::new_v4().to_string(),
+			user_id: user_id.to_string(),
+			music_id: music_id.to_string(),
+			listened_at: scrobble.listened_at.to_rfc3339(),
+			listened_duration: duration,
+			source_type: ListenSource::Import.as_str().to_string(),
+			source_id: Some(format.as_str().to_string()),
+			skipped: false,
+			completed: true,
+		});
+	}
+
+	let mut unmatched: Vec<UnmatchedItem> = unmatched
+		.into_iter()
+		.map(|((artist, title, album), count)| UnmatchedItem {
+			artist,
+			title,
+			album,
+			count,
+		})
+		.collect();
+	unmatched.sort_by_key(|item| std::cmp::Reverse(item.count));
+	report.unmatched = unmatched;
+
+	// Play counts to add per track along with the most recent of the imported listens
+	let mut plays: HashMap<&str, (i32, &str)> = HashMap::new();
+	for listen in &new_listens {
+		let entry = plays.entry(&listen.music_id).or_insert((0, &listen.listened_at));
+		entry.0 += 1;
+		entry.1 = entry.1.max(&listen.listened_at);
+	}
+
+	db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+		for chunk in new_listens.chunks(500) {
+			diesel::insert_into(listens::table).values(chunk).execute(conn)?;
+		}
+
+		for (music_id, (count, last_played)) in &plays {
+			let new_play_log = PlayLog {
+				user_id: user_id.to_string(),
+				music_id: music_id.to_string(),
+				music_played_date_time: last_played.to_string(),
+				user_times_played: *count,
+			};
+			// Old scrobbles must not move the track up in the recently played
+			diesel::insert_into(play_log::table)
+				.values(&new_play_log)
+				.on_conflict((play_log::user_id, play_log::music_id))
+				.do_update()
+				.set((
+					play_log::music_played_date_time.eq(sql::<Text>(
+						"MAX(play_log.music_played_date_time, excluded.music_played_date_time)",
+					)),
+					play_log::user_times_played.eq(play_log::user_times_played + count),
+				))
+				.execute(conn)?;
+		}
+		Ok(())
+	})?;
+
+	report.imported = new_listens.len();
+	Ok(report)
+}
+
+// Completed listens of the user in the range, oldest first
+pub fn load_history(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	from: Option<&str>,
+	to: Option<&str>,
+) -> QueryResult<Vec<(Listen, Music)>> {
+	let mut query = listens::table
+		.inner_join(music::table)
+		.filter(listens::user_id.eq(user_id))
+		.filter(listens::completed.eq(true))
+		.select((listens::all_columns, music::all_columns))
+		.order(listens::listened_at.asc())
+		.into_boxed();
+
+	if let Some(from) = from {
+		query = query.filter(listens::listened_at.ge(from.to_string()));
+	}
+	if let Some(to) = to {
+		query = query.filter(listens::listened_at.lt(to.to_string()));
+	}
+	query.load::<(Listen, Music)>(db_conn)
+}
+
+pub fn to_listenbrainz(history: &[(Listen, Music)]) -> Vec<ListenBrainzListen> {
+	history
+		.iter()
+		.filter_map(|(listen, entry)| {
+			let listened_at = DateTime::parse_from_rfc3339(&listen.listened_at).ok()?;
+			Some(ListenBrainzListen {
+				listened_at: listened_at.timestamp(),
+				track_metadata: ListenBrainzTrackMetadata {
+					artist_name: entry.artist.clone(),
+					track_name: entry.title.clone(),
+					release_name: Some(entry.album.clone()),
+					additional_info: Some(ListenBrainzAdditionalInfo {
+						duration_ms: Some(entry.duration * 1000),
+						submission_client: Some(SUBMISSION_CLIENT.to_string()),
+					}),
+				},
+			})
+		})
+		.collect()
+}
+
+// Same columns as the usual Last.fm dumps so that the export can be imported back here or elsewhere
+pub fn to_lastfm_csv(history: &[(Listen, Music)]) -> Result<String, String> {
+	let mut writer = csv::Writer::from_writer(Vec::new());
+	let to_err = |err: csv::Error| format!("Failed to write csv: {err}");
+
+	writer
+		.write_record([
+			"uts",
+			"utc_time",
+			"artist",
+			"artist_mbid",
+			"album",
+			"album_mbid",
+			"track",
+			"track_mbid",
+		])
+		.map_err(to_err)?;
+	for (listen, entry) in history {
+		let Ok(listened_at) = DateTime::parse_from_rfc3339(&listen.listened_at) else {
+			continue;
+		};
+		let uts = listened_at.timestamp().to_string();
+		let utc_time = listened_at.with_timezone(&Utc).format("%d %b %Y, %H:%M").to_string();
+		writer
+			.write_record([&uts, &utc_time, &entry.artist, "", &entry.album, "", &entry.title, ""])
+			.map_err(to_err)?;
+	}
+
+	let bytes = writer
+		.into_inner()
+		.map_err(|err| format!("Failed to write csv: {err}"))?;
+	String::from_utf8(bytes).map_err(|err| format!("Failed to write csv: {err}"))
+}
+
+#[derive(Debug, Serialize)]
+struct ListenBrainzSubmission<'a> {
+	listen_type: &'static str,
+	payload: &'a [ListenBrainzListen],
+}
+
+// Submits the listens to a ListenBrainz compatible api, returns the number of listens accepted
+pub async fn submit_listenbrainz(api_url: &str, token: &str, listens: &[ListenBrainzListen]) -> Result<usize, String> {
+	let client = reqwest::Client::new();
+	let url = format!("{}/1/submit-listens", api_url.trim_end_matches('/'));
+
+	let mut submitted = 0;
+	for batch in listens.chunks(LISTENBRAINZ_BATCH_SIZE) {
+		let response = client
+			.post(&url)
+			.header(reqwest::header::AUTHORIZATION, format!("Token {token}"))
+			.json(&ListenBrainzSubmission {
+				listen_type: "import",
+				payload: batch,
+			})
+			.send()
+			.await
+			.map_err(|err| format!("Failed to reach {url}: {err}"))?;
+
+		let status = response.status();
+		if !status.is_success() {
+			let body = response.text().await.unwrap_or_default();
+			return Err(format!(
+				"{url} rejected the listens after {submitted} were submitted ({status}): {body}"
+			));
+		}
+		submitted += batch.len();
+	}
+	Ok(submitted)
+}
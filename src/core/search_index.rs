@@ -0,0 +1,59 @@
+// Normalized text of the tracks kept in `music.search_text`, so that searches can narrow down the candidates in
+// SQL instead of loading and normalizing the whole library on every request.
+//
+// Every field is normalized (see utils::fuzzy) and padded with a space on both sides, which lets the trigrams
+// of a query be looked up with LIKE. Fields are separated by two spaces so no trigram spans two of them.
+
+use crate::lobic_db::db::DatabasePool;
+use crate::schema::music;
+use crate::utils::fuzzy;
+
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+
+// Upper bound on the tracks ranked for a single search
+pub const MAX_SEARCH_CANDIDATES: i64 = 500;
+
+pub fn search_text(title: &str, album: &str, artist: &str) -> String {
+	format!(
+		" {}  {}  {} ",
+		fuzzy::normalize(title),
+		fuzzy::normalize(album),
+		fuzzy::normalize(artist)
+	)
+}
+
+// Tracks that may loosely match the normalized query, they still have to be ranked with utils::fuzzy
+pub fn candidates<'a>(normalized_query: &str) -> music::BoxedQuery<'a, Sqlite> {
+	let mut query = music::table.into_boxed();
+	let mut patterns = fuzzy::like_patterns(normalized_query).into_iter();
+	if let Some(first) = patterns.next() {
+		query = query.filter(music::search_text.like(first));
+		for pattern in patterns {
+			query = query.or_filter(music::search_text.like(pattern));
+		}
+	}
+	query.limit(MAX_SEARCH_CANDIDATES)
+}
+
+// Fills in the search text of the tracks stored before it existed
+pub fn backfill(db_pool: &DatabasePool) -> Result<usize, String> {
+	let mut db_conn = db_pool.get().map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+	let missing = music::table
+		.filter(music::search_text.eq(""))
+		.select((music::music_id, music::title, music::album, music::artist))
+		.load::<(String, String, String, String)>(&mut db_conn)
+		.map_err(|err| format!("Failed to load music: {err}"))?;
+
+	db_conn
+		.transaction::<_, diesel::result::Error, _>(|conn| {
+			for (music_id, title, album, artist) in &missing {
+				diesel::update(music::table.filter(music::music_id.eq(music_id)))
+					.set(music::search_text.eq(search_text(title, album, artist)))
+					.execute(conn)?;
+			}
+			Ok(())
+		})
+		.map_err(|err| format!("Failed to update the search text: {err}"))?;
+	Ok(missing.len())
+}
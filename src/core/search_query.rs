@@ -0,0 +1,387 @@
+// Structured search queries for the `advanced` search category.
+//
+// A query is a list of whitespace separated terms, eg:
+//     artist:"Manish" genre:rock year:2010..2015 duration:<4m liked:yes played:>10 raw version
+//
+// Terms of the form `key:value` become filters, everything else is free text matched against
+// the title, artist and album. Numeric values support `5`, `<5`, `<=5`, `>5`, `>=5` and ranges `2..8`
+// (either side of a range can be left open).
+
+use crate::schema::{liked_songs, music, play_log};
+
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const FILTER_KEYS: [&str; 9] = [
+	"artist", "title", "album", "genre", "year", "duration", "plays", "liked", "played",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+	Eq(i64),
+	Lt(i64),
+	Le(i64),
+	Gt(i64),
+	Ge(i64),
+	Between(i64, i64),
+}
+
+impl Comparison {
+	pub fn matches(&self, value: i64) -> bool {
+		match *self {
+			Comparison::Eq(v) => value == v,
+			Comparison::Lt(v) => value < v,
+			Comparison::Le(v) => value <= v,
+			Comparison::Gt(v) => value > v,
+			Comparison::Ge(v) => value >= v,
+			Comparison::Between(lo, hi) => lo <= value && value <= hi,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "field", content = "value", rename_all = "snake_case")]
+pub enum Filter {
+	Text(String),
+	Artist(String),
+	Title(String),
+	Album(String),
+	Genre(String),
+	Year(Comparison),
+	Duration(Comparison), // in seconds
+	Plays(Comparison),    // global play count
+	Liked(bool),
+	Played(Comparison), // play count of the user running the search
+}
+
+impl Filter {
+	pub fn needs_user(&self) -> bool {
+		matches!(self, Filter::Liked(_) | Filter::Played(_))
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+	pub position: usize,
+	pub message: String,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} (at position {})", self.message, self.position)
+	}
+}
+
+struct Term {
+	position: usize,
+	key: Option<String>,
+	value: String,
+}
+
+pub fn parse(query: &str) -> Result<Vec<Filter>, ParseError> {
+	let mut filters = Vec::new();
+
+	for term in tokenize(query)? {
+		let key = match term.key {
+			Some(key) => key,
+			None => {
+				filters.push(Filter::Text(term.value));
+				continue;
+			}
+		};
+
+		let error = |message: String| ParseError {
+			position: term.position,
+			message,
+		};
+
+		if term.value.is_empty() {
+			return Err(error(format!("Missing value for filter '{key}:'")));
+		}
+
+		let filter = match key.as_str() {
+			"artist" => Filter::Artist(term.value),
+			"title" => Filter::Title(term.value),
+			"album" => Filter::Album(term.value),
+			"genre" => Filter::Genre(term.value),
+			"year" => Filter::Year(parse_comparison(&term.value, parse_number).map_err(|_| {
+				error(format!(
+					"Invalid year '{}', expected values like 2015, >=2010 or 2010..2015",
+					term.value
+				))
+			})?),
+			"duration" => Filter::Duration(parse_comparison(&term.value, parse_duration).map_err(|_| {
+				error(format!(
+					"Invalid duration '{}', expected values like 240, 4m, 3m30s, 3:30 or <4m",
+					term.value
+				))
+			})?),
+			"plays" => Filter::Plays(parse_comparison(&term.value, parse_number).map_err(|_| {
+				error(format!("Invalid play count '{}', expected values like 10 or >10", term.value))
+			})?),
+			"liked" => Filter::Liked(
+				parse_bool(&term.value)
+					.ok_or_else(|| error(format!("Invalid value '{}' for liked:, expected yes or no", term.value)))?,
+			),
+			"played" => {
+				let comparison = match parse_bool(&term.value) {
+					Some(true) => Ok(Comparison::Ge(1)),
+					Some(false) => Ok(Comparison::Eq(0)),
+					None => parse_comparison(&term.value, parse_number),
+				};
+				Filter::Played(comparison.map_err(|_| {
+					error(format!(
+						"Invalid value '{}' for played:, expected yes, no or a count like >10",
+						term.value
+					))
+				})?)
+			}
+			_ => {
+				return Err(error(format!(
+					"Unknown filter '{key}:', available filters are: {}",
+					FILTER_KEYS.join(", ")
+				)));
+			}
+		};
+		filters.push(filter);
+	}
+
+	Ok(filters)
+}
+
+// Splits the query into terms while keeping quoted values together
+fn tokenize(query: &str) -> Result<Vec<Term>, ParseError> {
+	let chars: Vec<(usize, char)> = query.char_indices().collect();
+	let mut terms = Vec::new();
+	let mut i = 0;
+
+	while i < chars.len() {
+		if chars[i].1.is_whitespace() {
+			i += 1;
+			continue;
+		}
+
+		let position = chars[i].0;
+		let mut key = None;
+		let mut value = String::new();
+
+		while i < chars.len() && !chars[i].1.is_whitespace() {
+			let c = chars[i].1;
+			if c == '"' {
+				let quote_position = chars[i].0;
+				i += 1;
+				let mut closed = false;
+				while i < chars.len() {
+					if chars[i].1 == '"' {
+						closed = true;
+						break;
+					}
+					value.push(chars[i].1);
+					i += 1;
+				}
+				if !closed {
+					return Err(ParseError {
+						position: quote_position,
+						message: "Unterminated quote".to_string(),
+					});
+				}
+			} else if c == ':' && key.is_none() && !value.is_empty() {
+				key = Some(value.to_lowercase());
+				value = String::new();
+			} else {
+				value.push(c);
+			}
+			i += 1;
+		}
+
+		if key.is_none() && value.is_empty() {
+			continue;
+		}
+		terms.push(Term { position, key, value });
+	}
+
+	Ok(terms)
+}
+
+fn parse_comparison(value: &str, parse_value: fn(&str) -> Option<i64>) -> Result<Comparison, ()> {
+	let value = value.trim();
+	let parsed = if let Some(rest) = value.strip_prefix("<=") {
+		Comparison::Le(parse_value(rest).ok_or(())?)
+	} else if let Some(rest) = value.strip_prefix(">=") {
+		Comparison::Ge(parse_value(rest).ok_or(())?)
+	} else if let Some(rest) = value.strip_prefix('<') {
+		Comparison::Lt(parse_value(rest).ok_or(())?)
+	} else if let Some(rest) = value.strip_prefix('>') {
+		Comparison::Gt(parse_value(rest).ok_or(())?)
+	} else if let Some((lo, hi)) = value.split_once("..") {
+		match (lo.is_empty(), hi.is_empty()) {
+			(true, true) => return Err(()),
+			(true, false) => Comparison::Le(parse_value(hi).ok_or(())?),
+			(false, true) => Comparison::Ge(parse_value(lo).ok_or(())?),
+			(false, false) => {
+				let (lo, hi) = (parse_value(lo).ok_or(())?, parse_value(hi).ok_or(())?);
+				if lo > hi {
+					return Err(());
+				}
+				Comparison::Between(lo, hi)
+			}
+		}
+	} else {
+		Comparison::Eq(parse_value(value).ok_or(())?)
+	};
+	Ok(parsed)
+}
+
+fn parse_number(value: &str) -> Option<i64> {
+	value.parse::<i64>().ok().filter(|v| *v >= 0)
+}
+
+// Accepts plain seconds (240), minutes/seconds (4m, 3m30s, 90s) and clock style (3:30)
+fn parse_duration(value: &str) -> Option<i64> {
+	if let Some(secs) = parse_number(value) {
+		return Some(secs);
+	}
+
+	if let Some((min, sec)) = value.split_once(':') {
+		let (min, sec) = (parse_number(min)?, parse_number(sec)?);
+		return if sec < 60 { Some(min * 60 + sec) } else { None };
+	}
+
+	let mut total = 0;
+	let mut digits = String::new();
+	for c in value.to_lowercase().chars() {
+		match c {
+			'0'..='9' => digits.push(c),
+			'h' | 'm' | 's' if !digits.is_empty() => {
+				let amount = parse_number(&digits)?;
+				total += match c {
+					'h' => amount * 3600,
+					'm' => amount * 60,
+					_ => amount,
+				};
+				digits.clear();
+			}
+			_ => return None,
+		}
+	}
+
+	if digits.is_empty() {
+		Some(total)
+	} else {
+		None
+	}
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+	match value.to_lowercase().as_str() {
+		"yes" | "true" | "y" | "1" => Some(true),
+		"no" | "false" | "n" | "0" => Some(false),
+		_ => None,
+	}
+}
+
+// Escaping the LIKE wildcards so that user input is always matched literally
+fn like_pattern(value: &str) -> String {
+	let escaped = value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+	format!("%{}%", escaped)
+}
+
+macro_rules! filter_comparison {
+	($query:expr, $column:expr, $comparison:expr, $ty:ty) => {
+		match *$comparison {
+			Comparison::Eq(v) => $query.filter($column.eq(v as $ty)),
+			Comparison::Lt(v) => $query.filter($column.lt(v as $ty)),
+			Comparison::Le(v) => $query.filter($column.le(v as $ty)),
+			Comparison::Gt(v) => $query.filter($column.gt(v as $ty)),
+			Comparison::Ge(v) => $query.filter($column.ge(v as $ty)),
+			Comparison::Between(lo, hi) => $query.filter($column.between(lo as $ty, hi as $ty)),
+		}
+	};
+}
+
+// Ids of the tracks whose play count (by the given user) satisfies the comparison, or doesn't when `negate` is set
+fn played_music_ids<'a>(
+	user_id: &'a str,
+	comparison: &Comparison,
+	negate: bool,
+) -> play_log::BoxedQuery<'a, Sqlite, diesel::sql_types::Text> {
+	let query = play_log::table
+		.filter(play_log::user_id.eq(user_id))
+		.select(play_log::music_id)
+		.into_boxed();
+	let count = play_log::user_times_played;
+
+	if negate {
+		match *comparison {
+			Comparison::Eq(v) => query.filter(count.ne(v as i32)),
+			Comparison::Lt(v) => query.filter(count.ge(v as i32)),
+			Comparison::Le(v) => query.filter(count.gt(v as i32)),
+			Comparison::Gt(v) => query.filter(count.le(v as i32)),
+			Comparison::Ge(v) => query.filter(count.lt(v as i32)),
+			Comparison::Between(lo, hi) => query.filter(count.lt(lo as i32).or(count.gt(hi as i32))),
+		}
+	} else {
+		filter_comparison!(query, count, comparison, i32)
+	}
+}
+
+// Compiles the filters into the given music query.
+// `user_id` is required by the filters that depend on who is searching (liked:, played:).
+pub fn apply_filters<'a>(
+	mut query: music::BoxedQuery<'a, Sqlite>,
+	filters: &'a [Filter],
+	user_id: Option<&'a str>,
+) -> Result<music::BoxedQuery<'a, Sqlite>, String> {
+	for filter in filters {
+		let user_id = match (filter.needs_user(), user_id) {
+			(true, None) => {
+				return Err("The liked: and played: filters require a user_id".to_string());
+			}
+			(_, user_id) => user_id.unwrap_or_default(),
+		};
+
+		query = match filter {
+			Filter::Text(text) => {
+				let pattern = like_pattern(text);
+				query.filter(
+					music::title
+						.like(pattern.clone())
+						.escape('\\')
+						.or(music::artist.like(pattern.clone()).escape('\\'))
+						.or(music::album.like(pattern).escape('\\')),
+				)
+			}
+			Filter::Artist(artist) => query.filter(music::artist.like(like_pattern(artist)).escape('\\')),
+			Filter::Title(title) => query.filter(music::title.like(like_pattern(title)).escape('\\')),
+			Filter::Album(album) => query.filter(music::album.like(like_pattern(album)).escape('\\')),
+			Filter::Genre(genre) => query.filter(music::genre.like(like_pattern(genre)).escape('\\')),
+			Filter::Year(comparison) => filter_comparison!(query, music::year, comparison, i32),
+			Filter::Duration(comparison) => filter_comparison!(query, music::duration, comparison, i64),
+			Filter::Plays(comparison) => filter_comparison!(query, music::times_played, comparison, i32),
+			Filter::Liked(liked) => {
+				let liked_ids = liked_songs::table
+					.filter(liked_songs::user_id.eq(user_id))
+					.select(liked_songs::music_id);
+				if *liked {
+					query.filter(music::music_id.eq_any(liked_ids))
+				} else {
+					query.filter(music::music_id.ne_all(liked_ids))
+				}
+			}
+			Filter::Played(comparison) => {
+				// Tracks that were never played have no row in the play log, so a comparison that holds for
+				// zero plays is turned into "not in the tracks that fail it"
+				if comparison.matches(0) {
+					query.filter(music::music_id.ne_all(played_music_ids(user_id, comparison, true)))
+				} else {
+					query.filter(music::music_id.eq_any(played_music_ids(user_id, comparison, false)))
+				}
+			}
+		};
+	}
+
+	Ok(query)
+}
@@ -0,0 +1,573 @@
+// Sharing a track, an album or a playlist with friends.
+//
+// The share reaches each recipient as a notification carrying the resolved subject, with its songs so it can be
+// played right away. Recipients open it, react to it or add its songs to one of their playlists, and the sender sees
+// who did what, live when they're online. Shared playlists have to be visible to every recipient; if they stop being
+// visible, or the subject goes away, the share shows it as unavailable.
+
+use crate::config::{OpCode, SocketResponse};
+use crate::core::app_state::AppState;
+use crate::core::blend;
+use crate::core::friendship;
+use crate::core::playlist;
+use crate::core::playlist_access::{self, Role};
+use crate::core::playlist_history::{self, Change};
+use crate::core::smart_playlist::EvaluateError;
+use crate::lobic_db::models::{Music, Notification, Playlist, Share, ShareRecipient};
+use crate::routes::notify::notify_from;
+use crate::schema::{music, playlists, share_recipients, shares};
+
+use axum::extract::ws::Message;
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use uuid::Uuid;
+
+pub const MAX_RECIPIENTS: usize = 20;
+pub const MAX_MESSAGE_LENGTH: usize = 500;
+
+#[derive(Debug)]
+pub enum ShareError {
+	NoRecipients,
+	TooManyRecipients,
+	MessageTooLong,
+	NotFriend(String),
+	NotFound(String),
+	CantSee(String),
+	InvalidShare(String),
+	Unavailable,
+	NotEditor,
+	InvalidPlaylist(String),
+	SmartPlaylist,
+	Blend,
+	InvalidRules(String),
+	Database(diesel::result::Error),
+}
+
+impl fmt::Display for ShareError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ShareError::NoRecipients => write!(f, "Share with at least one friend"),
+			ShareError::TooManyRecipients => write!(f, "Can share with at most {MAX_RECIPIENTS} friends at once"),
+			ShareError::MessageTooLong => write!(f, "The message can be at most {MAX_MESSAGE_LENGTH} characters"),
+			ShareError::NotFriend(user_id) => write!(f, "{user_id} is not a friend"),
+			ShareError::NotFound(subject) => write!(f, "Nothing to share: {subject}"),
+			ShareError::CantSee(user_id) => write!(f, "{user_id} can't see this playlist"),
+			ShareError::InvalidShare(share_id) => write!(f, "Invalid share_id: {share_id}"),
+			ShareError::Unavailable => write!(f, "What was shared is no longer available"),
+			ShareError::NotEditor => write!(f, "Only the owner and editors can change this playlist"),
+			ShareError::InvalidPlaylist(playlist_id) => write!(f, "Invalid playlist_id: {playlist_id}"),
+			ShareError::SmartPlaylist => write!(f, "Songs can't be added to a smart playlist, freeze it first"),
+			ShareError::Blend => write!(f, "The songs of a blend are picked automatically, songs can't be added"),
+			ShareError::InvalidRules(err) => write!(f, "Invalid smart playlist rules: {err}"),
+			ShareError::Database(err) => write!(f, "Database error: {err}"),
+		}
+	}
+}
+
+impl From<diesel::result::Error> for ShareError {
+	fn from(err: diesel::result::Error) -> ShareError {
+		ShareError::Database(err)
+	}
+}
+
+impl From<EvaluateError> for ShareError {
+	fn from(err: EvaluateError) -> ShareError {
+		match err {
+			EvaluateError::InvalidRules(err) => ShareError::InvalidRules(err),
+			EvaluateError::Database(err) => ShareError::Database(err),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareKind {
+	Track,
+	Album,
+	Playlist,
+}
+
+impl ShareKind {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ShareKind::Track => "track",
+			ShareKind::Album => "album",
+			ShareKind::Playlist => "playlist",
+		}
+	}
+
+	pub fn parse(kind: &str) -> Option<ShareKind> {
+		match kind {
+			"track" => Some(ShareKind::Track),
+			"album" => Some(ShareKind::Album),
+			"playlist" => Some(ShareKind::Playlist),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reaction {
+	Love,
+	Fire,
+	Laugh,
+	Wow,
+	Sad,
+}
+
+impl Reaction {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Reaction::Love => "love",
+			Reaction::Fire => "fire",
+			Reaction::Laugh => "laugh",
+			Reaction::Wow => "wow",
+			Reaction::Sad => "sad",
+		}
+	}
+}
+
+// A share as its recipient sees it
+#[derive(Debug, Serialize)]
+pub struct ReceivedShare {
+	pub share_id: String,
+	pub sender_id: String,
+	pub message: Option<String>,
+	pub created_at: String,
+	pub subject: Value,
+	pub opened_at: Option<String>,
+	pub reaction: Option<String>,
+}
+
+// A share as its sender sees it, with what each recipient did
+#[derive(Debug, Serialize)]
+pub struct SentShare {
+	pub share_id: String,
+	pub message: Option<String>,
+	pub created_at: String,
+	pub subject: Value,
+	pub recipients: Vec<ShareRecipient>,
+}
+
+// The share and the recipient entry, when the recipient just opened it for the first time
+pub type FirstOpen = Option<(Share, ShareRecipient)>;
+
+fn songs_response(songs: Vec<Music>) -> Vec<Value> {
+	songs
+		.into_iter()
+		.map(|song| serde_json::to_value(Music::create_music_response(song)).unwrap())
+		.collect()
+}
+
+fn album_songs(db_conn: &mut SqliteConnection, album: &str) -> QueryResult<Vec<Music>> {
+	music::table
+		.filter(music::album.eq(album))
+		.order(music::title.asc())
+		.load(db_conn)
+}
+
+// The playlist if the viewer can see it
+fn visible_playlist(
+	db_conn: &mut SqliteConnection,
+	viewer_id: &str,
+	playlist_id: &str,
+) -> QueryResult<Option<Playlist>> {
+	let playlist = playlists::table
+		.filter(playlists::playlist_id.eq(playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(db_conn)
+		.optional()?;
+	match playlist {
+		Some(playlist) if playlist_access::can_read(db_conn, &playlist, Some(viewer_id), None)? => {
+			Ok(Some(playlist))
+		}
+		_ => Ok(None),
+	}
+}
+
+// The songs of what was shared, None when the viewer can't get to it anymore
+fn songs_of(
+	db_conn: &mut SqliteConnection,
+	viewer_id: &str,
+	kind: ShareKind,
+	subject_id: &str,
+) -> Result<Option<Vec<Music>>, ShareError> {
+	let songs = match kind {
+		ShareKind::Track => music::table
+			.filter(music::music_id.eq(subject_id))
+			.load::<Music>(db_conn)?,
+		ShareKind::Album => album_songs(db_conn, subject_id)?,
+		ShareKind::Playlist => match visible_playlist(db_conn, viewer_id, subject_id)? {
+			Some(playlist) => playlist::load_songs(db_conn, &playlist)?,
+			None => return Ok(None),
+		},
+	};
+	Ok(Some(songs).filter(|songs| !songs.is_empty()))
+}
+
+// What was shared as the viewer gets to see it, None when they can't
+fn resolve(
+	db_conn: &mut SqliteConnection,
+	viewer_id: &str,
+	kind: ShareKind,
+	subject_id: &str,
+) -> Result<Option<Value>, ShareError> {
+	let subject = match kind {
+		ShareKind::Track => music::table
+			.filter(music::music_id.eq(subject_id))
+			.first::<Music>(db_conn)
+			.optional()?
+			.map(|entry| json!({ "music": Music::create_music_response(entry) })),
+		ShareKind::Album => {
+			let songs = album_songs(db_conn, subject_id)?;
+			songs.first().map(|first| first.artist.clone()).map(|artist| {
+				json!({
+					"album": subject_id,
+					"artist": artist,
+					"image_url": Music::image_uuid(&artist, subject_id).to_string(),
+					"songs": songs_response(songs),
+				})
+			})
+		}
+		ShareKind::Playlist => match visible_playlist(db_conn, viewer_id, subject_id)? {
+			Some(playlist) => {
+				let songs = playlist::load_songs(db_conn, &playlist)?;
+				Some(json!({
+					"playlist_name": playlist.playlist_name,
+					"user_id": playlist.user_id,
+					"description": playlist.description,
+					"is_playlist_combined": playlist.is_playlist_combined,
+					"songs": songs_response(songs),
+				}))
+			}
+			None => None,
+		},
+	};
+	Ok(subject.map(|mut subject| {
+		subject["kind"] = json!(kind.as_str());
+		subject["id"] = json!(subject_id);
+		subject["available"] = json!(true);
+		subject
+	}))
+}
+
+fn resolve_or_unavailable(db_conn: &mut SqliteConnection, viewer_id: &str, share: &Share) -> Result<Value, ShareError> {
+	let unavailable = json!({ "kind": share.kind, "id": share.subject_id, "available": false });
+	let Some(kind) = ShareKind::parse(&share.kind) else {
+		return Ok(unavailable);
+	};
+	Ok(resolve(db_conn, viewer_id, kind, &share.subject_id)?.unwrap_or(unavailable))
+}
+
+// Returns the share along with its resolved subject, the same for every recipient
+pub fn share(
+	db_conn: &mut SqliteConnection,
+	sender_id: &str,
+	recipient_ids: &[String],
+	kind: ShareKind,
+	subject_id: &str,
+	message: Option<String>,
+) -> Result<(Share, Value), ShareError> {
+	let mut recipients: Vec<&String> = Vec::new();
+	for recipient_id in recipient_ids {
+		if recipient_id != sender_id && !recipients.contains(&recipient_id) {
+			recipients.push(recipient_id);
+		}
+	}
+	if recipients.is_empty() {
+		return Err(ShareError::NoRecipients);
+	}
+	if recipients.len() > MAX_RECIPIENTS {
+		return Err(ShareError::TooManyRecipients);
+	}
+	let message = message.map(|message| message.trim().to_string()).filter(|message| !message.is_empty());
+	if message.as_ref().is_some_and(|message| message.chars().count() > MAX_MESSAGE_LENGTH) {
+		return Err(ShareError::MessageTooLong);
+	}
+
+	db_conn.transaction::<_, ShareError, _>(|conn| {
+		for recipient_id in &recipients {
+			if !friendship::are_friends(conn, sender_id, recipient_id)? {
+				return Err(ShareError::NotFriend(recipient_id.to_string()));
+			}
+		}
+		let subject = resolve(conn, sender_id, kind, subject_id)?
+			.ok_or_else(|| ShareError::NotFound(format!("{} {subject_id}", kind.as_str())))?;
+		if kind == ShareKind::Playlist {
+			for recipient_id in &recipients {
+				if visible_playlist(conn, recipient_id, subject_id)?.is_none() {
+					return Err(ShareError::CantSee(recipient_id.to_string()));
+				}
+			}
+		}
+
+		let share = Share {
+			share_id: Uuid::new_v4().to_string(),
+			sender_id: sender_id.to_string(),
+			kind: kind.as_str().to_string(),
+			subject_id: subject_id.to_string(),
+			message,
+			created_at: Utc::now().to_rfc3339(),
+		};
+		diesel::insert_into(shares::table).values(&share).execute(conn)?;
+		let rows: Vec<ShareRecipient> = recipients
+			.iter()
+			.map(|recipient_id| ShareRecipient {
+				share_id: share.share_id.clone(),
+				recipient_id: recipient_id.to_string(),
+				opened_at: None,
+				reaction: None,
+				reacted_at: None,
+			})
+			.collect();
+		diesel::insert_into(share_recipients::table)
+			.values(&rows)
+			.execute(conn)?;
+		Ok((share, subject))
+	})
+}
+
+// Sends the share to its recipients as notifications
+pub fn deliver(app_state: &AppState, db_conn: &mut SqliteConnection, share: &Share, subject: &Value) {
+	let recipients: Vec<String> = match share_recipients::table
+		.filter(share_recipients::share_id.eq(&share.share_id))
+		.select(share_recipients::recipient_id)
+		.load(db_conn)
+	{
+		Ok(recipients) => recipients,
+		Err(err) => {
+			println!("Error {}:{}: Failed to query share recipients: {err}", file!(), line!());
+			return;
+		}
+	};
+	let value = json!({
+		"share_id": share.share_id,
+		"sender_id": share.sender_id,
+		"message": share.message,
+		"created_at": share.created_at,
+		"subject": subject,
+	});
+	for recipient_id in recipients {
+		let notif = Notification::new(OpCode::SHARE_RECEIVED, value.clone());
+		notify_from(&share.sender_id, &recipient_id, notif, &app_state.db_pool, &app_state.user_pool);
+	}
+}
+
+// The share and what the recipient did with it. Shares from users who blocked the recipient or were blocked by them
+// are gone for them
+fn received_share(
+	db_conn: &mut SqliteConnection,
+	share_id: &str,
+	user_id: &str,
+) -> Result<(Share, ShareRecipient), ShareError> {
+	let found = shares::table
+		.inner_join(share_recipients::table)
+		.filter(shares::share_id.eq(share_id))
+		.filter(share_recipients::recipient_id.eq(user_id))
+		.select((Share::as_select(), ShareRecipient::as_select()))
+		.first::<(Share, ShareRecipient)>(db_conn)
+		.optional()?;
+	match found {
+		Some((share, recipient)) if !friendship::is_blocked(db_conn, &share.sender_id, user_id)? => Ok((share, recipient)),
+		_ => Err(ShareError::InvalidShare(share_id.to_string())),
+	}
+}
+
+fn received_entry(
+	db_conn: &mut SqliteConnection,
+	share: Share,
+	recipient: ShareRecipient,
+) -> Result<ReceivedShare, ShareError> {
+	Ok(ReceivedShare {
+		subject: resolve_or_unavailable(db_conn, &recipient.recipient_id, &share)?,
+		share_id: share.share_id,
+		sender_id: share.sender_id,
+		message: share.message,
+		created_at: share.created_at,
+		opened_at: recipient.opened_at,
+		reaction: recipient.reaction,
+	})
+}
+
+// Only the first time counts, the bool says whether this was it
+fn mark_opened(db_conn: &mut SqliteConnection, recipient: &mut ShareRecipient) -> QueryResult<bool> {
+	if recipient.opened_at.is_some() {
+		return Ok(false);
+	}
+	let now = Utc::now().to_rfc3339();
+	diesel::update(
+		share_recipients::table
+			.filter(share_recipients::share_id.eq(&recipient.share_id))
+			.filter(share_recipients::recipient_id.eq(&recipient.recipient_id)),
+	)
+	.set(share_recipients::opened_at.eq(&now))
+	.execute(db_conn)?;
+	recipient.opened_at = Some(now);
+	Ok(true)
+}
+
+// The share with its subject
+pub fn open(
+	db_conn: &mut SqliteConnection,
+	share_id: &str,
+	user_id: &str,
+) -> Result<(ReceivedShare, FirstOpen), ShareError> {
+	let (share, mut recipient) = received_share(db_conn, share_id, user_id)?;
+	let first_open = mark_opened(db_conn, &mut recipient)?;
+	let opened = first_open.then(|| (share.clone(), recipient.clone()));
+	Ok((received_entry(db_conn, share, recipient)?, opened))
+}
+
+// No reaction takes it back. Reacting also opens the share
+pub fn react(
+	db_conn: &mut SqliteConnection,
+	share_id: &str,
+	user_id: &str,
+	reaction: Option<Reaction>,
+) -> Result<(Share, ShareRecipient), ShareError> {
+	let (share, mut recipient) = received_share(db_conn, share_id, user_id)?;
+	mark_opened(db_conn, &mut recipient)?;
+	let reaction = reaction.map(|reaction| reaction.as_str().to_string());
+	let reacted_at = reaction.as_ref().map(|_| Utc::now().to_rfc3339());
+	diesel::update(
+		share_recipients::table
+			.filter(share_recipients::share_id.eq(share_id))
+			.filter(share_recipients::recipient_id.eq(user_id)),
+	)
+	.set((
+		share_recipients::reaction.eq(&reaction),
+		share_recipients::reacted_at.eq(&reacted_at),
+	))
+	.execute(db_conn)?;
+	recipient.reaction = reaction;
+	recipient.reacted_at = reacted_at;
+	Ok((share, recipient))
+}
+
+// Appends the shared songs to a playlist the recipient can edit, also opening the share
+pub fn add_to_playlist(
+	db_conn: &mut SqliteConnection,
+	share_id: &str,
+	user_id: &str,
+	playlist_id: &str,
+) -> Result<(Change, Vec<String>, FirstOpen), ShareError> {
+	db_conn.transaction::<_, ShareError, _>(|conn| {
+		let (share, mut recipient) = received_share(conn, share_id, user_id)?;
+		match playlist_access::has_role(conn, playlist_id, user_id, Role::Editor) {
+			Ok(true) => {}
+			Ok(false) => return Err(ShareError::NotEditor),
+			Err(diesel::result::Error::NotFound) => return Err(ShareError::InvalidPlaylist(playlist_id.to_string())),
+			Err(err) => return Err(err.into()),
+		}
+		if playlist::is_smart(conn, playlist_id)? {
+			return Err(ShareError::SmartPlaylist);
+		}
+		if blend::is_blend(conn, playlist_id)? {
+			return Err(ShareError::Blend);
+		}
+		let kind = ShareKind::parse(&share.kind).ok_or(ShareError::Unavailable)?;
+		let music_ids: Vec<String> = songs_of(conn, user_id, kind, &share.subject_id)?
+			.ok_or(ShareError::Unavailable)?
+			.into_iter()
+			.map(|song| song.music_id)
+			.collect();
+
+		let entries = playlist::append_entries(conn, playlist_id, &music_ids, user_id)?;
+		let change = Change::Add { entries };
+		playlist_history::record(conn, playlist_id, user_id, &change)?;
+		let first_open = mark_opened(conn, &mut recipient)?;
+		Ok((change, music_ids, first_open.then_some((share, recipient))))
+	})
+}
+
+// Shares sent to the user, latest first
+pub fn received(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	start_index: i64,
+	page_length: Option<i64>,
+) -> Result<Vec<ReceivedShare>, ShareError> {
+	let hidden: Vec<String> = friendship::hidden_from(db_conn, user_id)?.into_iter().collect();
+	let found = shares::table
+		.inner_join(share_recipients::table)
+		.filter(share_recipients::recipient_id.eq(user_id))
+		.filter(shares::sender_id.ne_all(hidden))
+		.order(shares::created_at.desc())
+		.offset(start_index)
+		.limit(page_length.filter(|length| *length > 0).unwrap_or(-1)) // sqlite treats a negative limit as none
+		.select((Share::as_select(), ShareRecipient::as_select()))
+		.load::<(Share, ShareRecipient)>(db_conn)?;
+	let mut entries = Vec::with_capacity(found.len());
+	for (share, recipient) in found {
+		entries.push(received_entry(db_conn, share, recipient)?);
+	}
+	Ok(entries)
+}
+
+// Shares sent by the user with who opened them and how they reacted, latest first
+pub fn sent(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	start_index: i64,
+	page_length: Option<i64>,
+) -> Result<Vec<SentShare>, ShareError> {
+	let found = shares::table
+		.filter(shares::sender_id.eq(user_id))
+		.order(shares::created_at.desc())
+		.offset(start_index)
+		.limit(page_length.filter(|length| *length > 0).unwrap_or(-1))
+		.load::<Share>(db_conn)?;
+	let mut entries = Vec::with_capacity(found.len());
+	for share in found {
+		let recipients = share_recipients::table
+			.filter(share_recipients::share_id.eq(&share.share_id))
+			.load::<ShareRecipient>(db_conn)?;
+		entries.push(SentShare {
+			subject: resolve_or_unavailable(db_conn, user_id, &share)?,
+			share_id: share.share_id,
+			message: share.message,
+			created_at: share.created_at,
+			recipients,
+		});
+	}
+	Ok(entries)
+}
+
+fn recipient_update(share: &Share, recipient: &ShareRecipient) -> Value {
+	json!({
+		"share_id": share.share_id,
+		"kind": share.kind,
+		"subject_id": share.subject_id,
+		"recipient": recipient,
+	})
+}
+
+// Lets the sender know the recipient opened the share, only when they're online since it shows in the sent shares
+pub fn tell_opened(app_state: &AppState, share: &Share, recipient: &ShareRecipient) {
+	if let Some(conn) = app_state.user_pool.get(&share.sender_id) {
+		let response = SocketResponse {
+			op_code: OpCode::OK,
+			r#for: OpCode::SHARE_OPENED,
+			value: recipient_update(share, recipient),
+		};
+		let _ = conn.send(Message::Text(response.to_string()));
+	}
+}
+
+pub fn tell_reaction(app_state: &AppState, share: &Share, recipient: &ShareRecipient) {
+	if recipient.reaction.is_none() {
+		return;
+	}
+	let notif = Notification::new(OpCode::SHARE_REACTION, recipient_update(share, recipient));
+	notify_from(
+		&recipient.recipient_id,
+		&share.sender_id,
+		notif,
+		&app_state.db_pool,
+		&app_state.user_pool,
+	);
+}
@@ -0,0 +1,285 @@
+// Smart playlists, their songs are computed from rules stored with the playlist instead of being curated.
+//
+// The conditions over the music columns, liked and played use the advanced search syntax, the play history
+// conditions and the sort are separate fields, eg:
+//     liked songs not played in 90 days:  { "query": "liked:yes", "not_played_within": "90d" }
+//     short lofi:                         { "query": "genre:lofi duration:<4m" }
+//     top 50 this month among my friends: { "listeners": "friends", "played_in": "30d", "sort": "plays",
+//                                           "descending": true, "limit": 50 }
+
+use crate::core::playlist::append_songs;
+use crate::core::playlist_access::Visibility;
+use crate::core::search_query;
+use crate::core::stats::StatsRange;
+use crate::lobic_db::models::{ListenSource, Music, Playlist};
+use crate::schema::{listens, music, play_log, playlists, user_friendship};
+
+use chrono::Utc;
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub const MAX_SMART_PLAYLIST_LENGTH: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Listeners {
+	#[default]
+	Me,
+	Friends,
+	Everyone,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartSort {
+	#[default]
+	Title,
+	Artist,
+	Album,
+	Year,
+	Duration,
+	TimesPlayed, // global play count
+	Plays,       // completed listens of the listeners, within played_in if given
+	LastPlayed,  // by the owner
+	Random,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SmartRules {
+	#[serde(default)]
+	pub query: String, // advanced search syntax, liked: and played: refer to the owner
+	pub not_played_within: Option<String>, // 90d, 4w...: the owner didn't complete a listen in that time
+	#[serde(default)]
+	pub listeners: Listeners, // whose listens count for played_in and the plays sort
+	pub played_in: Option<String>,         // 30d, 2026-10, all...: only tracks the listeners completed in that range
+	#[serde(default)]
+	pub sort: SmartSort,
+	#[serde(default)]
+	pub descending: bool,
+	pub limit: Option<i64>, // capped at MAX_SMART_PLAYLIST_LENGTH
+}
+
+impl SmartRules {
+	// Checks everything that can be checked without the database so that broken rules are never stored
+	pub fn validate(&self) -> Result<(), String> {
+		search_query::parse(&self.query).map_err(|err| format!("Invalid query: {err}"))?;
+		if let Some(window) = &self.not_played_within {
+			parse_window(window)?;
+		}
+		if let Some(range) = &self.played_in {
+			StatsRange::parse(range).ok_or(format!("Invalid played_in range: {range}"))?;
+		}
+		if let Some(limit) = self.limit {
+			if !(1..=MAX_SMART_PLAYLIST_LENGTH).contains(&limit) {
+				return Err(format!("limit has to be between 1 and {MAX_SMART_PLAYLIST_LENGTH}"));
+			}
+		}
+		Ok(())
+	}
+
+	pub fn from_json(json: &str) -> Result<SmartRules, String> {
+		serde_json::from_str(json).map_err(|err| format!("Invalid smart playlist rules: {err}"))
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string(self).unwrap()
+	}
+}
+
+// Only rolling windows make sense for "not played in", a calendar period would never change
+fn parse_window(window: &str) -> Result<StatsRange, String> {
+	let is_rolling = window.ends_with('d') || window.ends_with('w');
+	StatsRange::parse(window).filter(|_| is_rolling).ok_or(format!(
+		"Invalid not_played_within window: {window}, expected eg. 90d or 12w"
+	))
+}
+
+#[derive(Debug)]
+pub enum EvaluateError {
+	InvalidRules(String),
+	Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for EvaluateError {
+	fn from(err: diesel::result::Error) -> EvaluateError {
+		EvaluateError::Database(err)
+	}
+}
+
+// Computes the songs of a smart playlist owned by `owner_id`, in order
+pub fn evaluate(
+	db_conn: &mut SqliteConnection,
+	owner_id: &str,
+	rules: &SmartRules,
+) -> Result<Vec<Music>, EvaluateError> {
+	rules.validate().map_err(EvaluateError::InvalidRules)?;
+	let filters = search_query::parse(&rules.query).map_err(|err| EvaluateError::InvalidRules(err.to_string()))?;
+	let mut query = search_query::apply_filters(music::table.into_boxed(), &filters, Some(owner_id))
+		.map_err(EvaluateError::InvalidRules)?;
+
+	if let Some(window) = &rules.not_played_within {
+		let (from, _) = parse_window(window).map_err(EvaluateError::InvalidRules)?.bounds();
+		let recently_played = listens::table
+			.filter(listens::user_id.eq(owner_id))
+			.filter(listens::completed.eq(true))
+			.filter(listens::listened_at.ge(from))
+			.select(listens::music_id);
+		query = query.filter(music::music_id.ne_all(recently_played));
+	}
+
+	// Play counts of the listeners, only needed when filtering or sorting by them
+	let plays = if rules.played_in.is_some() || rules.sort == SmartSort::Plays {
+		let range = match &rules.played_in {
+			Some(range) => {
+				StatsRange::parse(range).ok_or(EvaluateError::InvalidRules(format!("Invalid range: {range}")))?
+			}
+			None => StatsRange { from: None, to: None },
+		};
+		listener_plays(db_conn, owner_id, rules.listeners, &range)?
+	} else {
+		HashMap::new()
+	};
+	if rules.played_in.is_some() {
+		query = query.filter(music::music_id.eq_any(plays.keys().cloned().collect::<Vec<_>>()));
+	}
+
+	let mut songs = query.load::<Music>(db_conn)?;
+
+	if rules.sort == SmartSort::Random {
+		songs.shuffle(&mut rand::rng());
+	} else {
+		let last_played: HashMap<String, String> = if rules.sort == SmartSort::LastPlayed {
+			play_log::table
+				.filter(play_log::user_id.eq(owner_id))
+				.select((play_log::music_id, play_log::music_played_date_time))
+				.load::<(String, String)>(db_conn)?
+				.into_iter()
+				.collect()
+		} else {
+			HashMap::new()
+		};
+		songs.sort_by(|a, b| {
+			let ordering = compare(rules.sort, &plays, &last_played, a, b);
+			let ordering = if rules.descending { ordering.reverse() } else { ordering };
+			// Ties keep a stable order by title
+			ordering
+				.then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+				.then_with(|| a.music_id.cmp(&b.music_id))
+		});
+	}
+
+	songs.truncate(rules.limit.unwrap_or(MAX_SMART_PLAYLIST_LENGTH) as usize);
+	Ok(songs)
+}
+
+fn compare(
+	sort: SmartSort,
+	plays: &HashMap<String, i64>,
+	last_played: &HashMap<String, String>,
+	a: &Music,
+	b: &Music,
+) -> Ordering {
+	match sort {
+		SmartSort::Artist => a.artist.to_lowercase().cmp(&b.artist.to_lowercase()),
+		SmartSort::Album => a.album.to_lowercase().cmp(&b.album.to_lowercase()),
+		SmartSort::Year => a.year.cmp(&b.year),
+		SmartSort::Duration => a.duration.cmp(&b.duration),
+		SmartSort::TimesPlayed => a.times_played.cmp(&b.times_played),
+		SmartSort::Plays => plays.get(&a.music_id).cmp(&plays.get(&b.music_id)),
+		// Never played tracks sort before everything else
+		SmartSort::LastPlayed => last_played.get(&a.music_id).cmp(&last_played.get(&b.music_id)),
+		SmartSort::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+		SmartSort::Random => Ordering::Equal,
+	}
+}
+
+// Completed listens per track of the owner, their friends or everyone within the range
+fn listener_plays(
+	db_conn: &mut SqliteConnection,
+	owner_id: &str,
+	listeners: Listeners,
+	range: &StatsRange,
+) -> QueryResult<HashMap<String, i64>> {
+	let (from, to) = range.bounds();
+	let mut query = listens::table
+		.filter(listens::completed.eq(true))
+		.filter(listens::listened_at.ge(from))
+		.filter(listens::listened_at.lt(to))
+		.group_by(listens::music_id)
+		.select((listens::music_id, count_star()))
+		.into_boxed();
+
+	query = match listeners {
+		Listeners::Me => query.filter(listens::user_id.eq(owner_id.to_string())),
+		Listeners::Friends => {
+			let friends: Vec<String> = user_friendship::table
+				.filter(user_friendship::user_id.eq(owner_id))
+				.select(user_friendship::friend_id)
+				.load(db_conn)?;
+			query.filter(listens::user_id.eq_any(friends))
+		}
+		// Imported history only counts towards the importer's own plays
+		Listeners::Everyone => query.filter(listens::source_type.ne(ListenSource::Import.as_str())),
+	};
+
+	Ok(query.load::<(String, i64)>(db_conn)?.into_iter().collect())
+}
+
+// Turns the current songs of a smart playlist into curated entries. Without a name the playlist itself becomes
+// a normal playlist, otherwise a normal copy with that name is created and the smart playlist stays as is.
+// Returns the id of the frozen playlist.
+pub fn freeze(
+	db_conn: &mut SqliteConnection,
+	smart_playlist: &Playlist,
+	new_playlist_name: Option<String>,
+) -> Result<String, EvaluateError> {
+	let rules = match &smart_playlist.smart_rules {
+		Some(rules) => SmartRules::from_json(rules).map_err(EvaluateError::InvalidRules)?,
+		None => {
+			return Err(EvaluateError::InvalidRules(
+				"The playlist isn't a smart playlist".to_string(),
+			))
+		}
+	};
+
+	db_conn.transaction::<_, EvaluateError, _>(|conn| {
+		let songs = evaluate(conn, &smart_playlist.user_id, &rules)?;
+		let now = Utc::now().to_rfc3339();
+
+		let frozen_playlist_id = match new_playlist_name {
+			Some(playlist_name) => {
+				let new_playlist = Playlist {
+					playlist_id: Uuid::new_v4().to_string(),
+					playlist_name,
+					user_id: smart_playlist.user_id.clone(),
+					creation_date_time: now.clone(),
+					last_updated_date_time: now.clone(),
+					is_playlist_combined: smart_playlist.is_playlist_combined,
+					smart_rules: None,
+					visibility: Visibility::Private.as_str().to_string(),
+					deleted_at: None,
+					description: smart_playlist.description.clone(),
+				};
+				diesel::insert_into(playlists::table)
+					.values(&new_playlist)
+					.execute(conn)?;
+				new_playlist.playlist_id
+			}
+			None => {
+				diesel::update(playlists::table.filter(playlists::playlist_id.eq(&smart_playlist.playlist_id)))
+					.set(playlists::smart_rules.eq(None::<String>))
+					.execute(conn)?;
+				smart_playlist.playlist_id.clone()
+			}
+		};
+
+		let music_ids: Vec<String> = songs.into_iter().map(|song| song.music_id).collect();
+		append_songs(conn, &frozen_playlist_id, &music_ids, &smart_playlist.user_id)?;
+		Ok(frozen_playlist_id)
+	})
+}
@@ -0,0 +1,396 @@
+// Listening statistics aggregated in sql over the listen history.
+//
+// Every statistic works over a time range: a rolling window ("7d", "4w"), a calendar period ("2026", "2026-03"),
+// "all" or explicit from/to dates. Every listen counts towards the listening time, only completed ones towards the plays.
+
+use crate::schema::{listens, music, user_friendship, user_profiles};
+use crate::utils::timestamp;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Nullable, Text};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// Consecutive listens further apart than this belong to different sessions
+pub const SESSION_GAP_SECS: i64 = 30 * 60;
+
+#[derive(Debug, Deserialize)]
+pub struct StatsQueryParams {
+	pub user_id: String,
+	pub range: Option<String>, // 7d, 4w, 2026, 2026-03 or all. Defaults to 30d
+	pub from: Option<String>,  // overrides the range, RFC3339 or YYYY-MM-DD
+	pub to: Option<String>,
+	#[serde(default)]
+	pub start_index: i64, //defaults to 0
+	pub page_length: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsRange {
+	pub from: Option<String>, // inclusive
+	pub to: Option<String>,   // exclusive
+}
+
+impl StatsRange {
+	pub fn from_params(params: &StatsQueryParams) -> Result<StatsRange, String> {
+		let range = params.range.as_deref().unwrap_or("30d");
+		let mut stats_range = StatsRange::parse(range).ok_or(format!("Invalid range: {range}"))?;
+
+		if let Some(from) = &params.from {
+			stats_range.from =
+				Some(timestamp::parse_date_bound(from, false).ok_or(format!("Invalid from date: {from}"))?);
+		}
+		if let Some(to) = &params.to {
+			stats_range.to = Some(timestamp::parse_date_bound(to, true).ok_or(format!("Invalid to date: {to}"))?);
+		}
+		Ok(stats_range)
+	}
+
+	pub fn parse(range: &str) -> Option<StatsRange> {
+		if range == "all" {
+			return Some(StatsRange { from: None, to: None });
+		}
+
+		let rolling = |days: i64| StatsRange {
+			from: Some((Utc::now() - Duration::days(days)).to_rfc3339()),
+			to: None,
+		};
+		if let Some(days) = range.strip_suffix('d').and_then(|days| days.parse::<i64>().ok()) {
+			return Some(rolling(days));
+		}
+		if let Some(weeks) = range.strip_suffix('w').and_then(|weeks| weeks.parse::<i64>().ok()) {
+			return Some(rolling(weeks * 7));
+		}
+
+		let (start, end) = timestamp::parse_period(range)?;
+		let bound = |date: NaiveDate| date.and_hms_opt(0, 0, 0).map(|date| date.and_utc().to_rfc3339());
+		Some(StatsRange {
+			from: bound(start),
+			to: bound(end),
+		})
+	}
+
+	// Bounds for the queries, open ends are replaced with values that sort before/after every timestamp
+	pub fn bounds(&self) -> (String, String) {
+		(
+			self.from.clone().unwrap_or_default(),
+			self.to.clone().unwrap_or_else(|| "9999".to_string()),
+		)
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum StatsDimension {
+	Artist,
+	Album,
+	Genre,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupStat {
+	pub name: String,
+	pub artist: Option<String>, // only for albums
+	pub plays: i64,
+	pub minutes: i64,
+	pub distinct_tracks: i64,
+}
+
+// Artists, albums or genres ordered by the number of plays
+pub fn top_grouped(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	range: &StatsRange,
+	dimension: StatsDimension,
+	start_index: i64,
+	page_length: Option<i64>,
+) -> QueryResult<Vec<GroupStat>> {
+	let (from, to) = range.bounds();
+	let base = listens::table
+		.inner_join(music::table)
+		.filter(listens::user_id.eq(user_id))
+		.filter(listens::listened_at.ge(from))
+		.filter(listens::listened_at.lt(to));
+
+	let aggregates = (
+		sql::<BigInt>("SUM(CASE WHEN listens.completed THEN 1 ELSE 0 END)"),
+		sql::<BigInt>("SUM(listens.listened_duration) / 60"),
+		sql::<BigInt>("COUNT(DISTINCT listens.music_id)"),
+	);
+	let order =
+		sql::<BigInt>("SUM(CASE WHEN listens.completed THEN 1 ELSE 0 END) DESC, SUM(listens.listened_duration) DESC");
+	let limit = page_length.filter(|length| *length > 0).unwrap_or(-1); // sqlite treats a negative limit as none
+
+	let rows: Vec<(String, Option<String>, i64, i64, i64)> = match dimension {
+		StatsDimension::Artist => base
+			.group_by(music::artist)
+			.select((
+				music::artist,
+				sql::<Nullable<Text>>("NULL"),
+				aggregates.0,
+				aggregates.1,
+				aggregates.2,
+			))
+			.order(order)
+			.offset(start_index)
+			.limit(limit)
+			.load(db_conn)?,
+		StatsDimension::Album => base
+			.group_by((music::album, music::artist))
+			.select((
+				music::album,
+				music::artist.nullable(),
+				aggregates.0,
+				aggregates.1,
+				aggregates.2,
+			))
+			.order(order)
+			.offset(start_index)
+			.limit(limit)
+			.load(db_conn)?,
+		StatsDimension::Genre => base
+			.group_by(music::genre)
+			.select((
+				music::genre,
+				sql::<Nullable<Text>>("NULL"),
+				aggregates.0,
+				aggregates.1,
+				aggregates.2,
+			))
+			.order(order)
+			.offset(start_index)
+			.limit(limit)
+			.load(db_conn)?,
+	};
+
+	Ok(rows
+		.into_iter()
+		.map(|(name, artist, plays, minutes, distinct_tracks)| GroupStat {
+			name,
+			artist,
+			plays,
+			minutes,
+			distinct_tracks,
+		})
+		.collect())
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListeningSummary {
+	pub range: StatsRange,
+	pub total_minutes: i64,
+	pub plays: i64,
+	pub skips: i64,
+	pub distinct_tracks: i64,
+	pub sessions: i64,
+	pub average_session_minutes: f64,
+	pub new_tracks: i64,             // tracks listened to for the first time in the range
+	pub discovery_rate: Option<f64>, // new tracks per week
+}
+
+#[derive(QueryableByName)]
+struct SessionRow {
+	#[diesel(sql_type = BigInt)]
+	sessions: i64,
+	#[diesel(sql_type = Nullable<Double>)]
+	average_secs: Option<f64>,
+}
+
+pub fn summary(db_conn: &mut SqliteConnection, user_id: &str, range: &StatsRange) -> QueryResult<ListeningSummary> {
+	let (from, to) = range.bounds();
+	let in_range = listens::table
+		.filter(listens::user_id.eq(user_id))
+		.filter(listens::listened_at.ge(from.clone()))
+		.filter(listens::listened_at.lt(to.clone()));
+
+	let (seconds, plays, skips, distinct_tracks) = in_range
+		.select((
+			sql::<BigInt>("COALESCE(SUM(listened_duration), 0)"),
+			sql::<BigInt>("COALESCE(SUM(CASE WHEN completed THEN 1 ELSE 0 END), 0)"),
+			sql::<BigInt>("COALESCE(SUM(CASE WHEN skipped THEN 1 ELSE 0 END), 0)"),
+			sql::<BigInt>("COUNT(DISTINCT music_id)"),
+		))
+		.first::<(i64, i64, i64, i64)>(db_conn)?;
+
+	// A listen starts a new session when it begins more than SESSION_GAP_SECS after the previous one ended
+	let session = diesel::sql_query(
+		"WITH ordered AS (
+			SELECT listened_duration,
+				julianday(listened_at) * 86400 AS started,
+				LAG(julianday(listened_at) * 86400 + listened_duration) OVER (ORDER BY listened_at) AS previous_end
+			FROM listens
+			WHERE user_id = ? AND listened_at >= ? AND listened_at < ?
+		), numbered AS (
+			SELECT listened_duration,
+				SUM(CASE WHEN previous_end IS NULL OR started - previous_end > ? THEN 1 ELSE 0 END)
+					OVER (ORDER BY started ROWS UNBOUNDED PRECEDING) AS session
+			FROM ordered
+		), sessions AS (
+			SELECT SUM(listened_duration) AS length FROM numbered GROUP BY session
+		)
+		SELECT COUNT(*) AS sessions, AVG(length) AS average_secs FROM sessions",
+	)
+	.bind::<Text, _>(user_id)
+	.bind::<Text, _>(&from)
+	.bind::<Text, _>(&to)
+	.bind::<BigInt, _>(SESSION_GAP_SECS)
+	.get_result::<SessionRow>(db_conn)?;
+
+	// First listen of every track ever, counted if it falls in the range
+	let new_tracks = diesel::sql_query(
+		"SELECT COUNT(*) AS count FROM (
+			SELECT MIN(listened_at) AS first_listen FROM listens WHERE user_id = ? GROUP BY music_id
+		) WHERE first_listen >= ? AND first_listen < ?",
+	)
+	.bind::<Text, _>(user_id)
+	.bind::<Text, _>(&from)
+	.bind::<Text, _>(&to)
+	.get_result::<CountRow>(db_conn)?
+	.count;
+
+	// Without an explicit start the range begins with the first listen of the user
+	let first_listen = match &range.from {
+		Some(from) => Some(from.clone()),
+		None => listens::table
+			.filter(listens::user_id.eq(user_id))
+			.select(diesel::dsl::min(listens::listened_at))
+			.first::<Option<String>>(db_conn)?,
+	};
+	let parse = |value: &str| {
+		DateTime::parse_from_rfc3339(value)
+			.ok()
+			.map(|value| value.with_timezone(&Utc))
+	};
+	let start = first_listen.as_deref().and_then(parse);
+	let end = range
+		.to
+		.as_deref()
+		.and_then(parse)
+		.unwrap_or_else(Utc::now)
+		.min(Utc::now());
+	let discovery_rate = start.map(|start| {
+		// At least a day, otherwise a range starting a minute ago would report absurd rates
+		let weeks = ((end - start).num_seconds() as f64 / (7.0 * 86400.0)).max(1.0 / 7.0);
+		(new_tracks as f64 / weeks * 100.0).round() / 100.0
+	});
+
+	Ok(ListeningSummary {
+		range: range.clone(),
+		total_minutes: seconds / 60,
+		plays,
+		skips,
+		distinct_tracks,
+		sessions: session.sessions,
+		average_session_minutes: (session.average_secs.unwrap_or(0.0) / 60.0 * 10.0).round() / 10.0,
+		new_tracks,
+		discovery_rate,
+	})
+}
+
+#[derive(QueryableByName)]
+struct CountRow {
+	#[diesel(sql_type = BigInt)]
+	count: i64,
+}
+
+#[derive(Debug, Serialize, QueryableByName)]
+pub struct FriendComparison {
+	#[diesel(sql_type = Text)]
+	pub user_id: String,
+	#[diesel(sql_type = Text)]
+	pub username: String,
+	#[diesel(sql_type = BigInt)]
+	pub minutes: i64,
+	#[diesel(sql_type = BigInt)]
+	pub plays: i64,
+	#[diesel(sql_type = BigInt)]
+	pub distinct_tracks: i64,
+	#[diesel(sql_type = Nullable<Text>)]
+	pub top_artist: Option<String>,
+	#[diesel(sql_type = BigInt)]
+	pub shared_artists: i64, // artists both the user and the friend listened to in the range
+}
+
+// The user and each of their friends side by side, ordered by listening time. Friends who hide their stats are
+// left out and the top artist of those who hide their top artists is too
+pub fn compare_with_friends(
+	db_conn: &mut SqliteConnection,
+	user_id: &str,
+	range: &StatsRange,
+	start_index: i64,
+	page_length: Option<i64>,
+) -> QueryResult<Vec<FriendComparison>> {
+	let friends: Vec<String> = user_friendship::table
+		.filter(user_friendship::user_id.eq(user_id))
+		.select(user_friendship::friend_id)
+		.load(db_conn)?;
+	let hidden: Vec<(String, bool, bool)> = user_profiles::table
+		.filter(user_profiles::user_id.eq_any(&friends))
+		.filter(user_profiles::show_stats.eq(false).or(user_profiles::show_top_artists.eq(false)))
+		.select((user_profiles::user_id, user_profiles::show_stats, user_profiles::show_top_artists))
+		.load(db_conn)?;
+	let hides_stats: HashSet<&str> = hidden
+		.iter()
+		.filter(|(_, show_stats, _)| !show_stats)
+		.map(|(friend_id, _, _)| friend_id.as_str())
+		.collect();
+	let hides_top_artists: HashSet<&str> = hidden
+		.iter()
+		.filter(|(_, _, show_top_artists)| !show_top_artists)
+		.map(|(friend_id, _, _)| friend_id.as_str())
+		.collect();
+
+	let mut members: Vec<&str> = friends
+		.iter()
+		.map(String::as_str)
+		.filter(|friend_id| !hides_stats.contains(friend_id))
+		.collect();
+	members.push(user_id);
+	let members = serde_json::to_string(&members).unwrap();
+
+	let (from, to) = range.bounds();
+	diesel::sql_query(
+		"WITH members AS (
+			SELECT value AS user_id FROM json_each(?)
+		), ranged AS (
+			SELECT listens.user_id, listens.music_id, listens.listened_duration, listens.completed, music.artist
+			FROM listens INNER JOIN music ON music.music_id = listens.music_id
+			WHERE listens.user_id IN (SELECT user_id FROM members) AND listens.listened_at >= ? AND listens.listened_at < ?
+		), artist_plays AS (
+			SELECT user_id, artist, COUNT(*) AS plays FROM ranged WHERE completed GROUP BY user_id, artist
+		)
+		SELECT users.user_id, users.username,
+			COALESCE(SUM(ranged.listened_duration), 0) / 60 AS minutes,
+			COALESCE(SUM(CASE WHEN ranged.completed THEN 1 ELSE 0 END), 0) AS plays,
+			COUNT(DISTINCT ranged.music_id) AS distinct_tracks,
+			(SELECT artist FROM artist_plays WHERE artist_plays.user_id = users.user_id
+				ORDER BY plays DESC, artist LIMIT 1) AS top_artist,
+			(SELECT COUNT(*) FROM artist_plays mine INNER JOIN artist_plays theirs ON theirs.artist = mine.artist
+				WHERE mine.user_id = ? AND theirs.user_id = users.user_id) AS shared_artists
+		FROM members
+		INNER JOIN users ON users.user_id = members.user_id
+		LEFT JOIN ranged ON ranged.user_id = users.user_id
+		GROUP BY users.user_id, users.username
+		ORDER BY minutes DESC, users.username
+		LIMIT ? OFFSET ?",
+	)
+	.bind::<Text, _>(members)
+	.bind::<Text, _>(&from)
+	.bind::<Text, _>(&to)
+	.bind::<Text, _>(user_id)
+	.bind::<BigInt, _>(page_length.filter(|length| *length > 0).unwrap_or(-1))
+	.bind::<BigInt, _>(start_index)
+	.load::<FriendComparison>(db_conn)
+	.map(|comparison| {
+		comparison
+			.into_iter()
+			.map(|mut entry| {
+				if hides_top_artists.contains(entry.user_id.as_str()) {
+					entry.top_artist = None;
+				}
+				entry
+			})
+			.collect()
+	})
+}
@@ -1,17 +1,32 @@
 use axum::extract::ws::Message;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 
+// Connected users who haven't done anything for this long are idle
+pub const IDLE_AFTER_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+	Online,
+	Idle,
+	Offline,
+}
+
 #[derive(Debug, Clone)]
 pub struct UserPool {
 	inner: Arc<Mutex<HashMap<String, broadcast::Sender<Message>>>>,
+	last_active: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
 }
 
 impl UserPool {
 	pub fn new() -> UserPool {
 		UserPool {
 			inner: Arc::new(Mutex::new(HashMap::new())),
+			last_active: Arc::new(Mutex::new(HashMap::new())),
 		}
 	}
 
@@ -38,13 +53,44 @@ impl UserPool {
 	pub fn insert(&self, id: &str, sender: &broadcast::Sender<Message>) {
 		let mut inner = self.inner.lock().unwrap();
 		inner.insert(id.to_string(), sender.clone());
+		self.last_active.lock().unwrap().insert(id.to_string(), Utc::now());
 	}
 
 	pub fn remove(&self, id: &str) -> bool {
 		let mut inner = self.inner.lock().unwrap();
+		self.last_active.lock().unwrap().remove(id);
 		match inner.remove(id) {
 			Some(_) => true,
 			None => false,
 		}
 	}
+
+	// Removes the user only if this is still their connection, they may have reconnected since
+	pub fn remove_conn(&self, id: &str, sender: &broadcast::Sender<Message>) -> bool {
+		let is_current = match self.get(id) {
+			Some(conn) => conn.same_channel(sender),
+			None => false,
+		};
+		is_current && self.remove(id)
+	}
+
+	// Marks the user as active, returns whether they were idle until now
+	pub fn touch(&self, id: &str) -> bool {
+		if !self.exists(id) {
+			return false;
+		}
+		let was_idle = self.status(id) == UserStatus::Idle;
+		self.last_active.lock().unwrap().insert(id.to_string(), Utc::now());
+		was_idle
+	}
+
+	pub fn status(&self, id: &str) -> UserStatus {
+		if !self.exists(id) {
+			return UserStatus::Offline;
+		}
+		match self.last_active.lock().unwrap().get(id) {
+			Some(last_active) if Utc::now() - *last_active < Duration::seconds(IDLE_AFTER_SECS) => UserStatus::Online,
+			_ => UserStatus::Idle,
+		}
+	}
 }
@@ -21,11 +21,11 @@ pub struct User {
 	pub otp_verified: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct UserDataResponse {
 	pub user_id: String,
 	pub username: String,
-	pub email: String,
+	pub display_name: Option<String>,
 }
 
 #[derive(Insertable, Queryable, Debug)]
@@ -35,6 +35,111 @@ pub struct UserFriendship {
 	pub friend_id: String,
 }
 
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = friend_requests)]
+pub struct FriendRequest {
+	pub request_id: String,
+	pub sender_id: String,
+	pub receiver_id: String,
+	pub status: String, // pending, accepted, declined or cancelled
+	pub created_at: String,
+	pub responded_at: Option<String>,
+}
+
+#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = user_blocks)]
+pub struct UserBlock {
+	pub user_id: String,
+	pub blocked_id: String,
+	pub created_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = activity_events)]
+pub struct ActivityEvent {
+	pub event_id: String,
+	pub user_id: String,
+	pub kind: String,
+	pub subject_id: String,
+	pub details: String, // json
+	pub created_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = conversations)]
+pub struct Conversation {
+	pub conversation_id: String,
+	pub is_group: bool,
+	pub name: Option<String>,
+	pub created_by: String,
+	pub created_at: String,
+	pub last_message_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = conversation_members)]
+pub struct ConversationMember {
+	pub conversation_id: String,
+	pub user_id: String,
+	pub joined_at: String,
+	pub last_read_at: Option<String>, // read receipt
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = direct_messages)]
+pub struct DirectMessage {
+	pub message_id: String,
+	pub conversation_id: String,
+	pub sender_id: String,
+	pub body: String,
+	pub attachment_kind: Option<String>, // track or playlist
+	pub attachment_id: Option<String>,
+	pub sent_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = shares)]
+pub struct Share {
+	pub share_id: String,
+	pub sender_id: String,
+	pub kind: String, // track, album or playlist
+	pub subject_id: String,
+	pub message: Option<String>,
+	pub created_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = share_recipients)]
+pub struct ShareRecipient {
+	pub share_id: String,
+	pub recipient_id: String,
+	pub opened_at: Option<String>,
+	pub reaction: Option<String>,
+	pub reacted_at: Option<String>,
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = user_profiles)]
+pub struct UserProfile {
+	pub user_id: String,
+	pub display_name: Option<String>,
+	pub bio: String,
+	pub avatar: Option<String>, // file name in USER_PFP_STORAGE
+	pub pronouns: Option<String>,
+	pub country: Option<String>, // ISO 3166-1 alpha-2
+	pub joined_at: String,
+	pub show_top_artists: bool,
+	pub show_stats: bool,
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = user_privacy)]
+pub struct UserPrivacy {
+	pub user_id: String,
+	pub share_activity: bool,
+	pub share_presence: bool,
+}
+
 #[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
 #[diesel(table_name = playlists)]
 pub struct Playlist {
@@ -44,6 +149,12 @@ pub struct Playlist {
 	pub creation_date_time: String,
 	pub last_updated_date_time: String,
 	pub is_playlist_combined: bool,
+	#[serde(skip)]
+	pub smart_rules: Option<String>, // json, see core::smart_playlist
+	pub visibility: String, // see core::playlist_access
+	#[serde(skip)]
+	pub deleted_at: Option<String>, // soft deleted, see core::playlist_history
+	pub description: String,
 }
 //for response
 #[derive(Debug, Serialize)]
@@ -54,6 +165,36 @@ pub struct PlaylistInfo {
 	pub creation_date_time: String,
 	pub last_updated_date_time: String,
 	pub is_playlist_combined: bool,
+	pub is_smart: bool,
+	pub visibility: String,
+	pub is_followed: bool, // a playlist of another user in the library
+	pub description: String,
+	pub tags: Vec<String>,
+	pub folder_id: Option<String>, // where it sits in the library of the user asking, see core::playlist_library
+	pub is_pinned: bool,
+	pub forked_from: Option<String>, // the source playlist of a fork
+	pub is_blend: bool,
+}
+impl From<Playlist> for PlaylistInfo {
+	fn from(playlist: Playlist) -> Self {
+		PlaylistInfo {
+			playlist_id: playlist.playlist_id,
+			user_id: playlist.user_id,
+			playlist_name: playlist.playlist_name,
+			creation_date_time: playlist.creation_date_time,
+			last_updated_date_time: playlist.last_updated_date_time,
+			is_playlist_combined: playlist.is_playlist_combined,
+			is_smart: playlist.smart_rules.is_some(),
+			visibility: playlist.visibility,
+			is_followed: false,
+			description: playlist.description,
+			tags: Vec::new(),
+			folder_id: None,
+			is_pinned: false,
+			forked_from: None,
+			is_blend: false,
+		}
+	}
 }
 #[derive(Debug, Serialize)]
 pub struct UserPlaylistsResponse {
@@ -61,13 +202,15 @@ pub struct UserPlaylistsResponse {
 	pub playlists: Vec<PlaylistInfo>,
 }
 
-#[derive(Insertable, Queryable, Debug)]
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
 #[diesel(table_name = playlist_songs)]
 pub struct PlaylistSong {
+	pub item_id: String,
 	pub playlist_id: String,
 	pub music_id: String,
 	pub song_adder_id: String,
 	pub song_added_date_time: String,
+	pub position: f64,
 }
 
 #[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
@@ -75,6 +218,82 @@ pub struct PlaylistSong {
 pub struct PlaylistShare {
 	pub playlist_id: String,
 	pub contributor_user_id: String,
+	pub role: String, // editor or viewer, see core::playlist_access
+}
+
+#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = playlist_history)]
+pub struct PlaylistHistoryEntry {
+	pub event_id: String,
+	pub playlist_id: String,
+	pub actor_id: String,
+	pub kind: String,
+	pub change: String, // json, see core::playlist_history
+	pub created_at: String,
+	pub undone_at: Option<String>,
+}
+
+#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = playlist_invitations)]
+pub struct PlaylistInvitation {
+	pub invitation_id: String,
+	pub playlist_id: String,
+	pub inviter_id: String,
+	pub invitee_id: String,
+	pub role: String,
+	pub created_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = playlist_share_tokens)]
+pub struct PlaylistShareToken {
+	pub token: String,
+	pub playlist_id: String,
+	pub created_by: String,
+	pub created_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = playlist_follows)]
+pub struct PlaylistFollow {
+	pub user_id: String,
+	pub playlist_id: String,
+	pub followed_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = playlist_forks)]
+pub struct PlaylistFork {
+	pub playlist_id: String,
+	pub source_id: String,
+	pub source_snapshot: String, // json array of music ids, see core::playlist_fork
+	pub synced_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = playlist_blends)]
+pub struct PlaylistBlend {
+	pub playlist_id: String,
+	pub refreshed_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = playlist_folders)]
+pub struct PlaylistFolder {
+	pub folder_id: String,
+	pub user_id: String,
+	pub parent_id: Option<String>, // None at the top level
+	pub folder_name: String,
+	pub created_at: String,
+}
+
+#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = playlist_library_entries)]
+pub struct PlaylistLibraryEntry {
+	pub user_id: String,
+	pub playlist_id: String,
+	pub folder_id: Option<String>,
+	pub pinned_at: Option<String>,
 }
 
 #[derive(Insertable, Queryable, Debug)]
@@ -86,6 +305,44 @@ pub struct PlayLog {
 	pub user_times_played: i32,
 }
 
+#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = listens)]
+pub struct Listen {
+	pub listen_id: String,
+	pub user_id: String,
+	pub music_id: String,
+	pub listened_at: String,
+	pub listened_duration: i64,
+	pub source_type: String,
+	pub source_id: Option<String>,
+	pub skipped: bool,
+	pub completed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenSource {
+	#[default]
+	Solo,
+	Lobby,
+	Playlist,
+	// Scrobbles imported from another service, the service is the source id.
+	// Only set by the import itself, clients can't report a listen as imported.
+	#[serde(skip_deserializing)]
+	Import,
+}
+
+impl ListenSource {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ListenSource::Solo => "solo",
+			ListenSource::Lobby => "lobby",
+			ListenSource::Playlist => "playlist",
+			ListenSource::Import => "import",
+		}
+	}
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LikedSongs {
 	pub user_id: String,
@@ -135,7 +392,7 @@ impl From<Notification> for Value {
 	}
 }
 
-#[derive(Insertable, Queryable, Debug, Selectable, Serialize, Deserialize)]
+#[derive(Insertable, Queryable, Debug, Clone, Selectable, Serialize, Deserialize)]
 #[diesel(table_name = music)]
 pub struct Music {
 	pub music_id: String,
@@ -145,14 +402,27 @@ pub struct Music {
 	pub genre: String,
 	pub times_played: i32,
 	pub duration: i64,
+	pub year: Option<i32>,
+	pub bpm: Option<f64>,
+	pub musical_key: Option<String>,
+	pub energy: Option<f64>,
+	pub danceability: Option<f64>,
+	pub analyzed_at: Option<String>,
+	#[serde(skip)]
+	pub search_text: String, // see core::search_index
 }
 impl Music {
-	pub fn create_music_response(entry: Music) -> MusicResponse {
+	// The cover image is shared by the songs of an album, see save_music
+	pub fn image_uuid(artist: &str, album: &str) -> Uuid {
 		let mut hasher = DefaultHasher::new();
-		entry.artist.hash(&mut hasher);
-		entry.album.hash(&mut hasher);
+		artist.hash(&mut hasher);
+		album.hash(&mut hasher);
 		let hash = hasher.finish();
-		let img_uuid = Uuid::from_u64_pair(hash, hash);
+		Uuid::from_u64_pair(hash, hash)
+	}
+
+	pub fn create_music_response(entry: Music) -> MusicResponse {
+		let img_uuid = Music::image_uuid(&entry.artist, &entry.album);
 		MusicResponse {
 			id: entry.music_id.clone(),
 			artist: entry.artist,
@@ -161,6 +431,11 @@ impl Music {
 			genre: entry.genre,
 			times_played: entry.times_played,
 			duration: entry.duration,
+			year: entry.year,
+			bpm: entry.bpm,
+			musical_key: entry.musical_key,
+			energy: entry.energy,
+			danceability: entry.danceability,
 			image_url: img_uuid.to_string(),
 		}
 	}
@@ -174,5 +449,10 @@ pub struct MusicResponse {
 	pub genre: String,
 	pub times_played: i32,
 	pub duration: i64,
+	pub year: Option<i32>,
+	pub bpm: Option<f64>,
+	pub musical_key: Option<String>,
+	pub energy: Option<f64>,       // 0.0 (quiet) to 1.0 (loud)
+	pub danceability: Option<f64>, // 0.0 to 1.0, how steady and strong the beat is
 	pub image_url: String,
 }
@@ -9,7 +9,10 @@ mod routes;
 mod schema;
 mod utils;
 
-use config::{COVER_IMG_STORAGE, server_ip, MUSIC_STORAGE, PLAYLIST_COVER_IMG_STORAGE, PORT, USER_PFP_STORAGE};
+use config::{
+	server_ip, COVER_IMG_STORAGE, MUSIC_STORAGE, PLAYLIST_COVER_HISTORY_STORAGE, PLAYLIST_COVER_IMG_STORAGE,
+	PLAYLIST_MOSAIC_STORAGE, PORT, REPORT_CARD_STORAGE, USER_PFP_STORAGE,
+};
 use core::{app_state::AppState, migrations::run_migrations};
 use dotenv::dotenv;
 
@@ -24,6 +27,19 @@ async fn main() {
 	run_migrations(&db_url);
 
 	let app_state = AppState::new();
+	if let Err(err) = core::search_index::backfill(&app_state.db_pool) {
+		println!("[search_index]: {err}");
+	}
+	core::charts::spawn_chart_refresher(app_state.db_pool.clone(), app_state.chart_cache.clone());
+	core::recommender::spawn_recommender_refresher(app_state.db_pool.clone(), app_state.recommender.clone());
+	core::audio_analysis::spawn_audio_analyzer(app_state.db_pool.clone());
+	core::playback::spawn_playback_sweeper(app_state.db_pool.clone(), app_state.playback_tracker.clone());
+	core::playlist_history::spawn_deleted_playlist_purger(app_state.db_pool.clone());
+	core::blend::spawn_blend_refresher(
+		app_state.db_pool.clone(),
+		app_state.playlist_rooms.clone(),
+		app_state.user_pool.clone(),
+	);
 
 	let app = core::routes::configure_routes(app_state)
 		.layer(axum::middleware::from_fn(core::server::logger))
@@ -44,6 +60,9 @@ fn create_storage_directories() -> std::io::Result<()> {
 		MUSIC_STORAGE,
 		USER_PFP_STORAGE,
 		PLAYLIST_COVER_IMG_STORAGE,
+		PLAYLIST_COVER_HISTORY_STORAGE,
+		PLAYLIST_MOSAIC_STORAGE,
+		REPORT_CARD_STORAGE,
 	];
 
 	for dir in subdirectories {
@@ -1,3 +1,4 @@
+use crate::core::activity;
 use crate::core::app_state::AppState;
 use crate::utils::cookie;
 
@@ -15,7 +16,11 @@ pub struct LogoutPayload {
 }
 
 pub async fn logout(State(app_state): State<AppState>, Json(payload): Json<LogoutPayload>) -> Response<String> {
-	let _ = app_state.user_pool.remove(&payload.user_id);
+	if app_state.user_pool.remove(&payload.user_id) {
+		if let Ok(mut db_conn) = app_state.db_pool.get() {
+			activity::broadcast_presence(&mut db_conn, &app_state.user_pool, &payload.user_id);
+		}
+	}
 
 	let user_cookie = cookie::create("user_id", "", 0);
 	let access_cookie = cookie::create("access_token", "", 0);
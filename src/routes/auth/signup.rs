@@ -1,4 +1,5 @@
 use crate::core::app_state::AppState;
+use crate::core::profile;
 use crate::lobic_db::models::User;
 use crate::mail::mailer::send_mail;
 use crate::mail::otp_mail::otp_mail;
@@ -96,6 +97,9 @@ pub async fn signup(State(app_state): State<AppState>, Json(payload): Json<Signu
 		.values(&new_user)
 		.execute(&mut db_conn)
 		.unwrap();
+	if let Err(err) = profile::create(&mut db_conn, &new_user_id) {
+		println!("Error {}:{}: Failed to create the profile: {err}", file!(), line!());
+	}
 
 	// Generate jwt
 	let jwt_secret_key = std::env::var("JWT_SECRET_KEY").expect("JWT_SECRET_KEY must be set in .env file");
@@ -0,0 +1,57 @@
+use crate::core::app_state::AppState;
+use crate::core::direct_message::{self, DmError};
+
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use serde::Deserialize;
+
+// /messages/history?conversation_id=123&user_id=456&start_index=0&page_length=50
+#[derive(Debug, Deserialize)]
+pub struct MessagesQueryParams {
+	pub conversation_id: String,
+	pub user_id: String,
+	#[serde(default)]
+	pub start_index: i64, //defaults to 0
+	pub page_length: Option<i64>,
+}
+
+// Messages of the conversation, latest first
+pub async fn get_messages(
+	State(app_state): State<AppState>,
+	Query(params): Query<MessagesQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match direct_message::history(
+		&mut db_conn,
+		&params.user_id,
+		&params.conversation_id,
+		params.start_index,
+		params.page_length,
+	) {
+		Ok(messages) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&messages).unwrap())
+			.unwrap(),
+		Err(err @ DmError::NotMember(_)) => Response::builder()
+			.status(StatusCode::FORBIDDEN)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to load messages: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,40 @@
+use crate::core::app_state::AppState;
+use crate::core::direct_message;
+use crate::lobic_db::db::*;
+
+use axum::{
+	extract::{Path, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use serde_json::json;
+
+// Unread messages across all the conversations of the user, e.g. for a badge
+pub async fn get_unread_count(State(app_state): State<AppState>, Path(user_id): Path<String>) -> Response<String> {
+	if !user_exists(&user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match direct_message::unread_total(&mut db_conn, &user_id) {
+		Ok(unread_count) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json!({ "unread_count": unread_count }).to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Database error: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,48 @@
+use crate::core::app_state::AppState;
+use crate::core::direct_message::{self, DmError};
+
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+	Json,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct MarkReadPayload {
+	pub conversation_id: String,
+	pub user_id: String,
+}
+
+// Reads the whole conversation and sends the read receipt to the other members
+pub async fn mark_read(State(app_state): State<AppState>, Json(payload): Json<MarkReadPayload>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match direct_message::mark_read(&mut db_conn, &payload.user_id, &payload.conversation_id) {
+		Ok(receipt) => {
+			direct_message::broadcast_read(&app_state, &mut db_conn, &receipt);
+			Response::builder()
+				.status(StatusCode::OK)
+				.header(header::CONTENT_TYPE, "application/json")
+				.body(serde_json::to_string(&receipt).unwrap())
+				.unwrap()
+		}
+		Err(err @ DmError::NotMember(_)) => Response::builder()
+			.status(StatusCode::FORBIDDEN)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to mark conversation as read: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,70 @@
+use crate::core::app_state::AppState;
+use crate::core::direct_message::{self, Attachment, DmError};
+
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+	Json,
+};
+use serde::Deserialize;
+
+// attachment: {"kind": "track" | "playlist", "id": "..."}, shown as a share card
+#[derive(Debug, Deserialize)]
+pub struct SendMessagePayload {
+	pub conversation_id: String,
+	pub user_id: String,
+	#[serde(default)]
+	pub body: String, // can be empty with an attachment
+	pub attachment: Option<Attachment>,
+}
+
+pub async fn send_message(
+	State(app_state): State<AppState>,
+	Json(payload): Json<SendMessagePayload>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let message = match direct_message::send(
+		&mut db_conn,
+		&payload.user_id,
+		&payload.conversation_id,
+		&payload.body,
+		payload.attachment,
+	) {
+		Ok(message) => message,
+		Err(err @ (DmError::EmptyMessage | DmError::MessageTooLong | DmError::InvalidAttachment(_))) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err @ (DmError::NotMember(_) | DmError::NotFriend(_))) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to send message: {err}"))
+				.unwrap();
+		}
+	};
+
+	direct_message::deliver(&app_state, &mut db_conn, &message);
+	Response::builder()
+		.status(StatusCode::CREATED)
+		.header(header::CONTENT_TYPE, "application/json")
+		.body(serde_json::to_string(&message).unwrap())
+		.unwrap()
+}
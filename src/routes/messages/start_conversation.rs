@@ -0,0 +1,60 @@
+use crate::core::app_state::AppState;
+use crate::core::direct_message::{self, DmError};
+use crate::lobic_db::db::*;
+
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+	Json,
+};
+use serde::Deserialize;
+
+// One member makes a one to one conversation, more make a group
+#[derive(Debug, Deserialize)]
+pub struct StartConversationPayload {
+	pub user_id: String,
+	pub member_ids: Vec<String>,
+	pub name: Option<String>, // groups only
+}
+
+pub async fn start_conversation(
+	State(app_state): State<AppState>,
+	Json(payload): Json<StartConversationPayload>,
+) -> Response<String> {
+	if !user_exists(&payload.user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", payload.user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let name = payload.name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty());
+	match direct_message::start(&mut db_conn, &payload.user_id, &payload.member_ids, name) {
+		Ok((conversation, created)) => Response::builder()
+			.status(if created { StatusCode::CREATED } else { StatusCode::OK })
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&conversation).unwrap())
+			.unwrap(),
+		Err(err @ (DmError::NoMembers | DmError::TooManyMembers)) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err @ DmError::NotFriend(_)) => Response::builder()
+			.status(StatusCode::FORBIDDEN)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to start conversation: {err}"))
+			.unwrap(),
+	}
+}
@@ -2,13 +2,18 @@ pub mod music {
 	pub mod get_cover_image;
 	pub mod get_music;
 	pub mod log_song_play;
+	pub mod playback_event;
 	pub mod save_music;
 	pub mod search_music;
 	pub mod send_music;
 	pub mod recently_played {
 		pub mod get_recently_played;
 	}
+	pub mod listen_history {
+		pub mod get_listen_history;
+	}
 	pub mod trending {
+		pub mod get_trending_movers;
 		pub mod get_trending_songs;
 	}
 	pub mod top_tracks {
@@ -31,16 +36,67 @@ pub mod playlist {
 	pub mod add_song_to_playlist;
 	pub mod create_new_playlist;
 	pub mod delete_playlist;
+	pub mod export_playlist;
 	pub mod get_playlist_cover_img;
 	pub mod get_playlist_music;
 	pub mod get_users_playlists;
+	pub mod import_playlist;
 	pub mod remove_song_from_playlist;
+	pub mod rename_playlist;
+	pub mod update_playlist_details;
+	pub mod reorder_playlist;
 	pub mod update_playlist_cover_img;
+	pub mod update_visibility;
 	pub mod combined_playlist {
 		pub mod add_contributor;
 		pub mod fetch_all_contributors;
+		pub mod get_invitations;
 		pub mod remove_contributor;
+		pub mod respond_invitation;
+		pub mod transfer_ownership;
+		pub mod update_role;
+	}
+	pub mod smart_playlist {
+		pub mod create_smart_playlist;
+		pub mod freeze_smart_playlist;
+		pub mod update_smart_rules;
+	}
+	pub mod share_token {
+		pub mod create_share_token;
+		pub mod get_share_tokens;
+		pub mod revoke_share_token;
+	}
+	pub mod follow {
+		pub mod follow_playlist;
+		pub mod unfollow_playlist;
+	}
+	pub mod folder {
+		pub mod create_folder;
+		pub mod delete_folder;
+		pub mod get_folders;
+		pub mod move_folder;
+		pub mod rename_folder;
+	}
+	pub mod library {
+		pub mod move_playlist_to_folder;
+		pub mod pin_playlist;
+	}
+	pub mod history {
+		pub mod get_deleted_playlists;
+		pub mod get_playlist_history;
+		pub mod restore_deleted_playlist;
+		pub mod restore_playlist;
+		pub mod undo_playlist_changes;
 	}
+	pub mod fork {
+		pub mod fork_playlist;
+		pub mod get_source_diff;
+		pub mod update_from_source;
+	}
+	pub mod blend {
+		pub mod create_blend;
+	}
+	pub mod merge_playlists;
 }
 pub mod users {
 	pub mod get_user;
@@ -49,10 +105,54 @@ pub mod users {
 	pub mod add_friend;
 	pub mod remove_friend;
 	pub mod get_friend;
+	pub mod respond_friend_request;
+	pub mod cancel_friend_request;
+	pub mod get_friend_requests;
+	pub mod block_user;
+	pub mod unblock_user;
+	pub mod get_blocked_users;
+	pub mod get_feed;
+	pub mod get_friends_presence;
+	pub mod get_privacy;
+	pub mod update_privacy;
+	pub mod get_profile;
+	pub mod update_profile;
 	pub mod search_user;
 	pub mod update_pfp;
 }
+pub mod messages {
+	pub mod get_conversations;
+	pub mod get_messages;
+	pub mod get_unread_count;
+	pub mod mark_read;
+	pub mod send_message;
+	pub mod start_conversation;
+}
+pub mod radio;
+pub mod recommend {
+	pub mod because_you_liked;
+	pub mod for_you;
+	pub mod similar;
+}
 pub mod search;
+pub mod share {
+	pub mod add_share_to_playlist;
+	pub mod get_shares;
+	pub mod open_share;
+	pub mod react_to_share;
+	pub mod share_item;
+}
+pub mod scrobbles {
+	pub mod export_scrobbles;
+	pub mod import_scrobbles;
+	pub mod submit_scrobbles;
+}
+pub mod stats {
+	pub mod compare_friends;
+	pub mod get_report;
+	pub mod get_summary;
+	pub mod get_top_groups;
+}
 pub mod auth {
 	pub mod login;
 	pub mod logout;
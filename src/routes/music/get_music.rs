@@ -20,6 +20,13 @@ pub struct MusicQuery {
 	album: Option<String>,
 	genre: Option<String>,
 	randomizer: Option<bool>,
+	min_bpm: Option<f64>,
+	max_bpm: Option<f64>,
+	key: Option<String>, // eg: "A minor", or just "A" for both modes
+	min_energy: Option<f64>,
+	max_energy: Option<f64>,
+	min_danceability: Option<f64>,
+	max_danceability: Option<f64>,
 	#[serde(default)]
 	start_index: i64,
 	page_length: Option<i64>,
@@ -54,6 +61,31 @@ pub async fn get_music(State(app_state): State<AppState>, Query(params): Query<M
 	if let Some(genre_val) = params.genre {
 		query = query.filter(genre.eq(genre_val));
 	}
+	if let Some(min_bpm) = params.min_bpm {
+		query = query.filter(bpm.ge(min_bpm));
+	}
+	if let Some(max_bpm) = params.max_bpm {
+		query = query.filter(bpm.le(max_bpm));
+	}
+	if let Some(key_val) = params.key {
+		if key_val.contains(' ') {
+			query = query.filter(musical_key.eq(key_val));
+		} else {
+			query = query.filter(musical_key.eq_any([format!("{key_val} major"), format!("{key_val} minor")]));
+		}
+	}
+	if let Some(min_energy) = params.min_energy {
+		query = query.filter(energy.ge(min_energy));
+	}
+	if let Some(max_energy) = params.max_energy {
+		query = query.filter(energy.le(max_energy));
+	}
+	if let Some(min_danceability) = params.min_danceability {
+		query = query.filter(danceability.ge(min_danceability));
+	}
+	if let Some(max_danceability) = params.max_danceability {
+		query = query.filter(danceability.le(max_danceability));
+	}
 	if params.randomizer.unwrap_or(false) {
 		query = query.order(sql::<Integer>("RANDOM()"));
 	}
@@ -1,8 +1,10 @@
+use crate::core::activity::{self, ActivityKind};
 use crate::core::app_state::AppState;
 use axum::{extract::State, http::status::StatusCode, response::Response, Json};
 use chrono::Utc;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AddLikedSong {
@@ -40,10 +42,20 @@ pub async fn add_to_liked_songs(
 		.values(&new_liked_song)
 		.execute(&mut db_conn)
 	{
-		Ok(_) => Response::builder()
-			.status(StatusCode::CREATED)
-			.body("Song added to liked songs".to_string())
-			.unwrap(),
+		Ok(_) => {
+			activity::share_track(
+				&mut db_conn,
+				&app_state.user_pool,
+				&payload.user_id,
+				ActivityKind::LikedTrack,
+				&payload.music_id,
+				json!({}),
+			);
+			Response::builder()
+				.status(StatusCode::CREATED)
+				.body("Song added to liked songs".to_string())
+				.unwrap()
+		}
 		Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _)) => {
 			Response::builder()
 				.status(StatusCode::CONFLICT)
@@ -1,8 +1,10 @@
+use crate::core::activity::{self, ActivityKind};
 use crate::core::app_state::AppState; // Assuming AppState is defined in your core module
 use axum::{extract::State, http::StatusCode, response::Response, Json};
 use chrono::Utc;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 // Struct for the request payload
 #[derive(Debug, Serialize, Deserialize)]
@@ -70,10 +72,20 @@ pub async fn toggle_liked_song(
 				.values(&new_liked_song)
 				.execute(&mut db_conn)
 			{
-				Ok(_) => Response::builder()
-					.status(StatusCode::CREATED)
-					.body("Song added to liked songs".to_string())
-					.unwrap(),
+				Ok(_) => {
+					activity::share_track(
+						&mut db_conn,
+						&app_state.user_pool,
+						&payload.user_id,
+						ActivityKind::LikedTrack,
+						&payload.music_id,
+						json!({}),
+					);
+					Response::builder()
+						.status(StatusCode::CREATED)
+						.body("Song added to liked songs".to_string())
+						.unwrap()
+				}
 				Err(err) => Response::builder()
 					.status(StatusCode::INTERNAL_SERVER_ERROR)
 					.body(format!("Failed to add song to liked songs: {}", err))
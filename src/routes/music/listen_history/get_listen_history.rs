@@ -0,0 +1,123 @@
+use crate::{
+	core::app_state::AppState,
+	lobic_db::models::{Listen, Music, MusicResponse},
+	schema::{listens, music},
+	utils::timestamp,
+};
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// /music/get_listen_history?user_id=123&from=2026-01-01&to=2026-01-31&page_length=50
+// /music/get_listen_history?user_id=123&from=2026-01-01T10:00:00Z
+#[derive(Debug, Deserialize)]
+pub struct ListenHistoryQueryParams {
+	pub user_id: String,
+	pub from: Option<String>, // inclusive, RFC3339 or YYYY-MM-DD
+	pub to: Option<String>,   // exclusive, a plain date includes the whole day
+	#[serde(default)]
+	pub start_index: i64, //defaults to 0
+	pub page_length: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListenHistoryEntry {
+	pub listen_id: String,
+	pub listened_at: String,
+	pub listened_duration: i64,
+	pub source_type: String,
+	pub source_id: Option<String>,
+	pub skipped: bool,
+	pub completed: bool, // counted as a play
+	pub music: MusicResponse,
+}
+
+pub async fn get_listen_history(
+	State(app_state): State<AppState>,
+	Query(params): Query<ListenHistoryQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let mut query = listens::table
+		.filter(listens::user_id.eq(&params.user_id))
+		.inner_join(music::table)
+		.select((listens::all_columns, music::all_columns))
+		.order(listens::listened_at.desc()) // Most recent first
+		.into_boxed();
+
+	if let Some(from) = &params.from {
+		match timestamp::parse_date_bound(from, false) {
+			Some(from) => query = query.filter(listens::listened_at.ge(from)),
+			None => {
+				return Response::builder()
+					.status(StatusCode::BAD_REQUEST)
+					.body(format!("Invalid from date: {from}"))
+					.unwrap();
+			}
+		}
+	}
+	if let Some(to) = &params.to {
+		match timestamp::parse_date_bound(to, true) {
+			Some(to) => query = query.filter(listens::listened_at.lt(to)),
+			None => {
+				return Response::builder()
+					.status(StatusCode::BAD_REQUEST)
+					.body(format!("Invalid to date: {to}"))
+					.unwrap();
+			}
+		}
+	}
+
+	query = query.offset(params.start_index);
+	if let Some(length) = params.page_length {
+		if length > 0 {
+			query = query.limit(length);
+		}
+	}
+
+	match query.load::<(Listen, Music)>(&mut db_conn) {
+		Ok(entries) => {
+			let responses: Vec<ListenHistoryEntry> = entries
+				.into_iter()
+				.map(|(listen, entry)| ListenHistoryEntry {
+					listen_id: listen.listen_id,
+					listened_at: listen.listened_at,
+					listened_duration: listen.listened_duration,
+					source_type: listen.source_type,
+					source_id: listen.source_id,
+					skipped: listen.skipped,
+					completed: listen.completed,
+					music: Music::create_music_response(entry),
+				})
+				.collect();
+
+			match serde_json::to_string(&responses) {
+				Ok(json) => Response::builder()
+					.status(StatusCode::OK)
+					.header(header::CONTENT_TYPE, "application/json")
+					.body(json)
+					.unwrap(),
+				Err(err) => Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Failed to serialize response: {err}"))
+					.unwrap(),
+			}
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Database error: {err}"))
+			.unwrap(),
+	}
+}
@@ -1,7 +1,9 @@
 use crate::{
-	core::app_state::AppState,
-	lobic_db::models::PlayLog,
-	schema::{music, play_log},
+	core::{
+		app_state::AppState,
+		playback::{self, NewListen},
+	},
+	lobic_db::models::ListenSource,
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use chrono::Utc;
@@ -13,6 +15,13 @@ use tracing::log::error;
 pub struct LogSongPlay {
 	pub user_id: String,
 	pub music_id: String,
+	#[serde(default)]
+	pub listened_duration: i64, // in seconds, a listen without it doesn't count as a play
+	#[serde(default)]
+	pub source: ListenSource, // defaults to solo
+	pub source_id: Option<String>, // lobby or playlist id
+	#[serde(default)]
+	pub skipped: bool,
 }
 
 const MAX_RETRIES: u32 = 3;
@@ -35,34 +44,19 @@ pub async fn log_song_play(State(app_state): State<AppState>, Json(payload): Jso
 	let mut retries = 0;
 	let transaction_result = loop {
 		match db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
-			let curr_music_played_date_time = Utc::now().to_rfc3339();
-
-			// Create new play log entry
-			let new_play_log = PlayLog {
-				user_id: payload.user_id.clone(),
-				music_id: payload.music_id.clone(),
-				music_played_date_time: curr_music_played_date_time.clone(),
-				user_times_played: 1,
-			};
-
-			// Update play log
-			diesel::insert_into(play_log::table)
-				.values(&new_play_log)
-				.on_conflict((play_log::user_id, play_log::music_id))
-				.do_update()
-				.set((
-					play_log::music_played_date_time.eq(curr_music_played_date_time),
-					play_log::user_times_played.eq(play_log::user_times_played + 1),
-				))
-				.execute(conn)?;
-
-			// Update global play count
-			diesel::update(music::table)
-				.filter(music::music_id.eq(&payload.music_id))
-				.set(music::times_played.eq(music::times_played + 1))
-				.execute(conn)?;
-
-			Ok(())
+			// Whether it counts as a play is decided by the completion threshold, not by the client
+			playback::record_listen(
+				conn,
+				NewListen {
+					user_id: payload.user_id.clone(),
+					music_id: payload.music_id.clone(),
+					listened_at: Utc::now().to_rfc3339(),
+					listened_duration: payload.listened_duration,
+					source: payload.source,
+					source_id: payload.source_id.clone(),
+					skip_requested: payload.skipped,
+				},
+			)
 		}) {
 			Ok(result) => break Ok(result),
 			Err(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::Unknown, _))
@@ -77,7 +71,13 @@ pub async fn log_song_play(State(app_state): State<AppState>, Json(payload): Jso
 	};
 
 	match transaction_result {
-		Ok(_) => (StatusCode::CREATED, "Song play logged successfully").into_response(),
+		Ok(listen) if listen.completed => (StatusCode::CREATED, "Song play logged successfully").into_response(),
+		Ok(_) => (StatusCode::CREATED, "Listen logged, not counted as a play").into_response(),
+		Err(diesel::result::Error::NotFound) => (
+			StatusCode::BAD_REQUEST,
+			format!("Invalid music_id: {}", payload.music_id),
+		)
+			.into_response(),
 		Err(err) => {
 			error!("Failed to log song play: {}", err);
 			(
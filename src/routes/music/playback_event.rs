@@ -0,0 +1,184 @@
+use crate::{
+	core::{
+		activity::{self, ActivityKind},
+		app_state::AppState,
+		playback::{self, Playback},
+	},
+	lobic_db::models::ListenSource,
+	schema::music,
+};
+use axum::{
+	extract::State,
+	http::{header, StatusCode},
+	response::Response,
+	Json,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaybackEventType {
+	Start,
+	Heartbeat,
+	End,
+	Skip,
+}
+
+// { "event": "start", "user_id": "123", "music_id": "456", "source": "lobby", "source_id": "789" }
+// { "event": "heartbeat", "user_id": "123", "playback_id": "abc", "position": 42 }
+#[derive(Debug, Deserialize)]
+pub struct PlaybackEvent {
+	pub event: PlaybackEventType,
+	pub user_id: String,
+	pub playback_id: Option<String>, // returned by start, required by the other events
+	pub music_id: Option<String>,    // required by start
+	#[serde(default)]
+	pub position: i64, // seconds into the track
+	#[serde(default)]
+	pub source: ListenSource, // defaults to solo
+	pub source_id: Option<String>,   // lobby or playlist id
+}
+
+pub async fn playback_event(State(app_state): State<AppState>, Json(payload): Json<PlaybackEvent>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Playing counts as activity for the presence of the user
+	app_state.user_pool.touch(&payload.user_id);
+
+	if payload.event == PlaybackEventType::Start {
+		let music_id = match &payload.music_id {
+			Some(music_id) => music_id,
+			None => {
+				return Response::builder()
+					.status(StatusCode::BAD_REQUEST)
+					.body("music_id is required to start a playback".to_string())
+					.unwrap();
+			}
+		};
+
+		let duration = match music::table
+			.filter(music::music_id.eq(music_id))
+			.select(music::duration)
+			.first::<i64>(&mut db_conn)
+		{
+			Ok(duration) => duration,
+			Err(diesel::result::Error::NotFound) => {
+				return Response::builder()
+					.status(StatusCode::BAD_REQUEST)
+					.body(format!("Invalid music_id: {music_id}"))
+					.unwrap();
+			}
+			Err(err) => {
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Database error: {err}"))
+					.unwrap();
+			}
+		};
+
+		let now = Utc::now();
+		let (progress, previous) = app_state.playback_tracker.start(Playback {
+			user_id: payload.user_id.clone(),
+			music_id: music_id.clone(),
+			source: payload.source,
+			source_id: payload.source_id.clone(),
+			duration,
+			started_at: now,
+			position: payload.position.max(0),
+			listened: 0,
+			last_event_at: now,
+		});
+
+		// Starting a new track ends whatever the user was playing before
+		if let Err(err) = playback::record_abandoned(&mut db_conn, previous) {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to record the previous playback: {err}"))
+				.unwrap();
+		}
+
+		activity::share_track(
+			&mut db_conn,
+			&app_state.user_pool,
+			&payload.user_id,
+			ActivityKind::NowPlaying,
+			music_id,
+			json!({
+				"source": payload.source,
+				"source_id": payload.source_id,
+			}),
+		);
+
+		return json_response(StatusCode::CREATED, &progress);
+	}
+
+	let playback_id = match &payload.playback_id {
+		Some(playback_id) => playback_id,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body("playback_id is required".to_string())
+				.unwrap();
+		}
+	};
+	let invalid_playback = || {
+		Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(format!("Invalid playback_id: {playback_id}"))
+			.unwrap()
+	};
+
+	if payload.event == PlaybackEventType::Heartbeat {
+		return match app_state
+			.playback_tracker
+			.heartbeat(playback_id, &payload.user_id, payload.position)
+		{
+			Some(progress) => json_response(StatusCode::OK, &progress),
+			None => invalid_playback(),
+		};
+	}
+
+	let skip_requested = payload.event == PlaybackEventType::Skip;
+	let new_listen =
+		match app_state
+			.playback_tracker
+			.finish(playback_id, &payload.user_id, payload.position, skip_requested)
+		{
+			Some(new_listen) => new_listen,
+			None => return invalid_playback(),
+		};
+
+	// The listen and the play counts are written together
+	match db_conn.transaction(|conn| playback::record_listen(conn, new_listen)) {
+		Ok(listen) => json_response(StatusCode::OK, &listen),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to record listen: {err}"))
+			.unwrap(),
+	}
+}
+
+fn json_response<T: Serialize>(status: StatusCode, value: &T) -> Response<String> {
+	match serde_json::to_string(value) {
+		Ok(json) => Response::builder()
+			.status(status)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to serialize response: {err}"))
+			.unwrap(),
+	}
+}
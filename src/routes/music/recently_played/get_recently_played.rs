@@ -1,7 +1,7 @@
 use crate::{
 	core::app_state::AppState,
 	lobic_db::models::{Music, MusicResponse},
-	schema::{music, play_log},
+	schema::{listens, music},
 };
 use axum::{
 	extract::{Query, State},
@@ -36,9 +36,10 @@ pub async fn get_recently_played(
 		}
 	};
 
-	let mut query = play_log::table
-		.filter(play_log::user_id.eq(&params.user_id))
-		.order(play_log::music_played_date_time.desc()) // Most recent first
+	// Every listen is returned, so a track played twice shows up twice
+	let mut query = listens::table
+		.filter(listens::user_id.eq(&params.user_id))
+		.order(listens::listened_at.desc()) // Most recent first
 		.inner_join(music::table)
 		.select(music::all_columns)
 		.offset(params.start_index)
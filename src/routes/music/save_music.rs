@@ -1,5 +1,5 @@
 use crate::config::{COVER_IMG_STORAGE, MUSIC_STORAGE};
-use crate::core::app_state::AppState;
+use crate::core::{app_state::AppState, audio_analysis, search_index};
 use crate::lobic_db::models::Music;
 use crate::schema::music::dsl::*;
 
@@ -54,6 +54,11 @@ pub async fn save_music(State(app_state): State<AppState>, Json(payload): Json<M
 		}
 	}
 
+	// Estimating bpm, key and the like of the new tracks in the background
+	if saved_count > 0 {
+		audio_analysis::spawn_audio_analyzer(app_state.db_pool.clone());
+	}
+
 	let status = if errors.is_empty() {
 		StatusCode::OK
 	} else {
@@ -143,6 +148,14 @@ fn process_music_file(path: &Path, db_conn: &mut SqliteConnection) -> Result<(),
 		genre: tag.genre().unwrap_or("Unknown Genre").to_string(),
 		times_played: 0,
 		duration: curr_duration,
+		year: tag.year(),
+		// Filled in by the audio analyzer once the file is stored
+		bpm: None,
+		musical_key: None,
+		energy: None,
+		danceability: None,
+		analyzed_at: None,
+		search_text: search_index::search_text(curr_title, curr_album, curr_artist),
 	};
 
 	extract_cover_art(path_str, &curr_artist, &curr_album)?;
@@ -1,5 +1,6 @@
 use crate::core::app_state::AppState;
 use crate::lobic_db::models::Music;
+use crate::utils::fuzzy;
 use axum::{
 	extract::{Query, State},
 	http::{header, StatusCode},
@@ -43,20 +44,26 @@ pub async fn search_music(State(app_state): State<AppState>, Query(params): Quer
 		}
 	};
 
+	// Everything is compared in its normalized form (accent folded and transliterated to latin)
+	let search_term = fuzzy::normalize(&params.search_string);
+
 	// Perform fuzzy search on all fields with weighted scores
 	let search_results = all_music
 		.into_iter()
 		.map(|entry| {
+			let entry_title = fuzzy::normalize(&entry.title);
+			let entry_artist = fuzzy::normalize(&entry.artist);
+			let entry_album = fuzzy::normalize(&entry.album);
+			let entry_genre = fuzzy::normalize(&entry.genre);
+
 			// Check for exact matches in title, artist, or album
-			let exact_match = entry.title.eq_ignore_ascii_case(&params.search_string)
-				|| entry.artist.eq_ignore_ascii_case(&params.search_string)
-				|| entry.album.eq_ignore_ascii_case(&params.search_string);
+			let exact_match = entry_title == search_term || entry_artist == search_term || entry_album == search_term;
 
 			// Calculate similarity scores for each field
-			let title_score = jaro_winkler(&entry.title, &params.search_string);
-			let artist_score = jaro_winkler(&entry.artist, &params.search_string);
-			let album_score = jaro_winkler(&entry.album, &params.search_string);
-			let genre_score = jaro_winkler(&entry.genre, &params.search_string);
+			let title_score = jaro_winkler(&entry_title, &search_term);
+			let artist_score = jaro_winkler(&entry_artist, &search_term);
+			let album_score = jaro_winkler(&entry_album, &search_term);
+			let genre_score = jaro_winkler(&entry_genre, &search_term);
 
 			let weighted_score =
 				if exact_match {
@@ -69,22 +76,30 @@ pub async fn search_music(State(app_state): State<AppState>, Query(params): Quer
 					let weighted_genre = genre_score * 2.0;
 
 					//bonus weights for partial match in artist and title
-					let search_term = params.search_string.to_lowercase();
 					let contains_search_term = |field: &str| -> f64 {
-						match field.to_lowercase().contains(&search_term) {
+						match field.contains(&search_term) {
 							true => 8.0,
 							false => 0.0,
 						}
 					};
 
-					let artist_contains_bonus = contains_search_term(&entry.artist);
-					let title_contains_bonus = contains_search_term(&entry.title) * 0.75;
+					let artist_contains_bonus = contains_search_term(&entry_artist);
+					let title_contains_bonus = contains_search_term(&entry_title) * 0.75;
+
+					// Typo tolerant fallback, the query may be spread over the title and the artist
+					// eg: "samarpit bhatarai" -> "Samarpit (Raw Version) - Manish Bhattarai"
+					let fuzzy_bonus = if artist_contains_bonus + title_contains_bonus == 0.0 {
+						let combined = format!("{} {}", entry_title, entry_artist);
+						fuzzy::trigram_similarity(&search_term, &combined) * 10.0
+					} else {
+						0.0
+					};
 
 					// Sum all components
 					weighted_artist
 						+ weighted_title + weighted_album
 						+ weighted_genre + artist_contains_bonus
-						+ title_contains_bonus
+						+ title_contains_bonus + fuzzy_bonus
 				};
 
 			(entry, weighted_score)
@@ -3,7 +3,9 @@ use axum::{
 	http::{header, StatusCode},
 	response::Response,
 };
+use diesel::dsl::sql;
 use diesel::prelude::*;
+use diesel::sql_types::Double;
 use serde::Deserialize;
 
 use crate::{
@@ -34,10 +36,15 @@ pub async fn get_top_tracks(
 		}
 	};
 
+	// Plays only count completed listens, every skip of the track by the user takes half a play away
+	let score = sql::<Double>(
+		"play_log.user_times_played - 0.5 * (SELECT COUNT(*) FROM listens \
+		WHERE listens.user_id = play_log.user_id AND listens.music_id = play_log.music_id AND listens.skipped)",
+	);
 	let mut query = play_log::table
 		.filter(play_log::user_id.eq(&params.user_id))
 		.filter(play_log::user_times_played.ge(1))
-		.order(play_log::user_times_played.desc())
+		.order((score.desc(), play_log::user_times_played.desc()))
 		.inner_join(music::table)
 		.select(music::all_columns)
 		.offset(params.start_index)
@@ -0,0 +1,124 @@
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::core::app_state::AppState;
+use crate::core::charts::{self, ChartWindow};
+use crate::lobic_db::models::{Music, MusicResponse};
+
+// /music/get_trending/movers?window=7d&genre=rock&page_length=10
+#[derive(Debug, Deserialize)]
+pub struct TrendingMoversQueryParams {
+	pub window: Option<String>, // 24h, 7d (default) or 30d
+	pub genre: Option<String>,
+	pub friends_of: Option<String>,
+	#[serde(default)]
+	pub start_index: i64, //defaults to 0
+	pub page_length: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrendingMoverResponse {
+	pub rank: usize,
+	pub previous_rank: Option<usize>,
+	pub change: Option<i64>, // positive when the track climbed, None for new entries
+	pub music: MusicResponse,
+}
+
+// Tracks of the chart sorted by how much they climbed since the previous period, new entries first
+pub async fn get_trending_movers(
+	State(app_state): State<AppState>,
+	Query(params): Query<TrendingMoversQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let window = params.window.as_deref().unwrap_or("7d");
+	let window = match ChartWindow::parse(window) {
+		Some(window) => window,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!("Invalid window: {window}, expected one of 24h, 7d or 30d"))
+				.unwrap();
+		}
+	};
+
+	let chart = match app_state.chart_cache.get_or_compute(
+		window,
+		params.genre.as_deref(),
+		params.friends_of.as_deref(),
+		&app_state.db_pool,
+	) {
+		Ok(chart) => chart,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err)
+				.unwrap();
+		}
+	};
+
+	let change = |entry: &charts::ChartEntry| {
+		entry
+			.previous_rank
+			.map(|previous_rank| previous_rank as i64 - entry.rank as i64)
+	};
+
+	let mut movers = chart.entries;
+	movers.sort_by_key(|entry| (change(entry).map_or(i64::MIN, |change| -change), entry.rank));
+
+	let page_length = params.page_length.filter(|length| *length > 0).unwrap_or(i64::MAX);
+	let movers = movers
+		.into_iter()
+		.skip(params.start_index.max(0) as usize)
+		.take(page_length as usize)
+		.collect();
+
+	match charts::load_chart_music(&mut db_conn, movers) {
+		Ok(entries) => {
+			if entries.is_empty() {
+				return Response::builder()
+					.status(StatusCode::NOT_FOUND)
+					.body("No trending movers found".to_string())
+					.unwrap();
+			}
+
+			let responses: Vec<TrendingMoverResponse> = entries
+				.into_iter()
+				.map(|(entry, music)| TrendingMoverResponse {
+					rank: entry.rank,
+					previous_rank: entry.previous_rank,
+					change: change(&entry),
+					music: Music::create_music_response(music),
+				})
+				.collect();
+
+			match serde_json::to_string(&responses) {
+				Ok(json) => Response::builder()
+					.status(StatusCode::OK)
+					.header(header::CONTENT_TYPE, "application/json")
+					.body(json)
+					.unwrap(),
+				Err(err) => Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Failed to serialize response: {err}"))
+					.unwrap(),
+			}
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Database error: {err}"))
+			.unwrap(),
+	}
+}
@@ -7,12 +7,19 @@ use diesel::prelude::*;
 use serde::Deserialize;
 
 use crate::core::app_state::AppState;
+use crate::core::charts::{self, ChartWindow};
 use crate::lobic_db::models::MusicResponse;
 
 use crate::{lobic_db::models::Music, schema::music};
 
+// /music/get_trending?window=7d&genre=rock&page_length=20
+// /music/get_trending?window=24h&friends_of=123
+// /music/get_trending?window=all (all-time play count)
 #[derive(Debug, Deserialize)]
 pub struct TrendingSongsQueryParams {
+	pub window: Option<String>, // 24h, 7d (default), 30d or all
+	pub genre: Option<String>,
+	pub friends_of: Option<String>, // only count the listens of this user's friends
 	#[serde(default)]
 	pub start_index: i64, //defaults to 0
 	pub page_length: Option<i64>,
@@ -32,21 +39,67 @@ pub async fn get_trending_songs(
 		}
 	};
 
-	//Fetch the most played songs with pagination
-	let mut query = music::table
-		.select(music::all_columns)
-		.order(music::times_played.desc())
-		.offset(params.start_index)
-		.into_boxed();
+	let window = params.window.as_deref().unwrap_or("7d");
+	let music_entries = if window == "all" {
+		//Fetch the most played songs with pagination
+		let mut query = music::table
+			.select(music::all_columns)
+			.order(music::times_played.desc())
+			.offset(params.start_index)
+			.into_boxed();
 
-	// Apply page length if specified
-	if let Some(length) = params.page_length {
-		if length > 0 {
-			query = query.limit(length);
+		// LIKE without wildcards compares ASCII case-insensitively, the same way the windowed charts do
+		if let Some(genre) = &params.genre {
+			let genre = genre.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+			query = query.filter(music::genre.like(genre).escape('\\'));
 		}
-		//else infinity
-	}
-	match query.load::<Music>(&mut db_conn) {
+
+		// Apply page length if specified
+		if let Some(length) = params.page_length {
+			if length > 0 {
+				query = query.limit(length);
+			}
+			//else infinity
+		}
+		query.load::<Music>(&mut db_conn)
+	} else {
+		let window = match ChartWindow::parse(window) {
+			Some(window) => window,
+			None => {
+				return Response::builder()
+					.status(StatusCode::BAD_REQUEST)
+					.body(format!("Invalid window: {window}, expected one of 24h, 7d, 30d or all"))
+					.unwrap();
+			}
+		};
+
+		let chart = match app_state.chart_cache.get_or_compute(
+			window,
+			params.genre.as_deref(),
+			params.friends_of.as_deref(),
+			&app_state.db_pool,
+		) {
+			Ok(chart) => chart,
+			Err(err) => {
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(err)
+					.unwrap();
+			}
+		};
+
+		let page_length = params.page_length.filter(|length| *length > 0).unwrap_or(i64::MAX);
+		let entries = chart
+			.entries
+			.into_iter()
+			.skip(params.start_index.max(0) as usize)
+			.take(page_length as usize)
+			.collect();
+		charts::load_chart_music(&mut db_conn, entries)
+			.map(|entries| entries.into_iter().map(|(_, music)| music).collect())
+	};
+
+	match music_entries {
 		Ok(music_entries) => {
 			if music_entries.is_empty() {
 				return Response::builder()
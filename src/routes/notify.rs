@@ -1,5 +1,6 @@
 use crate::config::{OpCode, SocketResponse};
 use crate::core::app_state::AppState;
+use crate::core::friendship;
 use crate::core::user_pool::UserPool;
 use crate::lobic_db::db::DatabasePool;
 use crate::lobic_db::models::{NotifModel, Notification};
@@ -43,6 +44,22 @@ pub fn notify(client_id: &str, notif: Notification, db_pool: &DatabasePool, user
 		.unwrap();
 }
 
+// Notifications from one user to another, dropped when either of them blocked the other
+pub fn notify_from(sender_id: &str, client_id: &str, notif: Notification, db_pool: &DatabasePool, user_pool: &UserPool) {
+	let blocked = match db_pool.get() {
+		Ok(mut db_conn) => friendship::is_blocked(&mut db_conn, sender_id, client_id),
+		Err(err) => {
+			println!("Error {}:{}: Failed to get DB from pool: {err}", file!(), line!());
+			return;
+		}
+	};
+	match blocked {
+		Ok(false) => notify(client_id, notif, db_pool, user_pool),
+		Ok(true) => (),
+		Err(err) => println!("Error {}:{}: Failed to check blocked users: {err}", file!(), line!()),
+	}
+}
+
 pub async fn get_all_notif(State(app_state): State<AppState>, Path(client_id): Path<String>) -> Response<String> {
 	let mut db_conn = match app_state.db_pool.get() {
 		Ok(conn) => conn,
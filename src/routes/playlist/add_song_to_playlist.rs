@@ -1,9 +1,17 @@
-use crate::core::app_state::AppState;
-use crate::lobic_db::models::PlaylistSong;
+use crate::core::{
+	app_state::AppState,
+	blend::{self, BlendError},
+	playlist,
+	playlist_access::{self, Role},
+	playlist_history::{self, Change},
+};
+use crate::lobic_db::models::{Playlist, PlaylistSong};
+use crate::schema::playlists;
 use axum::{extract::State, http::status::StatusCode, response::Response, Json};
 use chrono::Utc;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AddSongToPlaylist {
@@ -26,26 +34,115 @@ pub async fn add_song_to_playlist(
 		}
 	};
 
+	// Viewers can't add songs
+	match playlist_access::has_role(&mut db_conn, &payload.playlist_id, &payload.song_adder_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	match blend::ensure_not_blend(&mut db_conn, &payload.playlist_id) {
+		Ok(()) => {}
+		Err(err @ BlendError::ManualEdit) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err.to_string())
+				.unwrap();
+		}
+	}
+
 	use crate::schema::playlist_songs::dsl::*;
 	let curr_song_added_date_time = Utc::now().to_rfc3339();
 
-	// Create a new PlaylistSong record
-	let new_playlist_song = PlaylistSong {
-		playlist_id: payload.playlist_id,
-		music_id: payload.music_id,
-		song_added_date_time: curr_song_added_date_time,
-		song_adder_id: payload.song_adder_id,
-	};
+	// Songs are appended at the end, the same song can be added more than once
+	let result = db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+		// The songs of smart playlists are computed, they have to be frozen before adding songs
+		if playlist::is_smart(conn, &payload.playlist_id)? {
+			return Ok(None);
+		}
+		let new_playlist_song = PlaylistSong {
+			item_id: Uuid::new_v4().to_string(),
+			position: playlist::next_position(conn, &payload.playlist_id)?,
+			playlist_id: payload.playlist_id.clone(),
+			music_id: payload.music_id.clone(),
+			song_added_date_time: curr_song_added_date_time,
+			song_adder_id: payload.song_adder_id.clone(),
+		};
+		diesel::insert_into(playlist_songs).values(&new_playlist_song).execute(conn)?;
+		playlist::touch_playlist(conn, &payload.playlist_id)?;
+		let new_item_id = new_playlist_song.item_id.clone();
+		let change = Change::Add {
+			entries: vec![new_playlist_song],
+		};
+		playlist_history::record(conn, &payload.playlist_id, &payload.song_adder_id, &change)?;
+		Ok(Some((new_item_id, change)))
+	});
 
-	// Insert the new song into the playlist
-	match diesel::insert_into(playlist_songs)
-		.values(&new_playlist_song)
-		.execute(&mut db_conn)
-	{
-		Ok(_) => Response::builder()
-			.status(StatusCode::CREATED)
-			.body("Song added to playlist".to_string())
+	match result {
+		Ok(None) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body("Songs can't be added to a smart playlist, freeze it first".to_string())
 			.unwrap(),
+		Ok(Some((new_item_id, change))) => {
+			let published = app_state.playlist_rooms.publish_change(
+				&app_state.user_pool,
+				&mut db_conn,
+				&payload.playlist_id,
+				&payload.song_adder_id,
+				&change,
+			);
+			if let Err(err) = published {
+				println!("Error {}:{}: Failed to publish the change: {err}", file!(), line!());
+			}
+
+			// Followers of public playlists get notified, the song is added either way
+			let notified = playlists::table
+				.filter(playlists::playlist_id.eq(&payload.playlist_id))
+				.filter(playlists::deleted_at.is_null())
+				.first::<Playlist>(&mut db_conn)
+				.and_then(|curr_playlist| {
+					playlist_access::notify_followers(
+						&app_state,
+						&mut db_conn,
+						&curr_playlist,
+						std::slice::from_ref(&payload.music_id),
+						&payload.song_adder_id,
+					)
+				});
+			if let Err(err) = notified {
+				println!(
+					"Error {}:{}: Failed to notify the playlist followers: {err}",
+					file!(),
+					line!()
+				);
+			}
+			Response::builder()
+				.status(StatusCode::CREATED)
+				.body(format!("Song added to playlist as item {new_item_id}"))
+				.unwrap()
+		}
 		Err(err) => Response::builder()
 			.status(StatusCode::INTERNAL_SERVER_ERROR)
 			.body(format!("Failed to add song to playlist: {}", err))
@@ -0,0 +1,83 @@
+use crate::config::OpCode;
+use crate::core::{
+	app_state::AppState,
+	blend::{self, BlendError},
+};
+use crate::lobic_db::models::{Notification, PlaylistInfo};
+use crate::routes::notify::notify;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::Deserialize;
+use serde_json::json;
+
+// The friends are invited to the blend, their songs are blended in once they accept
+#[derive(Debug, Deserialize)]
+pub struct CreateBlend {
+	pub user_id: String,
+	pub friend_ids: Vec<String>,
+	pub playlist_name: Option<String>, // defaults to "Blend"
+}
+
+pub async fn create_blend(
+	State(app_state): State<AppState>,
+	Json(payload): Json<CreateBlend>,
+) -> Response<String> {
+	let playlist_name = match payload.playlist_name.as_deref().map(str::trim) {
+		Some("") => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body("The playlist name can't be empty".to_string())
+				.unwrap();
+		}
+		Some(name) => name.to_string(),
+		None => "Blend".to_string(),
+	};
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let (new_blend, invitations) =
+		match blend::create(&mut db_conn, &payload.user_id, &payload.friend_ids, playlist_name) {
+			Ok(created) => created,
+			Err(err @ (BlendError::NoFriends | BlendError::NotFriend(_) | BlendError::Collab(_))) => {
+				return Response::builder()
+					.status(StatusCode::BAD_REQUEST)
+					.body(err.to_string())
+					.unwrap();
+			}
+			Err(err) => {
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Failed to create blend: {err}"))
+					.unwrap();
+			}
+		};
+
+	for invitation in &invitations {
+		let value = json!({
+			"invitation_id": invitation.invitation_id,
+			"playlist_id": new_blend.playlist_id,
+			"playlist_name": new_blend.playlist_name,
+			"inviter_id": invitation.inviter_id,
+			"role": invitation.role,
+			"is_blend": true,
+		});
+		let notif = Notification::new(OpCode::PLAYLIST_INVITE, value);
+		notify(&invitation.invitee_id, notif, &app_state.db_pool, &app_state.user_pool);
+	}
+
+	let info = PlaylistInfo {
+		is_blend: true,
+		..PlaylistInfo::from(new_blend)
+	};
+	Response::builder()
+		.status(StatusCode::CREATED)
+		.body(serde_json::to_string(&info).unwrap())
+		.unwrap()
+}
@@ -1,13 +1,34 @@
-use crate::core::app_state::AppState;
-use crate::lobic_db::models::PlaylistShare;
-use crate::schema::{playlist_shares, playlists};
+use crate::config::OpCode;
+use crate::core::{
+	app_state::AppState,
+	collaboration::{self, CollabError},
+};
+use crate::lobic_db::models::{Notification, Playlist};
+use crate::routes::notify::notify_from;
+use crate::schema::playlists;
 use axum::Json;
 use axum::{extract::State, http::status::StatusCode, response::Response};
 use diesel::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+
+fn default_role() -> String {
+	"editor".to_string()
+}
+
+// Invites the contributor, they are only added once they accept through /playlist/combined/respond_invitation
+#[derive(Debug, Deserialize)]
+pub struct AddContributorPayload {
+	pub playlist_id: String,
+	pub user_id: String, // the owner
+	pub contributor_user_id: String,
+	#[serde(default = "default_role")]
+	pub role: String, // editor or viewer
+}
 
 pub async fn add_contributor(
 	State(app_state): State<AppState>,
-	Json(payload): Json<PlaylistShare>,
+	Json(payload): Json<AddContributorPayload>,
 ) -> Response<String> {
 	let mut db_conn = match app_state.db_pool.get() {
 		Ok(conn) => conn,
@@ -20,40 +41,85 @@ pub async fn add_contributor(
 		}
 	};
 
-	// Check if playlist is combined
-	let is_combined = match playlists::table
-		.select(playlists::is_playlist_combined)
+	let curr_playlist = match playlists::table
 		.filter(playlists::playlist_id.eq(&payload.playlist_id))
-		.first::<bool>(&mut db_conn)
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
 	{
-		Ok(combined) => combined,
+		Ok(curr_playlist) => curr_playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.playlist_id))
+				.unwrap();
+		}
 		Err(err) => {
-			let msg = format!("Failed to check playlist status: {err}");
-			return Response::builder().status(StatusCode::NOT_FOUND).body(msg).unwrap();
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
 		}
 	};
 
-	if !is_combined {
-		return Response::builder()
-			.status(StatusCode::BAD_REQUEST)
-			.body("Cannot add contributors to a solo playlist".to_string())
-			.unwrap();
-	}
-
-	match diesel::insert_into(playlist_shares::table)
-		.values(&payload)
-		.execute(&mut db_conn)
-	{
-		Ok(_) => Response::builder()
-			.status(StatusCode::OK)
-			.body("Successfully added or updated contributor".to_string())
-			.unwrap(),
+	let invitation = match collaboration::invite(
+		&mut db_conn,
+		&curr_playlist,
+		&payload.user_id,
+		&payload.contributor_user_id,
+		&payload.role,
+	) {
+		Ok(invitation) => invitation,
+		Err(CollabError::NotOwner) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner can invite contributors".to_string())
+				.unwrap();
+		}
+		Err(CollabError::SoloPlaylist) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body("Cannot add contributors to a solo playlist".to_string())
+				.unwrap();
+		}
+		Err(CollabError::InvalidRole(role)) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!("Invalid role: {role}, expected editor or viewer"))
+				.unwrap();
+		}
+		Err(CollabError::AlreadyMember(user_id)) => {
+			return Response::builder()
+				.status(StatusCode::CONFLICT)
+				.body(format!("{user_id} is already part of the playlist"))
+				.unwrap();
+		}
 		Err(err) => {
-			let msg = format!("Failed to add/update contributor: {err}");
-			Response::builder()
+			let msg = format!("Failed to invite contributor: {err}");
+			return Response::builder()
 				.status(StatusCode::INTERNAL_SERVER_ERROR)
 				.body(msg)
-				.unwrap()
+				.unwrap();
 		}
-	}
+	};
+
+	let value = json!({
+		"invitation_id": invitation.invitation_id,
+		"playlist_id": curr_playlist.playlist_id,
+		"playlist_name": curr_playlist.playlist_name,
+		"inviter_id": invitation.inviter_id,
+		"role": invitation.role,
+	});
+	let notif = Notification::new(OpCode::PLAYLIST_INVITE, value);
+	notify_from(
+		&invitation.inviter_id,
+		&invitation.invitee_id,
+		notif,
+		&app_state.db_pool,
+		&app_state.user_pool,
+	);
+
+	Response::builder()
+		.status(StatusCode::OK)
+		.body(serde_json::to_string(&invitation).unwrap())
+		.unwrap()
 }
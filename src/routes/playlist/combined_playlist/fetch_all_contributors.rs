@@ -9,6 +9,7 @@ use serde::Serialize;
 #[derive(Serialize)]
 pub struct Contributor {
 	contributor_user_id: String,
+	role: String, // editor or viewer
 }
 
 #[derive(Serialize)]
@@ -36,6 +37,7 @@ pub async fn fetch_all_contributors(
 	// Fetch the playlist owner
 	let playlist_owner: Result<String, diesel::result::Error> = playlists::table
 		.filter(playlists::playlist_id.eq(&playlist_id))
+		.filter(playlists::deleted_at.is_null())
 		.select(playlists::user_id)
 		.first(&mut db_conn);
 
@@ -52,12 +54,15 @@ pub async fn fetch_all_contributors(
 
 	let contributors = match playlist_shares::table
 		.filter(playlist_shares::playlist_id.eq(&playlist_id))
-		.select(playlist_shares::contributor_user_id)
-		.load::<String>(&mut db_conn)
+		.select((playlist_shares::contributor_user_id, playlist_shares::role))
+		.load::<(String, String)>(&mut db_conn)
 	{
 		Ok(contributors) => contributors
 			.into_iter()
-			.map(|contributor_user_id| Contributor { contributor_user_id })
+			.map(|(contributor_user_id, role)| Contributor {
+				contributor_user_id,
+				role,
+			})
 			.collect(),
 		Err(err) => {
 			let msg = format!("Failed to fetch contributors: {err}");
@@ -0,0 +1,36 @@
+use crate::core::{app_state::AppState, collaboration};
+use axum::extract::Path;
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+};
+
+// Pending invitations of the user
+pub async fn get_invitations(State(app_state): State<AppState>, Path(user_id): Path<String>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	match collaboration::pending_invitations(&mut db_conn, &user_id) {
+		Ok(invitations) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&invitations).unwrap())
+			.unwrap(),
+		Err(err) => {
+			let msg = format!("Failed to fetch invitations: {err}");
+			Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap()
+		}
+	}
+}
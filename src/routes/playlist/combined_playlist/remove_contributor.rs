@@ -1,13 +1,21 @@
-use crate::core::app_state::AppState;
-use crate::schema::playlist_shares;
+use crate::config::OpCode;
+use crate::core::{
+	app_state::AppState,
+	collaboration::{self, CollabError},
+};
+use crate::lobic_db::models::{Notification, Playlist};
+use crate::routes::notify::notify_from;
+use crate::schema::playlists;
 use axum::Json;
 use axum::{extract::State, http::status::StatusCode, response::Response};
 use diesel::prelude::*;
 use serde::Deserialize;
+use serde_json::json;
 
 #[derive(Deserialize)]
 pub struct RemoveContributorPayload {
 	playlist_id: String,
+	user_id: String, // the owner, or the contributor leaving the playlist
 	contributor_user_id: String,
 }
 
@@ -27,37 +35,90 @@ pub async fn remove_contributor(
 		}
 	};
 
-	// Attempt to delete the contributor from the playlist_shares table
-	match diesel::delete(
-		playlist_shares::table.filter(
-			playlist_shares::playlist_id
-				.eq(payload.playlist_id)
-				.and(playlist_shares::contributor_user_id.eq(payload.contributor_user_id)),
-		),
-	)
-	.execute(&mut db_conn)
+	let curr_playlist = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
 	{
-		Ok(0) => {
+		Ok(curr_playlist) => curr_playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	match collaboration::remove_contributor(
+		&mut db_conn,
+		&curr_playlist,
+		&payload.user_id,
+		&payload.contributor_user_id,
+	) {
+		Ok(()) => {}
+		Err(CollabError::NotOwner) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner can remove other contributors".to_string())
+				.unwrap();
+		}
+		Err(CollabError::NotMember(_)) => {
 			// No rows were affected, meaning the contributor was not found
-			Response::builder()
+			return Response::builder()
 				.status(StatusCode::NOT_FOUND)
 				.body("Contributor not found".to_string())
-				.unwrap()
-		}
-		Ok(_) => {
-			// Contributor was successfully removed
-			Response::builder()
-				.status(StatusCode::OK)
-				.body("Successfully removed contributor".to_string())
-				.unwrap()
+				.unwrap();
 		}
 		Err(err) => {
 			// An error occurred while trying to remove the contributor
 			let msg = format!("Failed to remove contributor: {err}");
-			Response::builder()
+			return Response::builder()
 				.status(StatusCode::INTERNAL_SERVER_ERROR)
 				.body(msg)
-				.unwrap()
+				.unwrap();
 		}
 	}
+
+	// The removed contributor isn't a member anymore, letting them know separately
+	let value = json!({
+		"playlist_id": curr_playlist.playlist_id,
+		"playlist_name": curr_playlist.playlist_name,
+		"contributor_user_id": payload.contributor_user_id,
+		"removed_by": payload.user_id,
+	});
+	if payload.contributor_user_id != payload.user_id {
+		let notif = Notification::new(OpCode::PLAYLIST_CONTRIBUTOR_REMOVED, value.clone());
+		notify_from(
+			&payload.user_id,
+			&payload.contributor_user_id,
+			notif,
+			&app_state.db_pool,
+			&app_state.user_pool,
+		);
+	}
+	if let Err(err) = collaboration::notify_members(
+		&app_state,
+		&mut db_conn,
+		&curr_playlist.playlist_id,
+		&payload.user_id,
+		OpCode::PLAYLIST_CONTRIBUTOR_REMOVED,
+		value,
+	) {
+		println!(
+			"Error {}:{}: Failed to notify the playlist members: {err}",
+			file!(),
+			line!()
+		);
+	}
+
+	Response::builder()
+		.status(StatusCode::OK)
+		.body("Successfully removed contributor".to_string())
+		.unwrap()
 }
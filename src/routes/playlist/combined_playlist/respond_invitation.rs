@@ -0,0 +1,115 @@
+use crate::config::OpCode;
+use crate::core::{
+	app_state::AppState,
+	blend,
+	collaboration::{self, CollabError},
+};
+use crate::lobic_db::models::Notification;
+use crate::routes::notify::notify_from;
+use axum::Json;
+use axum::{extract::State, http::status::StatusCode, response::Response};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize)]
+pub struct RespondInvitationPayload {
+	pub invitation_id: String,
+	pub user_id: String, // the invitee
+	pub accept: bool,
+}
+
+pub async fn respond_invitation(
+	State(app_state): State<AppState>,
+	Json(payload): Json<RespondInvitationPayload>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	let invitation =
+		match collaboration::respond(&mut db_conn, &payload.invitation_id, &payload.user_id, payload.accept) {
+			Ok(invitation) => invitation,
+			Err(CollabError::InvalidInvitation(invitation_id)) => {
+				return Response::builder()
+					.status(StatusCode::NOT_FOUND)
+					.body(format!("Invalid invitation_id: {invitation_id}"))
+					.unwrap();
+			}
+			Err(err) => {
+				let msg = format!("Failed to respond to invitation: {err}");
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(msg)
+					.unwrap();
+			}
+		};
+
+	let value = json!({
+		"invitation_id": invitation.invitation_id,
+		"playlist_id": invitation.playlist_id,
+		"user_id": invitation.invitee_id,
+		"role": invitation.role,
+		"accepted": payload.accept,
+	});
+	if payload.accept {
+		// The songs of whoever joins a blend are blended in right away
+		let refreshed = match blend::is_blend(&mut db_conn, &invitation.playlist_id) {
+			Ok(true) => blend::refresh(&mut db_conn, &invitation.playlist_id).and_then(|_| {
+				blend::publish_refresh(
+					&app_state.playlist_rooms,
+					&app_state.user_pool,
+					&mut db_conn,
+					&invitation.playlist_id,
+					&invitation.inviter_id,
+				)
+			}),
+			Ok(false) => Ok(None),
+			Err(err) => Err(err),
+		};
+		if let Err(err) = refreshed {
+			println!("Error {}:{}: Failed to refresh the blend: {err}", file!(), line!());
+		}
+
+		// Everyone already in the playlist gets to know about the new contributor
+		if let Err(err) = collaboration::notify_members(
+			&app_state,
+			&mut db_conn,
+			&invitation.playlist_id,
+			&invitation.invitee_id,
+			OpCode::PLAYLIST_INVITE_RESPONSE,
+			value,
+		) {
+			println!(
+				"Error {}:{}: Failed to notify the playlist members: {err}",
+				file!(),
+				line!()
+			);
+		}
+	} else {
+		let notif = Notification::new(OpCode::PLAYLIST_INVITE_RESPONSE, value);
+		notify_from(
+			&invitation.invitee_id,
+			&invitation.inviter_id,
+			notif,
+			&app_state.db_pool,
+			&app_state.user_pool,
+		);
+	}
+
+	let msg = if payload.accept {
+		"Invitation accepted"
+	} else {
+		"Invitation declined"
+	};
+	Response::builder()
+		.status(StatusCode::OK)
+		.body(msg.to_string())
+		.unwrap()
+}
@@ -0,0 +1,105 @@
+use crate::config::OpCode;
+use crate::core::{
+	app_state::AppState,
+	collaboration::{self, CollabError},
+};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
+use axum::Json;
+use axum::{extract::State, http::status::StatusCode, response::Response};
+use diesel::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+
+// The new owner has to be a contributor, the previous owner stays on as an editor
+#[derive(Debug, Deserialize)]
+pub struct TransferOwnershipPayload {
+	pub playlist_id: String,
+	pub user_id: String, // the current owner
+	pub new_owner_id: String,
+}
+
+pub async fn transfer_ownership(
+	State(app_state): State<AppState>,
+	Json(payload): Json<TransferOwnershipPayload>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	let curr_playlist = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
+	{
+		Ok(curr_playlist) => curr_playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	match collaboration::transfer_ownership(&mut db_conn, &curr_playlist, &payload.user_id, &payload.new_owner_id) {
+		Ok(()) => {}
+		Err(CollabError::NotOwner) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner can transfer the playlist".to_string())
+				.unwrap();
+		}
+		Err(CollabError::NotMember(user_id)) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!("{user_id} has to be a contributor of the playlist first"))
+				.unwrap();
+		}
+		Err(err) => {
+			let msg = format!("Failed to transfer ownership: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	}
+
+	let value = json!({
+		"playlist_id": curr_playlist.playlist_id,
+		"playlist_name": curr_playlist.playlist_name,
+		"previous_owner_id": payload.user_id,
+		"owner_id": payload.new_owner_id,
+	});
+	if let Err(err) = collaboration::notify_members(
+		&app_state,
+		&mut db_conn,
+		&curr_playlist.playlist_id,
+		&payload.user_id,
+		OpCode::PLAYLIST_OWNER_CHANGED,
+		value,
+	) {
+		println!(
+			"Error {}:{}: Failed to notify the playlist members: {err}",
+			file!(),
+			line!()
+		);
+	}
+
+	Response::builder()
+		.status(StatusCode::OK)
+		.body(format!("{} is now the owner of the playlist", payload.new_owner_id))
+		.unwrap()
+}
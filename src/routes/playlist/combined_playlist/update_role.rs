@@ -0,0 +1,117 @@
+use crate::config::OpCode;
+use crate::core::{
+	app_state::AppState,
+	collaboration::{self, CollabError},
+};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
+use axum::Json;
+use axum::{extract::State, http::status::StatusCode, response::Response};
+use diesel::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRolePayload {
+	pub playlist_id: String,
+	pub user_id: String, // the owner
+	pub contributor_user_id: String,
+	pub role: String, // editor or viewer
+}
+
+pub async fn update_role(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UpdateRolePayload>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	let curr_playlist = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
+	{
+		Ok(curr_playlist) => curr_playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	let role = match collaboration::set_role(
+		&mut db_conn,
+		&curr_playlist,
+		&payload.user_id,
+		&payload.contributor_user_id,
+		&payload.role,
+	) {
+		Ok(role) => role,
+		Err(CollabError::NotOwner) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner can change the roles of contributors".to_string())
+				.unwrap();
+		}
+		Err(CollabError::InvalidRole(role)) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!("Invalid role: {role}, expected editor or viewer"))
+				.unwrap();
+		}
+		Err(CollabError::NotMember(user_id)) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("{user_id} is not a contributor of the playlist"))
+				.unwrap();
+		}
+		Err(err) => {
+			let msg = format!("Failed to update role: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	let value = json!({
+		"playlist_id": curr_playlist.playlist_id,
+		"playlist_name": curr_playlist.playlist_name,
+		"contributor_user_id": payload.contributor_user_id,
+		"role": role.as_str(),
+	});
+	if let Err(err) = collaboration::notify_members(
+		&app_state,
+		&mut db_conn,
+		&curr_playlist.playlist_id,
+		&payload.user_id,
+		OpCode::PLAYLIST_ROLE_CHANGED,
+		value,
+	) {
+		println!(
+			"Error {}:{}: Failed to notify the playlist members: {err}",
+			file!(),
+			line!()
+		);
+	}
+
+	Response::builder()
+		.status(StatusCode::OK)
+		.body(format!("{} is now {}", payload.contributor_user_id, role.as_str()))
+		.unwrap()
+}
@@ -1,10 +1,25 @@
-use crate::core::app_state::AppState;
-use axum::{extract::State, http::status::StatusCode, response::Response};
-use diesel::prelude::*;
+use crate::core::{
+	app_state::AppState,
+	playlist_access::{self, Role},
+	playlist_history,
+};
+use axum::{
+	extract::{Query, State},
+	http::status::StatusCode,
+	response::Response,
+};
+use serde::Deserialize;
+
+// /playlist/delete/:curr_playlist_id?user_id=123
+#[derive(Deserialize)]
+pub struct DeletePlaylistQuery {
+	user_id: String,
+}
 
 pub async fn delete_playlist(
 	State(app_state): State<AppState>,
 	axum::extract::Path(curr_playlist_id): axum::extract::Path<String>,
+	Query(query): Query<DeletePlaylistQuery>,
 ) -> Response<String> {
 	// Get a database connection from the pool
 	let mut db_conn = match app_state.db_pool.get() {
@@ -17,42 +32,31 @@ pub async fn delete_playlist(
 		}
 	};
 
-	// Use the playlists table for deletion
-	use crate::schema::playlists::dsl::*;
-
-	// Delete associated records in playlist_songs
-	let songs_deleted = match diesel::delete(crate::schema::playlist_songs::dsl::playlist_songs)
-		.filter(crate::schema::playlist_songs::dsl::playlist_id.eq(&curr_playlist_id))
-		.execute(&mut db_conn)
-	{
-		Ok(count) => count,
-		Err(err) => {
+	// Only the owner can delete the playlist, contributors can leave it instead
+	match playlist_access::has_role(&mut db_conn, &curr_playlist_id, &query.user_id, Role::Owner) {
+		Ok(true) => {}
+		Ok(false) => {
 			return Response::builder()
-				.status(StatusCode::INTERNAL_SERVER_ERROR)
-				.body(format!("Failed to delete playlist songs: {}", err))
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner can delete the playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body("No playlist found to delete".to_string())
 				.unwrap();
 		}
-	};
-
-	// Delete associated records in playlist_shares
-	let shares_deleted = match diesel::delete(crate::schema::playlist_shares::dsl::playlist_shares)
-		.filter(crate::schema::playlist_shares::dsl::playlist_id.eq(&curr_playlist_id))
-		.execute(&mut db_conn)
-	{
-		Ok(count) => count,
 		Err(err) => {
 			return Response::builder()
 				.status(StatusCode::INTERNAL_SERVER_ERROR)
-				.body(format!("Failed to delete playlist shares: {}", err))
+				.body(format!("Failed to check playlist permissions: {err}"))
 				.unwrap();
 		}
-	};
+	}
 
-	// Delete the playlist itself
-	match diesel::delete(playlists)
-		.filter(playlist_id.eq(&curr_playlist_id))
-		.execute(&mut db_conn)
-	{
+	// Deleted playlists are kept for a while so that they can be restored, they are purged afterwards
+	match playlist_history::soft_delete(&mut db_conn, &curr_playlist_id) {
 		Ok(0) => Response::builder()
 			.status(StatusCode::NOT_FOUND)
 			.body("No playlist found to delete".to_string())
@@ -60,8 +64,8 @@ pub async fn delete_playlist(
 		Ok(_) => Response::builder()
 			.status(StatusCode::OK)
 			.body(format!(
-				"Playlist deleted. Songs deleted: {}, Shares deleted: {}",
-				songs_deleted, shares_deleted
+				"Playlist deleted, it can be restored within {} days",
+				playlist_history::RESTORE_WINDOW_DAYS
 			))
 			.unwrap(),
 		Err(err) => Response::builder()
@@ -0,0 +1,147 @@
+use crate::core::{
+	app_state::AppState,
+	playlist, playlist_access,
+	playlist_file::{self, PlaylistFormat},
+	smart_playlist::EvaluateError,
+};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+// /playlist/export?playlist_id=123&format=m3u8 (or xspf, json)&user_id=456 (or &share_token=abc)
+#[derive(Debug, Deserialize)]
+pub struct ExportPlaylistQueryParams {
+	pub playlist_id: String,
+	pub format: String,
+	pub user_id: Option<String>,
+	pub share_token: Option<String>,
+}
+
+pub async fn export_playlist(
+	State(app_state): State<AppState>,
+	Query(params): Query<ExportPlaylistQueryParams>,
+) -> Response<String> {
+	let format = match PlaylistFormat::parse(&params.format) {
+		Some(format) => format,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!(
+					"Invalid format: {}, expected m3u8, xspf or json",
+					params.format
+				))
+				.unwrap();
+		}
+	};
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let playlist = match playlists::table
+		.filter(playlists::playlist_id.eq(&params.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
+	{
+		Ok(playlist) => playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", params.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_access::can_read(
+		&mut db_conn,
+		&playlist,
+		params.user_id.as_deref(),
+		params.share_token.as_deref(),
+	) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("You don't have access to this playlist".to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	}
+
+	let songs = match playlist::load_songs(&mut db_conn, &playlist) {
+		Ok(songs) => songs,
+		Err(EvaluateError::InvalidRules(err)) => {
+			return Response::builder()
+				.status(StatusCode::UNPROCESSABLE_ENTITY)
+				.body(format!("The smart playlist rules are invalid: {err}"))
+				.unwrap();
+		}
+		Err(EvaluateError::Database(err)) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_file::export(format, &playlist.playlist_name, &songs) {
+		Ok(body) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, format.content_type())
+			.header(
+				header::CONTENT_DISPOSITION,
+				format!(
+					"attachment; filename=\"{}.{}\"",
+					file_name(&playlist.playlist_name),
+					format.as_str()
+				),
+			)
+			.body(body)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(err)
+			.unwrap(),
+	}
+}
+
+// Playlist names are free text, only keeping what is safe inside the header and on every file system
+fn file_name(playlist_name: &str) -> String {
+	let name: String = playlist_name
+		.chars()
+		.map(|c| {
+			if c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_') {
+				c
+			} else {
+				'_'
+			}
+		})
+		.collect();
+	match name.trim() {
+		"" => "playlist".to_string(),
+		name => name.to_string(),
+	}
+}
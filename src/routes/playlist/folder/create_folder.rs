@@ -0,0 +1,58 @@
+use crate::core::{
+	app_state::AppState,
+	playlist_library::{self, LibraryError},
+};
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+	Json,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFolder {
+	pub user_id: String,
+	pub folder_name: String,
+	pub parent_id: Option<String>, // top level when not given
+}
+
+pub async fn create_folder(State(app_state): State<AppState>, Json(payload): Json<CreateFolder>) -> Response<String> {
+	let folder_name = payload.folder_name.trim().to_string();
+	if folder_name.is_empty() {
+		return Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body("The folder name can't be empty".to_string())
+			.unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_library::create_folder(&mut db_conn, &payload.user_id, &folder_name, payload.parent_id.as_deref()) {
+		Ok(folder) => Response::builder()
+			.status(StatusCode::CREATED)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&folder).unwrap())
+			.unwrap(),
+		Err(LibraryError::Database(err)) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to create folder: {err}"))
+			.unwrap(),
+		Err(err @ (LibraryError::InvalidFolder(_) | LibraryError::NotInLibrary(_))) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(err.to_string())
+			.unwrap(),
+	}
+}
@@ -0,0 +1,44 @@
+use crate::core::{
+	app_state::AppState,
+	playlist_library::{self, LibraryError},
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteFolder {
+	pub folder_id: String,
+	pub user_id: String,
+}
+
+// What was in the folder moves up to its parent, the playlists are kept
+pub async fn delete_folder(State(app_state): State<AppState>, Json(payload): Json<DeleteFolder>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_library::delete_folder(&mut db_conn, &payload.user_id, &payload.folder_id) {
+		Ok(()) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Folder deleted".to_string())
+			.unwrap(),
+		Err(LibraryError::Database(err)) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to delete folder: {err}"))
+			.unwrap(),
+		Err(err @ (LibraryError::InvalidFolder(_) | LibraryError::NotInLibrary(_))) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(err.to_string())
+			.unwrap(),
+	}
+}
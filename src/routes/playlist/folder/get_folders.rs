@@ -0,0 +1,32 @@
+use crate::core::{app_state::AppState, playlist_library};
+use axum::extract::Path;
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+};
+
+// Every folder of the user, nested through parent_id
+pub async fn get_folders(State(app_state): State<AppState>, Path(user_id): Path<String>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_library::folders(&mut db_conn, &user_id) {
+		Ok(folders) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&folders).unwrap())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to query folders: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,49 @@
+use crate::core::{
+	app_state::AppState,
+	playlist_library::{self, LibraryError},
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct MoveFolder {
+	pub folder_id: String,
+	pub user_id: String,
+	pub parent_id: Option<String>, // moves it to the top level when not given
+}
+
+pub async fn move_folder(State(app_state): State<AppState>, Json(payload): Json<MoveFolder>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_library::move_folder(
+		&mut db_conn,
+		&payload.user_id,
+		&payload.folder_id,
+		payload.parent_id.as_deref(),
+	) {
+		Ok(()) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Folder moved".to_string())
+			.unwrap(),
+		Err(LibraryError::Database(err)) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to move folder: {err}"))
+			.unwrap(),
+		Err(err @ (LibraryError::InvalidFolder(_) | LibraryError::NotInLibrary(_))) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(err.to_string())
+			.unwrap(),
+	}
+}
@@ -0,0 +1,52 @@
+use crate::core::{
+	app_state::AppState,
+	playlist_library::{self, LibraryError},
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct RenameFolder {
+	pub folder_id: String,
+	pub user_id: String,
+	pub folder_name: String,
+}
+
+pub async fn rename_folder(State(app_state): State<AppState>, Json(payload): Json<RenameFolder>) -> Response<String> {
+	let folder_name = payload.folder_name.trim().to_string();
+	if folder_name.is_empty() {
+		return Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body("The folder name can't be empty".to_string())
+			.unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_library::rename_folder(&mut db_conn, &payload.user_id, &payload.folder_id, &folder_name) {
+		Ok(()) => Response::builder()
+			.status(StatusCode::OK)
+			.body(format!("Folder renamed to {folder_name}"))
+			.unwrap(),
+		Err(LibraryError::Database(err)) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to rename folder: {err}"))
+			.unwrap(),
+		Err(err @ (LibraryError::InvalidFolder(_) | LibraryError::NotInLibrary(_))) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(err.to_string())
+			.unwrap(),
+	}
+}
@@ -0,0 +1,70 @@
+use crate::core::{
+	app_state::AppState,
+	playlist_access::{self, FollowError},
+};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct FollowPlaylist {
+	pub user_id: String,
+	pub playlist_id: String,
+}
+
+// Adds a public playlist of another user to the user's library
+pub async fn follow_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<FollowPlaylist>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let curr_playlist = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
+	{
+		Ok(curr_playlist) => curr_playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_access::follow(&mut db_conn, &curr_playlist, &payload.user_id) {
+		Ok(()) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Playlist followed".to_string())
+			.unwrap(),
+		Err(FollowError::NotPublic) => Response::builder()
+			.status(StatusCode::FORBIDDEN)
+			.body("Only public playlists can be followed".to_string())
+			.unwrap(),
+		Err(FollowError::OwnPlaylist) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body("The playlist is already in your library".to_string())
+			.unwrap(),
+		Err(FollowError::Database(err)) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to follow playlist: {err}"))
+			.unwrap(),
+	}
+}
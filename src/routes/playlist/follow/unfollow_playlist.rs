@@ -0,0 +1,39 @@
+use crate::core::{app_state::AppState, playlist_access};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct UnfollowPlaylist {
+	pub user_id: String,
+	pub playlist_id: String,
+}
+
+pub async fn unfollow_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UnfollowPlaylist>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_access::unfollow(&mut db_conn, &payload.playlist_id, &payload.user_id) {
+		Ok(true) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Playlist unfollowed".to_string())
+			.unwrap(),
+		Ok(false) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body("You don't follow this playlist".to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to unfollow playlist: {err}"))
+			.unwrap(),
+	}
+}
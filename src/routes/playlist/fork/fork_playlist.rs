@@ -0,0 +1,108 @@
+use crate::core::{
+	activity,
+	app_state::AppState,
+	playlist_access,
+	playlist_fork::{self, ForkError},
+};
+use crate::lobic_db::models::{Playlist, PlaylistInfo};
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ForkPlaylist {
+	pub playlist_id: String,
+	pub user_id: String,
+	pub share_token: Option<String>,
+	pub playlist_name: Option<String>, // defaults to "<source name> (fork)"
+}
+
+pub async fn fork_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<ForkPlaylist>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let source = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
+	{
+		Ok(playlist) => playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_access::can_read(
+		&mut db_conn,
+		&source,
+		Some(&payload.user_id),
+		payload.share_token.as_deref(),
+	) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("You don't have access to this playlist".to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	let playlist_name = match payload.playlist_name.as_deref().map(str::trim) {
+		Some("") => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body("The playlist name can't be empty".to_string())
+				.unwrap();
+		}
+		Some(name) => name.to_string(),
+		None => format!("{} (fork)", source.playlist_name),
+	};
+
+	match playlist_fork::fork(&mut db_conn, &source, &payload.user_id, playlist_name) {
+		Ok(new_playlist) => {
+			activity::share_playlist(&mut db_conn, &app_state.user_pool, &new_playlist);
+			let info = PlaylistInfo {
+				forked_from: Some(source.playlist_id),
+				..PlaylistInfo::from(new_playlist)
+			};
+			Response::builder()
+				.status(StatusCode::CREATED)
+				.body(serde_json::to_string(&info).unwrap())
+				.unwrap()
+		}
+		Err(ForkError::InvalidRules(err)) => Response::builder()
+			.status(StatusCode::UNPROCESSABLE_ENTITY)
+			.body(format!("Failed to evaluate the source playlist: {err}"))
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to fork playlist: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,144 @@
+use crate::core::{
+	app_state::AppState,
+	playlist_access::{self, Role},
+	playlist_fork::{self, ForkError},
+	recommender,
+};
+use crate::lobic_db::models::{Music, MusicResponse};
+use axum::{
+	extract::{Query, State},
+	http::status::StatusCode,
+	response::Response,
+};
+use serde::{Deserialize, Serialize};
+
+// /playlist/fork/diff?playlist_id=123&user_id=456
+#[derive(Debug, Deserialize)]
+pub struct SourceDiffQueryParams {
+	pub playlist_id: String,
+	pub user_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceDiffResponse {
+	pub source_id: String,
+	pub source_name: String,
+	pub synced_at: String,
+	pub added: Vec<MusicResponse>,
+	pub removed: Vec<MusicResponse>,
+}
+
+pub async fn get_source_diff(
+	State(app_state): State<AppState>,
+	Query(params): Query<SourceDiffQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_access::has_role(&mut db_conn, &params.playlist_id, &params.user_id, Role::Viewer) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("You don't have access to this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", params.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	let fork = match playlist_fork::fork_of(&mut db_conn, &params.playlist_id) {
+		Ok(fork) => fork,
+		Err(err @ ForkError::NotAFork(_)) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to load fork: {err}"))
+				.unwrap();
+		}
+	};
+
+	let diff = match playlist_fork::source_diff(&mut db_conn, &fork) {
+		Ok(diff) => diff,
+		Err(err @ ForkError::SourceDeleted) => {
+			return Response::builder()
+				.status(StatusCode::GONE)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(ForkError::InvalidRules(err)) => {
+			return Response::builder()
+				.status(StatusCode::UNPROCESSABLE_ENTITY)
+				.body(format!("Failed to evaluate the source playlist: {err}"))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to compare with the source playlist: {err}"))
+				.unwrap();
+		}
+	};
+
+	// The source may have been made private since it was forked
+	match playlist_access::can_read(&mut db_conn, &diff.source, Some(&params.user_id), None) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("You don't have access to the source playlist anymore".to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	let songs = recommender::load_music_in_order(&mut db_conn, &diff.added).and_then(|added| {
+		recommender::load_music_in_order(&mut db_conn, &diff.removed).map(|removed| (added, removed))
+	});
+	match songs {
+		Ok((added, removed)) => {
+			let response = SourceDiffResponse {
+				source_id: diff.source.playlist_id,
+				source_name: diff.source.playlist_name,
+				synced_at: fork.synced_at,
+				added: added.into_iter().map(Music::create_music_response).collect(),
+				removed: removed.into_iter().map(Music::create_music_response).collect(),
+			};
+			Response::builder()
+				.status(StatusCode::OK)
+				.body(serde_json::to_string(&response).unwrap())
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Database error: {err}"))
+			.unwrap(),
+	}
+}
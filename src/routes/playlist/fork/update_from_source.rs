@@ -0,0 +1,159 @@
+use crate::core::{
+	app_state::AppState,
+	blend::{self, BlendError},
+	playlist_access::{self, Role},
+	playlist_fork::{self, ForkError},
+	playlist_history::Change,
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::Deserialize;
+
+fn default_include_removals() -> bool {
+	true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateFromSource {
+	pub playlist_id: String,
+	pub user_id: String,
+	#[serde(default = "default_include_removals")]
+	pub include_removals: bool, // false keeps the songs the source removed
+}
+
+pub async fn update_from_source(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UpdateFromSource>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_access::has_role(&mut db_conn, &payload.playlist_id, &payload.user_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	match blend::ensure_not_blend(&mut db_conn, &payload.playlist_id) {
+		Ok(()) => {}
+		Err(err @ BlendError::ManualEdit) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err.to_string())
+				.unwrap();
+		}
+	}
+
+	let source = match playlist_fork::fork_of(&mut db_conn, &payload.playlist_id)
+		.and_then(|fork| playlist_fork::source_of(&mut db_conn, &fork))
+	{
+		Ok(source) => source,
+		Err(err @ ForkError::NotAFork(_)) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err @ ForkError::SourceDeleted) => {
+			return Response::builder()
+				.status(StatusCode::GONE)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to load the source playlist: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_access::can_read(&mut db_conn, &source, Some(&payload.user_id), None) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("You don't have access to the source playlist anymore".to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	let changes = match playlist_fork::update_from_source(
+		&mut db_conn,
+		&payload.playlist_id,
+		&payload.user_id,
+		payload.include_removals,
+	) {
+		Ok(changes) => changes,
+		Err(ForkError::InvalidRules(err)) => {
+			return Response::builder()
+				.status(StatusCode::UNPROCESSABLE_ENTITY)
+				.body(format!("Failed to evaluate the source playlist: {err}"))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to update from the source playlist: {err}"))
+				.unwrap();
+		}
+	};
+
+	let (mut added, mut removed) = (0, 0);
+	for change in &changes {
+		match change {
+			Change::Add { entries } => added += entries.len(),
+			Change::Remove { entries } => removed += entries.len(),
+			_ => {}
+		}
+		let published = app_state.playlist_rooms.publish_change(
+			&app_state.user_pool,
+			&mut db_conn,
+			&payload.playlist_id,
+			&payload.user_id,
+			change,
+		);
+		if let Err(err) = published {
+			println!("Error {}:{}: Failed to publish the change: {err}", file!(), line!());
+		}
+	}
+
+	Response::builder()
+		.status(StatusCode::OK)
+		.body(format!("Added {added} and removed {removed} songs from the source playlist"))
+		.unwrap()
+}
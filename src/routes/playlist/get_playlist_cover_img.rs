@@ -1,21 +1,45 @@
 use crate::config::PLAYLIST_COVER_IMG_STORAGE;
+use crate::core::{app_state::AppState, playlist_cover};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
 use axum::{
 	body::Body,
-	extract::Path,
+	extract::{Path, State},
 	http::{
 		header::{self},
 		StatusCode,
 	},
 	response::{IntoResponse, Response},
 };
+use diesel::prelude::*;
 use std::path::PathBuf;
 use tokio::fs::File;
 use tokio_util::io::ReaderStream;
 
-pub async fn get_playlist_cover_img(Path(playlist_id): Path<String>) -> impl IntoResponse {
+pub async fn get_playlist_cover_img(
+	State(app_state): State<AppState>,
+	Path(playlist_id): Path<String>,
+) -> impl IntoResponse {
 	let mut path = PathBuf::from(PLAYLIST_COVER_IMG_STORAGE);
 	path.push(format!("{}.png", &playlist_id));
 
+	// Without an uploaded cover the playlist gets a mosaic of its albums, which changes along with its songs
+	let mut cache_control = "public, max-age=31536000";
+	if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+		match mosaic_cover(app_state, playlist_id).await {
+			Some(mosaic_path) => {
+				path = mosaic_path;
+				cache_control = "public, max-age=300";
+			}
+			None => {
+				return Response::builder()
+					.status(StatusCode::NOT_FOUND)
+					.body(Body::from("Playlist cover image not found"))
+					.unwrap();
+			}
+		}
+	}
+
 	let file = match File::open(&path).await {
 		Ok(file) => file,
 		Err(_) => {
@@ -42,7 +66,39 @@ pub async fn get_playlist_cover_img(Path(playlist_id): Path<String>) -> impl Int
 	Response::builder()
 		.status(StatusCode::OK)
 		.header(header::CONTENT_TYPE, mime_type)
-		.header(header::CACHE_CONTROL, "public, max-age=31536000") // Add caching
+		.header(header::CACHE_CONTROL, cache_control) // Add caching
 		.body(body)
 		.unwrap()
 }
+
+async fn mosaic_cover(app_state: AppState, playlist_id: String) -> Option<PathBuf> {
+	let result = tokio::task::spawn_blocking(move || {
+		let mut db_conn = app_state
+			.db_pool
+			.get()
+			.map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+		let curr_playlist = playlists::table
+			.filter(playlists::playlist_id.eq(&playlist_id))
+			.filter(playlists::deleted_at.is_null())
+			.first::<Playlist>(&mut db_conn)
+			.optional()
+			.map_err(|err| format!("Failed to query playlist: {err}"))?;
+		match curr_playlist {
+			Some(curr_playlist) => playlist_cover::mosaic_cover(&mut db_conn, &curr_playlist),
+			None => Ok(None),
+		}
+	})
+	.await;
+
+	match result {
+		Ok(Ok(path)) => path,
+		Ok(Err(err)) => {
+			println!("[playlist_cover]: {err}");
+			None
+		}
+		Err(err) => {
+			println!("[playlist_cover]: {err}");
+			None
+		}
+	}
+}
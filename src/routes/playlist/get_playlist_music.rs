@@ -1,10 +1,17 @@
-use crate::core::app_state::AppState;
+use crate::core::{
+	app_state::AppState,
+	playlist::POSITION_GAP,
+	playlist_access,
+	smart_playlist::{self, EvaluateError, SmartRules},
+};
+use crate::lobic_db::models::Music;
 use axum::{
 	body::Body,
 	extract::{Query, State},
 	http::{header, status::StatusCode},
 	response::Response,
 };
+use chrono::Utc;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::hash::Hash;
@@ -13,6 +20,8 @@ use uuid::Uuid;
 
 #[derive(Queryable)]
 struct MusicQueryResult {
+	item_id: String,
+	position: f64,
 	music_id: String,
 	artist: String,
 	title: String,
@@ -25,6 +34,8 @@ struct MusicQueryResult {
 
 #[derive(Debug, Serialize)]
 pub struct PlaylistMusicResponse {
+	pub item_id: String, // identifies the entry, the same song can be in the playlist more than once
+	pub position: f64,
 	pub music_id: String,
 	pub artist: String,
 	pub title: String,
@@ -36,15 +47,19 @@ pub struct PlaylistMusicResponse {
 	pub song_adder_id: String,
 }
 
+// /playlist/get_by_uuid?playlist_id=123&user_id=456 (or &share_token=abc)
 #[derive(Debug, Deserialize)]
 pub struct PlaylistQueryParams {
 	pub playlist_id: String,
+	pub user_id: Option<String>,
+	pub share_token: Option<String>,
 }
 
 use crate::lobic_db::models::Playlist;
 #[derive(Debug, Serialize)]
 pub struct PlaylistDetailsResponse {
 	pub playlist: Playlist,
+	pub smart_rules: Option<SmartRules>, // set for smart playlists, their songs are evaluated on every request
 	pub songs: Vec<PlaylistMusicResponse>,
 }
 
@@ -57,6 +72,8 @@ impl PlaylistMusicResponse {
 		let img_uuid = Uuid::from_u64_pair(hash, hash);
 
 		PlaylistMusicResponse {
+			item_id: result.item_id,
+			position: result.position,
 			music_id: result.music_id,
 			artist: result.artist,
 			title: result.title,
@@ -89,6 +106,7 @@ pub async fn get_playlist_music(
 	// Fetch playlist details
 	let playlist_result = playlists::table
 		.filter(playlists::playlist_id.eq(&params.playlist_id))
+		.filter(playlists::deleted_at.is_null())
 		.first::<Playlist>(&mut db_conn);
 
 	let playlist = match playlist_result {
@@ -101,11 +119,94 @@ pub async fn get_playlist_music(
 		}
 	};
 
-	// Fetch songs in the playlist with correct type mapping
+	match playlist_access::can_read(
+		&mut db_conn,
+		&playlist,
+		params.user_id.as_deref(),
+		params.share_token.as_deref(),
+	) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(Body::from("You don't have access to this playlist"))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(Body::from(format!("Failed to check playlist access: {}", err)))
+				.unwrap();
+		}
+	}
+
+	if let Some(rules) = &playlist.smart_rules {
+		let rules = match SmartRules::from_json(rules) {
+			Ok(rules) => rules,
+			Err(err) => {
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(Body::from(err))
+					.unwrap();
+			}
+		};
+		let songs = match smart_playlist::evaluate(&mut db_conn, &playlist.user_id, &rules) {
+			Ok(songs) => songs,
+			Err(EvaluateError::InvalidRules(err)) => {
+				return Response::builder()
+					.status(StatusCode::UNPROCESSABLE_ENTITY)
+					.body(Body::from(format!("The smart playlist rules are invalid: {err}")))
+					.unwrap();
+			}
+			Err(EvaluateError::Database(err)) => {
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(Body::from(format!("Failed to evaluate smart playlist: {}", err)))
+					.unwrap();
+			}
+		};
+
+		// Computed songs aren't entries, the music id identifies them since they can't repeat
+		let evaluated_at = Utc::now().to_rfc3339();
+		let songs = songs
+			.into_iter()
+			.enumerate()
+			.map(|(idx, song): (usize, Music)| {
+				PlaylistMusicResponse::from_query_result(MusicQueryResult {
+					item_id: song.music_id.clone(),
+					position: POSITION_GAP * (idx + 1) as f64,
+					music_id: song.music_id,
+					artist: song.artist,
+					title: song.title,
+					album: song.album,
+					genre: song.genre,
+					duration: song.duration,
+					song_added_date_time: evaluated_at.clone(),
+					song_adder_id: playlist.user_id.clone(),
+				})
+			})
+			.collect::<Vec<_>>();
+
+		let response = PlaylistDetailsResponse {
+			playlist,
+			smart_rules: Some(rules),
+			songs,
+		};
+		return Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(Body::from(serde_json::to_string(&response).unwrap()))
+			.unwrap();
+	}
+
+	// Fetch songs in the playlist in order with correct type mapping
 	let query_results = playlist_songs::table
 		.filter(playlist_songs::playlist_id.eq(&params.playlist_id))
 		.inner_join(music::table)
+		.order((playlist_songs::position.asc(), playlist_songs::item_id.asc()))
 		.select((
+			playlist_songs::item_id,
+			playlist_songs::position,
 			music::music_id,
 			music::artist,
 			music::title,
@@ -131,7 +232,11 @@ pub async fn get_playlist_music(
 	};
 
 	// Construct the final response
-	let response = PlaylistDetailsResponse { playlist, songs };
+	let response = PlaylistDetailsResponse {
+		playlist,
+		smart_rules: None,
+		songs,
+	};
 
 	Response::builder()
 		.status(StatusCode::OK)
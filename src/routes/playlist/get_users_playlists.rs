@@ -1,4 +1,6 @@
 use crate::core::app_state::AppState;
+use crate::core::playlist_access;
+use crate::core::playlist_library;
 use crate::lobic_db::models::Playlist;
 use crate::lobic_db::models::PlaylistInfo;
 use crate::lobic_db::models::UserPlaylistsResponse;
@@ -14,9 +16,11 @@ pub struct ApiResponse {
 	pub message: String,
 }
 
+// /playlist/get_users_playlists?user_uuid=123&viewer_id=456
 #[derive(Debug, Deserialize)]
 pub struct UserPlaylistsQuery {
 	pub user_uuid: String,
+	pub viewer_id: Option<String>, // the user's own library when it's the same user, otherwise what they can see
 }
 
 pub async fn get_users_playlists(
@@ -38,8 +42,20 @@ pub async fn get_users_playlists(
 		}
 	};
 
-	// Query all playlists for the given user_id
-	// let result = playlists.filter(user_id.eq(&user_uuid)).load::<Playlist>(&mut db_conn);
+	// Someone else's profile only lists the playlists they are allowed to see
+	if query.viewer_id.as_deref() != Some(user_uuid.as_str()) {
+		let result = playlist_access::filter_listed(
+			&mut db_conn,
+			playlists::table.filter(playlists::user_id.eq(&user_uuid)).into_boxed(),
+			query.viewer_id.as_deref(),
+		)
+		.and_then(|listed| listed.load::<Playlist>(&mut db_conn))
+		.and_then(|listed| {
+			let listed = listed.into_iter().map(PlaylistInfo::from).collect();
+			playlist_library::annotate(&mut db_conn, None, listed)
+		});
+		return playlists_response(user_uuid, result);
+	}
 
 	let result = playlists::table
 		.left_join(playlist_shares::table.on(playlists::playlist_id.eq(playlist_shares::playlist_id)))
@@ -48,10 +64,28 @@ pub async fn get_users_playlists(
 				.eq(&user_uuid) // Owned playlists
 				.or(playlist_shares::contributor_user_id.eq(&user_uuid)), // Shared with user as contributor
 		)
+		.filter(playlists::deleted_at.is_null())
 		.select(playlists::all_columns) // Explicitly select only playlists table columns
 		.distinct() // Add this to avoid duplicate results
-		.load::<Playlist>(&mut db_conn);
+		.load::<Playlist>(&mut db_conn)
+		.and_then(|user_playlists| {
+			// Public playlists of other users the user follows
+			let followed = playlist_access::followed_playlists(&mut db_conn, &user_uuid)?;
+			let library = user_playlists
+				.into_iter()
+				.map(PlaylistInfo::from)
+				.chain(followed.into_iter().map(|playlist| PlaylistInfo {
+					is_followed: true,
+					..PlaylistInfo::from(playlist)
+				}))
+				.collect();
+			// With the folders and pins of the user, see /playlist/folders for the folders themselves
+			playlist_library::annotate(&mut db_conn, Some(&user_uuid), library)
+		});
+	playlists_response(user_uuid, result)
+}
 
+fn playlists_response(user_uuid: String, result: QueryResult<Vec<PlaylistInfo>>) -> Response<String> {
 	match result {
 		Ok(user_playlists) => {
 			if user_playlists.is_empty() {
@@ -63,22 +97,9 @@ pub async fn get_users_playlists(
 					.body(serde_json::to_string(&response).unwrap())
 					.unwrap()
 			} else {
-				// Map the Playlist objects to PlaylistInfo
-				let playlists_info: Vec<PlaylistInfo> = user_playlists
-					.into_iter()
-					.map(|playlist| PlaylistInfo {
-						user_id: playlist.user_id,
-						playlist_id: playlist.playlist_id,
-						playlist_name: playlist.playlist_name,
-						creation_date_time: playlist.creation_date_time,
-						last_updated_date_time: playlist.last_updated_date_time,
-						is_playlist_combined: playlist.is_playlist_combined,
-					})
-					.collect();
-
 				let response = UserPlaylistsResponse {
 					user_id: user_uuid,
-					playlists: playlists_info,
+					playlists: user_playlists,
 				};
 				Response::builder()
 					.status(StatusCode::OK)
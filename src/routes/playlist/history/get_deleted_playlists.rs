@@ -0,0 +1,50 @@
+use crate::core::{app_state::AppState, playlist_history};
+use crate::lobic_db::models::PlaylistInfo;
+use axum::extract::Path;
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct DeletedPlaylistResponse {
+	pub playlist: PlaylistInfo,
+	pub deleted_at: String,
+}
+
+// Deleted playlists of the user that can still be restored
+pub async fn get_deleted_playlists(State(app_state): State<AppState>, Path(user_id): Path<String>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+
+	match playlist_history::deleted_playlists(&mut db_conn, &user_id) {
+		Ok(deleted) => {
+			let response: Vec<DeletedPlaylistResponse> = deleted
+				.into_iter()
+				.map(|mut playlist| DeletedPlaylistResponse {
+					deleted_at: playlist.deleted_at.take().unwrap_or_default(),
+					playlist: PlaylistInfo::from(playlist),
+				})
+				.collect();
+			Response::builder()
+				.status(StatusCode::OK)
+				.header(header::CONTENT_TYPE, "application/json")
+				.body(serde_json::to_string(&response).unwrap())
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to query deleted playlists: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,93 @@
+use crate::core::{
+	app_state::AppState,
+	playlist_access::{self, Role},
+	playlist_history,
+};
+use axum::{
+	extract::{Query, State},
+	http::{header, status::StatusCode},
+	response::Response,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// /playlist/history?playlist_id=123&user_id=456
+#[derive(Debug, Deserialize)]
+pub struct PlaylistHistoryQuery {
+	pub playlist_id: String,
+	pub user_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlaylistHistoryResponse {
+	pub event_id: String,
+	pub actor_id: String,
+	pub kind: String,
+	pub change: Value,
+	pub created_at: String,
+	pub undone_at: Option<String>,
+}
+
+// Latest changes first, undone ones included
+pub async fn get_playlist_history(
+	State(app_state): State<AppState>,
+	Query(params): Query<PlaylistHistoryQuery>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Only the people in the playlist can see its history
+	match playlist_access::has_role(&mut db_conn, &params.playlist_id, &params.user_id, Role::Viewer) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("You are not part of this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &params.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	match playlist_history::history(&mut db_conn, &params.playlist_id) {
+		Ok(entries) => {
+			let response: Vec<PlaylistHistoryResponse> = entries
+				.into_iter()
+				.map(|entry| PlaylistHistoryResponse {
+					event_id: entry.event_id,
+					actor_id: entry.actor_id,
+					kind: entry.kind,
+					change: serde_json::from_str(&entry.change).unwrap_or(Value::Null),
+					created_at: entry.created_at,
+					undone_at: entry.undone_at,
+				})
+				.collect();
+			Response::builder()
+				.status(StatusCode::OK)
+				.header(header::CONTENT_TYPE, "application/json")
+				.body(serde_json::to_string(&response).unwrap())
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to query playlist history: {err}"))
+			.unwrap(),
+	}
+}
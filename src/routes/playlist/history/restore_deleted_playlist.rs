@@ -0,0 +1,71 @@
+use crate::core::{app_state::AppState, playlist_history};
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreDeletedPlaylist {
+	pub playlist_id: String,
+	pub user_id: String, // the owner
+}
+
+pub async fn restore_deleted_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<RestoreDeletedPlaylist>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Only the owner can restore the playlist, like only they can delete it
+	let owner_id = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.select(playlists::user_id)
+		.first::<String>(&mut db_conn)
+	{
+		Ok(owner_id) => owner_id,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+	if owner_id != payload.user_id {
+		return Response::builder()
+			.status(StatusCode::FORBIDDEN)
+			.body("Only the owner can restore the playlist".to_string())
+			.unwrap();
+	}
+
+	match playlist_history::restore_deleted(&mut db_conn, &payload.playlist_id) {
+		Ok(true) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Playlist restored".to_string())
+			.unwrap(),
+		Ok(false) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(format!(
+				"The playlist isn't deleted or was deleted more than {} days ago",
+				playlist_history::RESTORE_WINDOW_DAYS
+			))
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to restore playlist: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,105 @@
+use crate::core::{
+	app_state::AppState,
+	blend::{self, BlendError},
+	playlist_access::{self, Role},
+	playlist_history,
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct RestorePlaylist {
+	pub playlist_id: String,
+	pub user_id: String,
+	pub at: String, // rfc3339, every change made after it is undone
+}
+
+pub async fn restore_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<RestorePlaylist>,
+) -> Response<String> {
+	let at = match DateTime::parse_from_rfc3339(&payload.at) {
+		Ok(at) => at.with_timezone(&Utc),
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!("Invalid time: {}, {err}", payload.at))
+				.unwrap();
+		}
+	};
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Viewers can't restore the playlist
+	match playlist_access::has_role(&mut db_conn, &payload.playlist_id, &payload.user_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	match blend::ensure_not_blend(&mut db_conn, &payload.playlist_id) {
+		Ok(()) => {}
+		Err(err @ BlendError::ManualEdit) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err.to_string())
+				.unwrap();
+		}
+	}
+
+	match playlist_history::restore_to(&mut db_conn, &payload.playlist_id, at) {
+		Ok(undone) => {
+			if undone > 0 {
+				let published = app_state.playlist_rooms.publish_undo(
+					&app_state.user_pool,
+					&mut db_conn,
+					&payload.playlist_id,
+					&payload.user_id,
+					undone,
+				);
+				if let Err(err) = published {
+					println!("Error {}:{}: Failed to publish the restore: {err}", file!(), line!());
+				}
+			}
+			Response::builder()
+				.status(StatusCode::OK)
+				.body(format!("Playlist restored, undid {undone} changes"))
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to restore playlist: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,111 @@
+use crate::core::{
+	app_state::AppState,
+	blend::{self, BlendError},
+	playlist_access::{self, Role},
+	playlist_history,
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::Deserialize;
+
+fn default_count() -> i64 {
+	1
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UndoPlaylistChanges {
+	pub playlist_id: String,
+	pub user_id: String,
+	#[serde(default = "default_count")]
+	pub count: i64, // how many of the latest changes to undo
+}
+
+pub async fn undo_playlist_changes(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UndoPlaylistChanges>,
+) -> Response<String> {
+	if payload.count < 1 || payload.count > playlist_history::MAX_HISTORY_LENGTH {
+		return Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(format!(
+				"count has to be between 1 and {}",
+				playlist_history::MAX_HISTORY_LENGTH
+			))
+			.unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Viewers can't undo changes
+	match playlist_access::has_role(&mut db_conn, &payload.playlist_id, &payload.user_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	match blend::ensure_not_blend(&mut db_conn, &payload.playlist_id) {
+		Ok(()) => {}
+		Err(err @ BlendError::ManualEdit) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err.to_string())
+				.unwrap();
+		}
+	}
+
+	match playlist_history::undo(&mut db_conn, &payload.playlist_id, payload.count) {
+		Ok(0) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body("Nothing to undo".to_string())
+			.unwrap(),
+		Ok(undone) => {
+			let published = app_state.playlist_rooms.publish_undo(
+				&app_state.user_pool,
+				&mut db_conn,
+				&payload.playlist_id,
+				&payload.user_id,
+				undone,
+			);
+			if let Err(err) = published {
+				println!("Error {}:{}: Failed to publish the undo: {err}", file!(), line!());
+			}
+			Response::builder()
+				.status(StatusCode::OK)
+				.body(format!("Undid {undone} changes"))
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to undo changes: {err}"))
+			.unwrap(),
+	}
+}
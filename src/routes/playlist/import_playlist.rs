@@ -0,0 +1,145 @@
+use crate::core::{
+	activity,
+	app_state::AppState,
+	playlist::{self, CreatePlaylistError},
+	playlist_file::{self, EntryMatch, MatchedBy, PlaylistFormat},
+};
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use serde::{Deserialize, Serialize};
+
+// /playlist/import?user_id=123&format=m3u8 (body: the playlist file)
+// The name defaults to the one in the file
+#[derive(Debug, Deserialize)]
+pub struct ImportPlaylistQueryParams {
+	pub user_id: String,
+	pub format: String, // m3u8, xspf or json
+	pub playlist_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportPlaylistReport {
+	pub playlist_id: Option<String>, // None when nothing could be matched
+	pub playlist_name: String,
+	pub total: usize,
+	pub matched: usize,
+	pub fuzzy_matched: usize,
+	pub entries: Vec<EntryMatch>,
+}
+
+pub async fn import_playlist(
+	State(app_state): State<AppState>,
+	Query(params): Query<ImportPlaylistQueryParams>,
+	body: String,
+) -> Response<String> {
+	let format = match PlaylistFormat::parse(&params.format) {
+		Some(format) => format,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!(
+					"Invalid format: {}, expected m3u8, xspf or json",
+					params.format
+				))
+				.unwrap();
+		}
+	};
+
+	let parsed = match playlist_file::parse(format, &body) {
+		Ok(parsed) => parsed,
+		Err(err) => {
+			return Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap();
+		}
+	};
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Matching against the whole catalog can take a while for large playlists
+	let user_pool = app_state.user_pool.clone();
+	let result = tokio::task::spawn_blocking(move || {
+		let entries = playlist_file::resolve(&mut db_conn, parsed.entries).map_err(|err| err.to_string())?;
+		let playlist_name = params
+			.playlist_name
+			.or(parsed.name)
+			.filter(|name| !name.trim().is_empty())
+			.unwrap_or_else(|| "Imported playlist".to_string());
+		let music_ids: Vec<String> = entries.iter().filter_map(|entry| entry.music_id.clone()).collect();
+
+		let mut report = ImportPlaylistReport {
+			playlist_id: None,
+			playlist_name,
+			total: entries.len(),
+			matched: music_ids.len(),
+			fuzzy_matched: entries
+				.iter()
+				.filter(|entry| entry.matched_by == Some(MatchedBy::Fuzzy))
+				.count(),
+			entries,
+		};
+		if music_ids.is_empty() {
+			return Ok(report);
+		}
+
+		let new_playlist = match playlist::create_playlist(
+			&mut db_conn,
+			report.playlist_name.clone(),
+			params.user_id.clone(),
+			false,
+			&[],
+		) {
+			Ok(new_playlist) => new_playlist,
+			Err(CreatePlaylistError::Storage(err)) => return Err(err),
+			Err(CreatePlaylistError::Database(err)) => return Err(format!("Failed to create playlist: {err}")),
+		};
+		playlist::append_songs(&mut db_conn, &new_playlist.playlist_id, &music_ids, &params.user_id)
+			.map_err(|err| format!("Failed to add songs to playlist: {err}"))?;
+		activity::share_playlist(&mut db_conn, &user_pool, &new_playlist);
+		report.playlist_id = Some(new_playlist.playlist_id);
+		Ok(report)
+	})
+	.await;
+
+	let report = match result {
+		Ok(Ok(report)) => report,
+		Ok(Err(err)) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err)
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Import task failed: {err}"))
+				.unwrap();
+		}
+	};
+
+	let status = if report.playlist_id.is_some() {
+		StatusCode::CREATED
+	} else {
+		StatusCode::UNPROCESSABLE_ENTITY
+	};
+	match serde_json::to_string(&report) {
+		Ok(json) => Response::builder()
+			.status(status)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to serialize response: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,53 @@
+use crate::core::{
+	app_state::AppState,
+	playlist_library::{self, LibraryError},
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct MovePlaylistToFolder {
+	pub user_id: String,
+	pub playlist_id: String,
+	pub folder_id: Option<String>, // back to the top level when not given
+}
+
+// Only moves it in the library of the user, contributors and followers keep it where they put it
+pub async fn move_playlist_to_folder(
+	State(app_state): State<AppState>,
+	Json(payload): Json<MovePlaylistToFolder>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_library::move_playlist(
+		&mut db_conn,
+		&payload.user_id,
+		&payload.playlist_id,
+		payload.folder_id.as_deref(),
+	) {
+		Ok(()) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Playlist moved".to_string())
+			.unwrap(),
+		Err(LibraryError::Database(err)) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to move playlist: {err}"))
+			.unwrap(),
+		Err(err @ (LibraryError::InvalidFolder(_) | LibraryError::NotInLibrary(_))) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(err.to_string())
+			.unwrap(),
+	}
+}
@@ -0,0 +1,45 @@
+use crate::core::{
+	app_state::AppState,
+	playlist_library::{self, LibraryError},
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct PinPlaylist {
+	pub user_id: String,
+	pub playlist_id: String,
+	pub pinned: bool,
+}
+
+// Pinned playlists come first in get_users_playlists, the latest pinned on top
+pub async fn pin_playlist(State(app_state): State<AppState>, Json(payload): Json<PinPlaylist>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_library::pin(&mut db_conn, &payload.user_id, &payload.playlist_id, payload.pinned) {
+		Ok(()) => Response::builder()
+			.status(StatusCode::OK)
+			.body(if payload.pinned { "Playlist pinned" } else { "Playlist unpinned" }.to_string())
+			.unwrap(),
+		Err(LibraryError::Database(err)) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to pin playlist: {err}"))
+			.unwrap(),
+		Err(err @ (LibraryError::InvalidFolder(_) | LibraryError::NotInLibrary(_))) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(err.to_string())
+			.unwrap(),
+	}
+}
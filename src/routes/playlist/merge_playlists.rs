@@ -0,0 +1,174 @@
+use crate::core::{
+	app_state::AppState,
+	blend::{self, BlendError},
+	playlist,
+	playlist_access::{self, Role},
+	playlist_fork::{self, ForkError},
+	playlist_history::Change,
+};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+// Adds the songs of the source playlist that the target doesn't have yet to the target
+#[derive(Debug, Deserialize)]
+pub struct MergePlaylists {
+	pub user_id: String,
+	pub target_playlist_id: String,
+	pub source_playlist_id: String,
+	pub share_token: Option<String>, // for a source the user can only read through a share link
+}
+
+pub async fn merge_playlists(
+	State(app_state): State<AppState>,
+	Json(payload): Json<MergePlaylists>,
+) -> Response<String> {
+	if payload.target_playlist_id == payload.source_playlist_id {
+		return Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body("Can't merge a playlist into itself".to_string())
+			.unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_access::has_role(&mut db_conn, &payload.target_playlist_id, &payload.user_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &payload.target_playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	match blend::ensure_not_blend(&mut db_conn, &payload.target_playlist_id) {
+		Ok(()) => {}
+		Err(err @ BlendError::ManualEdit) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err.to_string())
+				.unwrap();
+		}
+	}
+
+	match playlist::is_smart(&mut db_conn, &payload.target_playlist_id) {
+		Ok(false) => {}
+		Ok(true) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body("Songs can't be added to a smart playlist, freeze it first".to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	}
+
+	let source = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.source_playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
+	{
+		Ok(playlist) => playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.source_playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	match playlist_access::can_read(
+		&mut db_conn,
+		&source,
+		Some(&payload.user_id),
+		payload.share_token.as_deref(),
+	) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("You don't have access to this playlist".to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	match playlist_fork::merge(&mut db_conn, &payload.target_playlist_id, &source, &payload.user_id) {
+		Ok(None) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Nothing to merge, the playlist already has all the songs".to_string())
+			.unwrap(),
+		Ok(Some(change)) => {
+			let published = app_state.playlist_rooms.publish_change(
+				&app_state.user_pool,
+				&mut db_conn,
+				&payload.target_playlist_id,
+				&payload.user_id,
+				&change,
+			);
+			if let Err(err) = published {
+				println!("Error {}:{}: Failed to publish the change: {err}", file!(), line!());
+			}
+			let added = match &change {
+				Change::Add { entries } => entries.len(),
+				_ => 0,
+			};
+			Response::builder()
+				.status(StatusCode::OK)
+				.body(format!("Merged {added} songs from {}", source.playlist_name))
+				.unwrap()
+		}
+		Err(ForkError::InvalidRules(err)) => Response::builder()
+			.status(StatusCode::UNPROCESSABLE_ENTITY)
+			.body(format!("Failed to evaluate the source playlist: {err}"))
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to merge playlists: {err}"))
+			.unwrap(),
+	}
+}
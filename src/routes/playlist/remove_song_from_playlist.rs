@@ -1,4 +1,11 @@
-use crate::core::app_state::AppState;
+use crate::core::{
+	app_state::AppState,
+	blend::{self, BlendError},
+	playlist,
+	playlist_access::{self, Role},
+	playlist_history::{self, Change},
+};
+use crate::lobic_db::models::PlaylistSong;
 use crate::schema::playlist_songs::dsl::*;
 use axum::{extract::State, http::status::StatusCode, response::Response, Json};
 use diesel::prelude::*;
@@ -7,7 +14,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RemoveSongFromPlaylist {
 	pub playlist_id: String,
+	pub user_id: String,
 	pub music_id: String,
+	pub item_id: Option<String>, // removes only that entry, otherwise every occurrence of the song is removed
 }
 
 pub async fn remove_song_from_playlist(
@@ -24,14 +33,81 @@ pub async fn remove_song_from_playlist(
 		}
 	};
 
-	match diesel::delete(playlist_songs)
-		.filter(music_id.eq(&payload.music_id))
-		.filter(playlist_id.eq(&payload.playlist_id))
-		.execute(&mut db_conn)
-	{
-		Ok(rows_deleted) => {
-			if rows_deleted > 0 {
+	// Viewers can't remove songs
+	match playlist_access::has_role(&mut db_conn, &payload.playlist_id, &payload.user_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	match blend::ensure_not_blend(&mut db_conn, &payload.playlist_id) {
+		Ok(()) => {}
+		Err(err @ BlendError::ManualEdit) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err.to_string())
+				.unwrap();
+		}
+	}
+
+	// The removed entries are kept in the history so that the removal can be undone
+	let result = db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+		let mut query = playlist_songs
+			.filter(music_id.eq(&payload.music_id))
+			.filter(playlist_id.eq(&payload.playlist_id))
+			.into_boxed();
+		if let Some(curr_item_id) = &payload.item_id {
+			query = query.filter(item_id.eq(curr_item_id));
+		}
+		let removed = query.select(PlaylistSong::as_select()).load(conn)?;
+		if removed.is_empty() {
+			return Ok(None);
+		}
+
+		let removed_item_ids: Vec<&str> = removed.iter().map(|entry| entry.item_id.as_str()).collect();
+		let rows_deleted = diesel::delete(playlist_songs.filter(item_id.eq_any(removed_item_ids))).execute(conn)?;
+		playlist::touch_playlist(conn, &payload.playlist_id)?;
+		let change = Change::Remove { entries: removed };
+		playlist_history::record(conn, &payload.playlist_id, &payload.user_id, &change)?;
+		Ok(Some((rows_deleted, change)))
+	});
+
+	match result {
+		Ok(removed) => {
+			if let Some((_, change)) = removed {
 				// If a record was deleted
+				let published = app_state.playlist_rooms.publish_change(
+					&app_state.user_pool,
+					&mut db_conn,
+					&payload.playlist_id,
+					&payload.user_id,
+					&change,
+				);
+				if let Err(err) = published {
+					println!("Error {}:{}: Failed to publish the change: {err}", file!(), line!());
+				}
 				Response::builder()
 					.status(StatusCode::OK)
 					.body(format!(
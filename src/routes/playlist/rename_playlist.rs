@@ -0,0 +1,108 @@
+use crate::core::{
+	app_state::AppState,
+	playlist,
+	playlist_access::{self, Role},
+	playlist_history::{self, Change},
+};
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct RenamePlaylist {
+	pub playlist_id: String,
+	pub user_id: String,
+	pub playlist_name: String,
+}
+
+pub async fn rename_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<RenamePlaylist>,
+) -> Response<String> {
+	let playlist_name = payload.playlist_name.trim().to_string();
+	if playlist_name.is_empty() {
+		return Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body("The playlist name can't be empty".to_string())
+			.unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Viewers can't rename the playlist
+	match playlist_access::has_role(&mut db_conn, &payload.playlist_id, &payload.user_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	let result = db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+		let previous_name = playlists::table
+			.filter(playlists::playlist_id.eq(&payload.playlist_id))
+			.select(playlists::playlist_name)
+			.first::<String>(conn)?;
+		if previous_name == playlist_name {
+			return Ok(None);
+		}
+		diesel::update(playlists::table.filter(playlists::playlist_id.eq(&payload.playlist_id)))
+			.set(playlists::playlist_name.eq(&playlist_name))
+			.execute(conn)?;
+		playlist::touch_playlist(conn, &payload.playlist_id)?;
+		let change = Change::Rename {
+			from: previous_name,
+			to: playlist_name.clone(),
+		};
+		playlist_history::record(conn, &payload.playlist_id, &payload.user_id, &change)?;
+		Ok(Some(change))
+	});
+
+	match result {
+		Ok(renamed) => {
+			if let Some(change) = renamed {
+				let published = app_state.playlist_rooms.publish_change(
+					&app_state.user_pool,
+					&mut db_conn,
+					&payload.playlist_id,
+					&payload.user_id,
+					&change,
+				);
+				if let Err(err) = published {
+					println!("Error {}:{}: Failed to publish the change: {err}", file!(), line!());
+				}
+			}
+			Response::builder()
+				.status(StatusCode::OK)
+				.body(format!("Playlist renamed to {playlist_name}"))
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to rename playlist: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,147 @@
+use crate::core::{
+	app_state::AppState,
+	blend::{self, BlendError},
+	playlist::{self, MoveError, MoveTarget},
+	playlist_access::{self, Role},
+	playlist_history::{self, Change},
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// Moves one or many entries (item ids from /playlist/get_by_uuid) as a block, in the given order.
+// With neither before_item_id nor after_item_id the items go to the end, or to the start with to_start.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReorderPlaylist {
+	pub playlist_id: String,
+	pub user_id: String,
+	pub item_ids: Vec<String>,
+	pub before_item_id: Option<String>,
+	pub after_item_id: Option<String>,
+	#[serde(default)]
+	pub to_start: bool,
+}
+
+pub async fn reorder_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<ReorderPlaylist>,
+) -> Response<String> {
+	let target = match (&payload.before_item_id, &payload.after_item_id, payload.to_start) {
+		(Some(before), None, false) => MoveTarget::Before(before.clone()),
+		(None, Some(after), false) => MoveTarget::After(after.clone()),
+		(None, None, true) => MoveTarget::Start,
+		(None, None, false) => MoveTarget::End,
+		_ => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body("Only one of before_item_id, after_item_id and to_start can be given".to_string())
+				.unwrap();
+		}
+	};
+
+	if payload.item_ids.is_empty() {
+		return Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body("No item_ids to move".to_string())
+			.unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Viewers can't reorder songs
+	match playlist_access::has_role(&mut db_conn, &payload.playlist_id, &payload.user_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	match blend::ensure_not_blend(&mut db_conn, &payload.playlist_id) {
+		Ok(()) => {}
+		Err(err @ BlendError::ManualEdit) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err.to_string())
+				.unwrap();
+		}
+	}
+
+	// The positions before the move are kept in the history so that it can be undone
+	let result = db_conn.transaction::<_, MoveError, _>(|conn| {
+		let before = playlist::load_order(conn, &payload.playlist_id)?;
+		playlist::move_items(conn, &payload.playlist_id, &payload.item_ids, &target)?;
+		let after = playlist::load_order(conn, &payload.playlist_id)?;
+		let moves = playlist_history::position_changes(&before, &after);
+		if moves.is_empty() {
+			return Ok(None);
+		}
+		let change = Change::Reorder { moves };
+		playlist_history::record(conn, &payload.playlist_id, &payload.user_id, &change)?;
+		Ok(Some(change))
+	});
+
+	match result {
+		Ok(reordered) => {
+			// Subscribers get the whole resulting order, whatever order they applied the moves in
+			if let Some(change) = reordered {
+				let published = app_state.playlist_rooms.publish_change(
+					&app_state.user_pool,
+					&mut db_conn,
+					&payload.playlist_id,
+					&payload.user_id,
+					&change,
+				);
+				if let Err(err) = published {
+					println!("Error {}:{}: Failed to publish the change: {err}", file!(), line!());
+				}
+			}
+			Response::builder()
+				.status(StatusCode::OK)
+				.body("Playlist reordered".to_string())
+				.unwrap()
+		}
+		Err(MoveError::InvalidItem(item_id)) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(format!("Invalid or repeated item_id: {item_id}"))
+			.unwrap(),
+		Err(MoveError::InvalidAnchor(item_id)) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(format!(
+				"Invalid anchor item_id: {item_id}, it has to be in the playlist and not being moved"
+			))
+			.unwrap(),
+		Err(MoveError::Database(err)) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to reorder playlist: {err}"))
+			.unwrap(),
+	}
+}
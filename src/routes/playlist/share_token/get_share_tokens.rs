@@ -0,0 +1,70 @@
+use crate::core::{app_state::AppState, playlist_access};
+use crate::schema::playlists;
+use axum::{
+	extract::{Query, State},
+	http::{header, status::StatusCode},
+	response::Response,
+};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+// /playlist/share_token/get?playlist_id=123&user_id=456
+#[derive(Debug, Deserialize)]
+pub struct GetShareTokensQuery {
+	pub playlist_id: String,
+	pub user_id: String,
+}
+
+pub async fn get_share_tokens(
+	State(app_state): State<AppState>,
+	Query(params): Query<GetShareTokensQuery>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let owner_id = match playlists::table
+		.filter(playlists::playlist_id.eq(&params.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.select(playlists::user_id)
+		.first::<String>(&mut db_conn)
+	{
+		Ok(owner_id) => owner_id,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", params.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+	if owner_id != params.user_id {
+		return Response::builder()
+			.status(StatusCode::FORBIDDEN)
+			.body("Only the owner can manage the share links of a playlist".to_string())
+			.unwrap();
+	}
+
+	match playlist_access::share_tokens(&mut db_conn, &params.playlist_id) {
+		Ok(share_tokens) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&share_tokens).unwrap())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to query share links: {err}"))
+			.unwrap(),
+	}
+}
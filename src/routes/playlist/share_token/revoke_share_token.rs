@@ -0,0 +1,69 @@
+use crate::core::{app_state::AppState, playlist_access};
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeShareToken {
+	pub playlist_id: String,
+	pub user_id: String,
+	pub token: String,
+}
+
+pub async fn revoke_share_token(
+	State(app_state): State<AppState>,
+	Json(payload): Json<RevokeShareToken>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let owner_id = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.select(playlists::user_id)
+		.first::<String>(&mut db_conn)
+	{
+		Ok(owner_id) => owner_id,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+	if owner_id != payload.user_id {
+		return Response::builder()
+			.status(StatusCode::FORBIDDEN)
+			.body("Only the owner can manage the share links of a playlist".to_string())
+			.unwrap();
+	}
+
+	match playlist_access::revoke_share_token(&mut db_conn, &payload.playlist_id, &payload.token) {
+		Ok(true) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Share link revoked".to_string())
+			.unwrap(),
+		Ok(false) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(format!("Invalid token: {}", payload.token))
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to revoke share link: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,65 @@
+use crate::core::{activity, app_state::AppState, playlist_access::Visibility, smart_playlist::SmartRules};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSmartPlaylist {
+	pub playlist_name: String,
+	pub user_id: String,
+	pub rules: SmartRules,
+}
+
+pub async fn create_smart_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<CreateSmartPlaylist>,
+) -> Response<String> {
+	if let Err(err) = payload.rules.validate() {
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let now = Utc::now().to_rfc3339();
+	let new_playlist = Playlist {
+		playlist_id: Uuid::new_v4().to_string(),
+		playlist_name: payload.playlist_name,
+		user_id: payload.user_id,
+		creation_date_time: now.clone(),
+		last_updated_date_time: now,
+		is_playlist_combined: false,
+		smart_rules: Some(payload.rules.to_json()),
+		visibility: Visibility::Private.as_str().to_string(),
+		deleted_at: None,
+		description: String::new(),
+	};
+
+	match diesel::insert_into(playlists::table)
+		.values(&new_playlist)
+		.execute(&mut db_conn)
+	{
+		Ok(_) => {
+			activity::share_playlist(&mut db_conn, &app_state.user_pool, &new_playlist);
+			Response::builder()
+				.status(StatusCode::CREATED)
+				.body(format!("Smart playlist created with ID: {}", new_playlist.playlist_id))
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to create playlist: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,65 @@
+use crate::core::{
+	app_state::AppState,
+	smart_playlist::{self, EvaluateError},
+};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+// Without new_playlist_name the smart playlist itself is turned into a normal one,
+// with it a normal copy of its current songs is created
+#[derive(Debug, Deserialize)]
+pub struct FreezeSmartPlaylist {
+	pub playlist_id: String,
+	pub new_playlist_name: Option<String>,
+}
+
+pub async fn freeze_smart_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<FreezeSmartPlaylist>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let smart_playlist = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.filter(playlists::smart_rules.is_not_null())
+		.first::<Playlist>(&mut db_conn)
+	{
+		Ok(smart_playlist) => smart_playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("No smart playlist with ID: {}", payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to query playlist: {err}"))
+				.unwrap();
+		}
+	};
+
+	match smart_playlist::freeze(&mut db_conn, &smart_playlist, payload.new_playlist_name) {
+		Ok(frozen_playlist_id) => Response::builder()
+			.status(StatusCode::OK)
+			.body(format!("Playlist frozen with ID: {frozen_playlist_id}"))
+			.unwrap(),
+		Err(EvaluateError::InvalidRules(err)) => Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap(),
+		Err(EvaluateError::Database(err)) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to freeze playlist: {err}"))
+			.unwrap(),
+	}
+}
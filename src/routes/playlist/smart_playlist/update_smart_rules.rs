@@ -0,0 +1,61 @@
+use crate::core::{app_state::AppState, playlist, smart_playlist::SmartRules};
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSmartRules {
+	pub playlist_id: String,
+	pub rules: SmartRules,
+}
+
+pub async fn update_smart_rules(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UpdateSmartRules>,
+) -> Response<String> {
+	if let Err(err) = payload.rules.validate() {
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Only smart playlists have rules, giving a curated playlist rules would hide its songs
+	let result = db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+		let updated = diesel::update(
+			playlists::table
+				.filter(playlists::playlist_id.eq(&payload.playlist_id))
+				.filter(playlists::deleted_at.is_null())
+				.filter(playlists::smart_rules.is_not_null()),
+		)
+		.set(playlists::smart_rules.eq(payload.rules.to_json()))
+		.execute(conn)?;
+		if updated > 0 {
+			playlist::touch_playlist(conn, &payload.playlist_id)?;
+		}
+		Ok(updated)
+	});
+
+	match result {
+		Ok(0) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(format!("No smart playlist with ID: {}", payload.playlist_id))
+			.unwrap(),
+		Ok(_) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Smart playlist rules updated".to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to update rules: {err}"))
+			.unwrap(),
+	}
+}
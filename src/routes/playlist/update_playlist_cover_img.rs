@@ -1,6 +1,18 @@
 use crate::config::PLAYLIST_COVER_IMG_STORAGE;
+use crate::core::{
+	app_state::AppState,
+	playlist,
+	playlist_access::{self, Role},
+	playlist_history::{self, Change},
+};
 
-use axum::{body::Bytes, extract::Query, http::StatusCode, response::Response};
+use axum::{
+	body::Bytes,
+	extract::{Query, State},
+	http::StatusCode,
+	response::Response,
+};
+use diesel::prelude::*;
 use serde::Deserialize;
 use std::fs;
 use std::path::Path;
@@ -9,9 +21,14 @@ use uuid::Uuid;
 #[derive(Deserialize)]
 pub struct PlaylistId {
 	playlist_id: String,
+	user_id: String,
 }
 
-pub async fn update_playlist_cover_img(Query(playlist_id): Query<PlaylistId>, body: Bytes) -> Response<String> {
+pub async fn update_playlist_cover_img(
+	State(app_state): State<AppState>,
+	Query(playlist_id): Query<PlaylistId>,
+	body: Bytes,
+) -> Response<String> {
 	let uuid = match Uuid::parse_str(&playlist_id.playlist_id) {
 		Ok(uuid) => uuid,
 		Err(_) => {
@@ -22,6 +39,39 @@ pub async fn update_playlist_cover_img(Query(playlist_id): Query<PlaylistId>, bo
 		}
 	};
 
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Viewers can't change the cover
+	match playlist_access::has_role(&mut db_conn, &playlist_id.playlist_id, &playlist_id.user_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", playlist_id.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
 	let storage_path = Path::new(PLAYLIST_COVER_IMG_STORAGE);
 	if let Err(err) = fs::create_dir_all(storage_path) {
 		return Response::builder()
@@ -30,6 +80,17 @@ pub async fn update_playlist_cover_img(Query(playlist_id): Query<PlaylistId>, bo
 			.unwrap();
 	}
 
+	// The previous cover is kept so that the change can be undone
+	let backup = match playlist_history::backup_cover(&uuid.to_string()) {
+		Ok(backup) => backup,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(err.to_string())
+				.unwrap();
+		}
+	};
+
 	let image_path = storage_path.join(format!("{}.png", uuid));
 	if let Err(err) = fs::write(&image_path, body) {
 		return Response::builder()
@@ -38,6 +99,28 @@ pub async fn update_playlist_cover_img(Query(playlist_id): Query<PlaylistId>, bo
 			.unwrap();
 	}
 
+	let change = Change::Cover { backup };
+	let result = db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+		playlist::touch_playlist(conn, &playlist_id.playlist_id)?;
+		playlist_history::record(conn, &playlist_id.playlist_id, &playlist_id.user_id, &change)
+	});
+	if let Err(err) = result {
+		return Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to update playlist: {err}"))
+			.unwrap();
+	}
+	let published = app_state.playlist_rooms.publish_change(
+		&app_state.user_pool,
+		&mut db_conn,
+		&playlist_id.playlist_id,
+		&playlist_id.user_id,
+		&change,
+	);
+	if let Err(err) = published {
+		println!("Error {}:{}: Failed to publish the change: {err}", file!(), line!());
+	}
+
 	Response::builder()
 		.status(StatusCode::OK)
 		.body("Cover image updated successfully".to_string())
@@ -0,0 +1,116 @@
+use crate::core::{
+	app_state::AppState,
+	playlist,
+	playlist_access::{self, Role},
+	playlist_library::{self, LibraryError},
+};
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+
+// Fields left out are kept as they are, an empty list of tags removes them all
+#[derive(Debug, Deserialize)]
+pub struct UpdatePlaylistDetails {
+	pub playlist_id: String,
+	pub user_id: String,
+	pub description: Option<String>,
+	pub tags: Option<Vec<String>>,
+}
+
+pub async fn update_playlist_details(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UpdatePlaylistDetails>,
+) -> Response<String> {
+	let description = payload.description.as_deref().map(str::trim);
+	if description.is_some_and(|description| description.chars().count() > playlist_library::MAX_DESCRIPTION_LENGTH) {
+		return Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(format!(
+				"The description can't be longer than {} characters",
+				playlist_library::MAX_DESCRIPTION_LENGTH
+			))
+			.unwrap();
+	}
+	let tags = match payload.tags.as_deref().map(playlist_library::normalize_tags).transpose() {
+		Ok(tags) => tags,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(err.to_string())
+				.unwrap();
+		}
+	};
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Viewers can't change the details
+	match playlist_access::has_role(&mut db_conn, &payload.playlist_id, &payload.user_id, Role::Editor) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body("Only the owner and editors can change this playlist".to_string())
+				.unwrap();
+		}
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", &payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to check playlist permissions: {err}"))
+				.unwrap();
+		}
+	}
+
+	let result = db_conn.transaction::<_, LibraryError, _>(|conn| {
+		if let Some(description) = description {
+			playlist_library::set_description(conn, &payload.playlist_id, description)?;
+		}
+		if let Some(tags) = &tags {
+			playlist_library::set_tags(conn, &payload.playlist_id, tags)?;
+		}
+		playlist::touch_playlist(conn, &payload.playlist_id)?;
+		Ok(())
+	});
+
+	match result {
+		Ok(()) => {
+			let change = json!({
+				"kind": "details",
+				"description": description,
+				"tags": tags,
+			});
+			let published = app_state.playlist_rooms.publish(
+				&app_state.user_pool,
+				&mut db_conn,
+				&payload.playlist_id,
+				&payload.user_id,
+				change,
+			);
+			if let Err(err) = published {
+				println!("Error {}:{}: Failed to publish the change: {err}", file!(), line!());
+			}
+			Response::builder()
+				.status(StatusCode::OK)
+				.body("Playlist details updated".to_string())
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to update playlist details: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,99 @@
+use crate::core::{
+	activity,
+	app_state::AppState,
+	playlist,
+	playlist_access::{self, Visibility},
+};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateVisibility {
+	pub playlist_id: String,
+	pub user_id: String,
+	pub visibility: String, // private, friends, unlisted or public
+}
+
+pub async fn update_visibility(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UpdateVisibility>,
+) -> Response<String> {
+	let visibility = match Visibility::parse(&payload.visibility) {
+		Some(visibility) => visibility,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!(
+					"Invalid visibility: {}, expected private, friends, unlisted or public",
+					payload.visibility
+				))
+				.unwrap();
+		}
+	};
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let curr_playlist = match playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
+	{
+		Ok(curr_playlist) => curr_playlist,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid playlist_id: {}", payload.playlist_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Contributors can edit the songs but only the owner decides who sees them
+	if curr_playlist.user_id != payload.user_id {
+		return Response::builder()
+			.status(StatusCode::FORBIDDEN)
+			.body("Only the owner can change the visibility of a playlist".to_string())
+			.unwrap();
+	}
+
+	let result = db_conn.transaction::<_, diesel::result::Error, _>(|conn| {
+		playlist_access::set_visibility(conn, &payload.playlist_id, visibility)?;
+		playlist::touch_playlist(conn, &payload.playlist_id)
+	});
+	match result {
+		Ok(_) => {
+			// Friends only hear about the playlist once it stops being hidden from them
+			let was_hidden = !matches!(
+				Visibility::of(&curr_playlist),
+				Visibility::Friends | Visibility::Public
+			);
+			if was_hidden && matches!(visibility, Visibility::Friends | Visibility::Public) {
+				activity::announce_playlist(&mut db_conn, &app_state.user_pool, &payload.playlist_id);
+			}
+			Response::builder()
+				.status(StatusCode::OK)
+				.body(format!("Playlist is now {}", visibility.as_str()))
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to update visibility: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,121 @@
+use crate::{
+	core::{
+		app_state::AppState,
+		radio::{RadioSeed, RadioStation},
+	},
+	lobic_db::models::{Music, MusicResponse},
+};
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use serde::{Deserialize, Serialize};
+
+// /radio?seed_type=artist&seed=Manish%20Bhattarai&user_id=123&page_length=20
+// Next pages are requested with the session returned by the first one and the ids of the last tracks played:
+// /radio?seed_type=artist&seed=Manish%20Bhattarai&user_id=123&session=8213&start_index=20&window=id1,id2
+#[derive(Debug, Deserialize)]
+pub struct RadioQueryParams {
+	pub seed_type: String, // music, artist, genre or playlist
+	pub seed: String,
+	pub user_id: Option<String>,
+	pub session: Option<u64>,
+	pub artist_window: Option<usize>, // no artist is repeated within this many tracks
+	pub window: Option<String>, // comma separated ids of the last tracks played from the station, oldest first
+	#[serde(default)]
+	pub start_index: i64, //defaults to 0
+	pub page_length: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RadioResponse {
+	pub session: u64,
+	pub music: Vec<MusicResponse>,
+}
+
+pub async fn radio(State(app_state): State<AppState>, Query(params): Query<RadioQueryParams>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let seed = match RadioSeed::parse(&params.seed_type, &params.seed) {
+		Some(seed) => seed,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!(
+					"Invalid seed_type: {}, expected one of music, artist, genre or playlist",
+					params.seed_type
+				))
+				.unwrap();
+		}
+	};
+
+	match seed.is_visible_to(&mut db_conn, params.user_id.as_deref()) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Playlist not found: {}", params.seed))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	}
+
+	let station = RadioStation::new(seed, params.session, params.artist_window);
+	let page_length = params.page_length.filter(|length| *length > 0).unwrap_or(20);
+	let window: Vec<String> = params
+		.window
+		.as_deref()
+		.unwrap_or_default()
+		.split(',')
+		.map(str::trim)
+		.filter(|music_id| !music_id.is_empty())
+		.map(str::to_string)
+		.collect();
+	let tracks = match station.generate(
+		&mut db_conn,
+		&app_state.recommender,
+		params.user_id.as_deref(),
+		&window,
+		params.start_index.max(0) as usize,
+		page_length as usize,
+	) {
+		Ok(tracks) => tracks,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	let response = RadioResponse {
+		session: station.session,
+		music: tracks.into_iter().map(Music::create_music_response).collect(),
+	};
+
+	match serde_json::to_string(&response) {
+		Ok(json) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to serialize response: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,114 @@
+use crate::{
+	core::{app_state::AppState, recommender},
+	lobic_db::models::{Music, MusicResponse},
+	schema::liked_songs,
+};
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// /recommend/because_you_liked?user_id=123&rows=3&page_length=10
+#[derive(Debug, Deserialize)]
+pub struct BecauseYouLikedQueryParams {
+	pub user_id: String,
+	pub rows: Option<i64>,        // number of liked songs used as seeds, defaults to 3
+	pub page_length: Option<i64>, // songs per row, defaults to 10
+}
+
+#[derive(Debug, Serialize)]
+pub struct BecauseYouLikedRow {
+	pub seed: MusicResponse,
+	pub music: Vec<MusicResponse>,
+}
+
+// One row of similar songs for each of the most recently liked songs of the user
+pub async fn because_you_liked(
+	State(app_state): State<AppState>,
+	Query(params): Query<BecauseYouLikedQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let seeds = match liked_songs::table
+		.filter(liked_songs::user_id.eq(&params.user_id))
+		.order(liked_songs::song_added_date_time.desc())
+		.select(liked_songs::music_id)
+		.limit(params.rows.filter(|rows| *rows > 0).unwrap_or(3))
+		.load::<String>(&mut db_conn)
+	{
+		Ok(seeds) => seeds,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	let profile = match recommender::load_user_profile(&mut db_conn, &params.user_id) {
+		Ok(profile) => profile,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	let page_length = params.page_length.filter(|length| *length > 0).unwrap_or(10);
+	let mut rows = Vec::new();
+	for seed in recommender::load_music_in_order(&mut db_conn, &seeds).unwrap_or_default() {
+		let ids: Vec<String> = app_state
+			.recommender
+			.similar(&seed.music_id)
+			.into_iter()
+			.map(|(music_id, _)| music_id)
+			.filter(|music_id| !profile.contains_key(music_id))
+			.take(page_length as usize)
+			.collect();
+
+		match recommender::load_music_in_order(&mut db_conn, &ids) {
+			Ok(music_entries) if !music_entries.is_empty() => rows.push(BecauseYouLikedRow {
+				seed: Music::create_music_response(seed),
+				music: music_entries.into_iter().map(Music::create_music_response).collect(),
+			}),
+			Ok(_) => (),
+			Err(err) => {
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Database error: {err}"))
+					.unwrap();
+			}
+		}
+	}
+
+	if rows.is_empty() {
+		return Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body("No recommendations found".to_string())
+			.unwrap();
+	}
+
+	match serde_json::to_string(&rows) {
+		Ok(json) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to serialize response: {err}"))
+			.unwrap(),
+	}
+}
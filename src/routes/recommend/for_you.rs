@@ -0,0 +1,104 @@
+use crate::{
+	core::{app_state::AppState, recommender},
+	lobic_db::models::{Music, MusicResponse},
+	schema::music,
+};
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+// /recommend/for_you?user_id=123&page_length=20
+#[derive(Debug, Deserialize)]
+pub struct ForYouQueryParams {
+	pub user_id: String,
+	#[serde(default)]
+	pub start_index: i64, //defaults to 0
+	pub page_length: Option<i64>,
+}
+
+pub async fn for_you(State(app_state): State<AppState>, Query(params): Query<ForYouQueryParams>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let profile = match recommender::load_user_profile(&mut db_conn, &params.user_id) {
+		Ok(profile) => profile,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	let mut ids: Vec<String> = app_state
+		.recommender
+		.recommend(&profile)
+		.into_iter()
+		.map(|(music_id, _)| music_id)
+		.collect();
+
+	// New users (or users with nothing in common with anyone yet) get the most played tracks they don't know
+	if ids.is_empty() {
+		let known: Vec<&String> = profile.keys().collect();
+		ids = match music::table
+			.filter(music::music_id.ne_all(known))
+			.order(music::times_played.desc())
+			.select(music::music_id)
+			.load::<String>(&mut db_conn)
+		{
+			Ok(ids) => ids,
+			Err(err) => {
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Database error: {err}"))
+					.unwrap();
+			}
+		};
+	}
+
+	let page_length = params.page_length.filter(|length| *length > 0).unwrap_or(i64::MAX);
+	let ids: Vec<String> = ids
+		.into_iter()
+		.skip(params.start_index.max(0) as usize)
+		.take(page_length as usize)
+		.collect();
+
+	match recommender::load_music_in_order(&mut db_conn, &ids) {
+		Ok(music_entries) => {
+			if music_entries.is_empty() {
+				return Response::builder()
+					.status(StatusCode::NOT_FOUND)
+					.body("No recommendations found".to_string())
+					.unwrap();
+			}
+
+			let responses: Vec<MusicResponse> = music_entries.into_iter().map(Music::create_music_response).collect();
+			match serde_json::to_string(&responses) {
+				Ok(json) => Response::builder()
+					.status(StatusCode::OK)
+					.header(header::CONTENT_TYPE, "application/json")
+					.body(json)
+					.unwrap(),
+				Err(err) => Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Failed to serialize response: {err}"))
+					.unwrap(),
+			}
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Database error: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,72 @@
+use crate::{
+	core::{app_state::AppState, recommender},
+	lobic_db::models::{Music, MusicResponse},
+};
+use axum::{
+	extract::{Path, Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use serde::Deserialize;
+
+// /recommend/similar/:music_id?page_length=10
+#[derive(Debug, Deserialize)]
+pub struct SimilarQueryParams {
+	#[serde(default)]
+	pub start_index: i64, //defaults to 0
+	pub page_length: Option<i64>,
+}
+
+pub async fn similar(
+	State(app_state): State<AppState>,
+	Path(music_id): Path<String>,
+	Query(params): Query<SimilarQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let page_length = params.page_length.filter(|length| *length > 0).unwrap_or(i64::MAX);
+	let ids: Vec<String> = app_state
+		.recommender
+		.similar(&music_id)
+		.into_iter()
+		.map(|(music_id, _)| music_id)
+		.skip(params.start_index.max(0) as usize)
+		.take(page_length as usize)
+		.collect();
+
+	match recommender::load_music_in_order(&mut db_conn, &ids) {
+		Ok(music_entries) => {
+			if music_entries.is_empty() {
+				return Response::builder()
+					.status(StatusCode::NOT_FOUND)
+					.body(format!("No similar songs found for music_id: {music_id}"))
+					.unwrap();
+			}
+
+			let responses: Vec<MusicResponse> = music_entries.into_iter().map(Music::create_music_response).collect();
+			match serde_json::to_string(&responses) {
+				Ok(json) => Response::builder()
+					.status(StatusCode::OK)
+					.header(header::CONTENT_TYPE, "application/json")
+					.body(json)
+					.unwrap(),
+				Err(err) => Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Failed to serialize response: {err}"))
+					.unwrap(),
+			}
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Database error: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,107 @@
+use crate::{
+	core::{
+		app_state::AppState,
+		scrobble::{self, ScrobbleFormat},
+	},
+	utils::timestamp,
+};
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use serde::Deserialize;
+
+// /scrobbles/export?user_id=123&format=listenbrainz
+// /scrobbles/export?user_id=123&format=lastfm&from=2026-01-01&to=2026-06-30
+#[derive(Debug, Deserialize)]
+pub struct ExportScrobblesQueryParams {
+	pub user_id: String,
+	pub format: String,       // listenbrainz or lastfm
+	pub from: Option<String>, // inclusive, RFC3339 or YYYY-MM-DD
+	pub to: Option<String>,   // exclusive, a plain date includes the whole day
+}
+
+pub async fn export_scrobbles(
+	State(app_state): State<AppState>,
+	Query(params): Query<ExportScrobblesQueryParams>,
+) -> Response<String> {
+	let format = match ScrobbleFormat::parse(&params.format) {
+		Some(format) => format,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!(
+					"Invalid format: {}, expected listenbrainz or lastfm",
+					params.format
+				))
+				.unwrap();
+		}
+	};
+
+	let (from, to) = match parse_range(params.from.as_deref(), params.to.as_deref()) {
+		Ok(range) => range,
+		Err(err) => {
+			return Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap();
+		}
+	};
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let history = match scrobble::load_history(&mut db_conn, &params.user_id, from.as_deref(), to.as_deref()) {
+		Ok(history) => history,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	let body = match format {
+		ScrobbleFormat::ListenBrainz => serde_json::to_string(&scrobble::to_listenbrainz(&history))
+			.map_err(|err| format!("Failed to serialize response: {err}")),
+		ScrobbleFormat::LastFm => scrobble::to_lastfm_csv(&history),
+	};
+
+	match body {
+		Ok(body) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, format.content_type())
+			.header(
+				header::CONTENT_DISPOSITION,
+				format!(
+					"attachment; filename=\"lobic_{}_{}.{}\"",
+					params.user_id,
+					format.as_str(),
+					format.extension()
+				),
+			)
+			.body(body)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(err)
+			.unwrap(),
+	}
+}
+
+pub fn parse_range(from: Option<&str>, to: Option<&str>) -> Result<(Option<String>, Option<String>), String> {
+	let from = match from {
+		Some(from) => Some(timestamp::parse_date_bound(from, false).ok_or(format!("Invalid from date: {from}"))?),
+		None => None,
+	};
+	let to = match to {
+		Some(to) => Some(timestamp::parse_date_bound(to, true).ok_or(format!("Invalid to date: {to}"))?),
+		None => None,
+	};
+	Ok((from, to))
+}
@@ -0,0 +1,115 @@
+use crate::{
+	core::{
+		app_state::AppState,
+		scrobble::{self, ScrobbleFormat},
+	},
+	schema::users,
+};
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+// Exports of a few years of listening easily go past the default body limit
+pub const MAX_SCROBBLE_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+
+// /scrobbles/import?user_id=123&format=listenbrainz (body: json array or json lines of listens)
+// /scrobbles/import?user_id=123&format=lastfm (body: csv scrobble dump)
+#[derive(Debug, Deserialize)]
+pub struct ImportScrobblesQueryParams {
+	pub user_id: String,
+	pub format: String, // listenbrainz or lastfm
+}
+
+pub async fn import_scrobbles(
+	State(app_state): State<AppState>,
+	Query(params): Query<ImportScrobblesQueryParams>,
+	body: String,
+) -> Response<String> {
+	let format = match ScrobbleFormat::parse(&params.format) {
+		Some(format) => format,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!(
+					"Invalid format: {}, expected listenbrainz or lastfm",
+					params.format
+				))
+				.unwrap();
+		}
+	};
+
+	let parsed = match scrobble::parse(format, &body) {
+		Ok(parsed) => parsed,
+		Err(err) => {
+			return Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap();
+		}
+	};
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let user_exists = users::table
+		.filter(users::user_id.eq(&params.user_id))
+		.count()
+		.get_result::<i64>(&mut db_conn)
+		.map(|count| count > 0);
+	match user_exists {
+		Ok(true) => (),
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid user_id: {}", params.user_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	}
+
+	// Matching against the whole catalog can take a while for large exports
+	let user_id = params.user_id.clone();
+	let result =
+		tokio::task::spawn_blocking(move || scrobble::import_scrobbles(&mut db_conn, &user_id, format, parsed)).await;
+
+	let report = match result {
+		Ok(Ok(report)) => report,
+		Ok(Err(err)) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to import scrobbles: {err}"))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Import task failed: {err}"))
+				.unwrap();
+		}
+	};
+
+	match serde_json::to_string(&report) {
+		Ok(json) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to serialize response: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,67 @@
+use crate::{
+	config::LISTENBRAINZ_API_URL,
+	core::{app_state::AppState, scrobble},
+	routes::scrobbles::export_scrobbles::parse_range,
+};
+use axum::{extract::State, http::StatusCode, response::Response, Json};
+use serde::Deserialize;
+
+// Submits the history of the user to ListenBrainz, or to any compatible server set in LISTENBRAINZ_API_URL
+#[derive(Debug, Deserialize)]
+pub struct SubmitScrobbles {
+	pub user_id: String,
+	pub token: String,        // ListenBrainz user token, it is not stored
+	pub from: Option<String>, // inclusive, RFC3339 or YYYY-MM-DD
+	pub to: Option<String>,   // exclusive, a plain date includes the whole day
+}
+
+pub async fn submit_scrobbles(
+	State(app_state): State<AppState>,
+	Json(payload): Json<SubmitScrobbles>,
+) -> Response<String> {
+	let (from, to) = match parse_range(payload.from.as_deref(), payload.to.as_deref()) {
+		Ok(range) => range,
+		Err(err) => {
+			return Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap();
+		}
+	};
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let history = match scrobble::load_history(&mut db_conn, &payload.user_id, from.as_deref(), to.as_deref()) {
+		Ok(history) => history,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+	// Not holding on to the connection while waiting on the remote server
+	drop(db_conn);
+
+	let listens = scrobble::to_listenbrainz(&history);
+	if listens.is_empty() {
+		return Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body("No listens to submit".to_string())
+			.unwrap();
+	}
+
+	let api_url = std::env::var("LISTENBRAINZ_API_URL").unwrap_or_else(|_| LISTENBRAINZ_API_URL.to_string());
+	match scrobble::submit_listenbrainz(&api_url, &payload.token, &listens).await {
+		Ok(submitted) => Response::builder()
+			.status(StatusCode::OK)
+			.body(format!("Submitted {submitted} listens"))
+			.unwrap(),
+		Err(err) => Response::builder().status(StatusCode::BAD_GATEWAY).body(err).unwrap(),
+	}
+}
@@ -1,6 +1,12 @@
 use crate::core::app_state::AppState;
+use crate::core::friendship;
+use crate::core::playlist_access;
+use crate::core::profile;
+use crate::core::search_index;
+use crate::core::search_query;
 use crate::lobic_db::models::{Music, MusicResponse, Playlist, PlaylistInfo, User, UserDataResponse};
 use crate::schema::{music, playlists, users};
+use crate::utils::fuzzy;
 use axum::{
 	extract::{Query, State},
 	http::{header, StatusCode},
@@ -11,10 +17,17 @@ use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use strsim::jaro_winkler;
 
+// /search?search_category=advanced&search_string=artist:"Manish" duration:<4m liked:yes&user_id=123
 #[derive(Deserialize)]
 pub struct SearchQuery {
 	search_category: String,
 	search_string: String,
+	// needed by the liked: and played: filters of advanced search and for private playlists,
+	// users blocked either way are left out of the people
+	user_id: Option<String>,
+	#[serde(default)]
+	start_index: i64,
+	page_length: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -36,6 +49,20 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 		}
 	};
 
+	let hidden = match &params.user_id {
+		Some(searcher_id) => match friendship::hidden_from(&mut db_conn, searcher_id) {
+			Ok(hidden) => hidden,
+			Err(err) => {
+				let msg = format!("Failed to query blocked users: {}", err);
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(msg)
+					.unwrap();
+			}
+		},
+		None => Default::default(),
+	};
+
 	let category = params.search_category.to_lowercase();
 	let search_string = params.search_string.to_lowercase();
 	let response = match category.as_str() {
@@ -44,18 +71,20 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 			const SEARCH_LIMIT: i64 = 10;
 
 			// Search music with limit
-			let music_results = music::table
-				.filter(
-					music::title
-						.like(format!("%{}%", search_string))
-						.or(music::album.like(format!("%{}%", search_string)))
-						.or(music::artist.like(format!("%{}%", search_string))),
-				)
-				.limit(SEARCH_LIMIT)
+			// Matching is done on the normalized fields as LIKE cannot see through accents or scripts,
+			// the stored search text narrows down the tracks worth normalizing
+			let normalized_search = fuzzy::normalize(&search_string);
+			let music_results = search_index::candidates(&normalized_search)
 				.load::<Music>(&mut db_conn)
 				.map(|entries| {
 					entries
 						.into_iter()
+						.filter(|entry| {
+							fuzzy::matches(&normalized_search, &entry.title)
+								|| fuzzy::matches(&normalized_search, &entry.album)
+								|| fuzzy::matches(&normalized_search, &entry.artist)
+						})
+						.take(SEARCH_LIMIT as usize)
 						.map(Music::create_music_response)
 						.collect::<Vec<_>>()
 				})
@@ -64,37 +93,23 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 			// Search users with limit
 			let people_results = users::table
 				.filter(users::username.like(format!("%{}%", search_string)))
+				.filter(users::user_id.ne_all(&hidden))
 				.limit(SEARCH_LIMIT)
 				.load::<User>(&mut db_conn)
-				.map(|entries| {
-					entries
-						.into_iter()
-						.map(|entry| UserDataResponse {
-							user_id: entry.user_id,
-							username: entry.username,
-							email: entry.email,
-						})
-						.collect::<Vec<_>>()
-				})
+				.and_then(|entries| profile::people(&mut db_conn, entries))
 				.unwrap_or_else(|_| vec![]);
 
-			// Search playlists with limit
-			let playlist_results = playlists::table
-				.filter(playlists::playlist_name.like(format!("%{}%", search_string)))
-				.limit(SEARCH_LIMIT)
-				.load::<Playlist>(&mut db_conn)
-				.unwrap_or_else(|_| vec![]);
-			let playlists_response = playlist_results
-				.into_iter()
-				.map(|playlist| PlaylistInfo {
-					playlist_id: playlist.playlist_id,
-					user_id: playlist.user_id,
-					playlist_name: playlist.playlist_name,
-					creation_date_time: playlist.creation_date_time,
-					last_updated_date_time: playlist.last_updated_date_time,
-					is_playlist_combined: playlist.is_playlist_combined,
-				})
-				.collect();
+			// Search playlists with limit, among the ones listed for the user
+			let playlist_results = playlist_access::filter_listed(
+				&mut db_conn,
+				playlists::table
+					.filter(playlists::playlist_name.like(format!("%{}%", search_string)))
+					.into_boxed(),
+				params.user_id.as_deref(),
+			)
+			.and_then(|listed| listed.limit(SEARCH_LIMIT).load::<Playlist>(&mut db_conn))
+			.unwrap_or_else(|_| vec![]);
+			let playlists_response = playlist_results.into_iter().map(PlaylistInfo::from).collect();
 
 			SearchResponse {
 				songs: music_results,
@@ -103,7 +118,8 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 			}
 		}
 		"title" | "album" | "artist" => {
-			let all_music = match music::table.load::<Music>(&mut db_conn) {
+			let candidates = search_index::candidates(&fuzzy::normalize(&search_string));
+			let all_music = match candidates.load::<Music>(&mut db_conn) {
 				Ok(entries) => entries,
 				Err(err) => {
 					return Response::builder()
@@ -139,7 +155,7 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 			}
 		}
 		"people" => {
-			let all_users = match users::table.load::<User>(&mut db_conn) {
+			let all_users = match users::table.filter(users::user_id.ne_all(&hidden)).load::<User>(&mut db_conn) {
 				Ok(entries) => entries,
 				Err(err) => {
 					return Response::builder()
@@ -161,14 +177,16 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 			let mut sorted_results = search_results;
 			sorted_results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
 
-			let people_response = sorted_results
-				.into_iter()
-				.map(|(entry, _)| UserDataResponse {
-					user_id: entry.user_id,
-					username: entry.username,
-					email: entry.email,
-				})
-				.collect();
+			let people_response =
+				match profile::people(&mut db_conn, sorted_results.into_iter().map(|(entry, _)| entry).collect()) {
+					Ok(people) => people,
+					Err(err) => {
+						return Response::builder()
+							.status(StatusCode::INTERNAL_SERVER_ERROR)
+							.body(format!("Database error: {err}"))
+							.unwrap();
+					}
+				};
 
 			SearchResponse {
 				songs: vec![],
@@ -177,7 +195,13 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 			}
 		}
 		"playlists" => {
-			let all_playlists = match playlists::table.load::<Playlist>(&mut db_conn) {
+			let all_playlists = match playlist_access::filter_listed(
+				&mut db_conn,
+				playlists::table.into_boxed(),
+				params.user_id.as_deref(),
+			)
+			.and_then(|listed| listed.load::<Playlist>(&mut db_conn))
+			{
 				Ok(entries) => entries,
 				Err(err) => {
 					return Response::builder()
@@ -201,14 +225,7 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 
 			let playlist_response = sorted_results
 				.into_iter()
-				.map(|(entry, _)| PlaylistInfo {
-					playlist_id: entry.playlist_id,
-					user_id: entry.user_id,
-					playlist_name: entry.playlist_name,
-					creation_date_time: entry.creation_date_time,
-					last_updated_date_time: entry.last_updated_date_time,
-					is_playlist_combined: entry.is_playlist_combined,
-				})
+				.map(|(entry, _)| PlaylistInfo::from(entry))
 				.collect();
 
 			SearchResponse {
@@ -217,6 +234,51 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 				playlists: playlist_response,
 			}
 		}
+		"advanced" => {
+			let filters = match search_query::parse(&params.search_string) {
+				Ok(filters) => filters,
+				Err(err) => {
+					return Response::builder()
+						.status(StatusCode::BAD_REQUEST)
+						.body(format!("Invalid search query: {err}"))
+						.unwrap();
+				}
+			};
+
+			let query =
+				match search_query::apply_filters(music::table.into_boxed(), &filters, params.user_id.as_deref()) {
+					Ok(query) => query,
+					Err(err) => {
+						return Response::builder()
+							.status(StatusCode::BAD_REQUEST)
+							.body(format!("Invalid search query: {err}"))
+							.unwrap();
+					}
+				};
+
+			let mut query = query.order(music::title.asc()).offset(params.start_index);
+			if let Some(length) = params.page_length {
+				if length > 0 {
+					query = query.limit(length);
+				}
+			}
+
+			let music_results = match query.load::<Music>(&mut db_conn) {
+				Ok(entries) => entries.into_iter().map(Music::create_music_response).collect(),
+				Err(err) => {
+					return Response::builder()
+						.status(StatusCode::INTERNAL_SERVER_ERROR)
+						.body(format!("Database error: {err}"))
+						.unwrap();
+				}
+			};
+
+			SearchResponse {
+				songs: music_results,
+				people: vec![],
+				playlists: vec![],
+			}
+		}
 		_ => {
 			return Response::builder()
 				.status(StatusCode::BAD_REQUEST)
@@ -239,48 +301,39 @@ pub async fn search(State(app_state): State<AppState>, Query(params): Query<Sear
 	}
 }
 
-fn calculate_music_score(entry: &Music, category: &str, search_string: &str) -> (f64, bool) {
-	let search_term = search_string.to_lowercase();
+// Scores a field against an already normalized search term.
+// Trigram similarity is used as a fallback for typos when the term is not contained in the field.
+fn score_field(field: &str, search_term: &str, similarity_weight: f64, contains_weight: f64) -> (f64, bool) {
+	let field = fuzzy::normalize(field);
+	let exact = field == search_term;
+	let similarity = jaro_winkler(&field, search_term);
+	let contains = field.contains(search_term);
+	let contains_bonus = contains as i32 as f64 * 8.0 * contains_weight;
+	let fuzzy_bonus = if contains {
+		0.0
+	} else {
+		fuzzy::trigram_similarity(search_term, &field) * 10.0
+	};
+	(similarity * similarity_weight + contains_bonus + fuzzy_bonus, exact)
+}
 
-	let contains_search_term = |field: &str| -> f64 { field.to_lowercase().contains(&search_term) as i32 as f64 * 8.0 };
+fn calculate_music_score(entry: &Music, category: &str, search_string: &str) -> (f64, bool) {
+	let search_term = fuzzy::normalize(search_string);
 
 	match category {
-		"title" => {
-			let exact = entry.title.eq_ignore_ascii_case(search_string);
-			let similarity = jaro_winkler(&entry.title, search_string);
-			let contains_bonus = contains_search_term(&entry.title) * 0.75;
-			(similarity * 12.0 + contains_bonus, exact)
-		}
-		"album" => {
-			let exact = entry.album.eq_ignore_ascii_case(search_string);
-			let similarity = jaro_winkler(&entry.album, search_string);
-			let contains_bonus = contains_search_term(&entry.album);
-			(similarity * 6.0 + contains_bonus, exact)
-		}
-		"artist" => {
-			let exact = entry.artist.eq_ignore_ascii_case(search_string);
-			let similarity = jaro_winkler(&entry.artist, search_string);
-			let contains_bonus = contains_search_term(&entry.artist);
-			(similarity * 15.0 + contains_bonus, exact)
-		}
+		"title" => score_field(&entry.title, &search_term, 12.0, 0.75),
+		"album" => score_field(&entry.album, &search_term, 6.0, 1.0),
+		"artist" => score_field(&entry.artist, &search_term, 15.0, 1.0),
 		_ => (0.0, false),
 	}
 }
 
 fn calculate_people_score(entry: &User, search_string: &str) -> (f64, bool) {
-	let search_term = search_string.to_lowercase();
-	let contains_search_term = |field: &str| -> f64 { field.to_lowercase().contains(&search_term) as i32 as f64 * 8.0 };
-	let exact = entry.username.eq_ignore_ascii_case(search_string);
-	let similarity = jaro_winkler(&entry.username, search_string);
-	let contains_bonus = contains_search_term(&entry.username) * 0.75;
-	(similarity * 12.0 + contains_bonus, exact)
+	let search_term = fuzzy::normalize(search_string);
+	score_field(&entry.username, &search_term, 12.0, 0.75)
 }
 
 fn calculate_playlist_score(entry: &Playlist, search_string: &str) -> (f64, bool) {
-	let search_term = search_string.to_lowercase();
-	let contains_search_term = |field: &str| -> f64 { field.to_lowercase().contains(&search_term) as i32 as f64 * 8.0 };
-	let exact = entry.playlist_name.eq_ignore_ascii_case(search_string);
-	let similarity = jaro_winkler(&entry.playlist_name, search_string);
-	let contains_bonus = contains_search_term(&entry.playlist_name) * 0.75;
-	(similarity * 12.0 + contains_bonus, exact)
+	let search_term = fuzzy::normalize(search_string);
+	score_field(&entry.playlist_name, &search_term, 12.0, 0.75)
 }
@@ -0,0 +1,99 @@
+use crate::core::app_state::AppState;
+use crate::core::playlist_access;
+use crate::core::share::{self, ShareError};
+use crate::lobic_db::models::Playlist;
+use crate::schema::playlists;
+
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use diesel::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct AddShareToPlaylistPayload {
+	pub share_id: String,
+	pub user_id: String,
+	pub playlist_id: String,
+}
+
+// Appends the shared track, or the songs of the shared album or playlist, to a playlist of the recipient
+pub async fn add_share_to_playlist(
+	State(app_state): State<AppState>,
+	Json(payload): Json<AddShareToPlaylistPayload>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let (change, music_ids, opened) =
+		match share::add_to_playlist(&mut db_conn, &payload.share_id, &payload.user_id, &payload.playlist_id) {
+			Ok(result) => result,
+			Err(err @ (ShareError::InvalidShare(_) | ShareError::InvalidPlaylist(_))) => {
+				return Response::builder()
+					.status(StatusCode::NOT_FOUND)
+					.body(err.to_string())
+					.unwrap();
+			}
+			Err(err @ (ShareError::NotEditor | ShareError::Blend)) => {
+				return Response::builder()
+					.status(StatusCode::FORBIDDEN)
+					.body(err.to_string())
+					.unwrap();
+			}
+			Err(err @ (ShareError::SmartPlaylist | ShareError::InvalidRules(_))) => {
+				return Response::builder()
+					.status(StatusCode::BAD_REQUEST)
+					.body(err.to_string())
+					.unwrap();
+			}
+			Err(err @ ShareError::Unavailable) => {
+				return Response::builder().status(StatusCode::GONE).body(err.to_string()).unwrap();
+			}
+			Err(err) => {
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Failed to add to playlist: {err}"))
+					.unwrap();
+			}
+		};
+
+	let published = app_state.playlist_rooms.publish_change(
+		&app_state.user_pool,
+		&mut db_conn,
+		&payload.playlist_id,
+		&payload.user_id,
+		&change,
+	);
+	if let Err(err) = published {
+		println!("Error {}:{}: Failed to publish the change: {err}", file!(), line!());
+	}
+	if let Some((shared, recipient)) = opened {
+		share::tell_opened(&app_state, &shared, &recipient);
+	}
+
+	// Followers of public playlists get notified, the songs are added either way
+	let notified = playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
+		.and_then(|curr_playlist| {
+			playlist_access::notify_followers(&app_state, &mut db_conn, &curr_playlist, &music_ids, &payload.user_id)
+		});
+	if let Err(err) = notified {
+		println!(
+			"Error {}:{}: Failed to notify the playlist followers: {err}",
+			file!(),
+			line!()
+		);
+	}
+
+	Response::builder()
+		.status(StatusCode::CREATED)
+		.body(format!("Added {} songs to the playlist", music_ids.len()))
+		.unwrap()
+}
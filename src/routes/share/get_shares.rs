@@ -0,0 +1,85 @@
+use crate::core::app_state::AppState;
+use crate::core::share;
+use crate::lobic_db::db::*;
+
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use serde::Deserialize;
+
+// /share/received?user_id=123&start_index=0&page_length=20, same for /share/sent
+#[derive(Debug, Deserialize)]
+pub struct SharesQueryParams {
+	pub user_id: String,
+	#[serde(default)]
+	pub start_index: i64, //defaults to 0
+	pub page_length: Option<i64>,
+}
+
+// What friends shared with the user, latest first
+pub async fn get_received_shares(
+	State(app_state): State<AppState>,
+	Query(params): Query<SharesQueryParams>,
+) -> Response<String> {
+	if !user_exists(&params.user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", params.user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match share::received(&mut db_conn, &params.user_id, params.start_index, params.page_length) {
+		Ok(entries) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&entries).unwrap())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to load shares: {err}"))
+			.unwrap(),
+	}
+}
+
+// What the user shared, with who opened it and how they reacted, latest first
+pub async fn get_sent_shares(
+	State(app_state): State<AppState>,
+	Query(params): Query<SharesQueryParams>,
+) -> Response<String> {
+	if !user_exists(&params.user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", params.user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match share::sent(&mut db_conn, &params.user_id, params.start_index, params.page_length) {
+		Ok(entries) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&entries).unwrap())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to load shares: {err}"))
+			.unwrap(),
+	}
+}
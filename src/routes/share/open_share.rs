@@ -0,0 +1,50 @@
+use crate::core::app_state::AppState;
+use crate::core::share::{self, ShareError};
+
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+	Json,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct OpenSharePayload {
+	pub share_id: String,
+	pub user_id: String,
+}
+
+// Gives the share with its subject, ready to be played, and lets the sender know it was opened
+pub async fn open_share(State(app_state): State<AppState>, Json(payload): Json<OpenSharePayload>) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match share::open(&mut db_conn, &payload.share_id, &payload.user_id) {
+		Ok((entry, opened)) => {
+			if let Some((shared, recipient)) = opened {
+				share::tell_opened(&app_state, &shared, &recipient);
+			}
+			Response::builder()
+				.status(StatusCode::OK)
+				.header(header::CONTENT_TYPE, "application/json")
+				.body(serde_json::to_string(&entry).unwrap())
+				.unwrap()
+		}
+		Err(err @ ShareError::InvalidShare(_)) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to open share: {err}"))
+			.unwrap(),
+	}
+}
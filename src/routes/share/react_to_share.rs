@@ -0,0 +1,52 @@
+use crate::core::app_state::AppState;
+use crate::core::share::{self, Reaction, ShareError};
+
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+	Json,
+};
+use serde::Deserialize;
+
+// reaction: love, fire, laugh, wow or sad, leaving it out takes the reaction back
+#[derive(Debug, Deserialize)]
+pub struct ReactToSharePayload {
+	pub share_id: String,
+	pub user_id: String,
+	pub reaction: Option<Reaction>,
+}
+
+pub async fn react_to_share(
+	State(app_state): State<AppState>,
+	Json(payload): Json<ReactToSharePayload>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match share::react(&mut db_conn, &payload.share_id, &payload.user_id, payload.reaction) {
+		Ok((shared, recipient)) => {
+			share::tell_reaction(&app_state, &shared, &recipient);
+			Response::builder()
+				.status(StatusCode::OK)
+				.header(header::CONTENT_TYPE, "application/json")
+				.body(serde_json::to_string(&recipient).unwrap())
+				.unwrap()
+		}
+		Err(err @ ShareError::InvalidShare(_)) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to react to share: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,81 @@
+use crate::core::app_state::AppState;
+use crate::core::share::{self, ShareError, ShareKind};
+use crate::lobic_db::db::*;
+
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+	Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+// subject_id is the music_id of a track, the name of an album or the playlist_id of a playlist
+#[derive(Debug, Deserialize)]
+pub struct ShareItemPayload {
+	pub user_id: String,
+	pub recipient_ids: Vec<String>,
+	pub kind: ShareKind,
+	pub subject_id: String,
+	pub message: Option<String>,
+}
+
+pub async fn share_item(State(app_state): State<AppState>, Json(payload): Json<ShareItemPayload>) -> Response<String> {
+	if !user_exists(&payload.user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", payload.user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let (shared, subject) = match share::share(
+		&mut db_conn,
+		&payload.user_id,
+		&payload.recipient_ids,
+		payload.kind,
+		&payload.subject_id,
+		payload.message,
+	) {
+		Ok(result) => result,
+		Err(err @ (ShareError::NoRecipients | ShareError::TooManyRecipients | ShareError::MessageTooLong)) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err @ (ShareError::NotFriend(_) | ShareError::CantSee(_))) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err @ ShareError::NotFound(_)) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to share: {err}"))
+				.unwrap();
+		}
+	};
+
+	share::deliver(&app_state, &mut db_conn, &shared, &subject);
+	Response::builder()
+		.status(StatusCode::CREATED)
+		.header(header::CONTENT_TYPE, "application/json")
+		.body(json!({ "share": shared, "subject": subject }).to_string())
+		.unwrap()
+}
@@ -1,12 +1,17 @@
 use crate::config::{MusicState, OpCode, SocketPayload, SocketResponse};
 use crate::core::{
+	activity::{self, ActivityKind},
 	app_state::AppState,
 	lobby::{LobbyPool, Music},
+	playlist_access::{self, Role},
+	playlist_room::{PlaylistRoomPool, PresenceStatus},
+	radio::{RadioSeed, RadioStation},
+	recommender::Recommender,
 	user_pool::UserPool,
 };
 use crate::lobic_db::db::*;
-use crate::lobic_db::models::UserFriendship;
-use crate::schema::user_friendship;
+use crate::lobic_db::models::{Playlist, UserFriendship};
+use crate::schema::{playlists, user_friendship};
 
 use axum::{
 	extract::ws::{Message, WebSocket, WebSocketUpgrade},
@@ -31,12 +36,15 @@ pub async fn handle_socket(socket: WebSocket, State(app_state): State<AppState>)
 	let db_pool = app_state.db_pool;
 	let lobby_pool = app_state.lobby_pool;
 	let user_pool = app_state.user_pool;
+	let recommender = app_state.recommender;
+	let playlist_rooms = app_state.playlist_rooms;
 
 	// Receiving msg through sockets
 	tokio::spawn(async move {
 		// Temporary user state
 		let mut user_id: Option<String> = None;
 		let mut curr_lobby_id: Option<String> = None;
+		let mut subscribed_playlists: Vec<(String, String)> = Vec::new(); // (playlist_id, user_id)
 
 		while let Some(Ok(message)) = receiver.next().await {
 			if let Message::Text(text) = message {
@@ -54,6 +62,15 @@ pub async fn handle_socket(socket: WebSocket, State(app_state): State<AppState>)
 					}
 				};
 
+				// Anything the user sends counts as activity, which brings them back from idle
+				if let Some(curr_user_id) = &user_id {
+					if user_pool.touch(curr_user_id) {
+						if let Ok(mut db_conn) = db_pool.get() {
+							activity::broadcast_presence(&mut db_conn, &user_pool, curr_user_id);
+						}
+					}
+				}
+
 				// Operating according to the opcode
 				let response = match payload.op_code {
 					OpCode::CONNECT => handle_connect(&tx, payload.value, &db_pool, &user_pool),
@@ -64,13 +81,25 @@ pub async fn handle_socket(socket: WebSocket, State(app_state): State<AppState>)
 					OpCode::GET_LOBBY_MEMBERS => handle_get_lobby_members(payload.value, &lobby_pool),
 					OpCode::MESSAGE => handle_message(payload.value, &db_pool, &lobby_pool, &user_pool),
 					OpCode::GET_MESSAGES => handle_get_messages(payload.value, &lobby_pool),
-					OpCode::SET_MUSIC_STATE => handle_set_music_state(payload.value, &lobby_pool, &user_pool),
+					OpCode::SET_MUSIC_STATE => {
+						handle_set_music_state(payload.value, &db_pool, &lobby_pool, &user_pool, &recommender)
+					}
 					OpCode::SYNC_MUSIC => handle_sync_music(payload.value, &lobby_pool),
-					OpCode::SET_QUEUE => handle_set_queue(payload.value, &lobby_pool, &user_pool),
+					OpCode::SET_QUEUE => handle_set_queue(payload.value, &db_pool, &lobby_pool, &user_pool, &recommender),
 					OpCode::SYNC_QUEUE => handle_sync_queue(payload.value, &lobby_pool),
 					OpCode::REQUEST_MUSIC_PLAY => {
 						handle_request_music_play(payload.value, &lobby_pool, &user_pool, &db_pool)
 					}
+					OpCode::SET_AUTO_RADIO => {
+						handle_set_auto_radio(payload.value, &db_pool, &lobby_pool, &user_pool, &recommender)
+					}
+					OpCode::SUBSCRIBE_PLAYLIST => {
+						handle_subscribe_playlist(payload.value, &db_pool, &playlist_rooms, &user_pool)
+					}
+					OpCode::UNSUBSCRIBE_PLAYLIST => handle_unsubscribe_playlist(payload.value, &playlist_rooms, &user_pool),
+					OpCode::SET_PLAYLIST_PRESENCE => {
+						handle_set_playlist_presence(payload.value, &db_pool, &playlist_rooms, &user_pool)
+					}
 					_ => Err(format!("Invalid opcode: {:?}", payload.op_code)),
 				};
 
@@ -93,6 +122,19 @@ pub async fn handle_socket(socket: WebSocket, State(app_state): State<AppState>)
 							OpCode::LEAVE_LOBBY => {
 								curr_lobby_id = None;
 							}
+							OpCode::SUBSCRIBE_PLAYLIST => {
+								let subscription = (
+									soc_res.value.get("playlist_id").unwrap().as_str().unwrap().to_string(),
+									soc_res.value.get("user_id").unwrap().as_str().unwrap().to_string(),
+								);
+								if !subscribed_playlists.contains(&subscription) {
+									subscribed_playlists.push(subscription);
+								}
+							}
+							OpCode::UNSUBSCRIBE_PLAYLIST => {
+								let playlist_id = soc_res.value.get("playlist_id").unwrap().as_str().unwrap();
+								subscribed_playlists.retain(|(curr_playlist_id, _)| curr_playlist_id != playlist_id);
+							}
 							_ => (),
 						};
 
@@ -111,6 +153,22 @@ pub async fn handle_socket(socket: WebSocket, State(app_state): State<AppState>)
 			}
 		}
 
+		// Leaving the playlists the user had open, so that they don't show up as present anymore
+		for (playlist_id, subscriber_id) in subscribed_playlists {
+			if let Some(room) = playlist_rooms.unsubscribe(&playlist_id, &subscriber_id) {
+				playlist_rooms.broadcast_presence(&playlist_id, &room, &user_pool);
+			}
+		}
+
+		// The user is offline unless they connected again from somewhere else
+		if let Some(curr_user_id) = &user_id {
+			if user_pool.remove_conn(curr_user_id, &tx) {
+				if let Ok(mut db_conn) = db_pool.get() {
+					activity::broadcast_presence(&mut db_conn, &user_pool, curr_user_id);
+				}
+			}
+		}
+
 		// If the user suddenly disconnects, disconnect the user from the lobby
 		// if let Some(lobby_id) = curr_lobby_id {
 		// 	let payload = json!({
@@ -158,6 +216,9 @@ fn handle_connect(
 	};
 
 	user_pool.insert(&payload.user_id, tx);
+	if let Ok(mut db_conn) = db_pool.get() {
+		activity::broadcast_presence(&mut db_conn, user_pool, &payload.user_id);
+	}
 
 	Ok(response)
 }
@@ -178,15 +239,25 @@ fn handle_create_lobby(
 
 	let res = lobby_pool.create_lobby(&payload.host_id, db_pool)?;
 
+	// Getting db connection
+	let mut db_conn = db_pool.get().unwrap();
+
+	let lobby_id = res.get("lobby_id").unwrap().as_str().unwrap();
+	activity::share(
+		&mut db_conn,
+		user_pool,
+		&payload.host_id,
+		ActivityKind::HostingLobby,
+		lobby_id,
+		json!({ "lobby_id": lobby_id }),
+	);
+
 	let response = SocketResponse {
 		op_code: OpCode::OK,
 		r#for: OpCode::CREATE_LOBBY,
 		value: res,
 	};
 
-	// Getting db connection
-	let mut db_conn = db_pool.get().unwrap();
-
 	// Loading the friendship of the host
 	let friendships = user_friendship::table
 		.filter(user_friendship::user_id.eq(&payload.host_id))
@@ -204,7 +275,13 @@ fn handle_create_lobby(
 	for user_id in user_ids {
 		if friends.contains(&user_id) {
 			let conn = user_pool.get(&user_id).unwrap();
-			let ids = lobby_pool.get_ids_with_rel(user_id.clone(), db_pool);
+			let ids = match lobby_pool.get_ids_with_rel(user_id.clone(), db_pool) {
+				Ok(ids) => ids,
+				Err(err) => {
+					println!("Error {}:{}: Failed to get the lobby ids of {user_id}: {err}", file!(), line!());
+					continue;
+				}
+			};
 			let response = SocketResponse {
 				op_code: OpCode::OK,
 				r#for: OpCode::GET_LOBBY_IDS,
@@ -288,7 +365,13 @@ fn handle_leave_lobby(
 		for user_id in user_ids {
 			if friends.contains(&user_id) {
 				let conn = user_pool.get(&user_id).unwrap();
-				let ids = lobby_pool.get_ids_with_rel(user_id.clone(), db_pool);
+				let ids = match lobby_pool.get_ids_with_rel(user_id.clone(), db_pool) {
+					Ok(ids) => ids,
+					Err(err) => {
+						println!("Error {}:{}: Failed to get the lobby ids of {user_id}: {err}", file!(), line!());
+						continue;
+					}
+				};
 				let response = SocketResponse {
 					op_code: OpCode::OK,
 					r#for: OpCode::GET_LOBBY_IDS,
@@ -320,7 +403,7 @@ struct GetLobbyIdsPayload {
 
 fn handle_get_lobby_ids(value: Value, db_pool: &DatabasePool, lobby_pool: &LobbyPool) -> Result<SocketResponse, String> {
 	let payload: GetLobbyIdsPayload = serde_json::from_value(value).map_err(|x| x.to_string())?;
-	let ids = lobby_pool.get_ids_with_rel(payload.user_id, db_pool);
+	let ids = lobby_pool.get_ids_with_rel(payload.user_id, db_pool)?;
 	let response = SocketResponse {
 		op_code: OpCode::OK,
 		r#for: OpCode::GET_LOBBY_IDS,
@@ -442,8 +525,10 @@ struct SetMusicStatePayload {
 
 fn handle_set_music_state(
 	value: Value,
+	db_pool: &DatabasePool,
 	lobby_pool: &LobbyPool,
 	user_pool: &UserPool,
+	recommender: &Recommender,
 ) -> Result<SocketResponse, String> {
 	let payload: SetMusicStatePayload = serde_json::from_value(value).map_err(|x| x.to_string())?;
 
@@ -456,11 +541,35 @@ fn handle_set_music_state(
 		state: payload.state,
 	};
 
+	let previous_music_id = lobby_pool.get(&payload.lobby_id).map(|lobby| lobby.music.id);
 	lobby_pool.set_music_state(&payload.lobby_id, &payload.user_id, music)?;
 
 	let lobby = lobby_pool.get(&payload.lobby_id).unwrap();
 	let music = lobby.music;
 
+	// Pausing or seeking isn't a new track
+	if !music.id.is_empty() && previous_music_id.as_deref() != Some(music.id.as_str()) {
+		if let Ok(mut db_conn) = db_pool.get() {
+			let details = json!({
+				"music": {
+					"id": music.id,
+					"title": music.title,
+					"artist": music.artist,
+					"image_url": music.image_url,
+				},
+				"lobby_id": payload.lobby_id,
+			});
+			activity::share(
+				&mut db_conn,
+				user_pool,
+				&payload.user_id,
+				ActivityKind::NowPlaying,
+				&music.id,
+				details,
+			);
+		}
+	}
+
 	// Sending the sync request to every client in lobby
 	for client_id in lobby.clients {
 		if client_id == payload.user_id {
@@ -486,6 +595,10 @@ fn handle_set_music_state(
 		let _ = client_conn.send(Message::Text(response));
 	}
 
+	if let Err(err) = lobby_pool.top_up_radio(&payload.lobby_id, db_pool, recommender, user_pool) {
+		println!("[auto_radio]: {err}");
+	}
+
 	let response = SocketResponse {
 		op_code: OpCode::OK,
 		r#for: OpCode::SET_MUSIC_STATE,
@@ -532,7 +645,13 @@ struct SetQueuePayload {
 	pub queue: Vec<Music>,
 }
 
-fn handle_set_queue(value: Value, lobby_pool: &LobbyPool, user_pool: &UserPool) -> Result<SocketResponse, String> {
+fn handle_set_queue(
+	value: Value,
+	db_pool: &DatabasePool,
+	lobby_pool: &LobbyPool,
+	user_pool: &UserPool,
+	recommender: &Recommender,
+) -> Result<SocketResponse, String> {
 	let payload: SetQueuePayload = serde_json::from_value(value).map_err(|x| x.to_string())?;
 
 	lobby_pool.set_queue(&payload.lobby_id, payload.queue)?;
@@ -565,6 +684,10 @@ fn handle_set_queue(value: Value, lobby_pool: &LobbyPool, user_pool: &UserPool)
 		let _ = client_conn.send(Message::Text(response));
 	}
 
+	if let Err(err) = lobby_pool.top_up_radio(&payload.lobby_id, db_pool, recommender, user_pool) {
+		println!("[auto_radio]: {err}");
+	}
+
 	let response = SocketResponse {
 		op_code: OpCode::OK,
 		r#for: OpCode::SET_QUEUE,
@@ -621,3 +744,190 @@ fn handle_request_music_play(
 
 	Ok(response)
 }
+
+// :set_auto_radio
+#[derive(Serialize, Deserialize)]
+struct SetAutoRadioPayload {
+	pub lobby_id: String,
+	pub user_id: String,
+	pub enabled: bool,
+	pub seed_type: Option<String>, // music, artist, genre or playlist. Defaults to the music being played
+	pub seed: Option<String>,
+	pub artist_window: Option<usize>,
+}
+
+fn handle_set_auto_radio(
+	value: Value,
+	db_pool: &DatabasePool,
+	lobby_pool: &LobbyPool,
+	user_pool: &UserPool,
+	recommender: &Recommender,
+) -> Result<SocketResponse, String> {
+	let payload: SetAutoRadioPayload = serde_json::from_value(value).map_err(|x| x.to_string())?;
+
+	let lobby = match lobby_pool.get(&payload.lobby_id) {
+		Some(lobby) => lobby,
+		None => return Err(format!("Invalid lobby id: {}", payload.lobby_id)),
+	};
+
+	let station = if payload.enabled {
+		let seed = match (&payload.seed_type, &payload.seed) {
+			(Some(seed_type), Some(seed)) => match RadioSeed::parse(seed_type, seed) {
+				Some(seed) => seed,
+				None => return Err(format!("Invalid seed type: {}", seed_type)),
+			},
+			_ if !lobby.music.id.is_empty() => RadioSeed::Music(lobby.music.id.clone()),
+			_ => return Err("Nothing is playing to start the radio from, provide a seed".to_string()),
+		};
+		// Station is personalized for the host, so it can only be seeded with what the host can see
+		let mut db_conn = db_pool.get().map_err(|err| format!("Failed to get DB from pool: {err}"))?;
+		if !seed.is_visible_to(&mut db_conn, Some(&lobby.host_id)).map_err(|err| err.to_string())? {
+			return Err(format!("Playlist not found: {}", payload.seed.unwrap_or_default()));
+		}
+		Some(RadioStation::new(seed, None, payload.artist_window))
+	} else {
+		None
+	};
+
+	lobby_pool.set_auto_radio(&payload.lobby_id, &payload.user_id, station)?;
+	lobby_pool.top_up_radio(&payload.lobby_id, db_pool, recommender, user_pool)?;
+
+	// The lobby might have been closed in the meantime
+	let lobby = lobby_pool
+		.get(&payload.lobby_id)
+		.ok_or_else(|| format!("Invalid lobby id: {}", payload.lobby_id))?;
+	let response = SocketResponse {
+		op_code: OpCode::OK,
+		r#for: OpCode::SET_AUTO_RADIO,
+		value: serde_json::to_value(&lobby.auto_radio).unwrap(),
+	};
+
+	Ok(response)
+}
+
+// :subscribe_playlist
+#[derive(Serialize, Deserialize)]
+struct SubscribePlaylistPayload {
+	pub playlist_id: String,
+	pub user_id: String,
+}
+
+fn handle_subscribe_playlist(
+	value: Value,
+	db_pool: &DatabasePool,
+	playlist_rooms: &PlaylistRoomPool,
+	user_pool: &UserPool,
+) -> Result<SocketResponse, String> {
+	let payload: SubscribePlaylistPayload = serde_json::from_value(value).map_err(|x| x.to_string())?;
+
+	if !user_pool.exists(&payload.user_id) {
+		return Err(format!("{} has to connect before subscribing", payload.user_id));
+	}
+
+	// Anyone who can see the playlist can follow its changes
+	let mut db_conn = db_pool.get().map_err(|x| x.to_string())?;
+	let playlist = playlists::table
+		.filter(playlists::playlist_id.eq(&payload.playlist_id))
+		.filter(playlists::deleted_at.is_null())
+		.first::<Playlist>(&mut db_conn)
+		.optional()
+		.map_err(|x| x.to_string())?
+		.ok_or_else(|| format!("Invalid playlist id: {}", payload.playlist_id))?;
+	if !playlist_access::can_read(&mut db_conn, &playlist, Some(&payload.user_id), None).map_err(|x| x.to_string())? {
+		return Err(format!("{} can't view playlist {}", payload.user_id, payload.playlist_id));
+	}
+
+	let room = playlist_rooms.subscribe(&payload.playlist_id, &payload.user_id);
+	playlist_rooms.broadcast_presence(&payload.playlist_id, &room, user_pool);
+
+	let response = SocketResponse {
+		op_code: OpCode::OK,
+		r#for: OpCode::SUBSCRIBE_PLAYLIST,
+		value: json!({
+			"playlist_id": payload.playlist_id,
+			"user_id": payload.user_id,
+			"revision": room.revision,
+			"presence": room.presence(),
+		}),
+	};
+
+	Ok(response)
+}
+
+// :unsubscribe_playlist
+#[derive(Serialize, Deserialize)]
+struct UnsubscribePlaylistPayload {
+	pub playlist_id: String,
+	pub user_id: String,
+}
+
+fn handle_unsubscribe_playlist(
+	value: Value,
+	playlist_rooms: &PlaylistRoomPool,
+	user_pool: &UserPool,
+) -> Result<SocketResponse, String> {
+	let payload: UnsubscribePlaylistPayload = serde_json::from_value(value).map_err(|x| x.to_string())?;
+
+	let room = match playlist_rooms.unsubscribe(&payload.playlist_id, &payload.user_id) {
+		Some(room) => room,
+		None => {
+			return Err(format!(
+				"{} is not subscribed to playlist {}",
+				payload.user_id, payload.playlist_id
+			))
+		}
+	};
+	playlist_rooms.broadcast_presence(&payload.playlist_id, &room, user_pool);
+
+	let response = SocketResponse {
+		op_code: OpCode::OK,
+		r#for: OpCode::UNSUBSCRIBE_PLAYLIST,
+		value: json!({
+			"playlist_id": payload.playlist_id,
+			"user_id": payload.user_id,
+		}),
+	};
+
+	Ok(response)
+}
+
+// :set_playlist_presence
+#[derive(Serialize, Deserialize)]
+struct SetPlaylistPresencePayload {
+	pub playlist_id: String,
+	pub user_id: String,
+	pub status: PresenceStatus, // viewing or editing
+}
+
+fn handle_set_playlist_presence(
+	value: Value,
+	db_pool: &DatabasePool,
+	playlist_rooms: &PlaylistRoomPool,
+	user_pool: &UserPool,
+) -> Result<SocketResponse, String> {
+	let payload: SetPlaylistPresencePayload = serde_json::from_value(value).map_err(|x| x.to_string())?;
+
+	// Only those who can change the playlist show up as editing
+	if payload.status == PresenceStatus::Editing {
+		let mut db_conn = db_pool.get().map_err(|x| x.to_string())?;
+		let can_edit = playlist_access::has_role(&mut db_conn, &payload.playlist_id, &payload.user_id, Role::Editor)
+			.map_err(|x| x.to_string())?;
+		if !can_edit {
+			return Err(format!("{} can't edit playlist {}", payload.user_id, payload.playlist_id));
+		}
+	}
+
+	let room = playlist_rooms.set_status(&payload.playlist_id, &payload.user_id, payload.status)?;
+	playlist_rooms.broadcast_presence(&payload.playlist_id, &room, user_pool);
+
+	let response = SocketResponse {
+		op_code: OpCode::OK,
+		r#for: OpCode::SET_PLAYLIST_PRESENCE,
+		value: json!({
+			"playlist_id": payload.playlist_id,
+			"presence": room.presence(),
+		}),
+	};
+
+	Ok(response)
+}
@@ -0,0 +1,88 @@
+use crate::core::{
+	app_state::AppState,
+	profile::{self, ListeningSection},
+	stats::{self, StatsQueryParams, StatsRange},
+};
+use crate::utils::jwt;
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use axum_extra::extract::cookie::CookieJar;
+
+// /stats/compare?user_id=123&range=7d&start_index=0&page_length=10
+// The user and their friends ranked by listening time, the user is included to see where they stand
+pub async fn compare_friends(
+	State(app_state): State<AppState>,
+	jar: CookieJar,
+	Query(params): Query<StatsQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let range = match StatsRange::from_params(&params) {
+		Ok(range) => range,
+		Err(err) => {
+			return Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap();
+		}
+	};
+
+	match profile::shows(&mut db_conn, &params.user_id, jwt::caller_id(&jar).as_deref(), ListeningSection::Stats) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(format!("{} keeps their listening stats private", params.user_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	}
+
+	let comparison = match stats::compare_with_friends(
+		&mut db_conn,
+		&params.user_id,
+		&range,
+		params.start_index,
+		params.page_length,
+	) {
+		Ok(comparison) => comparison,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	if comparison.is_empty() {
+		return Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(format!("Invalid user_id: {}", params.user_id))
+			.unwrap();
+	}
+
+	match serde_json::to_string(&comparison) {
+		Ok(json) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to serialize response: {err}"))
+			.unwrap(),
+	}
+}
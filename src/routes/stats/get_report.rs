@@ -0,0 +1,152 @@
+use crate::{
+	config::REPORT_CARD_STORAGE,
+	core::{
+		app_state::AppState,
+		profile::{self, ListeningSection},
+		report,
+	},
+	utils::{jwt, report_card, timestamp},
+};
+use axum::{
+	body::Body,
+	extract::{Path, Query, State},
+	http::{header, StatusCode},
+	response::{IntoResponse, Response},
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::FixedOffset;
+use diesel::result::Error as DieselError;
+use serde::Deserialize;
+use std::path::PathBuf;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+
+// /stats/report?user_id=123&period=2026
+// /stats/report?user_id=123&period=2026-03&utc_offset=345&limit=10
+#[derive(Debug, Deserialize)]
+pub struct ReportQueryParams {
+	pub user_id: String,
+	pub period: String,          // a year (2026) or a month (2026-03)
+	pub utc_offset: Option<i32>, // timezone of the user in minutes, defaults to UTC
+	pub limit: Option<usize>,    // entries in every top list, defaults to 5
+}
+
+pub async fn get_report(
+	State(app_state): State<AppState>,
+	jar: CookieJar,
+	Query(params): Query<ReportQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let bounds = match timestamp::parse_period(&params.period) {
+		Some(bounds) => bounds,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!(
+					"Invalid period: {}, expected a year (2026) or a month (2026-03)",
+					params.period
+				))
+				.unwrap();
+		}
+	};
+
+	let offset = match FixedOffset::east_opt(params.utc_offset.unwrap_or(0) * 60) {
+		Some(offset) => offset,
+		None => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(format!("Invalid utc_offset: {}", params.utc_offset.unwrap_or(0)))
+				.unwrap();
+		}
+	};
+
+	match profile::shows(&mut db_conn, &params.user_id, jwt::caller_id(&jar).as_deref(), ListeningSection::Stats) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(format!("{} keeps their listening stats private", params.user_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	}
+
+	let limit = params.limit.filter(|limit| *limit > 0).unwrap_or(5);
+	let mut report = match report::build_report(&mut db_conn, &params.user_id, &params.period, bounds, offset, limit) {
+		Ok(report) => report,
+		Err(DieselError::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid user_id: {}", params.user_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	// Rendering the shareable card next to the json, it is overwritten every time the report is requested
+	let filename = format!("{}_{}.png", report.user_id, report.period);
+	let path = PathBuf::from(REPORT_CARD_STORAGE).join(&filename);
+	match report_card::render(&report, &path) {
+		Ok(()) => report.card_url = Some(format!("/stats/report/card/{filename}")),
+		Err(err) => println!("[report_card]: {err}"),
+	}
+
+	match serde_json::to_string(&report) {
+		Ok(json) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to serialize response: {err}"))
+			.unwrap(),
+	}
+}
+
+pub async fn get_report_card(Path(filename): Path<String>) -> impl IntoResponse {
+	// Only the file name is taken so the path can't escape the storage directory
+	let filename = std::path::Path::new(&filename)
+		.file_name()
+		.map(|name| name.to_os_string())
+		.unwrap_or_default();
+	let path = PathBuf::from(REPORT_CARD_STORAGE).join(filename);
+
+	let file = match File::open(&path).await {
+		Ok(file) => file,
+		Err(_) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(Body::from("Report card not found"))
+				.unwrap();
+		}
+	};
+
+	let stream = ReaderStream::new(file);
+	let body = Body::from_stream(stream);
+
+	Response::builder()
+		.status(StatusCode::OK)
+		.header(header::CONTENT_TYPE, "image/png")
+		.body(body)
+		.unwrap()
+}
@@ -0,0 +1,75 @@
+use crate::core::{
+	app_state::AppState,
+	profile::{self, ListeningSection},
+	stats::{self, StatsQueryParams, StatsRange},
+};
+use crate::utils::jwt;
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use axum_extra::extract::cookie::CookieJar;
+
+// /stats/summary?user_id=123&range=30d
+// Total listening time, average session length and discovery rate (new tracks per week) in the range
+pub async fn get_summary(
+	State(app_state): State<AppState>,
+	jar: CookieJar,
+	Query(params): Query<StatsQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let range = match StatsRange::from_params(&params) {
+		Ok(range) => range,
+		Err(err) => {
+			return Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap();
+		}
+	};
+
+	match profile::shows(&mut db_conn, &params.user_id, jwt::caller_id(&jar).as_deref(), ListeningSection::Stats) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(format!("{} keeps their listening stats private", params.user_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	}
+
+	let summary = match stats::summary(&mut db_conn, &params.user_id, &range) {
+		Ok(summary) => summary,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	match serde_json::to_string(&summary) {
+		Ok(json) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to serialize response: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,121 @@
+use crate::core::{
+	app_state::AppState,
+	profile::{self, ListeningSection},
+	stats::{self, StatsDimension, StatsQueryParams, StatsRange},
+};
+use crate::utils::jwt;
+use axum::{
+	extract::{Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use axum_extra::extract::cookie::CookieJar;
+
+// /stats/top_artists?user_id=123&range=7d
+// /stats/top_albums?user_id=123&range=2026-03&start_index=0&page_length=10
+// /stats/top_genres?user_id=123&from=2026-01-01&to=2026-06-30
+pub async fn get_top_artists(
+	State(app_state): State<AppState>,
+	jar: CookieJar,
+	Query(params): Query<StatsQueryParams>,
+) -> Response<String> {
+	get_top_grouped(app_state, jar, params, StatsDimension::Artist, "artists")
+}
+
+pub async fn get_top_albums(
+	State(app_state): State<AppState>,
+	jar: CookieJar,
+	Query(params): Query<StatsQueryParams>,
+) -> Response<String> {
+	get_top_grouped(app_state, jar, params, StatsDimension::Album, "albums")
+}
+
+pub async fn get_top_genres(
+	State(app_state): State<AppState>,
+	jar: CookieJar,
+	Query(params): Query<StatsQueryParams>,
+) -> Response<String> {
+	get_top_grouped(app_state, jar, params, StatsDimension::Genre, "genres")
+}
+
+fn get_top_grouped(
+	app_state: AppState,
+	jar: CookieJar,
+	params: StatsQueryParams,
+	dimension: StatsDimension,
+	label: &str,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let range = match StatsRange::from_params(&params) {
+		Ok(range) => range,
+		Err(err) => {
+			return Response::builder().status(StatusCode::BAD_REQUEST).body(err).unwrap();
+		}
+	};
+
+	// Top artists can be hidden on their own, albums and genres go with the rest of the stats
+	let section = match dimension {
+		StatsDimension::Artist => ListeningSection::TopArtists,
+		_ => ListeningSection::Stats,
+	};
+	match profile::shows(&mut db_conn, &params.user_id, jwt::caller_id(&jar).as_deref(), section) {
+		Ok(true) => {}
+		Ok(false) => {
+			return Response::builder()
+				.status(StatusCode::FORBIDDEN)
+				.body(format!("{} keeps their listening stats private", params.user_id))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	}
+
+	let stats = match stats::top_grouped(
+		&mut db_conn,
+		&params.user_id,
+		&range,
+		dimension,
+		params.start_index,
+		params.page_length,
+	) {
+		Ok(stats) => stats,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	if stats.is_empty() {
+		return Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(format!("No top {label} found"))
+			.unwrap();
+	}
+
+	match serde_json::to_string(&stats) {
+		Ok(json) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(json)
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to serialize response: {err}"))
+			.unwrap(),
+	}
+}
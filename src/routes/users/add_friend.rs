@@ -1,14 +1,15 @@
 use crate::config::OpCode;
 use crate::core::app_state::AppState;
+use crate::core::friendship::{self, FriendError};
 use crate::lobic_db::db::*;
-use crate::lobic_db::models::{Notification, UserFriendship};
-use crate::routes::notify::notify;
-use crate::schema::user_friendship::dsl::*;
+use crate::lobic_db::models::Notification;
+use crate::routes::notify::notify_from;
 
 use axum::{extract::State, http::status::StatusCode, response::Response, Json};
-use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
+// Sends a friend request, they only become friends once it's accepted through /friend/request/respond
 #[derive(Serialize, Deserialize)]
 pub struct AddFriendPayload {
 	pub user_id: String,
@@ -37,79 +38,55 @@ pub async fn add_friend(State(app_state): State<AppState>, Json(payload): Json<A
 		}
 	};
 
-	// Querying the friendships
-	let query = user_friendship
-		.filter(user_id.eq(&payload.user_id))
-		.load::<UserFriendship>(&mut db_conn);
-
-	let friendships = match query {
-		Ok(val) => val,
-		Err(_) => {
+	let request = match friendship::send_request(&mut db_conn, &payload.user_id, &payload.friend_id) {
+		Ok(request) => request,
+		Err(FriendError::Database(err)) => {
+			let msg = format!("Failed to send friend request: {err}");
 			return Response::builder()
-				.status(StatusCode::BAD_REQUEST)
-				.body(format!("Something went wrong ({}: {})", file!(), line!()))
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
 				.unwrap();
 		}
-	};
-
-	// Checking if the intended one is already the user's friend
-	for friendship in friendships {
-		if friendship.friend_id == payload.friend_id {
-			let msg = format!(
-				"user with id: {} is already a friend of {}",
-				payload.friend_id, payload.user_id
-			);
-			return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::BAD_REQUEST)
+				.body(err.to_string())
+				.unwrap();
 		}
-	}
-
-	// Creating a new friendship
-	let new_friendship = UserFriendship {
-		user_id: payload.user_id.clone(),
-		friend_id: payload.friend_id.clone(),
 	};
 
-	diesel::insert_into(user_friendship)
-		.values(&new_friendship)
-		.execute(&mut db_conn)
-		.unwrap();
-
-	// Sending the notification only if the the targeted user is not a friend of ours (req sender)
-	{
-		// Querying the friendships
-		let query = user_friendship
-			.filter(user_id.eq(&payload.friend_id))
-			.load::<UserFriendship>(&mut db_conn);
-
-		let friendships = match query {
-			Ok(val) => val,
-			Err(_) => {
-				return Response::builder()
-					.status(StatusCode::BAD_REQUEST)
-					.body(format!("Something went wrong ({}: {})", file!(), line!()))
-					.unwrap();
-			}
-		};
-
-		// Checking if the intended one is already the user's friend
-		let mut is_friend = false;
-		for friendship in friendships {
-			if friendship.friend_id == payload.user_id {
-				is_friend = true;
-				break;
-			}
-		}
-
-		if !is_friend {
-			// Send notification to the friend
-			let notif = Notification::new(OpCode::ADD_FRIEND, payload.user_id.into());
-			notify(&payload.friend_id, notif, &app_state.db_pool, &app_state.user_pool);
-		}
+	// The friend had already sent a request, which got accepted instead
+	if request.status == friendship::ACCEPTED {
+		let value = json!({
+			"request_id": request.request_id,
+			"user_id": payload.user_id,
+			"accepted": true,
+		});
+		let notif = Notification::new(OpCode::FRIEND_REQUEST_RESPONSE, value);
+		notify_from(
+			&payload.user_id,
+			&payload.friend_id,
+			notif,
+			&app_state.db_pool,
+			&app_state.user_pool,
+		);
+	} else {
+		let value = json!({
+			"request_id": request.request_id,
+			"sender_id": request.sender_id,
+		});
+		let notif = Notification::new(OpCode::FRIEND_REQUEST, value);
+		notify_from(
+			&payload.user_id,
+			&payload.friend_id,
+			notif,
+			&app_state.db_pool,
+			&app_state.user_pool,
+		);
 	}
 
-	// Finish
 	Response::builder()
 		.status(StatusCode::OK)
-		.body("Sucessfully added friend".to_string())
+		.body(serde_json::to_string(&request).unwrap())
 		.unwrap()
 }
@@ -0,0 +1,51 @@
+use crate::core::app_state::AppState;
+use crate::core::friendship::{self, FriendError};
+use crate::lobic_db::db::*;
+
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::{Deserialize, Serialize};
+
+// Also ends the friendship and cancels pending friend requests between the two
+#[derive(Serialize, Deserialize)]
+pub struct BlockUserPayload {
+	pub user_id: String,
+	pub blocked_id: String,
+}
+
+pub async fn block_user(State(app_state): State<AppState>, Json(payload): Json<BlockUserPayload>) -> Response<String> {
+	if !user_exists(&payload.user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", payload.user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	if !user_exists(&payload.blocked_id, &app_state.db_pool) {
+		let msg = format!("Invalid blocked_id: {}", payload.blocked_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	match friendship::block(&mut db_conn, &payload.user_id, &payload.blocked_id) {
+		Ok(()) => Response::builder()
+			.status(StatusCode::OK)
+			.body(format!("Blocked {}", payload.blocked_id))
+			.unwrap(),
+		Err(err @ FriendError::SelfRequest) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to block user: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,42 @@
+use crate::core::app_state::AppState;
+use crate::core::friendship::{self, FriendError};
+
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct CancelFriendRequestPayload {
+	pub request_id: String,
+	pub user_id: String, // the sender
+}
+
+pub async fn cancel_friend_request(
+	State(app_state): State<AppState>,
+	Json(payload): Json<CancelFriendRequestPayload>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	match friendship::cancel(&mut db_conn, &payload.request_id, &payload.user_id) {
+		Ok(_) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Friend request cancelled".to_string())
+			.unwrap(),
+		Err(err @ FriendError::InvalidRequest(_)) => Response::builder()
+			.status(StatusCode::NOT_FOUND)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to cancel friend request: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,40 @@
+use crate::core::app_state::AppState;
+use crate::core::friendship;
+use crate::lobic_db::db::*;
+
+use axum::{
+	extract::{Path, State},
+	http::status::StatusCode,
+	response::Response,
+};
+use serde_json::json;
+
+// The users blocked by the user, not the ones who blocked them
+pub async fn get_blocked_users(State(app_state): State<AppState>, Path(user_id): Path<String>) -> Response<String> {
+	if !user_exists(&user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	match friendship::blocked_users(&mut db_conn, &user_id) {
+		Ok(blocks) => {
+			let response = json!({ "blocked": blocks }).to_string();
+			Response::builder().status(StatusCode::OK).body(response).unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to query blocked users: {err}"))
+			.unwrap(),
+	}
+}
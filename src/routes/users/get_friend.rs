@@ -1,7 +1,6 @@
 use crate::core::app_state::AppState;
+use crate::core::friendship;
 use crate::lobic_db::db::*;
-use crate::lobic_db::models::UserFriendship;
-use crate::schema::user_friendship;
 
 use serde_json::json;
 use axum::{
@@ -9,7 +8,6 @@ use axum::{
 	http::status::StatusCode,
 	response::Response,
 };
-use diesel::prelude::*;
 
 
 pub async fn get_friend(State(app_state): State<AppState>, Path(user_id): Path<String>) -> Response<String> {
@@ -32,12 +30,8 @@ pub async fn get_friend(State(app_state): State<AppState>, Path(user_id): Path<S
 		}
 	};
 
-	// Loading the friendship of the user
-	let query = user_friendship::table
-		.filter(user_friendship::user_id.eq(&user_id))
-		.load::<UserFriendship>(&mut db_conn);
-
-	let friendships = match query {
+	// Loading the friends of the user, friendships are mutual
+	let friends = match friendship::friends_of(&mut db_conn, &user_id) {
 		Ok(data) => data,
 		Err(_) => {
 			return Response::builder()
@@ -47,12 +41,6 @@ pub async fn get_friend(State(app_state): State<AppState>, Path(user_id): Path<S
 		}
 	};
 
-	// Collecting all the friends ids
-	let friends: Vec<String> = friendships
-		.iter()
-		.map(|f| f.friend_id.clone())
-		.collect();
-
 	let response = json!({
 		"friends": friends
 	}).to_string();
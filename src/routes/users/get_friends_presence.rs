@@ -0,0 +1,43 @@
+use crate::core::activity;
+use crate::core::app_state::AppState;
+use crate::lobic_db::db::*;
+
+use axum::{
+	extract::{Path, State},
+	http::status::StatusCode,
+	response::Response,
+};
+use serde_json::json;
+
+// Online, idle or offline for every friend, changes are sent over the socket as they happen
+pub async fn get_friends_presence(
+	State(app_state): State<AppState>,
+	Path(user_id): Path<String>,
+) -> Response<String> {
+	if !user_exists(&user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	match activity::friends_presence(&mut db_conn, &app_state.user_pool, &user_id) {
+		Ok(presence) => {
+			let response = json!({ "presence": presence }).to_string();
+			Response::builder().status(StatusCode::OK).body(response).unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to query friends: {err}"))
+			.unwrap(),
+	}
+}
@@ -0,0 +1,38 @@
+use crate::core::activity;
+use crate::core::app_state::AppState;
+use crate::lobic_db::db::*;
+
+use axum::{
+	extract::{Path, State},
+	http::status::StatusCode,
+	response::Response,
+};
+
+pub async fn get_privacy(State(app_state): State<AppState>, Path(user_id): Path<String>) -> Response<String> {
+	if !user_exists(&user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	match activity::privacy_of(&mut db_conn, &user_id) {
+		Ok(privacy) => Response::builder()
+			.status(StatusCode::OK)
+			.body(serde_json::to_string(&privacy).unwrap())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to query privacy settings: {err}"))
+			.unwrap(),
+	}
+}
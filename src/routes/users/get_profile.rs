@@ -0,0 +1,85 @@
+use crate::core::app_state::AppState;
+use crate::core::{friendship, profile};
+use crate::lobic_db::models::User;
+use crate::schema::users;
+use crate::utils::jwt;
+
+use axum::{
+	extract::{Path, Query, State},
+	http::{header, StatusCode},
+	response::Response,
+};
+use axum_extra::extract::cookie::CookieJar;
+use diesel::prelude::*;
+use serde::Deserialize;
+
+// /user/123/profile?viewer_id=456
+#[derive(Debug, Deserialize)]
+pub struct ProfileQueryParams {
+	pub viewer_id: Option<String>, // for the mutual friends, users blocked either way don't see each other's profile
+}
+
+// The user themselves, as told by their access token, also gets their email and the stats they hide from others
+pub async fn get_profile(
+	State(app_state): State<AppState>,
+	jar: CookieJar,
+	Path(user_id): Path<String>,
+	Query(params): Query<ProfileQueryParams>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	let user = match users::table.filter(users::user_id.eq(&user_id)).first::<User>(&mut db_conn) {
+		Ok(user) => user,
+		Err(diesel::result::Error::NotFound) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(format!("Invalid user_id: {user_id}"))
+				.unwrap();
+		}
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Database error: {err}"))
+				.unwrap();
+		}
+	};
+
+	if let Some(viewer_id) = &params.viewer_id {
+		match friendship::is_blocked(&mut db_conn, viewer_id, &user_id) {
+			Ok(false) => {}
+			Ok(true) => {
+				return Response::builder()
+					.status(StatusCode::NOT_FOUND)
+					.body(format!("Invalid user_id: {user_id}"))
+					.unwrap();
+			}
+			Err(err) => {
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(format!("Failed to check blocked users: {err}"))
+					.unwrap();
+			}
+		}
+	}
+
+	let is_self = jwt::caller_id(&jar).as_deref() == Some(user_id.as_str());
+	match profile::view(&mut db_conn, user, params.viewer_id.as_deref(), is_self) {
+		Ok(response) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&response).unwrap())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to load profile: {err}"))
+			.unwrap(),
+	}
+}
@@ -1,12 +1,15 @@
 use crate::core::app_state::AppState;
+use crate::core::profile;
 use crate::lobic_db::models::User;
 use crate::schema::users;
+use crate::utils::jwt;
 
 use axum::{
 	extract::{Query, State},
 	http::status::StatusCode,
 	response::Response,
 };
+use axum_extra::extract::cookie::CookieJar;
 use diesel::prelude::*;
 use serde::Deserialize;
 use serde_json::json;
@@ -17,8 +20,10 @@ pub struct GetUserDataQuery {
 	pub email: Option<String>,
 }
 
+// The email is only there for the user themselves, as told by their access token
 pub async fn get_user_data(
 	State(app_state): State<AppState>,
+	jar: CookieJar,
 	Query(params): Query<GetUserDataQuery>,
 ) -> Response<String> {
 	let mut db_conn = match app_state.db_pool.get() {
@@ -48,12 +53,18 @@ pub async fn get_user_data(
 
 	match query {
 		Ok(user) => {
-			let user_data = json!({
+			let display_name = profile::profile_of(&mut db_conn, &user.user_id)
+				.ok()
+				.and_then(|profile| profile.display_name);
+			let mut user_data = json!({
 				"id": user.user_id.clone(),
 				"username": user.username,
-				"email": user.email,
-			})
-			.to_string();
+				"display_name": display_name,
+			});
+			if jwt::caller_id(&jar).as_deref() == Some(user.user_id.as_str()) {
+				user_data["email"] = json!(user.email);
+			}
+			let user_data = user_data.to_string();
 			Response::builder().status(StatusCode::OK).body(user_data).unwrap()
 		}
 		Err(err) => Response::builder()
@@ -1,10 +1,8 @@
 use crate::core::app_state::AppState;
+use crate::core::friendship::{self, FriendError};
 use crate::lobic_db::db::*;
-use crate::lobic_db::models::UserFriendship;
-use crate::schema::user_friendship::dsl::*;
 
 use axum::{extract::State, http::status::StatusCode, response::Response, Json};
-use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -38,36 +36,20 @@ pub async fn remove_friend(
 		}
 	};
 
-	// Querying the friendships
-	let query = user_friendship
-		.filter(user_id.eq(&payload.user_id))
-		.load::<UserFriendship>(&mut db_conn);
-
-	let friendships = match query {
-		Ok(val) => val,
-		Err(_) => {
-			return Response::builder()
-				.status(StatusCode::BAD_REQUEST)
-				.body(format!("Something went wrong ({}: {})", file!(), line!()))
-				.unwrap();
-		}
-	};
-
-	// Deleting the friendship from db if the relation exists
-	for friendship in friendships {
-		if friendship.friend_id == payload.friend_id {
-			diesel::delete(user_friendship.filter(friend_id.eq(&payload.friend_id)))
-				.execute(&mut db_conn)
-				.unwrap();
-
-			return Response::builder()
-				.status(StatusCode::OK)
-				.body("Sucessfully removed friend".to_string())
-				.unwrap();
+	// The friendship is removed for both of them
+	match friendship::unfriend(&mut db_conn, &payload.user_id, &payload.friend_id) {
+		Ok(()) => Response::builder()
+			.status(StatusCode::OK)
+			.body("Sucessfully removed friend".to_string())
+			.unwrap(),
+		Err(FriendError::NotFriend(_)) => {
+			// No relation found
+			let msg = format!("{} is not a friend of {}", payload.friend_id, payload.user_id);
+			Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap()
 		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to remove friend: {err}"))
+			.unwrap(),
 	}
-
-	// No relation found
-	let msg = format!("{} is not a friend of {}", payload.friend_id, payload.user_id);
-	Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap()
 }
@@ -0,0 +1,73 @@
+use crate::config::OpCode;
+use crate::core::app_state::AppState;
+use crate::core::friendship::{self, FriendError};
+use crate::lobic_db::models::Notification;
+use crate::routes::notify::notify_from;
+
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Serialize, Deserialize)]
+pub struct RespondFriendRequestPayload {
+	pub request_id: String,
+	pub user_id: String, // the receiver
+	pub accept: bool,
+}
+
+pub async fn respond_friend_request(
+	State(app_state): State<AppState>,
+	Json(payload): Json<RespondFriendRequestPayload>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	let request = match friendship::respond(&mut db_conn, &payload.request_id, &payload.user_id, payload.accept) {
+		Ok(request) => request,
+		Err(err @ FriendError::InvalidRequest(_)) => {
+			return Response::builder()
+				.status(StatusCode::NOT_FOUND)
+				.body(err.to_string())
+				.unwrap();
+		}
+		Err(err) => {
+			let msg = format!("Failed to respond to friend request: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	let value = json!({
+		"request_id": request.request_id,
+		"user_id": request.receiver_id,
+		"accepted": payload.accept,
+	});
+	let notif = Notification::new(OpCode::FRIEND_REQUEST_RESPONSE, value);
+	notify_from(
+		&request.receiver_id,
+		&request.sender_id,
+		notif,
+		&app_state.db_pool,
+		&app_state.user_pool,
+	);
+
+	let msg = if payload.accept {
+		"Friend request accepted"
+	} else {
+		"Friend request declined"
+	};
+	Response::builder()
+		.status(StatusCode::OK)
+		.body(msg.to_string())
+		.unwrap()
+}
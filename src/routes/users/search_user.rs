@@ -1,4 +1,6 @@
 use crate::core::app_state::AppState;
+use crate::core::friendship;
+use crate::core::profile;
 use crate::lobic_db::models::User;
 use crate::schema::users::dsl::*;
 
@@ -15,7 +17,7 @@ use serde_json::json;
 pub struct SearchUserResponse {
 	pub id: String,
 	pub username: String,
-	pub email: String,
+	pub display_name: Option<String>,
 	pub pfp: String,
 }
 
@@ -23,6 +25,7 @@ pub struct SearchUserResponse {
 pub struct SearchUserQuery {
 	pub search_string: String,
 	pub max_results: i64,
+	pub user_id: Option<String>, // the user searching, users blocked either way are left out
 }
 
 pub async fn search_user(State(app_state): State<AppState>, Query(params): Query<SearchUserQuery>) -> Response<String> {
@@ -37,10 +40,32 @@ pub async fn search_user(State(app_state): State<AppState>, Query(params): Query
 		}
 	};
 
-	// Searching in db
+	let hidden = match &params.user_id {
+		Some(searcher_id) => match friendship::hidden_from(&mut db_conn, searcher_id) {
+			Ok(hidden) => hidden,
+			Err(err) => {
+				let msg = format!("Failed to query blocked users: {}", err);
+				return Response::builder()
+					.status(StatusCode::INTERNAL_SERVER_ERROR)
+					.body(msg)
+					.unwrap();
+			}
+		},
+		None => Default::default(),
+	};
+
+	// Searching in db. Users can be found by their whole email address, which doesn't tell anything about the others,
+	// so the wildcards of LIKE are escaped there
 	let search_query = format!("%{}%", params.search_string.to_lowercase());
+	let email_query = params
+		.search_string
+		.trim()
+		.replace('\\', "\\\\")
+		.replace('%', "\\%")
+		.replace('_', "\\_");
 	let query = users
-		.filter(username.like(&search_query).or(email.like(&search_query)))
+		.filter(username.like(&search_query).or(email.like(&email_query).escape('\\')))
+		.filter(user_id.ne_all(hidden))
 		.limit(params.max_results)
 		.load::<User>(&mut db_conn);
 
@@ -55,6 +80,18 @@ pub async fn search_user(State(app_state): State<AppState>, Query(params): Query
 		}
 	};
 
+	let ids: Vec<String> = matches.iter().map(|entry| entry.user_id.clone()).collect();
+	let mut names = match profile::display_names(&mut db_conn, &ids) {
+		Ok(names) => names,
+		Err(err) => {
+			let msg = format!("Failed to query profiles: {}", err);
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
 	// Mapping the results into a reponse structure
 	let results: Vec<SearchUserResponse> = matches
 		.into_iter()
@@ -62,7 +99,7 @@ pub async fn search_user(State(app_state): State<AppState>, Query(params): Query
 			SearchUserResponse {
 				id: entry.user_id.clone(),
 				username: entry.username,
-				email: entry.email,
+				display_name: names.remove(&entry.user_id),
 				pfp: entry.user_id,
 			}
 		})
@@ -0,0 +1,42 @@
+use crate::core::app_state::AppState;
+use crate::core::friendship;
+
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct UnblockUserPayload {
+	pub user_id: String,
+	pub blocked_id: String,
+}
+
+pub async fn unblock_user(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UnblockUserPayload>,
+) -> Response<String> {
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	match friendship::unblock(&mut db_conn, &payload.user_id, &payload.blocked_id) {
+		Ok(true) => Response::builder()
+			.status(StatusCode::OK)
+			.body(format!("Unblocked {}", payload.blocked_id))
+			.unwrap(),
+		Ok(false) => {
+			let msg = format!("{} is not blocked by {}", payload.blocked_id, payload.user_id);
+			Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to unblock user: {err}"))
+			.unwrap(),
+	}
+}
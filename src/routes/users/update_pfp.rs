@@ -1,6 +1,14 @@
 use crate::config::USER_PFP_STORAGE;
+use crate::core::app_state::AppState;
+use crate::core::profile;
+use crate::lobic_db::db::*;
 
-use axum::{body::Bytes, extract::Query, http::StatusCode, response::Response};
+use axum::{
+	body::Bytes,
+	extract::{Query, State},
+	http::StatusCode,
+	response::Response,
+};
 use serde::Deserialize;
 use std::fs;
 use std::path::Path;
@@ -11,7 +19,11 @@ pub struct UserUuid {
 	user_uuid: String,
 }
 
-pub async fn update_pfp(Query(user_uuid): Query<UserUuid>, body: Bytes) -> Response<String> {
+pub async fn update_pfp(
+	State(app_state): State<AppState>,
+	Query(user_uuid): Query<UserUuid>,
+	body: Bytes,
+) -> Response<String> {
 	let user_uuid = match Uuid::parse_str(&user_uuid.user_uuid) {
 		Ok(uuid) => uuid,
 		Err(_) => {
@@ -30,7 +42,8 @@ pub async fn update_pfp(Query(user_uuid): Query<UserUuid>, body: Bytes) -> Respo
 			.unwrap();
 	}
 
-	let image_path = storage_path.join(format!("{}.png", user_uuid));
+	let avatar = format!("{}.png", user_uuid);
+	let image_path = storage_path.join(&avatar);
 	if let Err(err) = fs::write(&image_path, body) {
 		return Response::builder()
 			.status(StatusCode::INTERNAL_SERVER_ERROR)
@@ -38,6 +51,18 @@ pub async fn update_pfp(Query(user_uuid): Query<UserUuid>, body: Bytes) -> Respo
 			.unwrap();
 	}
 
+	// Profiles say whether the user has a picture
+	let user_id = user_uuid.to_string();
+	if user_exists(&user_id, &app_state.db_pool) {
+		let updated = match app_state.db_pool.get() {
+			Ok(mut db_conn) => profile::set_avatar(&mut db_conn, &user_id, &avatar).map_err(|err| err.to_string()),
+			Err(err) => Err(err.to_string()),
+		};
+		if let Err(err) = updated {
+			println!("Error {}:{}: Failed to update the profile avatar: {err}", file!(), line!());
+		}
+	}
+
 	Response::builder()
 		.status(StatusCode::OK)
 		.body("Profile picture updated successfully".to_string())
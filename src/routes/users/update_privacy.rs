@@ -0,0 +1,57 @@
+use crate::core::activity;
+use crate::core::app_state::AppState;
+use crate::lobic_db::db::*;
+
+use axum::{extract::State, http::status::StatusCode, response::Response, Json};
+use serde::{Deserialize, Serialize};
+
+// The settings that are left out stay as they are
+#[derive(Serialize, Deserialize)]
+pub struct UpdatePrivacyPayload {
+	pub user_id: String,
+	pub share_activity: Option<bool>, // in the feed of friends
+	pub share_presence: Option<bool>, // online, idle or offline
+}
+
+pub async fn update_privacy(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UpdatePrivacyPayload>,
+) -> Response<String> {
+	if !user_exists(&payload.user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", payload.user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			let msg = format!("Failed to get DB from pool: {err}");
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(msg)
+				.unwrap();
+		}
+	};
+
+	match activity::set_privacy(
+		&mut db_conn,
+		&payload.user_id,
+		payload.share_activity,
+		payload.share_presence,
+	) {
+		Ok(privacy) => {
+			// Friends see the user go offline, or come back
+			if payload.share_presence.is_some() {
+				activity::broadcast_presence(&mut db_conn, &app_state.user_pool, &payload.user_id);
+			}
+			Response::builder()
+				.status(StatusCode::OK)
+				.body(serde_json::to_string(&privacy).unwrap())
+				.unwrap()
+		}
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to update privacy settings: {err}"))
+			.unwrap(),
+	}
+}
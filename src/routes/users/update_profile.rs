@@ -0,0 +1,55 @@
+use crate::core::app_state::AppState;
+use crate::core::profile::{self, ProfileError, ProfileUpdate};
+use crate::lobic_db::db::*;
+
+use axum::{
+	extract::State,
+	http::{header, status::StatusCode},
+	response::Response,
+	Json,
+};
+use serde::Deserialize;
+
+// {"user_id": "123", "bio": "...", "country": "NP", "show_stats": false}, see ProfileUpdate
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfilePayload {
+	pub user_id: String,
+	#[serde(flatten)]
+	pub changes: ProfileUpdate,
+}
+
+pub async fn update_profile(
+	State(app_state): State<AppState>,
+	Json(payload): Json<UpdateProfilePayload>,
+) -> Response<String> {
+	if !user_exists(&payload.user_id, &app_state.db_pool) {
+		let msg = format!("Invalid user_id: {}", payload.user_id);
+		return Response::builder().status(StatusCode::BAD_REQUEST).body(msg).unwrap();
+	}
+
+	let mut db_conn = match app_state.db_pool.get() {
+		Ok(conn) => conn,
+		Err(err) => {
+			return Response::builder()
+				.status(StatusCode::INTERNAL_SERVER_ERROR)
+				.body(format!("Failed to get DB from pool: {err}"))
+				.unwrap();
+		}
+	};
+
+	match profile::update(&mut db_conn, &payload.user_id, payload.changes) {
+		Ok(updated) => Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(serde_json::to_string(&updated).unwrap())
+			.unwrap(),
+		Err(err @ (ProfileError::TooLong(..) | ProfileError::InvalidCountry(_))) => Response::builder()
+			.status(StatusCode::BAD_REQUEST)
+			.body(err.to_string())
+			.unwrap(),
+		Err(err) => Response::builder()
+			.status(StatusCode::INTERNAL_SERVER_ERROR)
+			.body(format!("Failed to update profile: {err}"))
+			.unwrap(),
+	}
+}
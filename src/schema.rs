@@ -1,5 +1,59 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    activity_events (event_id) {
+        event_id -> Text,
+        user_id -> Text,
+        kind -> Text,
+        subject_id -> Text,
+        details -> Text,
+        created_at -> Text,
+    }
+}
+
+diesel::table! {
+    conversation_members (conversation_id, user_id) {
+        conversation_id -> Text,
+        user_id -> Text,
+        joined_at -> Text,
+        last_read_at -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    conversations (conversation_id) {
+        conversation_id -> Text,
+        is_group -> Bool,
+        name -> Nullable<Text>,
+        created_by -> Text,
+        created_at -> Text,
+        last_message_at -> Text,
+    }
+}
+
+diesel::table! {
+    direct_messages (message_id) {
+        message_id -> Text,
+        conversation_id -> Text,
+        sender_id -> Text,
+        body -> Text,
+        attachment_kind -> Nullable<Text>,
+        attachment_id -> Nullable<Text>,
+        sent_at -> Text,
+    }
+}
+
+diesel::table! {
+    friend_requests (request_id) {
+        request_id -> Text,
+        sender_id -> Text,
+        receiver_id -> Text,
+        status -> Text,
+        created_at -> Text,
+        responded_at -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     liked_songs (user_id, music_id) {
         user_id -> Text,
@@ -8,6 +62,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    listens (listen_id) {
+        listen_id -> Text,
+        user_id -> Text,
+        music_id -> Text,
+        listened_at -> Text,
+        listened_duration -> BigInt,
+        source_type -> Text,
+        source_id -> Nullable<Text>,
+        skipped -> Bool,
+        completed -> Bool,
+    }
+}
+
 diesel::table! {
     music (music_id) {
         music_id -> Text,
@@ -17,6 +85,13 @@ diesel::table! {
         genre -> Text,
         times_played -> Integer,
         duration -> BigInt,
+        year -> Nullable<Integer>,
+        bpm -> Nullable<Double>,
+        musical_key -> Nullable<Text>,
+        energy -> Nullable<Double>,
+        danceability -> Nullable<Double>,
+        analyzed_at -> Nullable<Text>,
+        search_text -> Text,
     }
 }
 
@@ -38,19 +113,104 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    playlist_blends (playlist_id) {
+        playlist_id -> Text,
+        refreshed_at -> Text,
+    }
+}
+
+diesel::table! {
+    playlist_folders (folder_id) {
+        folder_id -> Text,
+        user_id -> Text,
+        parent_id -> Nullable<Text>,
+        folder_name -> Text,
+        created_at -> Text,
+    }
+}
+
+diesel::table! {
+    playlist_forks (playlist_id) {
+        playlist_id -> Text,
+        source_id -> Text,
+        source_snapshot -> Text,
+        synced_at -> Text,
+    }
+}
+
+diesel::table! {
+    playlist_history (event_id) {
+        event_id -> Text,
+        playlist_id -> Text,
+        actor_id -> Text,
+        kind -> Text,
+        change -> Text,
+        created_at -> Text,
+        undone_at -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    playlist_invitations (invitation_id) {
+        invitation_id -> Text,
+        playlist_id -> Text,
+        inviter_id -> Text,
+        invitee_id -> Text,
+        role -> Text,
+        created_at -> Text,
+    }
+}
+
+diesel::table! {
+    playlist_follows (user_id, playlist_id) {
+        user_id -> Text,
+        playlist_id -> Text,
+        followed_at -> Text,
+    }
+}
+
+diesel::table! {
+    playlist_library_entries (user_id, playlist_id) {
+        user_id -> Text,
+        playlist_id -> Text,
+        folder_id -> Nullable<Text>,
+        pinned_at -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    playlist_share_tokens (token) {
+        token -> Text,
+        playlist_id -> Text,
+        created_by -> Text,
+        created_at -> Text,
+    }
+}
+
 diesel::table! {
     playlist_shares (playlist_id, contributor_user_id) {
         playlist_id -> Text,
         contributor_user_id -> Text,
+        role -> Text,
     }
 }
 
 diesel::table! {
-    playlist_songs (playlist_id, music_id) {
+    playlist_songs (item_id) {
+        item_id -> Text,
         playlist_id -> Text,
         music_id -> Text,
         song_adder_id -> Text,
         song_added_date_time -> Text,
+        position -> Double,
+    }
+}
+
+diesel::table! {
+    playlist_tags (playlist_id, tag) {
+        playlist_id -> Text,
+        tag -> Text,
     }
 }
 
@@ -62,6 +222,39 @@ diesel::table! {
         creation_date_time -> Text,
         last_updated_date_time -> Text,
         is_playlist_combined -> Bool,
+        smart_rules -> Nullable<Text>,
+        visibility -> Text,
+        deleted_at -> Nullable<Text>,
+        description -> Text,
+    }
+}
+
+diesel::table! {
+    share_recipients (share_id, recipient_id) {
+        share_id -> Text,
+        recipient_id -> Text,
+        opened_at -> Nullable<Text>,
+        reaction -> Nullable<Text>,
+        reacted_at -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    shares (share_id) {
+        share_id -> Text,
+        sender_id -> Text,
+        kind -> Text,
+        subject_id -> Text,
+        message -> Nullable<Text>,
+        created_at -> Text,
+    }
+}
+
+diesel::table! {
+    user_blocks (user_id, blocked_id) {
+        user_id -> Text,
+        blocked_id -> Text,
+        created_at -> Text,
     }
 }
 
@@ -72,6 +265,28 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_privacy (user_id) {
+        user_id -> Text,
+        share_activity -> Bool,
+        share_presence -> Bool,
+    }
+}
+
+diesel::table! {
+    user_profiles (user_id) {
+        user_id -> Text,
+        display_name -> Nullable<Text>,
+        bio -> Text,
+        avatar -> Nullable<Text>,
+        pronouns -> Nullable<Text>,
+        country -> Nullable<Text>,
+        joined_at -> Text,
+        show_top_artists -> Bool,
+        show_stats -> Bool,
+    }
+}
+
 diesel::table! {
     users (user_id) {
         user_id -> Text,
@@ -85,26 +300,72 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(activity_events -> users (user_id));
+diesel::joinable!(conversation_members -> conversations (conversation_id));
+diesel::joinable!(conversation_members -> users (user_id));
+diesel::joinable!(conversations -> users (created_by));
+diesel::joinable!(direct_messages -> conversations (conversation_id));
+diesel::joinable!(direct_messages -> users (sender_id));
 diesel::joinable!(liked_songs -> music (music_id));
 diesel::joinable!(liked_songs -> users (user_id));
+diesel::joinable!(listens -> music (music_id));
+diesel::joinable!(listens -> users (user_id));
 diesel::joinable!(notifications -> users (user_id));
 diesel::joinable!(play_log -> music (music_id));
 diesel::joinable!(play_log -> users (user_id));
+diesel::joinable!(playlist_follows -> playlists (playlist_id));
+diesel::joinable!(playlist_follows -> users (user_id));
+diesel::joinable!(playlist_blends -> playlists (playlist_id));
+diesel::joinable!(playlist_folders -> users (user_id));
+diesel::joinable!(playlist_history -> playlists (playlist_id));
+diesel::joinable!(playlist_history -> users (actor_id));
+diesel::joinable!(playlist_invitations -> playlists (playlist_id));
+diesel::joinable!(playlist_library_entries -> playlist_folders (folder_id));
+diesel::joinable!(playlist_library_entries -> playlists (playlist_id));
+diesel::joinable!(playlist_library_entries -> users (user_id));
+diesel::joinable!(playlist_share_tokens -> playlists (playlist_id));
+diesel::joinable!(playlist_share_tokens -> users (created_by));
 diesel::joinable!(playlist_shares -> playlists (playlist_id));
 diesel::joinable!(playlist_shares -> users (contributor_user_id));
 diesel::joinable!(playlist_songs -> music (music_id));
 diesel::joinable!(playlist_songs -> playlists (playlist_id));
 diesel::joinable!(playlist_songs -> users (song_adder_id));
+diesel::joinable!(playlist_tags -> playlists (playlist_id));
 diesel::joinable!(playlists -> users (user_id));
+diesel::joinable!(share_recipients -> shares (share_id));
+diesel::joinable!(share_recipients -> users (recipient_id));
+diesel::joinable!(shares -> users (sender_id));
+diesel::joinable!(user_privacy -> users (user_id));
+diesel::joinable!(user_profiles -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    activity_events,
+    conversation_members,
+    conversations,
+    direct_messages,
+    friend_requests,
     liked_songs,
+    listens,
     music,
     notifications,
     play_log,
+    playlist_blends,
+    playlist_folders,
+    playlist_follows,
+    playlist_forks,
+    playlist_history,
+    playlist_invitations,
+    playlist_library_entries,
+    playlist_share_tokens,
     playlist_shares,
     playlist_songs,
+    playlist_tags,
     playlists,
+    share_recipients,
+    shares,
+    user_blocks,
     user_friendship,
+    user_privacy,
+    user_profiles,
     users,
 );
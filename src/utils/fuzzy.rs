@@ -0,0 +1,222 @@
+use deunicode::deunicode;
+use std::collections::HashSet;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+// Minimum share of the query trigrams that has to be present in a field to count as a fuzzy match
+pub const TRIGRAM_MATCH_THRESHOLD: f64 = 0.6;
+
+// Folds the text into lowercase latin words separated by a single space.
+// "Bhattarái", "БХАТТАРАИ" and "भट्टराई" all end up comparable with a romanized query.
+pub fn normalize(text: &str) -> String {
+	// Accent folding: decompose and drop the combining marks that sit on latin/cyrillic letters.
+	// Marks on other scripts (eg: devanagari vowel signs) carry sound, so they are kept for transliteration.
+	let mut folded = String::with_capacity(text.len());
+	let mut strip_marks = false;
+	for c in text.nfkd() {
+		if is_combining_mark(c) {
+			if !strip_marks {
+				folded.push(c);
+			}
+			continue;
+		}
+		strip_marks = is_accented_script(c);
+		folded.push(c);
+	}
+
+	// Transliterating whatever is left into latin
+	let latin = deunicode(&transliterate_devanagari(&folded.nfc().collect::<String>()));
+
+	latin
+		.to_lowercase()
+		.split(|c: char| !c.is_ascii_alphanumeric())
+		.filter(|word| !word.is_empty())
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+fn is_accented_script(c: char) -> bool {
+	matches!(c as u32,
+		0x0000..=0x024F // Latin
+		| 0x0370..=0x03FF // Greek
+		| 0x0400..=0x052F // Cyrillic
+		| 0x1E00..=0x1EFF // Latin extended additional
+	)
+}
+
+// deunicode maps devanagari letter by letter which drops the inherent vowel ("समर्पित" -> "smrpit"),
+// so it is romanized here with the usual nepali/hindi spelling ("samarpit") instead
+fn transliterate_devanagari(text: &str) -> String {
+	let mut result = String::with_capacity(text.len());
+	let mut chars = text.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		if let Some(consonant) = devanagari_consonant(c) {
+			result.push_str(consonant);
+
+			// Skipping the nukta as it only alters the pronunciation slightly
+			while chars.peek() == Some(&'\u{093C}') {
+				chars.next();
+			}
+
+			match chars.peek() {
+				// Virama: no vowel after the consonant
+				Some('\u{094D}') => {
+					chars.next();
+				}
+				Some(next) if devanagari_vowel_sign(*next).is_some() => {
+					result.push_str(devanagari_vowel_sign(*next).unwrap());
+					chars.next();
+				}
+				// The inherent vowel is silent at the end of a word
+				Some(next) if devanagari_consonant(*next).is_some() || devanagari_modifier(*next).is_some() => {
+					result.push('a');
+				}
+				_ => (),
+			}
+		} else if let Some(vowel) = devanagari_vowel(c) {
+			result.push_str(vowel);
+		} else if let Some(modifier) = devanagari_modifier(c) {
+			result.push_str(modifier);
+		} else if let Some(sign) = devanagari_vowel_sign(c) {
+			result.push_str(sign);
+		} else if ('\u{0966}'..='\u{096F}').contains(&c) {
+			// Devanagari digits
+			result.push(char::from(b'0' + (c as u32 - 0x0966) as u8));
+		} else if ('\u{0900}'..='\u{097F}').contains(&c) {
+			// Stray virama, nukta, dandas and the like have no latin counterpart
+		} else {
+			result.push(c);
+		}
+	}
+
+	result
+}
+
+fn devanagari_consonant(c: char) -> Option<&'static str> {
+	let latin = match c {
+		'क' => "k",
+		'ख' => "kh",
+		'ग' => "g",
+		'घ' => "gh",
+		'ङ' => "n",
+		'च' => "ch",
+		'छ' => "chh",
+		'ज' => "j",
+		'झ' => "jh",
+		'ञ' => "n",
+		'ट' => "t",
+		'ठ' => "th",
+		'ड' => "d",
+		'ढ' => "dh",
+		'ण' => "n",
+		'त' => "t",
+		'थ' => "th",
+		'द' => "d",
+		'ध' => "dh",
+		'न' => "n",
+		'प' => "p",
+		'फ' => "ph",
+		'ब' => "b",
+		'भ' => "bh",
+		'म' => "m",
+		'य' => "y",
+		'र' => "r",
+		'ल' => "l",
+		'ळ' => "l",
+		'व' => "v",
+		'श' => "sh",
+		'ष' => "sh",
+		'स' => "s",
+		'ह' => "h",
+		_ => return None,
+	};
+	Some(latin)
+}
+
+fn devanagari_vowel(c: char) -> Option<&'static str> {
+	let latin = match c {
+		'अ' | 'आ' => "a",
+		'इ' | 'ई' => "i",
+		'उ' | 'ऊ' => "u",
+		'ऋ' => "ri",
+		'ए' => "e",
+		'ऐ' => "ai",
+		'ओ' => "o",
+		'औ' => "au",
+		_ => return None,
+	};
+	Some(latin)
+}
+
+fn devanagari_vowel_sign(c: char) -> Option<&'static str> {
+	let latin = match c {
+		'\u{093E}' => "a",
+		'\u{093F}' | '\u{0940}' => "i",
+		'\u{0941}' | '\u{0942}' => "u",
+		'\u{0943}' => "ri",
+		'\u{0947}' => "e",
+		'\u{0948}' => "ai",
+		'\u{094B}' => "o",
+		'\u{094C}' => "au",
+		_ => return None,
+	};
+	Some(latin)
+}
+
+fn devanagari_modifier(c: char) -> Option<&'static str> {
+	let latin = match c {
+		'\u{0901}' | '\u{0902}' => "n", // Chandrabindu, anusvara
+		'\u{0903}' => "h",               // Visarga
+		_ => return None,
+	};
+	Some(latin)
+}
+
+// Trigrams of every word padded the same way as postgres' pg_trgm ("  ab", " ab", "ab ")
+fn trigrams(text: &str) -> HashSet<String> {
+	let mut grams = HashSet::new();
+	for word in text.split_whitespace() {
+		let padded: Vec<char> = format!("  {} ", word).chars().collect();
+		for window in padded.windows(3) {
+			grams.insert(window.iter().collect());
+		}
+	}
+	grams
+}
+
+// Share of the query trigrams found in the target (0.0..=1.0).
+// Only the query side is used as denominator so long titles are not penalized for extra words.
+// Both of the inputs are expected to be normalized.
+pub fn trigram_similarity(query: &str, target: &str) -> f64 {
+	let query_grams = trigrams(query);
+	if query_grams.is_empty() {
+		return 0.0;
+	}
+	let target_grams = trigrams(target);
+	let common = query_grams.intersection(&target_grams).count();
+	common as f64 / query_grams.len() as f64
+}
+
+// LIKE patterns of which a field padded with a space on both sides matches at least one whenever `matches`
+// would accept it: the query itself and its trigrams. The "  a" trigrams that only tell the first letter of a word
+// are left out, they are never enough on their own to reach the threshold and would match nearly everything.
+// An empty query has no patterns.
+pub fn like_patterns(query: &str) -> Vec<String> {
+	if query.is_empty() {
+		return Vec::new();
+	}
+	let mut patterns: Vec<String> = trigrams(query)
+		.into_iter()
+		.filter(|gram| !gram.starts_with("  "))
+		.map(|gram| format!("%{gram}%"))
+		.collect();
+	patterns.sort();
+	patterns.push(format!("%{query}%"));
+	patterns
+}
+
+// Checks if a normalized query loosely matches the given raw field
+pub fn matches(query: &str, field: &str) -> bool {
+	let field = normalize(field);
+	field.contains(query) || trigram_similarity(query, &field) >= TRIGRAM_MATCH_THRESHOLD
+}
@@ -1,3 +1,4 @@
+use axum_extra::extract::cookie::CookieJar;
 use jsonwebtoken::{
 	decode, encode, errors::Result, Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
 };
@@ -24,3 +25,10 @@ pub fn verify(token: &str, secret_key: &str) -> Result<TokenData<Claims>> {
 		&Validation::new(Algorithm::HS256),
 	)
 }
+
+// The user the access token cookie was issued to, None when it's missing or no longer valid
+pub fn caller_id(jar: &CookieJar) -> Option<String> {
+	let access_token = jar.get("access_token")?;
+	let secret_key = std::env::var("JWT_SECRET_KEY").ok()?;
+	verify(access_token.value(), &secret_key).ok().map(|data| data.claims.id)
+}
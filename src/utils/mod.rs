@@ -1,4 +1,6 @@
 pub mod cookie;
 pub mod exp;
+pub mod fuzzy;
 pub mod jwt;
+pub mod report_card;
 pub mod timestamp;
@@ -0,0 +1,178 @@
+use crate::core::report::ListeningReport;
+
+use deunicode::deunicode;
+use embedded_graphics::{
+	mono_font::{ascii::FONT_10X20, MonoTextStyle},
+	pixelcolor::Rgb888,
+	prelude::*,
+	primitives::{PrimitiveStyle, Rectangle},
+	text::{Baseline, Text},
+};
+use image::{Rgb, RgbImage};
+use std::convert::Infallible;
+use std::path::Path;
+
+const CARD_WIDTH: u32 = 800;
+const CARD_HEIGHT: u32 = 1000;
+const MARGIN: i32 = 40;
+
+const BACKGROUND_TOP: [u8; 3] = [43, 16, 85];
+const BACKGROUND_BOTTOM: [u8; 3] = [12, 12, 30];
+const TEXT: Rgb888 = Rgb888::new(245, 245, 250);
+const MUTED: Rgb888 = Rgb888::new(170, 160, 200);
+const ACCENT: Rgb888 = Rgb888::new(255, 111, 145);
+
+// Draws into the image with every pixel blown up to a `scale` x `scale` block, so the small bitmap fonts stay
+// crisp at larger sizes. Coordinates are in unscaled units.
+struct Canvas<'a> {
+	image: &'a mut RgbImage,
+	scale: u32,
+}
+
+impl OriginDimensions for Canvas<'_> {
+	fn size(&self) -> Size {
+		Size::new(self.image.width() / self.scale, self.image.height() / self.scale)
+	}
+}
+
+impl DrawTarget for Canvas<'_> {
+	type Color = Rgb888;
+	type Error = Infallible;
+
+	fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+	where
+		I: IntoIterator<Item = Pixel<Self::Color>>,
+	{
+		for Pixel(point, color) in pixels {
+			if point.x < 0 || point.y < 0 {
+				continue;
+			}
+			for dy in 0..self.scale {
+				for dx in 0..self.scale {
+					let x = point.x as u32 * self.scale + dx;
+					let y = point.y as u32 * self.scale + dy;
+					if x < self.image.width() && y < self.image.height() {
+						self.image.put_pixel(x, y, Rgb([color.r(), color.g(), color.b()]));
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+// The bitmap font only has ascii glyphs, titles in other scripts are transliterated
+fn card_text(text: &str, max_chars: usize) -> String {
+	let text = deunicode(text);
+	if text.chars().count() <= max_chars {
+		return text;
+	}
+	let mut truncated: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+	truncated.push_str("...");
+	truncated
+}
+
+// Writes a line of text at the given pixel position, `scale` 1 is 10x20 pixels per character
+fn write(image: &mut RgbImage, text: &str, x: i32, y: i32, scale: u32, color: Rgb888) {
+	let max_chars = ((CARD_WIDTH as i32 - x - MARGIN) / (10 * scale as i32)).max(0) as usize;
+	let mut canvas = Canvas { image, scale };
+	let style = MonoTextStyle::new(&FONT_10X20, color);
+	let position = Point::new(x / scale as i32, y / scale as i32);
+	let _ = Text::with_baseline(&card_text(text, max_chars), position, style, Baseline::Top).draw(&mut canvas);
+}
+
+fn fill(image: &mut RgbImage, x: i32, y: i32, width: u32, height: u32, color: Rgb888) {
+	let mut canvas = Canvas { image, scale: 1 };
+	let _ = Rectangle::new(Point::new(x, y), Size::new(width, height))
+		.into_styled(PrimitiveStyle::with_fill(color))
+		.draw(&mut canvas);
+}
+
+// Renders a shareable summary of the report as a PNG
+pub fn render(report: &ListeningReport, path: &Path) -> Result<(), String> {
+	let mut image = RgbImage::new(CARD_WIDTH, CARD_HEIGHT);
+
+	// Vertical gradient background
+	for y in 0..CARD_HEIGHT {
+		let t = y as f32 / CARD_HEIGHT as f32;
+		let color: [u8; 3] = std::array::from_fn(|i| {
+			(BACKGROUND_TOP[i] as f32 * (1.0 - t) + BACKGROUND_BOTTOM[i] as f32 * t) as u8
+		});
+		for x in 0..CARD_WIDTH {
+			image.put_pixel(x, y, Rgb(color));
+		}
+	}
+
+	let mut y = MARGIN;
+	write(&mut image, &format!("LOBIC WRAPPED {}", report.period), MARGIN, y, 2, ACCENT);
+	y += 50;
+	write(&mut image, &report.username, MARGIN, y, 2, TEXT);
+	y += 80;
+
+	write(&mut image, "MINUTES LISTENED", MARGIN, y, 1, MUTED);
+	y += 24;
+	write(&mut image, &report.total_minutes.to_string(), MARGIN, y, 4, TEXT);
+	y += 100;
+
+	let streak = match &report.longest_streak {
+		Some(streak) if streak.days > 1 => format!("{} day streak", streak.days),
+		_ => format!("{} days listened", report.days_listened),
+	};
+	write(&mut image, &streak, MARGIN, y, 1, MUTED);
+	y += 50;
+
+	let sections: [(&str, Vec<String>); 3] = [
+		(
+			"TOP TRACKS",
+			report
+				.top_tracks
+				.iter()
+				.map(|track| format!("{} - {}", track.music.title, track.music.artist))
+				.collect(),
+		),
+		(
+			"TOP ARTISTS",
+			report.top_artists.iter().map(|artist| artist.name.clone()).collect(),
+		),
+		(
+			"TOP GENRES",
+			report.top_genres.iter().map(|genre| genre.name.clone()).collect(),
+		),
+	];
+	for (title, entries) in sections {
+		if entries.is_empty() {
+			continue;
+		}
+		write(&mut image, title, MARGIN, y, 1, ACCENT);
+		y += 28;
+		for (idx, entry) in entries.iter().take(3).enumerate() {
+			write(&mut image, &format!("{}. {}", idx + 1, entry), MARGIN, y, 1, TEXT);
+			y += 24;
+		}
+		y += 20;
+	}
+
+	if let Some(friend) = report.top_friends.first() {
+		write(&mut image, "LISTENED MOST WITH", MARGIN, y, 1, ACCENT);
+		y += 28;
+		write(&mut image, &friend.username, MARGIN, y, 1, TEXT);
+	}
+
+	// Listening time per hour of the day along the bottom
+	let hours: Vec<i64> = (0..24).map(|hour| report.heatmap.iter().map(|day| day[hour]).sum()).collect();
+	let peak = hours.iter().copied().max().unwrap_or(0).max(1);
+	let chart_height = 90;
+	let chart_bottom = CARD_HEIGHT as i32 - MARGIN - 24;
+	let bar_width = (CARD_WIDTH as i32 - 2 * MARGIN) / 24;
+	write(&mut image, "WHEN YOU LISTEN", MARGIN, chart_bottom - chart_height - 30, 1, MUTED);
+	for (hour, minutes) in hours.iter().enumerate() {
+		let height = ((*minutes as f64 / peak as f64) * chart_height as f64).round().max(2.0) as u32;
+		let x = MARGIN + hour as i32 * bar_width;
+		fill(&mut image, x, chart_bottom - height as i32, (bar_width - 4) as u32, height, ACCENT);
+	}
+	for (hour, label) in [(0, "0h"), (6, "6h"), (12, "12h"), (18, "18h")] {
+		write(&mut image, label, MARGIN + hour * bar_width, chart_bottom + 4, 1, MUTED);
+	}
+
+	image.save(path).map_err(|err| format!("Failed to save report card: {err}"))
+}
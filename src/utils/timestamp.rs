@@ -1,4 +1,4 @@
-use chrono::{Local, Timelike};
+use chrono::{DateTime, Days, Local, Months, NaiveDate, Timelike, Utc};
 
 pub fn now() -> String {
 	let now = Local::now();
@@ -18,3 +18,28 @@ pub fn now() -> String {
 	let timestamp = format!("{:02}:{:02} {}", hour12, minute, period);
 	return timestamp;
 }
+
+// Parses a date filter given as RFC3339 or as a plain date (YYYY-MM-DD) into the RFC3339 (UTC) form stored in db.
+// With `end_of_day` a plain date resolves to the start of the next day, so it can be used as an exclusive upper bound.
+pub fn parse_date_bound(value: &str, end_of_day: bool) -> Option<String> {
+	if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+		return Some(date_time.with_timezone(&Utc).to_rfc3339());
+	}
+
+	let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+	let date = if end_of_day { date.checked_add_days(Days::new(1))? } else { date };
+	Some(date.and_hms_opt(0, 0, 0)?.and_utc().to_rfc3339())
+}
+
+// Parses a year ("2026") or a month ("2026-03") into its first day and the first day after it
+pub fn parse_period(period: &str) -> Option<(NaiveDate, NaiveDate)> {
+	if let Ok(year) = period.parse::<i32>() {
+		let start = NaiveDate::from_ymd_opt(year, 1, 1)?;
+		let end = NaiveDate::from_ymd_opt(year + 1, 1, 1)?;
+		return Some((start, end));
+	}
+
+	let start = NaiveDate::parse_from_str(&format!("{period}-01"), "%Y-%m-%d").ok()?;
+	let end = start.checked_add_months(Months::new(1))?;
+	Some((start, end))
+}